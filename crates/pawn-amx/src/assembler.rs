@@ -0,0 +1,150 @@
+//! Textual assembler for hand-written AMX programs
+//!
+//! Each line holds an instruction such as `const.pri 5` or `halt`, a label
+//! definition such as `loop:`, or both. Several instructions may share a line
+//! when separated by `;`, and `#` starts a comment. Operands are decimal or
+//! `0x` hexadecimal integers, or label names which resolve to cod-relative
+//! byte offsets.
+
+use crate::error::*;
+use crate::header::*;
+use crate::instructions::{Instruction, Opcode};
+use crate::types::*;
+use std::collections::HashMap;
+
+/// Bytes reserved for the stack and heap of an assembled program
+const STACK_HEAP_SIZE: i32 = 4096;
+
+/// Operand of an instruction before labels are resolved
+enum Operand {
+    Value(Cell),
+    Label(String),
+}
+
+/// Assemble textual AMX instructions into a complete AMX file
+pub fn assemble(text: &str) -> AmxResult<Vec<u8>> {
+    let mnemonics = mnemonic_table();
+    let mut labels: HashMap<String, Cell> = HashMap::new();
+    let mut pending: Vec<(usize, Opcode, Operand)> = Vec::new();
+    let mut address: Cell = 0;
+
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let code = line.split('#').next().unwrap_or("");
+
+        for statement in code.split(';') {
+            let mut statement = statement.trim();
+
+            if let Some((label, rest)) = statement.split_once(':') {
+                let label = label.trim();
+                if !is_identifier(label) {
+                    return Err(error(line_number, format!("invalid label '{}'", label)));
+                }
+                if labels.insert(label.to_string(), address).is_some() {
+                    return Err(error(line_number, format!("duplicate label '{}'", label)));
+                }
+                statement = rest.trim();
+            }
+            if statement.is_empty() {
+                continue;
+            }
+
+            let mut parts = statement.split_whitespace();
+            let mnemonic = parts.next().unwrap_or_default();
+            let opcode = *mnemonics
+                .get(mnemonic.to_lowercase().as_str())
+                .ok_or_else(|| error(line_number, format!("unknown mnemonic '{}'", mnemonic)))?;
+
+            let operand = match parts.next() {
+                None => Operand::Value(0),
+                Some(_) if !opcode.has_operand() => {
+                    return Err(error(
+                        line_number,
+                        format!("'{}' takes no operand", mnemonic),
+                    ));
+                }
+                Some(text) => parse_operand(text)
+                    .ok_or_else(|| error(line_number, format!("invalid operand '{}'", text)))?,
+            };
+            if let Some(extra) = parts.next() {
+                return Err(error(
+                    line_number,
+                    format!("unexpected '{}' after operand", extra),
+                ));
+            }
+
+            address += Instruction::new(opcode, 0).to_bytes().len() as Cell;
+            pending.push((line_number, opcode, operand));
+        }
+    }
+
+    let mut code = Vec::with_capacity(address as usize);
+    for (line_number, opcode, operand) in pending {
+        let operand = match operand {
+            Operand::Value(value) => value,
+            Operand::Label(label) => *labels
+                .get(&label)
+                .ok_or_else(|| error(line_number, format!("undefined label '{}'", label)))?,
+        };
+        code.extend_from_slice(&Instruction::new(opcode, operand).to_bytes());
+    }
+
+    let mut header = AmxHeader::new();
+    header.defsize = FUNCSTUB_NT_BYTES as i16;
+    header.cod = AMX_HEADER_BYTES as i32;
+    header.dat = header.cod + code.len() as i32;
+    header.hea = header.dat;
+    header.stp = header.hea + STACK_HEAP_SIZE;
+    header.cip = 0;
+    header.size = header.hea;
+
+    let mut bytes = write_header(&header);
+    bytes.extend_from_slice(&code);
+    Ok(bytes)
+}
+
+/// Map lowercase mnemonics such as `const.pri` to their opcodes
+fn mnemonic_table() -> HashMap<String, Opcode> {
+    (0..=u8::MAX)
+        .filter_map(Opcode::from_byte)
+        .map(|opcode| (opcode.name().to_lowercase(), opcode))
+        .collect()
+}
+
+/// Parse a numeric literal or a label reference
+fn parse_operand(text: &str) -> Option<Operand> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        u32::from_str_radix(hex, 16).ok().map(|v| v as Cell)
+    } else if digits.starts_with(|c: char| c.is_ascii_digit()) {
+        digits.parse::<u32>().ok().map(|v| v as Cell)
+    } else if !negative && is_identifier(text) {
+        return Some(Operand::Label(text.to_string()));
+    } else {
+        None
+    }?;
+
+    Some(Operand::Value(if negative {
+        value.wrapping_neg()
+    } else {
+        value
+    }))
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '@')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '@')
+}
+
+fn error(line: usize, message: String) -> AmxRuntimeError {
+    AmxRuntimeError::AssemblyError { line, message }
+}
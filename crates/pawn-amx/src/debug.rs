@@ -0,0 +1,66 @@
+//! AMX debug information
+//!
+//! Debug info follows the program image (after `size` bytes) and starts with
+//! an `AMX_DBG_HDR`-style header. Only the line table is written: one record
+//! per statement mapping a cod-relative code address to a source line.
+
+use crate::error::*;
+use crate::types::*;
+
+/// Signature of the debug information header
+pub const AMX_DBG_MAGIC: u16 = 0xf1ef;
+
+/// Size of the debug information header in bytes
+const DBG_HEADER_SIZE: usize = 22;
+
+/// Size of a line table record in bytes
+const LINE_RECORD_SIZE: usize = 8;
+
+/// Serialize a line table of `(code address, line)` pairs
+pub fn write_debug_info(lines: &[(Cell, u32)]) -> Vec<u8> {
+    let size = DBG_HEADER_SIZE + lines.len() * LINE_RECORD_SIZE;
+    let mut data = Vec::with_capacity(size);
+
+    data.extend_from_slice(&(size as i32).to_le_bytes());
+    data.extend_from_slice(&AMX_DBG_MAGIC.to_le_bytes());
+    data.push(8); // file version
+    data.push(10); // minimum AMX version
+    data.extend_from_slice(&0i16.to_le_bytes()); // flags
+    data.extend_from_slice(&0i16.to_le_bytes()); // files
+    data.extend_from_slice(&(lines.len() as i16).to_le_bytes());
+    // symbols, tags, automatons and states
+    data.extend_from_slice(&[0u8; 8]);
+
+    for &(address, line) in lines {
+        data.extend_from_slice(&(address as u32).to_le_bytes());
+        data.extend_from_slice(&line.to_le_bytes());
+    }
+    data
+}
+
+/// Parse the line table written by [`write_debug_info`]
+pub fn read_debug_info(data: &[u8]) -> AmxResult<Vec<(Cell, u32)>> {
+    if data.len() < DBG_HEADER_SIZE {
+        return Err(AmxRuntimeError::InvalidFormat);
+    }
+    let magic = u16::from_le_bytes([data[4], data[5]]);
+    let count = i16::from_le_bytes([data[12], data[13]]);
+    if magic != AMX_DBG_MAGIC || count < 0 {
+        return Err(AmxRuntimeError::InvalidFormat);
+    }
+
+    let records = &data[DBG_HEADER_SIZE..];
+    if records.len() < count as usize * LINE_RECORD_SIZE {
+        return Err(AmxRuntimeError::InvalidFormat);
+    }
+
+    Ok(records
+        .chunks_exact(LINE_RECORD_SIZE)
+        .take(count as usize)
+        .map(|record| {
+            let address = i32::from_le_bytes([record[0], record[1], record[2], record[3]]) as Cell;
+            let line = u32::from_le_bytes([record[4], record[5], record[6], record[7]]);
+            (address, line)
+        })
+        .collect())
+}
@@ -0,0 +1,112 @@
+//! Textual listings of compiled AMX files
+//!
+//! [`disassemble`] lists the code section one instruction per line;
+//! [`disassemble_full`] adds the header and the data section, much like
+//! `pawndisasm`. Sections are delimited by the header offsets, so data is
+//! never decoded as code or the other way around.
+
+use crate::error::*;
+use crate::header::*;
+use crate::instructions::{Instruction, Opcode};
+use crate::types::*;
+use std::fmt::Write;
+
+/// Shortest run of printable characters shown as a string in the data section
+const MIN_STRING_LEN: usize = 2;
+
+/// List the code section, one instruction per line with its cod-relative
+/// offset
+pub fn disassemble(bytecode: &[u8]) -> AmxResult<String> {
+    let header = try_read_header(bytecode)?;
+    let natives = table_names(bytecode, &header, header.natives, header.libraries);
+    let compact = AmxFlags::from_bits(header.flags).compact;
+
+    let code = &bytecode[..header.dat as usize];
+    let mut offset = header.cod as usize;
+    let mut out = String::new();
+    while offset < code.len() {
+        let (instruction, size) = if compact {
+            Instruction::from_bytes_compact(code, offset)?
+        } else {
+            let instruction = Instruction::from_bytes(code, offset)?;
+            let size = instruction.size();
+            (instruction, size)
+        };
+        let _ = write!(
+            out,
+            "{:08x}  {}",
+            offset - header.cod as usize,
+            instruction.opcode.name()
+        );
+        if instruction.opcode.has_operand() {
+            let _ = write!(out, " {}", instruction.operand);
+        }
+        if instruction.opcode == Opcode::Sysreq
+            && let Some(name) = natives.get(instruction.operand as usize)
+        {
+            let _ = write!(out, "  ; {}", name);
+        }
+        out.push('\n');
+        offset += size;
+    }
+    Ok(out)
+}
+
+/// List a whole AMX file: the header, the code section and the data section
+///
+/// Runs of printable characters ending in a zero cell are shown as
+/// `.asciz "..."`; every other data cell is shown as `.cell`.
+pub fn disassemble_full(bytecode: &[u8]) -> AmxResult<String> {
+    let header = read_header(bytecode)?;
+    let mut out = header.to_string();
+    out.push_str("\nCODE\n");
+    out.push_str(&disassemble(bytecode)?);
+    out.push_str("\nDATA\n");
+
+    let cells: Vec<Cell> = bytecode[header.dat as usize..header.hea as usize]
+        .chunks_exact(CELL_BYTES)
+        .map(|chunk| {
+            let mut bytes = [0u8; CELL_BYTES];
+            bytes.copy_from_slice(chunk);
+            Cell::from_le_bytes(bytes)
+        })
+        .collect();
+    let mut index = 0;
+    while index < cells.len() {
+        let address = index * CELL_BYTES;
+        let run = cells[index..]
+            .iter()
+            .take_while(|&&cell| is_printable(cell))
+            .count();
+        if run >= MIN_STRING_LEN && cells.get(index + run) == Some(&0) {
+            let text: String = cells[index..index + run]
+                .iter()
+                .map(|&cell| cell as u8 as char)
+                .collect();
+            let _ = writeln!(out, "{:08x}  .asciz {:?}", address, text);
+            index += run + 1;
+        } else {
+            let _ = writeln!(out, "{:08x}  .cell 0x{:08x}", address, cells[index]);
+            index += 1;
+        }
+    }
+    Ok(out)
+}
+
+/// Names of the entries in one of the header's symbol tables
+fn table_names<'a>(bytecode: &'a [u8], header: &AmxHeader, table: i32, next: i32) -> Vec<&'a str> {
+    if table == 0 {
+        return Vec::new();
+    }
+    header
+        .table_entries(bytecode, table, next)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(_, name)| name)
+        .collect()
+}
+
+/// Whether a cell holds a printable ASCII character
+fn is_printable(cell: Cell) -> bool {
+    (0x20..0x7f).contains(&cell) || cell == Cell::from(b'\n') || cell == Cell::from(b'\t')
+}
@@ -1,294 +1,395 @@
-//! AMX header structure and related functionality
-
-use crate::types::*;
-use std::fmt;
-
-/// AMX header structure - both memory and file format
-#[derive(Debug, Clone)]
-pub struct AmxHeader {
-    /// Size of the "file"
-    pub size: i32,
-    /// Signature (magic number)
-    pub magic: u16,
-    /// File format version
-    pub file_version: u8,
-    /// Required version of the AMX
-    pub amx_version: u8,
-    /// Flags
-    pub flags: u16,
-    /// Size of a definition record
-    pub defsize: i16,
-    /// Initial value of COD - code block
-    pub cod: i32,
-    /// Initial value of DAT - data block
-    pub dat: i32,
-    /// Initial value of HEA - start of the heap
-    pub hea: i32,
-    /// Initial value of STP - stack top
-    pub stp: i32,
-    /// Initial value of CIP - the instruction pointer
-    pub cip: i32,
-    /// Offset to the "public functions" table
-    pub publics: i32,
-    /// Offset to the "native functions" table
-    pub natives: i32,
-    /// Offset to the table of libraries
-    pub libraries: i32,
-    /// The "public variables" table
-    pub pubvars: i32,
-    /// The "public tagnames" table
-    pub tags: i32,
-    /// Name table
-    pub nametable: i32,
-}
-
-impl AmxHeader {
-    /// Create a new AMX header with default values
-    pub fn new() -> Self {
-        Self {
-            size: 0,
-            magic: AMX_MAGIC,
-            file_version: 9, // Current file version
-            amx_version: 10, // Minimum AMX version
-            flags: 0,
-            defsize: 0,
-            cod: 0,
-            dat: 0,
-            hea: 0,
-            stp: 0,
-            cip: 0,
-            publics: 0,
-            natives: 0,
-            libraries: 0,
-            pubvars: 0,
-            tags: 0,
-            nametable: 0,
-        }
-    }
-
-    /// Check if the header uses name table
-    pub fn uses_name_table(&self) -> bool {
-        self.defsize == std::mem::size_of::<FuncStubNt>() as i16
-    }
-
-    /// Get the number of entries in a table
-    pub fn num_entries(&self, field: i32, next_field: i32) -> usize {
-        ((next_field - field) / self.defsize as i32) as usize
-    }
-
-    /// Get an entry from a table
-    pub fn get_entry<'a>(&self, base: &'a [u8], table: i32, index: usize) -> &'a [u8] {
-        let offset = table as usize + index * self.defsize as usize;
-        &base[offset..offset + self.defsize as usize]
-    }
-
-    /// Get entry name (works for both FuncStub and FuncStubNt)
-    pub fn get_entry_name<'a>(&self, base: &'a [u8], entry: &'a [u8]) -> &'a str {
-        if self.uses_name_table() {
-            // FuncStubNt - name is stored in name table
-            let nameofs = u32::from_le_bytes([entry[4], entry[5], entry[6], entry[7]]);
-            let name_start = nameofs as usize;
-            let name_end = base[name_start..].iter().position(|&b| b == 0).unwrap_or(0);
-            std::str::from_utf8(&base[name_start..name_start + name_end]).unwrap_or("")
-        } else {
-            // FuncStub - name is stored directly in entry
-            let name_end = entry[4..].iter().position(|&b| b == 0).unwrap_or(SEXPMAX);
-            std::str::from_utf8(&entry[4..4 + name_end]).unwrap_or("")
-        }
-    }
-
-    /// Validate the header
-    pub fn validate(&self) -> Result<(), AmxError> {
-        if self.magic != AMX_MAGIC {
-            return Err(AmxError::Format);
-        }
-
-        if self.file_version < 6 {
-            return Err(AmxError::Version);
-        }
-
-        if self.amx_version > 10 {
-            return Err(AmxError::Version);
-        }
-
-        Ok(())
-    }
-}
-
-impl Default for AmxHeader {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-impl fmt::Display for AmxHeader {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "AMX Header:\n")?;
-        write!(f, "  Size: {}\n", self.size)?;
-        write!(f, "  Magic: 0x{:04x}\n", self.magic)?;
-        write!(f, "  File Version: {}\n", self.file_version)?;
-        write!(f, "  AMX Version: {}\n", self.amx_version)?;
-        write!(f, "  Flags: 0x{:04x}\n", self.flags)?;
-        write!(f, "  Def Size: {}\n", self.defsize)?;
-        write!(f, "  Code: 0x{:08x}\n", self.cod)?;
-        write!(f, "  Data: 0x{:08x}\n", self.dat)?;
-        write!(f, "  Heap: 0x{:08x}\n", self.hea)?;
-        write!(f, "  Stack Top: 0x{:08x}\n", self.stp)?;
-        write!(f, "  CIP: 0x{:08x}\n", self.cip)?;
-        write!(f, "  Publics: 0x{:08x}\n", self.publics)?;
-        write!(f, "  Natives: 0x{:08x}\n", self.natives)?;
-        write!(f, "  Libraries: 0x{:08x}\n", self.libraries)?;
-        write!(f, "  Pub Vars: 0x{:08x}\n", self.pubvars)?;
-        write!(f, "  Tags: 0x{:08x}\n", self.tags)?;
-        write!(f, "  Name Table: 0x{:08x}\n", self.nametable)
-    }
-}
-
-/// Read AMX header from bytes
-pub fn read_header(data: &[u8]) -> Result<AmxHeader, AmxError> {
-    if data.len() < std::mem::size_of::<AmxHeader>() {
-        return Err(AmxError::Format);
-    }
-
-    let mut header = AmxHeader::new();
-    let mut offset = 0;
-
-    // Read fields in order
-    header.size = i32::from_le_bytes([
-        data[offset],
-        data[offset + 1],
-        data[offset + 2],
-        data[offset + 3],
-    ]);
-    offset += 4;
-
-    header.magic = u16::from_le_bytes([data[offset], data[offset + 1]]);
-    offset += 2;
-
-    header.file_version = data[offset];
-    offset += 1;
-
-    header.amx_version = data[offset];
-    offset += 1;
-
-    header.flags = u16::from_le_bytes([data[offset], data[offset + 1]]);
-    offset += 2;
-
-    header.defsize = i16::from_le_bytes([data[offset], data[offset + 1]]);
-    offset += 2;
-
-    header.cod = i32::from_le_bytes([
-        data[offset],
-        data[offset + 1],
-        data[offset + 2],
-        data[offset + 3],
-    ]);
-    offset += 4;
-
-    header.dat = i32::from_le_bytes([
-        data[offset],
-        data[offset + 1],
-        data[offset + 2],
-        data[offset + 3],
-    ]);
-    offset += 4;
-
-    header.hea = i32::from_le_bytes([
-        data[offset],
-        data[offset + 1],
-        data[offset + 2],
-        data[offset + 3],
-    ]);
-    offset += 4;
-
-    header.stp = i32::from_le_bytes([
-        data[offset],
-        data[offset + 1],
-        data[offset + 2],
-        data[offset + 3],
-    ]);
-    offset += 4;
-
-    header.cip = i32::from_le_bytes([
-        data[offset],
-        data[offset + 1],
-        data[offset + 2],
-        data[offset + 3],
-    ]);
-    offset += 4;
-
-    header.publics = i32::from_le_bytes([
-        data[offset],
-        data[offset + 1],
-        data[offset + 2],
-        data[offset + 3],
-    ]);
-    offset += 4;
-
-    header.natives = i32::from_le_bytes([
-        data[offset],
-        data[offset + 1],
-        data[offset + 2],
-        data[offset + 3],
-    ]);
-    offset += 4;
-
-    header.libraries = i32::from_le_bytes([
-        data[offset],
-        data[offset + 1],
-        data[offset + 2],
-        data[offset + 3],
-    ]);
-    offset += 4;
-
-    header.pubvars = i32::from_le_bytes([
-        data[offset],
-        data[offset + 1],
-        data[offset + 2],
-        data[offset + 3],
-    ]);
-    offset += 4;
-
-    header.tags = i32::from_le_bytes([
-        data[offset],
-        data[offset + 1],
-        data[offset + 2],
-        data[offset + 3],
-    ]);
-    offset += 4;
-
-    header.nametable = i32::from_le_bytes([
-        data[offset],
-        data[offset + 1],
-        data[offset + 2],
-        data[offset + 3],
-    ]);
-
-    header.validate()?;
-    Ok(header)
-}
-
-/// Write AMX header to bytes
-pub fn write_header(header: &AmxHeader) -> Vec<u8> {
-    let mut data = Vec::with_capacity(std::mem::size_of::<AmxHeader>());
-
-    data.extend_from_slice(&header.size.to_le_bytes());
-    data.extend_from_slice(&header.magic.to_le_bytes());
-    data.push(header.file_version);
-    data.push(header.amx_version);
-    data.extend_from_slice(&header.flags.to_le_bytes());
-    data.extend_from_slice(&header.defsize.to_le_bytes());
-    data.extend_from_slice(&header.cod.to_le_bytes());
-    data.extend_from_slice(&header.dat.to_le_bytes());
-    data.extend_from_slice(&header.hea.to_le_bytes());
-    data.extend_from_slice(&header.stp.to_le_bytes());
-    data.extend_from_slice(&header.cip.to_le_bytes());
-    data.extend_from_slice(&header.publics.to_le_bytes());
-    data.extend_from_slice(&header.natives.to_le_bytes());
-    data.extend_from_slice(&header.libraries.to_le_bytes());
-    data.extend_from_slice(&header.pubvars.to_le_bytes());
-    data.extend_from_slice(&header.tags.to_le_bytes());
-    data.extend_from_slice(&header.nametable.to_le_bytes());
-
-    data
-}
+//! AMX header structure and related functionality
+
+use crate::types::*;
+use std::fmt;
+
+/// AMX header structure - both memory and file format
+#[derive(Debug, Clone)]
+pub struct AmxHeader {
+    /// Size of the "file"
+    pub size: i32,
+    /// Signature (magic number)
+    pub magic: u16,
+    /// File format version
+    pub file_version: u8,
+    /// Required version of the AMX
+    pub amx_version: u8,
+    /// Flags
+    pub flags: u16,
+    /// Size of a definition record
+    pub defsize: i16,
+    /// Initial value of COD - code block
+    pub cod: i32,
+    /// Initial value of DAT - data block
+    pub dat: i32,
+    /// Initial value of HEA - start of the heap
+    pub hea: i32,
+    /// Initial value of STP - stack top
+    pub stp: i32,
+    /// Initial value of CIP - the instruction pointer
+    pub cip: i32,
+    /// Offset to the "public functions" table
+    pub publics: i32,
+    /// Offset to the "native functions" table
+    pub natives: i32,
+    /// Offset to the table of libraries
+    pub libraries: i32,
+    /// The "public variables" table
+    pub pubvars: i32,
+    /// The "public tagnames" table
+    pub tags: i32,
+    /// Name table
+    pub nametable: i32,
+}
+
+impl AmxHeader {
+    /// Create a new AMX header with default values
+    pub fn new() -> Self {
+        Self {
+            size: 0,
+            magic: AMX_MAGIC,
+            file_version: 9, // Current file version
+            amx_version: 10, // Minimum AMX version
+            flags: 0,
+            defsize: 0,
+            cod: 0,
+            dat: 0,
+            hea: 0,
+            stp: 0,
+            cip: 0,
+            publics: 0,
+            natives: 0,
+            libraries: 0,
+            pubvars: 0,
+            tags: 0,
+            nametable: 0,
+        }
+    }
+
+    /// Check if the header uses name table
+    pub fn uses_name_table(&self) -> bool {
+        self.defsize == FUNCSTUB_NT_BYTES as i16
+    }
+
+    /// Get the number of entries in a table
+    pub fn num_entries(&self, field: i32, next_field: i32) -> usize {
+        if self.defsize <= 0 || next_field < field {
+            return 0;
+        }
+        ((next_field - field) / self.defsize as i32) as usize
+    }
+
+    /// Get an entry from a table, or `None` if it lies outside `base`
+    pub fn get_entry<'a>(&self, base: &'a [u8], table: i32, index: usize) -> Option<&'a [u8]> {
+        let size = usize::try_from(self.defsize).ok()?;
+        let offset = usize::try_from(table)
+            .ok()?
+            .checked_add(index.checked_mul(size)?)?;
+        base.get(offset..offset.checked_add(size)?)
+    }
+
+    /// Get entry name (works for both FuncStub and FuncStubNt)
+    ///
+    /// Returns `None` if the name lies outside `base`, is not terminated or
+    /// is not valid UTF-8.
+    pub fn get_entry_name<'a>(&self, base: &'a [u8], entry: &'a [u8]) -> Option<&'a str> {
+        let name = if self.uses_name_table() {
+            // FuncStubNt - name is stored in name table
+            let nameofs = u32::from_le_bytes(entry.get(4..8)?.try_into().ok()?);
+            let name = base.get(nameofs as usize..)?;
+            &name[..name.iter().position(|&b| b == 0)?]
+        } else {
+            // FuncStub - name is stored directly in entry
+            let name = entry.get(4..)?;
+            let name = &name[..name.len().min(SEXPMAX + 1)];
+            &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())]
+        };
+        std::str::from_utf8(name).ok()
+    }
+
+    /// The leading cell of every entry in a table with its name: the address
+    /// of a public or public variable, or the identifier of a tag
+    ///
+    /// Returns `None` if any entry is malformed.
+    pub fn table_entries<'a>(
+        &self,
+        base: &'a [u8],
+        table: i32,
+        next_table: i32,
+    ) -> Option<Vec<(u32, &'a str)>> {
+        (0..self.num_entries(table, next_table))
+            .map(|index| {
+                let entry = self.get_entry(base, table, index)?;
+                let value = u32::from_le_bytes(entry.get(..4)?.try_into().ok()?);
+                Some((value, self.get_entry_name(base, entry)?))
+            })
+            .collect()
+    }
+
+    /// Validate the header
+    pub fn validate(&self) -> Result<(), AmxError> {
+        // A file for another cell size is a valid AMX this build can't run
+        if self.magic != AMX_MAGIC {
+            return Err(match self.magic {
+                AMX_MAGIC_16 | AMX_MAGIC_32 | AMX_MAGIC_64 => AmxError::Version,
+                _ => AmxError::Format,
+            });
+        }
+
+        if self.file_version < 6 {
+            return Err(AmxError::Version);
+        }
+
+        if self.amx_version > 10 {
+            return Err(AmxError::Version);
+        }
+
+        Ok(())
+    }
+
+    /// Validate the header against the buffer it was read from
+    ///
+    /// Checks that the declared file size fits in `data`, that the
+    /// code/data/heap/stack markers are ordered, and that every symbol table
+    /// offset is monotonically increasing and lies within the file.
+    pub fn validate_against(&self, data: &[u8]) -> Result<(), AmxError> {
+        self.validate()?;
+
+        if self.size < 0 || self.size as usize > data.len() {
+            return Err(AmxError::Format);
+        }
+        if (self.size as usize) < AMX_HEADER_BYTES {
+            return Err(AmxError::Format);
+        }
+
+        if self.cod < 0 || self.cod > self.dat || self.dat > self.hea || self.hea > self.stp {
+            return Err(AmxError::Format);
+        }
+        if self.cod > self.size || self.dat > self.size || self.hea > self.size {
+            return Err(AmxError::Format);
+        }
+
+        let tables = [
+            self.publics,
+            self.natives,
+            self.libraries,
+            self.pubvars,
+            self.tags,
+            self.nametable,
+        ];
+        let mut previous = 0;
+        for offset in tables.into_iter().filter(|&offset| offset != 0) {
+            if offset < previous || offset > self.size {
+                return Err(AmxError::Format);
+            }
+            previous = offset;
+        }
+        // Entry counts are derived from table offsets divided by `defsize`
+        if previous != 0 && self.defsize <= 0 {
+            return Err(AmxError::Format);
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for AmxHeader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for AmxHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "AMX Header:")?;
+        writeln!(f, "  Size: {}", self.size)?;
+        writeln!(f, "  Magic: 0x{:04x}", self.magic)?;
+        writeln!(f, "  File Version: {}", self.file_version)?;
+        writeln!(f, "  AMX Version: {}", self.amx_version)?;
+        writeln!(f, "  Flags: 0x{:04x}", self.flags)?;
+        writeln!(f, "  Def Size: {}", self.defsize)?;
+        writeln!(f, "  Code: 0x{:08x}", self.cod)?;
+        writeln!(f, "  Data: 0x{:08x}", self.dat)?;
+        writeln!(f, "  Heap: 0x{:08x}", self.hea)?;
+        writeln!(f, "  Stack Top: 0x{:08x}", self.stp)?;
+        writeln!(f, "  CIP: 0x{:08x}", self.cip)?;
+        writeln!(f, "  Publics: 0x{:08x}", self.publics)?;
+        writeln!(f, "  Natives: 0x{:08x}", self.natives)?;
+        writeln!(f, "  Libraries: 0x{:08x}", self.libraries)?;
+        writeln!(f, "  Pub Vars: 0x{:08x}", self.pubvars)?;
+        writeln!(f, "  Tags: 0x{:08x}", self.tags)?;
+        writeln!(f, "  Name Table: 0x{:08x}", self.nametable)
+    }
+}
+
+/// Read AMX header from bytes
+pub fn read_header(data: &[u8]) -> Result<AmxHeader, AmxError> {
+    if data.len() < AMX_HEADER_BYTES {
+        return Err(AmxError::Format);
+    }
+
+    let mut header = AmxHeader::new();
+    let mut offset = 0;
+
+    // Read fields in order
+    header.size = i32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ]);
+    offset += 4;
+
+    header.magic = u16::from_le_bytes([data[offset], data[offset + 1]]);
+    offset += 2;
+
+    header.file_version = data[offset];
+    offset += 1;
+
+    header.amx_version = data[offset];
+    offset += 1;
+
+    header.flags = u16::from_le_bytes([data[offset], data[offset + 1]]);
+    offset += 2;
+
+    header.defsize = i16::from_le_bytes([data[offset], data[offset + 1]]);
+    offset += 2;
+
+    header.cod = i32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ]);
+    offset += 4;
+
+    header.dat = i32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ]);
+    offset += 4;
+
+    header.hea = i32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ]);
+    offset += 4;
+
+    header.stp = i32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ]);
+    offset += 4;
+
+    header.cip = i32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ]);
+    offset += 4;
+
+    header.publics = i32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ]);
+    offset += 4;
+
+    header.natives = i32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ]);
+    offset += 4;
+
+    header.libraries = i32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ]);
+    offset += 4;
+
+    header.pubvars = i32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ]);
+    offset += 4;
+
+    header.tags = i32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ]);
+    offset += 4;
+
+    header.nametable = i32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ]);
+
+    header.validate_against(data)?;
+    Ok(header)
+}
+
+/// Read an AMX header and check that every symbol table entry can be read
+///
+/// Unlike [`read_header`], this also rejects files whose table entries or
+/// names lie outside the buffer, so nothing reading the tables afterwards can
+/// fail. It never panics, whatever the input.
+pub fn try_read_header(data: &[u8]) -> Result<AmxHeader, AmxError> {
+    let header = read_header(data)?;
+    for (table, next) in [
+        (header.publics, header.natives),
+        (header.natives, header.libraries),
+        (header.libraries, header.pubvars),
+        (header.pubvars, header.tags),
+        (header.tags, header.nametable),
+    ] {
+        if table != 0 {
+            header
+                .table_entries(data, table, next)
+                .ok_or(AmxError::Format)?;
+        }
+    }
+    Ok(header)
+}
+
+/// Write AMX header to bytes
+pub fn write_header(header: &AmxHeader) -> Vec<u8> {
+    let mut data = Vec::with_capacity(AMX_HEADER_BYTES);
+
+    data.extend_from_slice(&header.size.to_le_bytes());
+    data.extend_from_slice(&header.magic.to_le_bytes());
+    data.push(header.file_version);
+    data.push(header.amx_version);
+    data.extend_from_slice(&header.flags.to_le_bytes());
+    data.extend_from_slice(&header.defsize.to_le_bytes());
+    data.extend_from_slice(&header.cod.to_le_bytes());
+    data.extend_from_slice(&header.dat.to_le_bytes());
+    data.extend_from_slice(&header.hea.to_le_bytes());
+    data.extend_from_slice(&header.stp.to_le_bytes());
+    data.extend_from_slice(&header.cip.to_le_bytes());
+    data.extend_from_slice(&header.publics.to_le_bytes());
+    data.extend_from_slice(&header.natives.to_le_bytes());
+    data.extend_from_slice(&header.libraries.to_le_bytes());
+    data.extend_from_slice(&header.pubvars.to_le_bytes());
+    data.extend_from_slice(&header.tags.to_le_bytes());
+    data.extend_from_slice(&header.nametable.to_le_bytes());
+
+    data
+}
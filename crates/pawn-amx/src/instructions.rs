@@ -1,585 +1,688 @@
-//! AMX instruction set and execution
-
-use crate::error::*;
-use crate::types::*;
-
-/// AMX instruction opcodes
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum Opcode {
-    // Load instructions
-    LoadPri = 0x01,
-    LoadAlt = 0x02,
-    LoadSPri = 0x03,
-    LoadSAlt = 0x04,
-    LrefPri = 0x05,
-    LrefAlt = 0x06,
-    LrefSPri = 0x07,
-    LrefSAlt = 0x08,
-    LoadI = 0x09,
-    LodbI = 0x0A,
-
-    // Store instructions
-    ConstPri = 0x0B,
-    ConstAlt = 0x0C,
-    AddrPri = 0x0D,
-    AddrAlt = 0x0E,
-    StorPri = 0x0F,
-    StorAlt = 0x10,
-    StorSPri = 0x11,
-    StorSAlt = 0x12,
-    SrefPri = 0x13,
-    SrefAlt = 0x14,
-    SrefSPri = 0x15,
-    SrefSAlt = 0x16,
-    StorI = 0x17,
-    StrbI = 0x18,
-
-    // Stack instructions
-    Lidx = 0x19,
-    Idxaddr = 0x1A,
-    LoadB = 0x1B,
-    LoadW = 0x1C,
-    LoadS = 0x1D,
-    StorB = 0x1E,
-    StorW = 0x1F,
-    StorS = 0x20,
-
-    // Arithmetic instructions
-    Add = 0x21,
-    Sub = 0x22,
-    Smul = 0x23,
-    Sdiv = 0x24,
-    Srem = 0x25,
-    Umul = 0x26,
-    Udiv = 0x27,
-    Urem = 0x28,
-    And = 0x29,
-    Or = 0x2A,
-    Xor = 0x2B,
-    Shl = 0x2C,
-    Shr = 0x2D,
-    Sshr = 0x2E,
-    Inc = 0x2F,
-    Dec = 0x30,
-    Neg = 0x31,
-    Invert = 0x32,
-
-    // Comparison instructions
-    Eq = 0x33,
-    Neq = 0x34,
-    Less = 0x35,
-    Leq = 0x36,
-    Grtr = 0x37,
-    Geq = 0x38,
-    Sless = 0x39,
-    Sleq = 0x3A,
-    Sgrtr = 0x3B,
-    Sgeq = 0x3C,
-
-    // Jump instructions
-    Jump = 0x3D,
-    Jzer = 0x3E,
-    Jnz = 0x3F,
-    Jeq = 0x40,
-    Jneq = 0x41,
-    Jless = 0x42,
-    Jleq = 0x43,
-    Jgrtr = 0x44,
-    Jgeq = 0x45,
-    Jsless = 0x46,
-    Jsleq = 0x47,
-    Jsgrtr = 0x48,
-    Jsgeq = 0x49,
-    ShlC = 0x4A,
-    ShrC = 0x4B,
-    SshrC = 0x4C,
-
-    // Call instructions
-    Call = 0x4D,
-    CallI = 0x4E,
-    Ret = 0x4F,
-    Retn = 0x50,
-
-    // Stack manipulation
-    PushPri = 0x51,
-    PushAlt = 0x52,
-    PushR = 0x53,
-    PushC = 0x54,
-    Push = 0x55,
-    PushS = 0x56,
-    PopPri = 0x57,
-    PopAlt = 0x58,
-
-    // Stack frame
-    Stack = 0x59,
-    Heap = 0x5A,
-    Proc = 0x5B,
-    CallP = 0x5F,
-    CallPRI = 0x60,
-    CallALT = 0x61,
-    Frame = 0x62,
-
-    // System instructions
-    Sysreq = 0x63,
-    SysreqD = 0x64,
-    SysreqC = 0x65,
-    SysreqI = 0x66,
-    SysreqP = 0x67,
-    SysreqS = 0x68,
-    SysreqT = 0x69,
-    SysreqU = 0x6A,
-    SysreqV = 0x6B,
-    SysreqW = 0x6C,
-    SysreqX = 0x6D,
-    SysreqY = 0x6E,
-    SysreqZ = 0x6F,
-
-    // Switch instructions
-    Switch = 0x70,
-    Casetbl = 0x71,
-    SwapPri = 0x72,
-    SwapAlt = 0x73,
-    PushAddr = 0x74,
-    Nop = 0x75,
-
-    // Symbol table instructions
-    SymTag = 0x76,
-    SymTagI = 0x77,
-    SymTagP = 0x78,
-    SymTagS = 0x79,
-    SymTagT = 0x7A,
-    SymTagU = 0x7B,
-    SymTagV = 0x7C,
-    SymTagW = 0x7D,
-    SymTagX = 0x7E,
-    SymTagY = 0x7F,
-    SymTagZ = 0x80,
-
-    // Additional instructions
-    Break = 0x81,
-    Sleep = 0x82,
-    Halt = 0x83,
-    Bounds = 0x84,
-    BoundsI = 0x85,
-    BoundsP = 0x86,
-    BoundsS = 0x87,
-    BoundsT = 0x88,
-    BoundsU = 0x89,
-    BoundsV = 0x8A,
-    BoundsW = 0x8B,
-    BoundsX = 0x8C,
-    BoundsY = 0x8D,
-    BoundsZ = 0x8E,
-
-    // Macro instructions
-    Macro = 0x8F,
-    MacroI = 0x90,
-    MacroP = 0x91,
-    MacroS = 0x92,
-    MacroT = 0x93,
-    MacroU = 0x94,
-    MacroV = 0x95,
-    MacroW = 0x96,
-    MacroX = 0x97,
-    MacroY = 0x98,
-    MacroZ = 0x99,
-
-    // Statement instructions
-    Stmt = 0x9A,
-    StmtI = 0x9B,
-    StmtP = 0x9C,
-    StmtS = 0x9D,
-    StmtT = 0x9E,
-    StmtU = 0x9F,
-    StmtV = 0xA0,
-    StmtW = 0xA1,
-    StmtX = 0xA2,
-    StmtY = 0xA3,
-    StmtZ = 0xA4,
-}
-
-impl Opcode {
-    /// Get opcode from byte value
-    pub fn from_byte(byte: u8) -> Option<Self> {
-        match byte {
-            0x01 => Some(Opcode::LoadPri),
-            0x02 => Some(Opcode::LoadAlt),
-            0x03 => Some(Opcode::LoadSPri),
-            0x04 => Some(Opcode::LoadSAlt),
-            0x05 => Some(Opcode::LrefPri),
-            0x06 => Some(Opcode::LrefAlt),
-            0x07 => Some(Opcode::LrefSPri),
-            0x08 => Some(Opcode::LrefSAlt),
-            0x09 => Some(Opcode::LoadI),
-            0x0A => Some(Opcode::LodbI),
-            0x0B => Some(Opcode::ConstPri),
-            0x0C => Some(Opcode::ConstAlt),
-            0x0D => Some(Opcode::AddrPri),
-            0x0E => Some(Opcode::AddrAlt),
-            0x0F => Some(Opcode::StorPri),
-            0x10 => Some(Opcode::StorAlt),
-            0x11 => Some(Opcode::StorSPri),
-            0x12 => Some(Opcode::StorSAlt),
-            0x13 => Some(Opcode::SrefPri),
-            0x14 => Some(Opcode::SrefAlt),
-            0x15 => Some(Opcode::SrefSPri),
-            0x16 => Some(Opcode::SrefSAlt),
-            0x17 => Some(Opcode::StorI),
-            0x18 => Some(Opcode::StrbI),
-            0x19 => Some(Opcode::Lidx),
-            0x1A => Some(Opcode::Idxaddr),
-            0x1B => Some(Opcode::LoadB),
-            0x1C => Some(Opcode::LoadW),
-            0x1D => Some(Opcode::LoadS),
-            0x1E => Some(Opcode::StorB),
-            0x1F => Some(Opcode::StorW),
-            0x20 => Some(Opcode::StorS),
-            0x21 => Some(Opcode::Add),
-            0x22 => Some(Opcode::Sub),
-            0x23 => Some(Opcode::Smul),
-            0x24 => Some(Opcode::Sdiv),
-            0x25 => Some(Opcode::Srem),
-            0x26 => Some(Opcode::Umul),
-            0x27 => Some(Opcode::Udiv),
-            0x28 => Some(Opcode::Urem),
-            0x29 => Some(Opcode::And),
-            0x2A => Some(Opcode::Or),
-            0x2B => Some(Opcode::Xor),
-            0x2C => Some(Opcode::Shl),
-            0x2D => Some(Opcode::Shr),
-            0x2E => Some(Opcode::Sshr),
-            0x2F => Some(Opcode::Inc),
-            0x30 => Some(Opcode::Dec),
-            0x31 => Some(Opcode::Neg),
-            0x32 => Some(Opcode::Invert),
-            0x33 => Some(Opcode::Eq),
-            0x34 => Some(Opcode::Neq),
-            0x35 => Some(Opcode::Less),
-            0x36 => Some(Opcode::Leq),
-            0x37 => Some(Opcode::Grtr),
-            0x38 => Some(Opcode::Geq),
-            0x39 => Some(Opcode::Sless),
-            0x3A => Some(Opcode::Sleq),
-            0x3B => Some(Opcode::Sgrtr),
-            0x3C => Some(Opcode::Sgeq),
-            0x3D => Some(Opcode::Jump),
-            0x3E => Some(Opcode::Jzer),
-            0x3F => Some(Opcode::Jnz),
-            0x40 => Some(Opcode::Jeq),
-            0x41 => Some(Opcode::Jneq),
-            0x42 => Some(Opcode::Jless),
-            0x43 => Some(Opcode::Jleq),
-            0x44 => Some(Opcode::Jgrtr),
-            0x45 => Some(Opcode::Jgeq),
-            0x46 => Some(Opcode::Jsless),
-            0x47 => Some(Opcode::Jsleq),
-            0x48 => Some(Opcode::Jsgrtr),
-            0x49 => Some(Opcode::Jsgeq),
-            0x4A => Some(Opcode::ShlC),
-            0x4B => Some(Opcode::ShrC),
-            0x4C => Some(Opcode::SshrC),
-            0x4D => Some(Opcode::Call),
-            0x4E => Some(Opcode::CallI),
-            0x4F => Some(Opcode::Ret),
-            0x50 => Some(Opcode::Retn),
-            0x51 => Some(Opcode::PushPri),
-            0x52 => Some(Opcode::PushAlt),
-            0x53 => Some(Opcode::PushR),
-            0x54 => Some(Opcode::PushC),
-            0x55 => Some(Opcode::Push),
-            0x56 => Some(Opcode::PushS),
-            0x57 => Some(Opcode::PopPri),
-            0x58 => Some(Opcode::PopAlt),
-            0x59 => Some(Opcode::Stack),
-            0x5A => Some(Opcode::Heap),
-            0x5B => Some(Opcode::Proc),
-            0x5C => Some(Opcode::Retn),
-            0x5D => Some(Opcode::Call),
-            0x5E => Some(Opcode::CallI),
-            0x5F => Some(Opcode::CallP),
-            0x60 => Some(Opcode::CallPRI),
-            0x61 => Some(Opcode::CallALT),
-            0x62 => Some(Opcode::Frame),
-            0x63 => Some(Opcode::Sysreq),
-            0x64 => Some(Opcode::SysreqD),
-            0x65 => Some(Opcode::SysreqC),
-            0x66 => Some(Opcode::SysreqI),
-            0x67 => Some(Opcode::SysreqP),
-            0x68 => Some(Opcode::SysreqS),
-            0x69 => Some(Opcode::SysreqT),
-            0x6A => Some(Opcode::SysreqU),
-            0x6B => Some(Opcode::SysreqV),
-            0x6C => Some(Opcode::SysreqW),
-            0x6D => Some(Opcode::SysreqX),
-            0x6E => Some(Opcode::SysreqY),
-            0x6F => Some(Opcode::SysreqZ),
-            0x70 => Some(Opcode::Switch),
-            0x71 => Some(Opcode::Casetbl),
-            0x72 => Some(Opcode::SwapPri),
-            0x73 => Some(Opcode::SwapAlt),
-            0x74 => Some(Opcode::PushAddr),
-            0x75 => Some(Opcode::Nop),
-            0x76 => Some(Opcode::SymTag),
-            0x77 => Some(Opcode::SymTagI),
-            0x78 => Some(Opcode::SymTagP),
-            0x79 => Some(Opcode::SymTagS),
-            0x7A => Some(Opcode::SymTagT),
-            0x7B => Some(Opcode::SymTagU),
-            0x7C => Some(Opcode::SymTagV),
-            0x7D => Some(Opcode::SymTagW),
-            0x7E => Some(Opcode::SymTagX),
-            0x7F => Some(Opcode::SymTagY),
-            0x80 => Some(Opcode::SymTagZ),
-            0x81 => Some(Opcode::Break),
-            0x82 => Some(Opcode::Sleep),
-            0x83 => Some(Opcode::Halt),
-            0x84 => Some(Opcode::Bounds),
-            0x85 => Some(Opcode::BoundsI),
-            0x86 => Some(Opcode::BoundsP),
-            0x87 => Some(Opcode::BoundsS),
-            0x88 => Some(Opcode::BoundsT),
-            0x89 => Some(Opcode::BoundsU),
-            0x8A => Some(Opcode::BoundsV),
-            0x8B => Some(Opcode::BoundsW),
-            0x8C => Some(Opcode::BoundsX),
-            0x8D => Some(Opcode::BoundsY),
-            0x8E => Some(Opcode::BoundsZ),
-            0x8F => Some(Opcode::Macro),
-            0x90 => Some(Opcode::MacroI),
-            0x91 => Some(Opcode::MacroP),
-            0x92 => Some(Opcode::MacroS),
-            0x93 => Some(Opcode::MacroT),
-            0x94 => Some(Opcode::MacroU),
-            0x95 => Some(Opcode::MacroV),
-            0x96 => Some(Opcode::MacroW),
-            0x97 => Some(Opcode::MacroX),
-            0x98 => Some(Opcode::MacroY),
-            0x99 => Some(Opcode::MacroZ),
-            0x9A => Some(Opcode::Stmt),
-            0x9B => Some(Opcode::StmtI),
-            0x9C => Some(Opcode::StmtP),
-            0x9D => Some(Opcode::StmtS),
-            0x9E => Some(Opcode::StmtT),
-            0x9F => Some(Opcode::StmtU),
-            0xA0 => Some(Opcode::StmtV),
-            0xA1 => Some(Opcode::StmtW),
-            0xA2 => Some(Opcode::StmtX),
-            0xA3 => Some(Opcode::StmtY),
-            0xA4 => Some(Opcode::StmtZ),
-            _ => None,
-        }
-    }
-
-    /// Get byte value of opcode
-    pub fn to_byte(self) -> u8 {
-        self as u8
-    }
-
-    /// Get human-readable name of opcode
-    pub fn name(self) -> &'static str {
-        match self {
-            Opcode::LoadPri => "LOAD.pri",
-            Opcode::LoadAlt => "LOAD.alt",
-            Opcode::LoadSPri => "LOAD.S.pri",
-            Opcode::LoadSAlt => "LOAD.S.alt",
-            Opcode::LrefPri => "LREF.pri",
-            Opcode::LrefAlt => "LREF.alt",
-            Opcode::LrefSPri => "LREF.S.pri",
-            Opcode::LrefSAlt => "LREF.S.alt",
-            Opcode::LoadI => "LOAD.I",
-            Opcode::LodbI => "LODB.I",
-            Opcode::ConstPri => "CONST.pri",
-            Opcode::ConstAlt => "CONST.alt",
-            Opcode::AddrPri => "ADDR.pri",
-            Opcode::AddrAlt => "ADDR.alt",
-            Opcode::StorPri => "STOR.pri",
-            Opcode::StorAlt => "STOR.alt",
-            Opcode::StorSPri => "STOR.S.pri",
-            Opcode::StorSAlt => "STOR.S.alt",
-            Opcode::SrefPri => "SREF.pri",
-            Opcode::SrefAlt => "SREF.alt",
-            Opcode::SrefSPri => "SREF.S.pri",
-            Opcode::SrefSAlt => "SREF.S.alt",
-            Opcode::StorI => "STOR.I",
-            Opcode::StrbI => "STRB.I",
-            Opcode::Lidx => "LIDX",
-            Opcode::Idxaddr => "IDXADDR",
-            Opcode::LoadB => "LOAD.B",
-            Opcode::LoadW => "LOAD.W",
-            Opcode::LoadS => "LOAD.S",
-            Opcode::StorB => "STOR.B",
-            Opcode::StorW => "STOR.W",
-            Opcode::StorS => "STOR.S",
-            Opcode::Add => "ADD",
-            Opcode::Sub => "SUB",
-            Opcode::Smul => "SMUL",
-            Opcode::Sdiv => "SDIV",
-            Opcode::Srem => "SREM",
-            Opcode::Umul => "UMUL",
-            Opcode::Udiv => "UDIV",
-            Opcode::Urem => "UREM",
-            Opcode::And => "AND",
-            Opcode::Or => "OR",
-            Opcode::Xor => "XOR",
-            Opcode::Shl => "SHL",
-            Opcode::Shr => "SHR",
-            Opcode::Sshr => "SSHR",
-            Opcode::Inc => "INC",
-            Opcode::Dec => "DEC",
-            Opcode::Neg => "NEG",
-            Opcode::Invert => "INVERT",
-            Opcode::Eq => "EQ",
-            Opcode::Neq => "NEQ",
-            Opcode::Less => "LESS",
-            Opcode::Leq => "LEQ",
-            Opcode::Grtr => "GRTR",
-            Opcode::Geq => "GEQ",
-            Opcode::Sless => "SLESS",
-            Opcode::Sleq => "SLEQ",
-            Opcode::Sgrtr => "SGRTR",
-            Opcode::Sgeq => "SGEQ",
-            Opcode::Jump => "JUMP",
-            Opcode::Jzer => "JZER",
-            Opcode::Jnz => "JNZ",
-            Opcode::Jeq => "JEQ",
-            Opcode::Jneq => "JNEQ",
-            Opcode::Jless => "JLESS",
-            Opcode::Jleq => "JLEQ",
-            Opcode::Jgrtr => "JGRTR",
-            Opcode::Jgeq => "JGEQ",
-            Opcode::Jsless => "JSLESS",
-            Opcode::Jsleq => "JSLEQ",
-            Opcode::Jsgrtr => "JSGRTR",
-            Opcode::Jsgeq => "JSGEQ",
-            Opcode::ShlC => "SHL.C",
-            Opcode::ShrC => "SHR.C",
-            Opcode::SshrC => "SSHR.C",
-            Opcode::Call => "CALL",
-            Opcode::CallI => "CALL.I",
-            Opcode::Ret => "RET",
-            Opcode::Retn => "RETN",
-            Opcode::PushPri => "PUSH.pri",
-            Opcode::PushAlt => "PUSH.alt",
-            Opcode::PushR => "PUSH.R",
-            Opcode::PushC => "PUSH.C",
-            Opcode::Push => "PUSH",
-            Opcode::PushS => "PUSH.S",
-            Opcode::PopPri => "POP.pri",
-            Opcode::PopAlt => "POP.alt",
-            Opcode::Stack => "STACK",
-            Opcode::Heap => "HEAP",
-            Opcode::Proc => "PROC",
-            Opcode::Frame => "FRAME",
-            Opcode::CallP => "CALL.P",
-            Opcode::CallPRI => "CALL.pri",
-            Opcode::CallALT => "CALL.alt",
-            Opcode::Sysreq => "SYSREQ",
-            Opcode::SysreqD => "SYSREQ.D",
-            Opcode::SysreqC => "SYSREQ.C",
-            Opcode::SysreqI => "SYSREQ.I",
-            Opcode::SysreqP => "SYSREQ.P",
-            Opcode::SysreqS => "SYSREQ.S",
-            Opcode::SysreqT => "SYSREQ.T",
-            Opcode::SysreqU => "SYSREQ.U",
-            Opcode::SysreqV => "SYSREQ.V",
-            Opcode::SysreqW => "SYSREQ.W",
-            Opcode::SysreqX => "SYSREQ.X",
-            Opcode::SysreqY => "SYSREQ.Y",
-            Opcode::SysreqZ => "SYSREQ.Z",
-            Opcode::Switch => "SWITCH",
-            Opcode::Casetbl => "CASETBL",
-            Opcode::SwapPri => "SWAP.pri",
-            Opcode::SwapAlt => "SWAP.alt",
-            Opcode::PushAddr => "PUSH.ADDR",
-            Opcode::Nop => "NOP",
-            Opcode::SymTag => "SYMTAG",
-            Opcode::SymTagI => "SYMTAG.I",
-            Opcode::SymTagP => "SYMTAG.P",
-            Opcode::SymTagS => "SYMTAG.S",
-            Opcode::SymTagT => "SYMTAG.T",
-            Opcode::SymTagU => "SYMTAG.U",
-            Opcode::SymTagV => "SYMTAG.V",
-            Opcode::SymTagW => "SYMTAG.W",
-            Opcode::SymTagX => "SYMTAG.X",
-            Opcode::SymTagY => "SYMTAG.Y",
-            Opcode::SymTagZ => "SYMTAG.Z",
-            Opcode::Break => "BREAK",
-            Opcode::Sleep => "SLEEP",
-            Opcode::Halt => "HALT",
-            Opcode::Bounds => "BOUNDS",
-            Opcode::BoundsI => "BOUNDS.I",
-            Opcode::BoundsP => "BOUNDS.P",
-            Opcode::BoundsS => "BOUNDS.S",
-            Opcode::BoundsT => "BOUNDS.T",
-            Opcode::BoundsU => "BOUNDS.U",
-            Opcode::BoundsV => "BOUNDS.V",
-            Opcode::BoundsW => "BOUNDS.W",
-            Opcode::BoundsX => "BOUNDS.X",
-            Opcode::BoundsY => "BOUNDS.Y",
-            Opcode::BoundsZ => "BOUNDS.Z",
-            Opcode::Macro => "MACRO",
-            Opcode::MacroI => "MACRO.I",
-            Opcode::MacroP => "MACRO.P",
-            Opcode::MacroS => "MACRO.S",
-            Opcode::MacroT => "MACRO.T",
-            Opcode::MacroU => "MACRO.U",
-            Opcode::MacroV => "MACRO.V",
-            Opcode::MacroW => "MACRO.W",
-            Opcode::MacroX => "MACRO.X",
-            Opcode::MacroY => "MACRO.Y",
-            Opcode::MacroZ => "MACRO.Z",
-            Opcode::Stmt => "STMT",
-            Opcode::StmtI => "STMT.I",
-            Opcode::StmtP => "STMT.P",
-            Opcode::StmtS => "STMT.S",
-            Opcode::StmtT => "STMT.T",
-            Opcode::StmtU => "STMT.U",
-            Opcode::StmtV => "STMT.V",
-            Opcode::StmtW => "STMT.W",
-            Opcode::StmtX => "STMT.X",
-            Opcode::StmtY => "STMT.Y",
-            Opcode::StmtZ => "STMT.Z",
-        }
-    }
-}
-
-/// Instruction with operand
-#[derive(Debug, Clone)]
-pub struct Instruction {
-    pub opcode: Opcode,
-    pub operand: Cell,
-}
-
-impl Instruction {
-    pub fn new(opcode: Opcode, operand: Cell) -> Self {
-        Self { opcode, operand }
-    }
-
-    /// Read instruction from byte array
-    pub fn from_bytes(data: &[u8], offset: usize) -> AmxResult<Self> {
-        if offset + 5 > data.len() {
-            return Err(AmxRuntimeError::InvalidInstruction(offset));
-        }
-
-        let opcode_byte = data[offset];
-        let opcode = Opcode::from_byte(opcode_byte)
-            .ok_or_else(|| AmxRuntimeError::InvalidInstruction(offset))?;
-
-        let operand = Cell::from_le_bytes([
-            data[offset + 1],
-            data[offset + 2],
-            data[offset + 3],
-            data[offset + 4],
-        ]);
-
-        Ok(Self { opcode, operand })
-    }
-
-    /// Write instruction to byte array
-    pub fn to_bytes(&self) -> [u8; 5] {
-        let mut bytes = [0u8; 5];
-        bytes[0] = self.opcode.to_byte();
-        bytes[1..5].copy_from_slice(&self.operand.to_le_bytes());
-        bytes
-    }
-}
+//! AMX instruction set and execution
+
+use crate::error::*;
+use crate::types::*;
+
+/// AMX instruction opcodes, numbered as in the reference `OPCODE` enumeration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Opcode {
+    // Load instructions
+    LoadPri = 1,
+    LoadAlt = 2,
+    LoadSPri = 3,
+    LoadSAlt = 4,
+    LrefPri = 5,
+    LrefAlt = 6,
+    LrefSPri = 7,
+    LrefSAlt = 8,
+    LoadI = 9,
+    LodbI = 10,
+
+    // Constants and addresses; store instructions
+    ConstPri = 11,
+    ConstAlt = 12,
+    AddrPri = 13,
+    AddrAlt = 14,
+    StorPri = 15,
+    StorAlt = 16,
+    StorSPri = 17,
+    StorSAlt = 18,
+    SrefPri = 19,
+    SrefAlt = 20,
+    SrefSPri = 21,
+    SrefSAlt = 22,
+
+    // Indirect and indexed access
+    StorI = 23,
+    StrbI = 24,
+    Lidx = 25,
+    Idxaddr = 27,
+
+    // Special registers and register moves
+    /// Load special register `operand` into PRI
+    Lctrl = 31,
+    /// Store PRI into special register `operand`
+    Sctrl = 32,
+    MovePri = 33,
+    MoveAlt = 34,
+    Xchg = 35,
+
+    // Stack instructions
+    PushPri = 36,
+    PushAlt = 37,
+    PushR = 38,
+    PushC = 39,
+    Push = 40,
+    PushS = 41,
+    PopPri = 42,
+    PopAlt = 43,
+
+    // Stack frame and calls
+    Stack = 44,
+    Heap = 45,
+    Proc = 46,
+    Ret = 47,
+    Retn = 48,
+    Call = 49,
+    /// Call the address in PRI
+    CallI = 50,
+
+    // Jump instructions
+    Jump = 51,
+    Jzer = 53,
+    Jnz = 54,
+    Jeq = 55,
+    Jneq = 56,
+    Jless = 57,
+    Jleq = 58,
+    Jgrtr = 59,
+    Jgeq = 60,
+    Jsless = 61,
+    Jsleq = 62,
+    Jsgrtr = 63,
+    Jsgeq = 64,
+
+    // Arithmetic and bitwise instructions
+    Shl = 65,
+    Shr = 66,
+    Sshr = 67,
+    ShlC = 68,
+    ShrC = 70,
+    Smul = 72,
+    Sdiv = 73,
+    Umul = 75,
+    Udiv = 76,
+    Add = 78,
+    Sub = 79,
+    And = 81,
+    Or = 82,
+    Xor = 83,
+    Not = 84,
+    Neg = 85,
+    Invert = 86,
+
+    // Zeroing instructions
+    ZeroPri = 89,
+    ZeroAlt = 90,
+    Zero = 91,
+    ZeroS = 92,
+
+    // Comparison instructions
+    Eq = 95,
+    Neq = 96,
+    Less = 97,
+    Leq = 98,
+    Grtr = 99,
+    Geq = 100,
+    Sless = 101,
+    Sleq = 102,
+    Sgrtr = 103,
+    Sgeq = 104,
+
+    // Increments and decrements
+    Incr = 107,
+    Inc = 109,
+    Decr = 112,
+    Dec = 114,
+
+    // System instructions
+    /// Stop with error code `operand`; `AMX_ERR_SLEEP` suspends the script
+    Halt = 120,
+    Bounds = 121,
+    /// `SYSREQ.C`: call native `operand`
+    Sysreq = 123,
+
+    // Switch instructions
+    Switch = 129,
+    /// Case table of `operand` records, which follow the instruction
+    Casetbl = 130,
+
+    // Miscellaneous instructions
+    SwapPri = 131,
+    SwapAlt = 132,
+    PushAddr = 133,
+    Nop = 134,
+    SymTag = 136,
+    Break = 137,
+}
+
+impl Opcode {
+    /// Get opcode from byte value
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            1 => Some(Opcode::LoadPri),
+            2 => Some(Opcode::LoadAlt),
+            3 => Some(Opcode::LoadSPri),
+            4 => Some(Opcode::LoadSAlt),
+            5 => Some(Opcode::LrefPri),
+            6 => Some(Opcode::LrefAlt),
+            7 => Some(Opcode::LrefSPri),
+            8 => Some(Opcode::LrefSAlt),
+            9 => Some(Opcode::LoadI),
+            10 => Some(Opcode::LodbI),
+            11 => Some(Opcode::ConstPri),
+            12 => Some(Opcode::ConstAlt),
+            13 => Some(Opcode::AddrPri),
+            14 => Some(Opcode::AddrAlt),
+            15 => Some(Opcode::StorPri),
+            16 => Some(Opcode::StorAlt),
+            17 => Some(Opcode::StorSPri),
+            18 => Some(Opcode::StorSAlt),
+            19 => Some(Opcode::SrefPri),
+            20 => Some(Opcode::SrefAlt),
+            21 => Some(Opcode::SrefSPri),
+            22 => Some(Opcode::SrefSAlt),
+            23 => Some(Opcode::StorI),
+            24 => Some(Opcode::StrbI),
+            25 => Some(Opcode::Lidx),
+            27 => Some(Opcode::Idxaddr),
+            31 => Some(Opcode::Lctrl),
+            32 => Some(Opcode::Sctrl),
+            33 => Some(Opcode::MovePri),
+            34 => Some(Opcode::MoveAlt),
+            35 => Some(Opcode::Xchg),
+            36 => Some(Opcode::PushPri),
+            37 => Some(Opcode::PushAlt),
+            38 => Some(Opcode::PushR),
+            39 => Some(Opcode::PushC),
+            40 => Some(Opcode::Push),
+            41 => Some(Opcode::PushS),
+            42 => Some(Opcode::PopPri),
+            43 => Some(Opcode::PopAlt),
+            44 => Some(Opcode::Stack),
+            45 => Some(Opcode::Heap),
+            46 => Some(Opcode::Proc),
+            47 => Some(Opcode::Ret),
+            48 => Some(Opcode::Retn),
+            49 => Some(Opcode::Call),
+            50 => Some(Opcode::CallI),
+            51 => Some(Opcode::Jump),
+            53 => Some(Opcode::Jzer),
+            54 => Some(Opcode::Jnz),
+            55 => Some(Opcode::Jeq),
+            56 => Some(Opcode::Jneq),
+            57 => Some(Opcode::Jless),
+            58 => Some(Opcode::Jleq),
+            59 => Some(Opcode::Jgrtr),
+            60 => Some(Opcode::Jgeq),
+            61 => Some(Opcode::Jsless),
+            62 => Some(Opcode::Jsleq),
+            63 => Some(Opcode::Jsgrtr),
+            64 => Some(Opcode::Jsgeq),
+            65 => Some(Opcode::Shl),
+            66 => Some(Opcode::Shr),
+            67 => Some(Opcode::Sshr),
+            68 => Some(Opcode::ShlC),
+            70 => Some(Opcode::ShrC),
+            72 => Some(Opcode::Smul),
+            73 => Some(Opcode::Sdiv),
+            75 => Some(Opcode::Umul),
+            76 => Some(Opcode::Udiv),
+            78 => Some(Opcode::Add),
+            79 => Some(Opcode::Sub),
+            81 => Some(Opcode::And),
+            82 => Some(Opcode::Or),
+            83 => Some(Opcode::Xor),
+            84 => Some(Opcode::Not),
+            85 => Some(Opcode::Neg),
+            86 => Some(Opcode::Invert),
+            89 => Some(Opcode::ZeroPri),
+            90 => Some(Opcode::ZeroAlt),
+            91 => Some(Opcode::Zero),
+            92 => Some(Opcode::ZeroS),
+            95 => Some(Opcode::Eq),
+            96 => Some(Opcode::Neq),
+            97 => Some(Opcode::Less),
+            98 => Some(Opcode::Leq),
+            99 => Some(Opcode::Grtr),
+            100 => Some(Opcode::Geq),
+            101 => Some(Opcode::Sless),
+            102 => Some(Opcode::Sleq),
+            103 => Some(Opcode::Sgrtr),
+            104 => Some(Opcode::Sgeq),
+            107 => Some(Opcode::Incr),
+            109 => Some(Opcode::Inc),
+            112 => Some(Opcode::Decr),
+            114 => Some(Opcode::Dec),
+            120 => Some(Opcode::Halt),
+            121 => Some(Opcode::Bounds),
+            123 => Some(Opcode::Sysreq),
+            129 => Some(Opcode::Switch),
+            130 => Some(Opcode::Casetbl),
+            131 => Some(Opcode::SwapPri),
+            132 => Some(Opcode::SwapAlt),
+            133 => Some(Opcode::PushAddr),
+            134 => Some(Opcode::Nop),
+            136 => Some(Opcode::SymTag),
+            137 => Some(Opcode::Break),
+            _ => None,
+        }
+    }
+
+    /// Get byte value of opcode
+    pub fn to_byte(self) -> u8 {
+        self as u8
+    }
+
+    /// Whether the opcode is followed by an operand cell
+    pub const fn has_operand(self) -> bool {
+        matches!(
+            self,
+            Opcode::LoadPri
+                | Opcode::LoadAlt
+                | Opcode::LoadSPri
+                | Opcode::LoadSAlt
+                | Opcode::LrefPri
+                | Opcode::LrefAlt
+                | Opcode::LrefSPri
+                | Opcode::LrefSAlt
+                | Opcode::LodbI
+                | Opcode::ConstPri
+                | Opcode::ConstAlt
+                | Opcode::AddrPri
+                | Opcode::AddrAlt
+                | Opcode::StorPri
+                | Opcode::StorAlt
+                | Opcode::StorSPri
+                | Opcode::StorSAlt
+                | Opcode::SrefPri
+                | Opcode::SrefAlt
+                | Opcode::SrefSPri
+                | Opcode::SrefSAlt
+                | Opcode::StrbI
+                | Opcode::Lctrl
+                | Opcode::Sctrl
+                | Opcode::PushR
+                | Opcode::PushC
+                | Opcode::Push
+                | Opcode::PushS
+                | Opcode::Stack
+                | Opcode::Heap
+                | Opcode::Call
+                | Opcode::Jump
+                | Opcode::Jzer
+                | Opcode::Jnz
+                | Opcode::Jeq
+                | Opcode::Jneq
+                | Opcode::Jless
+                | Opcode::Jleq
+                | Opcode::Jgrtr
+                | Opcode::Jgeq
+                | Opcode::Jsless
+                | Opcode::Jsleq
+                | Opcode::Jsgrtr
+                | Opcode::Jsgeq
+                | Opcode::ShlC
+                | Opcode::ShrC
+                | Opcode::Zero
+                | Opcode::ZeroS
+                | Opcode::Inc
+                | Opcode::Dec
+                | Opcode::Halt
+                | Opcode::Bounds
+                | Opcode::Sysreq
+                | Opcode::Switch
+                | Opcode::Casetbl
+                | Opcode::PushAddr
+                | Opcode::SymTag
+        )
+    }
+
+    /// Get human-readable name of opcode
+    pub fn name(self) -> &'static str {
+        match self {
+            Opcode::LoadPri => "LOAD.pri",
+            Opcode::LoadAlt => "LOAD.alt",
+            Opcode::LoadSPri => "LOAD.S.pri",
+            Opcode::LoadSAlt => "LOAD.S.alt",
+            Opcode::LrefPri => "LREF.pri",
+            Opcode::LrefAlt => "LREF.alt",
+            Opcode::LrefSPri => "LREF.S.pri",
+            Opcode::LrefSAlt => "LREF.S.alt",
+            Opcode::LoadI => "LOAD.I",
+            Opcode::LodbI => "LODB.I",
+            Opcode::ConstPri => "CONST.pri",
+            Opcode::ConstAlt => "CONST.alt",
+            Opcode::AddrPri => "ADDR.pri",
+            Opcode::AddrAlt => "ADDR.alt",
+            Opcode::StorPri => "STOR.pri",
+            Opcode::StorAlt => "STOR.alt",
+            Opcode::StorSPri => "STOR.S.pri",
+            Opcode::StorSAlt => "STOR.S.alt",
+            Opcode::SrefPri => "SREF.pri",
+            Opcode::SrefAlt => "SREF.alt",
+            Opcode::SrefSPri => "SREF.S.pri",
+            Opcode::SrefSAlt => "SREF.S.alt",
+            Opcode::StorI => "STOR.I",
+            Opcode::StrbI => "STRB.I",
+            Opcode::Lidx => "LIDX",
+            Opcode::Idxaddr => "IDXADDR",
+            Opcode::Lctrl => "LCTRL",
+            Opcode::Sctrl => "SCTRL",
+            Opcode::MovePri => "MOVE.pri",
+            Opcode::MoveAlt => "MOVE.alt",
+            Opcode::Xchg => "XCHG",
+            Opcode::PushPri => "PUSH.pri",
+            Opcode::PushAlt => "PUSH.alt",
+            Opcode::PushR => "PUSH.R",
+            Opcode::PushC => "PUSH.C",
+            Opcode::Push => "PUSH",
+            Opcode::PushS => "PUSH.S",
+            Opcode::PopPri => "POP.pri",
+            Opcode::PopAlt => "POP.alt",
+            Opcode::Stack => "STACK",
+            Opcode::Heap => "HEAP",
+            Opcode::Proc => "PROC",
+            Opcode::Ret => "RET",
+            Opcode::Retn => "RETN",
+            Opcode::Call => "CALL",
+            Opcode::CallI => "CALL.pri",
+            Opcode::Jump => "JUMP",
+            Opcode::Jzer => "JZER",
+            Opcode::Jnz => "JNZ",
+            Opcode::Jeq => "JEQ",
+            Opcode::Jneq => "JNEQ",
+            Opcode::Jless => "JLESS",
+            Opcode::Jleq => "JLEQ",
+            Opcode::Jgrtr => "JGRTR",
+            Opcode::Jgeq => "JGEQ",
+            Opcode::Jsless => "JSLESS",
+            Opcode::Jsleq => "JSLEQ",
+            Opcode::Jsgrtr => "JSGRTR",
+            Opcode::Jsgeq => "JSGEQ",
+            Opcode::Shl => "SHL",
+            Opcode::Shr => "SHR",
+            Opcode::Sshr => "SSHR",
+            Opcode::ShlC => "SHL.C.pri",
+            Opcode::ShrC => "SHR.C.pri",
+            Opcode::Smul => "SMUL",
+            Opcode::Sdiv => "SDIV",
+            Opcode::Umul => "UMUL",
+            Opcode::Udiv => "UDIV",
+            Opcode::Add => "ADD",
+            Opcode::Sub => "SUB",
+            Opcode::And => "AND",
+            Opcode::Or => "OR",
+            Opcode::Xor => "XOR",
+            Opcode::Not => "NOT",
+            Opcode::Neg => "NEG",
+            Opcode::Invert => "INVERT",
+            Opcode::ZeroPri => "ZERO.pri",
+            Opcode::ZeroAlt => "ZERO.alt",
+            Opcode::Zero => "ZERO",
+            Opcode::ZeroS => "ZERO.S",
+            Opcode::Eq => "EQ",
+            Opcode::Neq => "NEQ",
+            Opcode::Less => "LESS",
+            Opcode::Leq => "LEQ",
+            Opcode::Grtr => "GRTR",
+            Opcode::Geq => "GEQ",
+            Opcode::Sless => "SLESS",
+            Opcode::Sleq => "SLEQ",
+            Opcode::Sgrtr => "SGRTR",
+            Opcode::Sgeq => "SGEQ",
+            Opcode::Incr => "INC.pri",
+            Opcode::Inc => "INC",
+            Opcode::Decr => "DEC.pri",
+            Opcode::Dec => "DEC",
+            Opcode::Halt => "HALT",
+            Opcode::Bounds => "BOUNDS",
+            Opcode::Sysreq => "SYSREQ",
+            Opcode::Switch => "SWITCH",
+            Opcode::Casetbl => "CASETBL",
+            Opcode::SwapPri => "SWAP.pri",
+            Opcode::SwapAlt => "SWAP.alt",
+            Opcode::PushAddr => "PUSH.ADDR",
+            Opcode::Nop => "NOP",
+            Opcode::SymTag => "SYMTAG",
+            Opcode::Break => "BREAK",
+        }
+    }
+}
+
+/// Instruction with operand
+///
+/// Instructions are encoded as in the reference AMX: the opcode is a cell,
+/// followed by an operand cell for the opcodes that take one. `operand` is
+/// 0 for the others.
+#[derive(Debug, Clone)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub operand: Cell,
+}
+
+impl Instruction {
+    pub const fn new(opcode: Opcode, operand: Cell) -> Self {
+        Self { opcode, operand }
+    }
+
+    /// Length in bytes of the instruction in the plain encoding, which
+    /// [`Instruction::to_bytes`] writes
+    ///
+    /// A `CASETBL` also spans its default target and its `operand` records.
+    pub const fn size(&self) -> usize {
+        let cells = match self.opcode {
+            Opcode::Casetbl if self.operand > 0 => {
+                (self.operand as usize).saturating_mul(2).saturating_add(3)
+            }
+            Opcode::Casetbl => 3,
+            opcode if opcode.has_operand() => 2,
+            _ => 1,
+        };
+        cells.saturating_mul(CELL_BYTES)
+    }
+
+    /// Read instruction from byte array
+    pub fn from_bytes(data: &[u8], offset: usize) -> AmxResult<Self> {
+        let opcode = read_cell(data, offset)
+            .and_then(|opcode| u8::try_from(opcode).ok())
+            .and_then(Opcode::from_byte)
+            .ok_or(AmxRuntimeError::InvalidInstruction(offset))?;
+        let operand = if opcode.has_operand() {
+            read_cell(data, offset + CELL_BYTES)
+                .ok_or(AmxRuntimeError::InvalidInstruction(offset))?
+        } else {
+            0
+        };
+
+        let instruction = Self { opcode, operand };
+        if data.len() - offset < instruction.size() {
+            return Err(AmxRuntimeError::InvalidInstruction(offset));
+        }
+        Ok(instruction)
+    }
+
+    /// Read an instruction in the compact encoding
+    ///
+    /// Each cell of the instruction is stored as a variable-length cell:
+    /// seven bits per byte, most significant group first, with the high bit
+    /// set on every byte but the last and bit 6 of the first byte giving the
+    /// sign, as the reference `expand` routine decodes them. Returns the
+    /// instruction and its length in bytes.
+    pub fn from_bytes_compact(data: &[u8], offset: usize) -> AmxResult<(Self, usize)> {
+        let (opcode, mut size) = read_compact_cell(data, offset)?;
+        let opcode = u8::try_from(opcode)
+            .ok()
+            .and_then(Opcode::from_byte)
+            .ok_or(AmxRuntimeError::InvalidInstruction(offset))?;
+        let mut instruction = Self { opcode, operand: 0 };
+        if opcode.has_operand() {
+            let (operand, operand_size) = read_compact_cell(data, offset + size)?;
+            instruction.operand = operand;
+            size += operand_size;
+        }
+
+        // Skip the rest of a case table
+        let cells = instruction.size() / CELL_BYTES;
+        for _ in 2..cells {
+            size += read_compact_cell(data, offset + size)?.1;
+        }
+
+        Ok((instruction, size))
+    }
+
+    /// Write instruction to byte array
+    ///
+    /// Only the opcode and the operand are written; the records of a
+    /// `CASETBL` must follow them.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.opcode.to_byte().to_le_bytes().to_vec();
+        bytes.resize(CELL_BYTES, 0);
+        if self.opcode.has_operand() {
+            bytes.extend_from_slice(&self.operand.to_le_bytes());
+        }
+        bytes
+    }
+}
+
+/// Define a constructor per opcode, documented with its mnemonic
+macro_rules! constructors {
+    (
+        without_operand { $($name:ident => $opcode:ident, $mnemonic:literal;)* }
+        with_operand { $($with:ident($operand:ident) => $with_opcode:ident, $with_mnemonic:literal;)* }
+    ) => {
+        impl Instruction {
+            $(
+                #[doc = concat!("`", $mnemonic, "`")]
+                pub const fn $name() -> Self {
+                    Self::new(Opcode::$opcode, 0)
+                }
+            )*
+            $(
+                #[doc = concat!("`", $with_mnemonic, " ", stringify!($operand), "`")]
+                pub const fn $with($operand: Cell) -> Self {
+                    Self::new(Opcode::$with_opcode, $operand)
+                }
+            )*
+        }
+    };
+}
+
+constructors! {
+    without_operand {
+        load_i => LoadI, "LOAD.I";
+        stor_i => StorI, "STOR.I";
+        lidx => Lidx, "LIDX";
+        idxaddr => Idxaddr, "IDXADDR";
+        add => Add, "ADD";
+        sub => Sub, "SUB";
+        smul => Smul, "SMUL";
+        sdiv => Sdiv, "SDIV";
+        umul => Umul, "UMUL";
+        udiv => Udiv, "UDIV";
+        and => And, "AND";
+        or => Or, "OR";
+        xor => Xor, "XOR";
+        shl => Shl, "SHL";
+        shr => Shr, "SHR";
+        sshr => Sshr, "SSHR";
+        neg => Neg, "NEG";
+        invert => Invert, "INVERT";
+        not => Not, "NOT";
+        inc_pri => Incr, "INC.pri";
+        dec_pri => Decr, "DEC.pri";
+        eq => Eq, "EQ";
+        neq => Neq, "NEQ";
+        less => Less, "LESS";
+        leq => Leq, "LEQ";
+        grtr => Grtr, "GRTR";
+        geq => Geq, "GEQ";
+        sless => Sless, "SLESS";
+        sleq => Sleq, "SLEQ";
+        sgrtr => Sgrtr, "SGRTR";
+        sgeq => Sgeq, "SGEQ";
+        call_i => CallI, "CALL.pri";
+        ret => Ret, "RET";
+        retn => Retn, "RETN";
+        proc => Proc, "PROC";
+        push_pri => PushPri, "PUSH.pri";
+        push_alt => PushAlt, "PUSH.alt";
+        pop_pri => PopPri, "POP.pri";
+        pop_alt => PopAlt, "POP.alt";
+        move_pri => MovePri, "MOVE.pri";
+        move_alt => MoveAlt, "MOVE.alt";
+        xchg => Xchg, "XCHG";
+        swap_pri => SwapPri, "SWAP.pri";
+        swap_alt => SwapAlt, "SWAP.alt";
+        zero_pri => ZeroPri, "ZERO.pri";
+        zero_alt => ZeroAlt, "ZERO.alt";
+        halt => Halt, "HALT";
+        nop => Nop, "NOP";
+    }
+    with_operand {
+        load_pri(address) => LoadPri, "LOAD.pri";
+        load_alt(address) => LoadAlt, "LOAD.alt";
+        load_s_pri(offset) => LoadSPri, "LOAD.S.pri";
+        load_s_alt(offset) => LoadSAlt, "LOAD.S.alt";
+        lref_pri(address) => LrefPri, "LREF.pri";
+        lref_alt(address) => LrefAlt, "LREF.alt";
+        lref_s_pri(offset) => LrefSPri, "LREF.S.pri";
+        lref_s_alt(offset) => LrefSAlt, "LREF.S.alt";
+        lodb_i(width) => LodbI, "LODB.I";
+        strb_i(width) => StrbI, "STRB.I";
+        const_pri(value) => ConstPri, "CONST.pri";
+        const_alt(value) => ConstAlt, "CONST.alt";
+        addr_pri(offset) => AddrPri, "ADDR.pri";
+        addr_alt(offset) => AddrAlt, "ADDR.alt";
+        stor_pri(address) => StorPri, "STOR.pri";
+        stor_alt(address) => StorAlt, "STOR.alt";
+        stor_s_pri(offset) => StorSPri, "STOR.S.pri";
+        stor_s_alt(offset) => StorSAlt, "STOR.S.alt";
+        sref_pri(address) => SrefPri, "SREF.pri";
+        sref_alt(address) => SrefAlt, "SREF.alt";
+        sref_s_pri(offset) => SrefSPri, "SREF.S.pri";
+        sref_s_alt(offset) => SrefSAlt, "SREF.S.alt";
+        shl_c(bits) => ShlC, "SHL.C.pri";
+        shr_c(bits) => ShrC, "SHR.C.pri";
+        jump(target) => Jump, "JUMP";
+        jzer(target) => Jzer, "JZER";
+        jnz(target) => Jnz, "JNZ";
+        jeq(target) => Jeq, "JEQ";
+        jneq(target) => Jneq, "JNEQ";
+        jless(target) => Jless, "JLESS";
+        jleq(target) => Jleq, "JLEQ";
+        jgrtr(target) => Jgrtr, "JGRTR";
+        jgeq(target) => Jgeq, "JGEQ";
+        jsless(target) => Jsless, "JSLESS";
+        jsleq(target) => Jsleq, "JSLEQ";
+        jsgrtr(target) => Jsgrtr, "JSGRTR";
+        jsgeq(target) => Jsgeq, "JSGEQ";
+        call(target) => Call, "CALL";
+        push_c(value) => PushC, "PUSH.C";
+        push(address) => Push, "PUSH";
+        push_s(offset) => PushS, "PUSH.S";
+        push_addr(offset) => PushAddr, "PUSH.ADDR";
+        lctrl(index) => Lctrl, "LCTRL";
+        sctrl(index) => Sctrl, "SCTRL";
+        stack(bytes) => Stack, "STACK";
+        heap(bytes) => Heap, "HEAP";
+        sysreq(index) => Sysreq, "SYSREQ";
+        bounds(limit) => Bounds, "BOUNDS";
+        zero(address) => Zero, "ZERO";
+        zero_s(offset) => ZeroS, "ZERO.S";
+    }
+}
+
+/// Decode one variable-length cell, returning it with its length in bytes
+fn read_compact_cell(data: &[u8], offset: usize) -> AmxResult<(Cell, usize)> {
+    // Enough groups of seven bits to cover every bit of a cell
+    const MAX_BYTES: usize = (CELL_BYTES * 8).div_ceil(7);
+
+    let first = *data
+        .get(offset)
+        .ok_or(AmxRuntimeError::InvalidInstruction(offset))?;
+    let mut value: Cell = if first & 0x40 != 0 { -1 } else { 0 };
+    for (index, &byte) in data[offset..].iter().take(MAX_BYTES).enumerate() {
+        value = (value << 7) | (byte & 0x7f) as Cell;
+        if byte & 0x80 == 0 {
+            return Ok((value, index + 1));
+        }
+    }
+    Err(AmxRuntimeError::InvalidInstruction(offset))
+}
+
+/// Read one cell in the plain encoding
+fn read_cell(data: &[u8], offset: usize) -> Option<Cell> {
+    let bytes = data.get(offset..offset.checked_add(CELL_BYTES)?)?;
+    Some(Cell::from_le_bytes(bytes.try_into().ok()?))
+}
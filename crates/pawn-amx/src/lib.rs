@@ -1,15 +1,23 @@
-//! AMX (Abstract Machine eXecutor) runtime for Pawn
-//!
-//! This crate provides the core AMX runtime implementation for executing
-//! compiled Pawn bytecode.
-
-pub mod error;
-pub mod header;
-pub mod instructions;
-pub mod runtime;
-pub mod types;
-
-pub use error::*;
-pub use header::*;
-pub use runtime::*;
-pub use types::*;
+//! AMX (Abstract Machine eXecutor) runtime for Pawn
+//!
+//! This crate provides the core AMX runtime implementation for executing
+//! compiled Pawn bytecode.
+
+pub mod assembler;
+pub mod debug;
+pub mod disassembler;
+pub mod error;
+pub mod header;
+pub mod instructions;
+pub mod runtime;
+pub mod types;
+pub mod writer;
+
+pub use assembler::assemble;
+pub use debug::*;
+pub use disassembler::{disassemble, disassemble_full};
+pub use error::*;
+pub use header::*;
+pub use runtime::*;
+pub use types::*;
+pub use writer::*;
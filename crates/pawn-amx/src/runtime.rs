@@ -1,470 +1,1477 @@
-//! AMX runtime implementation
-
-use crate::error::*;
-use crate::header::*;
-use crate::instructions::*;
-use crate::types::*;
-use std::collections::HashMap;
-
-/// AMX runtime for executing bytecode
-pub struct AmxRuntime {
-    /// The AMX instance
-    pub amx: Amx,
-    /// Native functions registry
-    natives: HashMap<String, NativeInfo>,
-    /// Public functions registry
-    publics: HashMap<String, FuncStub>,
-    /// Public variables registry
-    pubvars: HashMap<String, PubVar>,
-    /// Tags registry
-    tags: HashMap<String, TagInfo>,
-}
-
-impl AmxRuntime {
-    /// Create a new AMX runtime
-    pub fn new() -> Self {
-        Self {
-            amx: Amx::new(),
-            natives: HashMap::new(),
-            publics: HashMap::new(),
-            pubvars: HashMap::new(),
-            tags: HashMap::new(),
-        }
-    }
-
-    /// Initialize AMX from bytecode
-    pub fn init(&mut self, bytecode: &[u8]) -> AmxResult<()> {
-        // Read and validate header
-        let header = read_header(bytecode)?;
-
-        // Set up AMX state
-        self.amx.base = bytecode.to_vec();
-        // Start executing at the beginning of the code section
-        self.amx.cip = header.cod;
-        self.amx.frm = header.dat;
-        self.amx.hea = header.hea;
-        self.amx.stp = header.stp;
-        self.amx.stk = header.dat;
-        self.amx.hlw = header.dat;
-
-        // Load symbol tables
-        self.load_publics(&header)?;
-        self.load_natives(&header)?;
-        self.load_pubvars(&header)?;
-        self.load_tags(&header)?;
-
-        Ok(())
-    }
-
-    /// Execute AMX bytecode
-    pub fn exec(&mut self, index: i32) -> AmxResult<Cell> {
-        if index == AMX_EXEC_MAIN {
-            // Entry point already set during init; do not override
-        } else if index == AMX_EXEC_CONT {
-            // Continue from current position
-            // No change needed
-        } else {
-            // Jump to specific function
-            if let Some(func) = self.publics.get(&format!("func_{}", index)) {
-                self.amx.cip = func.address as Cell;
-            } else {
-                return Err(AmxRuntimeError::PublicNotFound(format!("func_{}", index)));
-            }
-        }
-
-        let mut _retval = 0;
-        self.execute_instructions(&mut _retval)?;
-        Ok(0)
-    }
-
-    /// Execute instructions until completion
-    fn execute_instructions(&mut self, _retval: &mut Cell) -> AmxResult<()> {
-        loop {
-            // Check bounds
-            if self.amx.cip as usize >= self.amx.base.len() {
-                break;
-            }
-
-            // Read instruction
-            let instruction = Instruction::from_bytes(&self.amx.base, self.amx.cip as usize)?;
-
-            // Execute instruction
-            match self.execute_instruction(instruction, _retval) {
-                Ok(should_continue) => {
-                    if !should_continue {
-                        break;
-                    }
-                }
-                Err(e) => {
-                    self.amx.error = 1; // Generic error for now
-                    return Err(e);
-                }
-            }
-        }
-
-        Ok(())
-    }
-
-    /// Execute a single instruction
-    fn execute_instruction(
-        &mut self,
-        instruction: Instruction,
-        _retval: &mut Cell,
-    ) -> AmxResult<bool> {
-        match instruction.opcode {
-            Opcode::Nop => {
-                self.amx.cip += 5;
-                Ok(true)
-            }
-
-            Opcode::Halt => Ok(false),
-
-            Opcode::ConstPri => {
-                self.amx.pri = instruction.operand;
-                self.amx.cip += 5;
-                Ok(true)
-            }
-
-            Opcode::ConstAlt => {
-                self.amx.alt = instruction.operand;
-                self.amx.cip += 5;
-                Ok(true)
-            }
-
-            Opcode::Add => {
-                self.amx.pri = self.amx.pri.wrapping_add(self.amx.alt);
-                self.amx.cip += 5;
-                Ok(true)
-            }
-
-            Opcode::Sub => {
-                self.amx.pri = self.amx.pri.wrapping_sub(self.amx.alt);
-                self.amx.cip += 5;
-                Ok(true)
-            }
-
-            Opcode::Smul => {
-                self.amx.pri = self.amx.pri.wrapping_mul(self.amx.alt);
-                self.amx.cip += 5;
-                Ok(true)
-            }
-
-            Opcode::Sdiv => {
-                if self.amx.alt == 0 {
-                    return Err(AmxRuntimeError::DomainError("Division by zero".to_string()));
-                }
-                self.amx.pri = self.amx.pri.wrapping_div(self.amx.alt);
-                self.amx.cip += 5;
-                Ok(true)
-            }
-
-            Opcode::Eq => {
-                self.amx.pri = if self.amx.pri == self.amx.alt { 1 } else { 0 };
-                self.amx.cip += 5;
-                Ok(true)
-            }
-
-            Opcode::Neq => {
-                self.amx.pri = if self.amx.pri != self.amx.alt { 1 } else { 0 };
-                self.amx.cip += 5;
-                Ok(true)
-            }
-
-            Opcode::Less => {
-                self.amx.pri = if self.amx.pri < self.amx.alt { 1 } else { 0 };
-                self.amx.cip += 5;
-                Ok(true)
-            }
-
-            Opcode::Leq => {
-                self.amx.pri = if self.amx.pri <= self.amx.alt { 1 } else { 0 };
-                self.amx.cip += 5;
-                Ok(true)
-            }
-
-            Opcode::Grtr => {
-                self.amx.pri = if self.amx.pri > self.amx.alt { 1 } else { 0 };
-                self.amx.cip += 5;
-                Ok(true)
-            }
-
-            Opcode::Geq => {
-                self.amx.pri = if self.amx.pri >= self.amx.alt { 1 } else { 0 };
-                self.amx.cip += 5;
-                Ok(true)
-            }
-
-            Opcode::Jump => {
-                self.amx.cip = instruction.operand;
-                Ok(true)
-            }
-
-            Opcode::Jzer => {
-                if self.amx.pri == 0 {
-                    self.amx.cip = instruction.operand;
-                } else {
-                    self.amx.cip += 5;
-                }
-                Ok(true)
-            }
-
-            Opcode::Jnz => {
-                if self.amx.pri != 0 {
-                    self.amx.cip = instruction.operand;
-                } else {
-                    self.amx.cip += 5;
-                }
-                Ok(true)
-            }
-
-            Opcode::Call => {
-                // Push return address
-                self.push_stack(self.amx.cip + 5)?;
-                // Jump to function
-                self.amx.cip = instruction.operand;
-                Ok(true)
-            }
-
-            Opcode::Ret => {
-                // Pop return address
-                self.amx.cip = self.pop_stack()?;
-                Ok(true)
-            }
-
-            Opcode::Retn => {
-                // Pop return address and parameters
-                let param_count = instruction.operand;
-                self.amx.cip = self.pop_stack()?;
-                self.amx.stk += param_count;
-                Ok(true)
-            }
-
-            Opcode::PushPri => {
-                self.push_stack(self.amx.pri)?;
-                self.amx.cip += 5;
-                Ok(true)
-            }
-
-            Opcode::PopPri => {
-                self.amx.pri = self.pop_stack()?;
-                self.amx.cip += 5;
-                Ok(true)
-            }
-
-            Opcode::PushAlt => {
-                self.push_stack(self.amx.alt)?;
-                self.amx.cip += 5;
-                Ok(true)
-            }
-
-            Opcode::PopAlt => {
-                self.amx.alt = self.pop_stack()?;
-                self.amx.cip += 5;
-                Ok(true)
-            }
-
-            Opcode::LoadPri => {
-                let addr = self.amx.frm + instruction.operand;
-                self.amx.pri = self.read_cell(addr)?;
-                self.amx.cip += 5;
-                Ok(true)
-            }
-
-            Opcode::LoadAlt => {
-                let addr = self.amx.frm + instruction.operand;
-                self.amx.alt = self.read_cell(addr)?;
-                self.amx.cip += 5;
-                Ok(true)
-            }
-
-            Opcode::StorPri => {
-                let addr = self.amx.frm + instruction.operand;
-                self.write_cell(addr, self.amx.pri)?;
-                self.amx.cip += 5;
-                Ok(true)
-            }
-
-            Opcode::StorAlt => {
-                let addr = self.amx.frm + instruction.operand;
-                self.write_cell(addr, self.amx.alt)?;
-                self.amx.cip += 5;
-                Ok(true)
-            }
-
-            Opcode::Sysreq => {
-                // Call native function
-                let native_index = instruction.operand as usize;
-                if let Some(_native) = self.natives.values().nth(native_index) {
-                    // For now, just set return value to 0
-                    self.amx.pri = 0;
-                } else {
-                    return Err(AmxRuntimeError::NativeNotFound(format!(
-                        "native_{}",
-                        native_index
-                    )));
-                }
-                self.amx.cip += 5;
-                Ok(true)
-            }
-
-            _ => {
-                // Unimplemented instruction
-                self.amx.cip += 5;
-                Ok(true)
-            }
-        }
-    }
-
-    /// Push value to stack
-    fn push_stack(&mut self, value: Cell) -> AmxResult<()> {
-        if self.amx.stk >= self.amx.stp {
-            return Err(AmxRuntimeError::StackOverflow);
-        }
-
-        self.write_cell(self.amx.stk, value)?;
-        self.amx.stk += std::mem::size_of::<Cell>() as Cell;
-        Ok(())
-    }
-
-    /// Pop value from stack
-    fn pop_stack(&mut self) -> AmxResult<Cell> {
-        if self.amx.stk <= self.amx.frm {
-            return Err(AmxRuntimeError::StackUnderflow);
-        }
-
-        self.amx.stk -= std::mem::size_of::<Cell>() as Cell;
-        self.read_cell(self.amx.stk)
-    }
-
-    /// Read cell from memory
-    fn read_cell(&self, addr: Cell) -> AmxResult<Cell> {
-        let offset = addr as usize;
-        if offset + 4 > self.amx.base.len() {
-            return Err(AmxRuntimeError::InvalidMemoryAccess(offset));
-        }
-
-        Ok(Cell::from_le_bytes([
-            self.amx.base[offset],
-            self.amx.base[offset + 1],
-            self.amx.base[offset + 2],
-            self.amx.base[offset + 3],
-        ]))
-    }
-
-    /// Write cell to memory
-    fn write_cell(&mut self, addr: Cell, value: Cell) -> AmxResult<()> {
-        let offset = addr as usize;
-        if offset + 4 > self.amx.base.len() {
-            return Err(AmxRuntimeError::InvalidMemoryAccess(offset));
-        }
-
-        let bytes = value.to_le_bytes();
-        self.amx.base[offset..offset + 4].copy_from_slice(&bytes);
-        Ok(())
-    }
-
-    /// Load public functions from header
-    fn load_publics(&mut self, header: &AmxHeader) -> AmxResult<()> {
-        if header.publics == 0 {
-            return Ok(());
-        }
-
-        let num_publics = header.num_entries(header.publics, header.natives);
-        for i in 0..num_publics {
-            let entry = header.get_entry(&self.amx.base, header.publics, i);
-            let address = UCell::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]);
-            let name = header.get_entry_name(&self.amx.base, entry);
-            self.publics
-                .insert(name.to_string(), FuncStub::new(address, name.to_string()));
-        }
-
-        Ok(())
-    }
-
-    /// Load native functions from header
-    fn load_natives(&mut self, header: &AmxHeader) -> AmxResult<()> {
-        if header.natives == 0 {
-            return Ok(());
-        }
-
-        let num_natives = header.num_entries(header.natives, header.libraries);
-        for i in 0..num_natives {
-            let entry = header.get_entry(&self.amx.base, header.natives, i);
-            let _address = UCell::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]);
-            let name = header.get_entry_name(&self.amx.base, entry);
-            // For now, create a dummy native function
-            let native = NativeInfo::new(name.to_string(), |_amx, _params| 0);
-            self.natives.insert(name.to_string(), native);
-        }
-
-        Ok(())
-    }
-
-    /// Load public variables from header
-    fn load_pubvars(&mut self, header: &AmxHeader) -> AmxResult<()> {
-        if header.pubvars == 0 {
-            return Ok(());
-        }
-
-        let num_pubvars = header.num_entries(header.pubvars, header.tags);
-        for i in 0..num_pubvars {
-            let entry = header.get_entry(&self.amx.base, header.pubvars, i);
-            let address = UCell::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]);
-            let name = header.get_entry_name(&self.amx.base, entry);
-            self.pubvars
-                .insert(name.to_string(), PubVar::new(address, name.to_string()));
-        }
-
-        Ok(())
-    }
-
-    /// Load tags from header
-    fn load_tags(&mut self, header: &AmxHeader) -> AmxResult<()> {
-        if header.tags == 0 {
-            return Ok(());
-        }
-
-        let num_tags = header.num_entries(header.tags, header.nametable);
-        for i in 0..num_tags {
-            let entry = header.get_entry(&self.amx.base, header.tags, i);
-            let tag_id = Cell::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]);
-            let name = header.get_entry_name(&self.amx.base, entry);
-            self.tags
-                .insert(name.to_string(), TagInfo::new(tag_id, name.to_string()));
-        }
-
-        Ok(())
-    }
-
-    /// Register a native function
-    pub fn register_native(&mut self, name: String, func: NativeFunction) {
-        let native = NativeInfo::new(name.clone(), func);
-        self.natives.insert(name, native);
-    }
-
-    /// Find public function by name
-    pub fn find_public(&self, name: &str) -> Option<&FuncStub> {
-        self.publics.get(name)
-    }
-
-    /// Find native function by name
-    pub fn find_native(&self, name: &str) -> Option<&NativeInfo> {
-        self.natives.get(name)
-    }
-
-    /// Find public variable by name
-    pub fn find_pubvar(&self, name: &str) -> Option<&PubVar> {
-        self.pubvars.get(name)
-    }
-
-    /// Find tag by name
-    pub fn find_tag(&self, name: &str) -> Option<&TagInfo> {
-        self.tags.get(name)
-    }
-}
-
-impl Default for AmxRuntime {
-    fn default() -> Self {
-        Self::new()
-    }
-}
+//! AMX runtime implementation
+
+use crate::debug::*;
+use crate::error::*;
+use crate::header::*;
+use crate::instructions::*;
+use crate::types::*;
+use std::collections::HashMap;
+
+/// Size of a cell in bytes
+const CELL_SIZE: Cell = CELL_BYTES as Cell;
+
+/// Largest character value of an unpacked string
+const UNPACKED_MAX: UCell = 0x00ff_ffff;
+
+/// Default limit on the data, heap and stack region a program may ask for
+pub const DEFAULT_MAX_MEMORY: usize = 64 * 1024 * 1024;
+
+/// Saved execution state of an AMX instance
+#[derive(Debug, Clone)]
+pub struct AmxSnapshot {
+    /// Contents of the data, heap and stack region
+    pub data: Vec<u8>,
+    pub cip: Cell,
+    pub frm: Cell,
+    pub stk: Cell,
+    pub hea: Cell,
+    pub pri: Cell,
+    pub alt: Cell,
+}
+
+/// How `ADD`, `SUB` and `SMUL` handle signed overflow
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowMode {
+    /// Wrap around, like the reference AMX
+    #[default]
+    Wrapping,
+    /// Raise a domain error, to catch overflow bugs in scripts
+    Trap,
+}
+
+/// AMX runtime for executing bytecode
+pub struct AmxRuntime {
+    /// The AMX instance
+    pub amx: Amx,
+    /// Native functions registry; SYSREQ operands index into it, so the
+    /// natives of the header's table come first, in table order
+    natives: Vec<NativeInfo>,
+    /// Public functions registry
+    publics: HashMap<String, FuncStub>,
+    /// Public variables registry
+    pubvars: HashMap<String, PubVar>,
+    /// Tags registry
+    tags: HashMap<String, TagInfo>,
+    /// Header of the loaded program
+    header: AmxHeader,
+    /// Line table from the debug information, sorted by code address
+    debug_lines: Vec<(Cell, u32)>,
+    overflow_mode: OverflowMode,
+    /// Whether unimplemented opcodes fail instead of being skipped
+    strict_opcodes: bool,
+    /// Unimplemented opcodes met since `init`, in order of first use
+    unsupported_opcodes: Vec<Opcode>,
+    /// Largest data, heap and stack region `init` allocates, in bytes
+    max_memory: usize,
+}
+
+impl AmxRuntime {
+    /// Create a new AMX runtime
+    pub fn new() -> Self {
+        Self {
+            amx: Amx::new(),
+            natives: Vec::new(),
+            publics: HashMap::new(),
+            pubvars: HashMap::new(),
+            tags: HashMap::new(),
+            header: AmxHeader::new(),
+            debug_lines: Vec::new(),
+            overflow_mode: OverflowMode::Wrapping,
+            strict_opcodes: false,
+            unsupported_opcodes: Vec::new(),
+            max_memory: DEFAULT_MAX_MEMORY,
+        }
+    }
+
+    /// Choose whether signed arithmetic wraps or traps on overflow
+    pub fn set_overflow_mode(&mut self, mode: OverflowMode) {
+        self.overflow_mode = mode;
+    }
+
+    /// Choose whether an opcode the runtime does not implement is an
+    /// `InvalidInstruction` error or is skipped
+    pub fn set_strict_opcodes(&mut self, strict: bool) {
+        self.strict_opcodes = strict;
+    }
+
+    /// Limit the size of the data, heap and stack region, so a program
+    /// whose header asks for more is rejected by `init` as `InvalidFormat`
+    pub fn set_max_memory(&mut self, bytes: usize) {
+        self.max_memory = bytes;
+    }
+
+    /// Opcodes the runtime does not implement that were met since the
+    /// program was loaded, in order of first use
+    pub fn unsupported_opcodes(&self) -> Vec<Opcode> {
+        self.unsupported_opcodes.clone()
+    }
+
+    /// Initialize AMX from bytecode
+    pub fn init(&mut self, bytecode: &[u8]) -> AmxResult<()> {
+        // Read and validate header
+        let header = try_read_header(bytecode)?;
+        self.unsupported_opcodes.clear();
+
+        // Set up AMX state: the code stays in `base`, while the data section,
+        // heap and stack live in a separate mutable region addressed relative
+        // to DAT
+        let size = (header.stp - header.dat) as usize;
+        if size > self.max_memory {
+            return Err(AmxRuntimeError::InvalidFormat);
+        }
+        self.amx.base = bytecode.to_vec();
+        let mut data = vec![0u8; size];
+        let initialized = (header.hea - header.dat) as usize;
+        data[..initialized].copy_from_slice(&bytecode[header.dat as usize..header.hea as usize]);
+        self.amx.data = Some(data);
+
+        // Start executing at the beginning of the code section
+        self.amx.cip = header.cip as Cell;
+        self.amx.hea = (header.hea - header.dat) as Cell;
+        self.amx.hlw = self.amx.hea;
+        self.amx.stp = (header.stp - header.dat) as Cell;
+        // The stack grows down from the top of the region towards the heap
+        self.amx.stk = self.amx.stp;
+        self.amx.frm = self.amx.stk;
+        self.amx.reset_stk = self.amx.stk;
+        self.amx.reset_hea = self.amx.hea;
+        self.amx.flags = AmxFlags::from_bits(header.flags);
+        self.header = header.clone();
+
+        // Load symbol tables
+        self.load_publics(&header)?;
+        self.load_natives(&header)?;
+        self.load_pubvars(&header)?;
+        self.load_tags(&header)?;
+
+        // Debug information follows the program image
+        self.debug_lines = if self.amx.flags.debug && bytecode.len() > header.size as usize {
+            let mut lines = read_debug_info(&bytecode[header.size as usize..])?;
+            lines.sort_by_key(|&(address, _)| address);
+            lines
+        } else {
+            Vec::new()
+        };
+
+        Ok(())
+    }
+
+    /// Reset the VM to its post-init state
+    ///
+    /// Restores the registers and the initial contents of the data section
+    /// so the program can be executed again without re-parsing the header.
+    /// Registered natives are kept.
+    pub fn reset(&mut self) {
+        let header = &self.header;
+        if let Some(data) = self.amx.data.as_mut() {
+            let initialized = (header.hea - header.dat) as usize;
+            data[..initialized]
+                .copy_from_slice(&self.amx.base[header.dat as usize..header.hea as usize]);
+            data[initialized..].fill(0);
+        }
+
+        self.amx.cip = header.cip as Cell;
+        self.amx.stk = self.amx.reset_stk;
+        self.amx.frm = self.amx.reset_stk;
+        self.amx.hea = self.amx.reset_hea;
+        self.amx.pri = 0;
+        self.amx.alt = 0;
+        self.amx.error = 0;
+    }
+
+    /// Capture the data region and registers
+    pub fn snapshot(&self) -> AmxSnapshot {
+        AmxSnapshot {
+            data: self.amx.data.clone().unwrap_or_default(),
+            cip: self.amx.cip,
+            frm: self.amx.frm,
+            stk: self.amx.stk,
+            hea: self.amx.hea,
+            pri: self.amx.pri,
+            alt: self.amx.alt,
+        }
+    }
+
+    /// Restore a state previously captured with [`AmxRuntime::snapshot`]
+    pub fn restore(&mut self, snapshot: &AmxSnapshot) {
+        self.amx.data = Some(snapshot.data.clone());
+        self.amx.cip = snapshot.cip;
+        self.amx.frm = snapshot.frm;
+        self.amx.stk = snapshot.stk;
+        self.amx.hea = snapshot.hea;
+        self.amx.pri = snapshot.pri;
+        self.amx.alt = snapshot.alt;
+    }
+
+    /// Execute AMX bytecode
+    ///
+    /// Returns `AmxRuntimeError::Sleep` when the script yields with `SLEEP`;
+    /// calling `exec(AMX_EXEC_CONT)` then resumes where it stopped.
+    pub fn exec(&mut self, index: i32) -> AmxResult<Cell> {
+        self.exec_with_limit(index, u64::MAX)
+    }
+
+    /// Execute AMX bytecode, stopping after at most `max_instructions`
+    ///
+    /// Returns `AmxRuntimeError::InstructionLimitExceeded` when the budget
+    /// runs out before the script finishes. The registers are left at the
+    /// next instruction, so `exec_with_limit(AMX_EXEC_CONT, ..)` continues.
+    pub fn exec_with_limit(&mut self, index: i32, max_instructions: u64) -> AmxResult<Cell> {
+        if index == AMX_EXEC_MAIN {
+            // Start from the program entry point
+            self.amx.cip = self.header.cip as Cell;
+        } else if index == AMX_EXEC_CONT {
+            // Continue from current position
+            // No change needed
+        } else {
+            // Jump to specific function
+            if let Some(func) = self.publics.get(&format!("func_{}", index)) {
+                self.amx.cip = func.address as Cell;
+            } else {
+                return Err(AmxRuntimeError::PublicNotFound(format!("func_{}", index)));
+            }
+        }
+
+        let mut _retval = 0;
+        self.execute_instructions(&mut _retval, max_instructions, None)?;
+        Ok(self.amx.pri)
+    }
+
+    /// Call a public function with arguments and return its result
+    ///
+    /// The arguments are pushed last to first followed by their size in
+    /// bytes, as a `CALL` from script code would. Execution stops when the
+    /// function returns, rather than at the end of the program.
+    pub fn call_public(&mut self, name: &str, args: &[Cell]) -> AmxResult<Cell> {
+        let address = self
+            .find_public(name)
+            .ok_or_else(|| AmxRuntimeError::PublicNotFound(name.to_string()))?
+            .address as Cell;
+
+        let (stk, frm) = (self.amx.stk, self.amx.frm);
+        for &arg in args.iter().rev() {
+            self.push_stack(arg)?;
+        }
+        self.push_stack(args.len() as Cell * CELL_SIZE)?;
+        // The return address is never jumped to: the call ends as soon as
+        // RETN brings the stack back to where it was
+        self.push_stack(self.amx.cip)?;
+        self.amx.cip = address;
+
+        let mut _retval = 0;
+        let result = self.execute_instructions(&mut _retval, u64::MAX, Some(stk));
+        if result.is_err() {
+            self.amx.stk = stk;
+            self.amx.frm = frm;
+        }
+        result?;
+        Ok(self.amx.pri)
+    }
+
+    /// Execute instructions until completion
+    ///
+    /// With `return_stk`, execution also stops once a return brings the
+    /// stack pointer back down to that level.
+    fn execute_instructions(
+        &mut self,
+        _retval: &mut Cell,
+        max_instructions: u64,
+        return_stk: Option<Cell>,
+    ) -> AmxResult<()> {
+        let mut executed: u64 = 0;
+        loop {
+            // Stop when running off the end of the code section
+            if self.amx.cip < 0 || self.amx.cip >= (self.header.dat - self.header.cod) as Cell {
+                break;
+            }
+
+            if executed == max_instructions {
+                return Err(AmxRuntimeError::InstructionLimitExceeded);
+            }
+            executed += 1;
+
+            // Read instruction; CIP is relative to the start of the code section
+            let offset = self.header.cod as usize + self.amx.cip as usize;
+            let (instruction, size) = if self.amx.flags.compact {
+                Instruction::from_bytes_compact(&self.amx.base, offset)?
+            } else {
+                let instruction = Instruction::from_bytes(&self.amx.base, offset)?;
+                let size = instruction.size();
+                (instruction, size)
+            };
+
+            // Execute instruction
+            let returns = matches!(instruction.opcode, Opcode::Ret | Opcode::Retn);
+            match self.execute_instruction(instruction, size as Cell, _retval) {
+                Ok(should_continue) => {
+                    if !should_continue {
+                        break;
+                    }
+                    if returns && return_stk.is_some_and(|stk| self.amx.stk >= stk) {
+                        break;
+                    }
+                }
+                // Sleeping is not a failure; the script resumes from here
+                Err(e @ AmxRuntimeError::Sleep(_)) => {
+                    self.amx.error = AmxError::Sleep as i32;
+                    return Err(e);
+                }
+                Err(e) => {
+                    self.amx.error = AmxError::from(&e) as i32;
+                    return Err(AmxRuntimeError::Runtime {
+                        kind: Box::new(e),
+                        cip: self.amx.cip,
+                        line: self.current_line(),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Execute a single instruction that is `size` bytes long
+    fn execute_instruction(
+        &mut self,
+        instruction: Instruction,
+        size: Cell,
+        _retval: &mut Cell,
+    ) -> AmxResult<bool> {
+        match instruction.opcode {
+            Opcode::Nop => {
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            // The operand is the error code to stop with
+            Opcode::Halt => match instruction.operand {
+                0 => Ok(false),
+                code if code == AmxError::Sleep as Cell => {
+                    // All registers stay in place, so `AMX_EXEC_CONT` resumes
+                    // with the next instruction
+                    self.amx.cip += size;
+                    Err(AmxRuntimeError::Sleep(self.amx.pri))
+                }
+                code if code == AmxError::Assert as Cell => Err(AmxRuntimeError::AssertionFailed),
+                code => {
+                    // Codes the reference does not define map to `AmxError::General`
+                    Err(AmxError::from(code).into())
+                }
+            },
+
+            Opcode::ConstPri => {
+                self.amx.pri = instruction.operand;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::ConstAlt => {
+                self.amx.alt = instruction.operand;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::Add => {
+                self.amx.pri = self.overflow_checked(
+                    self.amx.pri.checked_add(self.amx.alt),
+                    self.amx.pri.wrapping_add(self.amx.alt),
+                )?;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::Sub => {
+                self.amx.pri = self.overflow_checked(
+                    self.amx.pri.checked_sub(self.amx.alt),
+                    self.amx.pri.wrapping_sub(self.amx.alt),
+                )?;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::Smul => {
+                self.amx.pri = self.overflow_checked(
+                    self.amx.pri.checked_mul(self.amx.alt),
+                    self.amx.pri.wrapping_mul(self.amx.alt),
+                )?;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            // The quotient goes to PRI and the remainder to ALT
+            Opcode::Sdiv => {
+                let (dividend, divisor) = (self.amx.pri, self.amx.alt);
+                if divisor == 0 {
+                    return Err(AmxError::Divide.into());
+                }
+                // Floored division with a remainder of the divisor's sign,
+                // as the reference VM computes it
+                let (mut quotient, mut remainder) = (
+                    dividend.wrapping_div(divisor),
+                    dividend.wrapping_rem(divisor),
+                );
+                if remainder != 0 && (remainder ^ divisor) < 0 {
+                    quotient = quotient.wrapping_sub(1);
+                    remainder += divisor;
+                }
+                self.amx.pri = quotient;
+                self.amx.alt = remainder;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::And => {
+                self.amx.pri &= self.amx.alt;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::Or => {
+                self.amx.pri |= self.amx.alt;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::Xor => {
+                self.amx.pri ^= self.amx.alt;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::Shl => {
+                self.amx.pri = self.amx.pri.wrapping_shl(self.amx.alt as u32);
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::Shr => {
+                self.amx.pri = (self.amx.pri as UCell).wrapping_shr(self.amx.alt as u32) as Cell;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::Sshr => {
+                self.amx.pri = self.amx.pri.wrapping_shr(self.amx.alt as u32);
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::Neg => {
+                self.amx.pri = self.amx.pri.wrapping_neg();
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::Invert => {
+                self.amx.pri = !self.amx.pri;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::Not => {
+                self.amx.pri = if self.amx.pri == 0 { 1 } else { 0 };
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::Incr => {
+                self.amx.pri = self.amx.pri.wrapping_add(1);
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::Decr => {
+                self.amx.pri = self.amx.pri.wrapping_sub(1);
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::Eq => {
+                self.amx.pri = if self.amx.pri == self.amx.alt { 1 } else { 0 };
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::Neq => {
+                self.amx.pri = if self.amx.pri != self.amx.alt { 1 } else { 0 };
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            // LESS and friends compare unsigned, the S forms signed
+            Opcode::Less
+            | Opcode::Leq
+            | Opcode::Grtr
+            | Opcode::Geq
+            | Opcode::Sless
+            | Opcode::Sleq
+            | Opcode::Sgrtr
+            | Opcode::Sgeq => {
+                let (pri, alt) = (self.amx.pri, self.amx.alt);
+                let (upri, ualt) = (pri as UCell, alt as UCell);
+                let holds = match instruction.opcode {
+                    Opcode::Less => upri < ualt,
+                    Opcode::Leq => upri <= ualt,
+                    Opcode::Grtr => upri > ualt,
+                    Opcode::Geq => upri >= ualt,
+                    Opcode::Sless => pri < alt,
+                    Opcode::Sleq => pri <= alt,
+                    Opcode::Sgrtr => pri > alt,
+                    _ => pri >= alt,
+                };
+                self.amx.pri = Cell::from(holds);
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::Jump => {
+                self.amx.cip = instruction.operand;
+                Ok(true)
+            }
+
+            Opcode::Jzer => {
+                if self.amx.pri == 0 {
+                    self.amx.cip = instruction.operand;
+                } else {
+                    self.amx.cip += size;
+                }
+                Ok(true)
+            }
+
+            Opcode::Jnz => {
+                if self.amx.pri != 0 {
+                    self.amx.cip = instruction.operand;
+                } else {
+                    self.amx.cip += size;
+                }
+                Ok(true)
+            }
+
+            // Compare PRI with ALT and branch; JLESS and friends compare
+            // unsigned, the JS forms signed
+            Opcode::Jeq
+            | Opcode::Jneq
+            | Opcode::Jless
+            | Opcode::Jleq
+            | Opcode::Jgrtr
+            | Opcode::Jgeq
+            | Opcode::Jsless
+            | Opcode::Jsleq
+            | Opcode::Jsgrtr
+            | Opcode::Jsgeq => {
+                let (pri, alt) = (self.amx.pri, self.amx.alt);
+                let (upri, ualt) = (pri as UCell, alt as UCell);
+                let taken = match instruction.opcode {
+                    Opcode::Jeq => pri == alt,
+                    Opcode::Jneq => pri != alt,
+                    Opcode::Jless => upri < ualt,
+                    Opcode::Jleq => upri <= ualt,
+                    Opcode::Jgrtr => upri > ualt,
+                    Opcode::Jgeq => upri >= ualt,
+                    Opcode::Jsless => pri < alt,
+                    Opcode::Jsleq => pri <= alt,
+                    Opcode::Jsgrtr => pri > alt,
+                    _ => pri >= alt,
+                };
+                if taken {
+                    self.amx.cip = instruction.operand;
+                } else {
+                    self.amx.cip += size;
+                }
+                Ok(true)
+            }
+
+            Opcode::Call => {
+                // Push return address
+                self.push_stack(self.amx.cip + size)?;
+                // Jump to function
+                self.amx.cip = instruction.operand;
+                Ok(true)
+            }
+
+            Opcode::CallI => {
+                // Indirect call: the target address is in PRI
+                self.push_stack(self.amx.cip + size)?;
+                self.amx.cip = self.amx.pri;
+                Ok(true)
+            }
+
+            Opcode::Ret => {
+                // Pop return address
+                self.amx.cip = self.pop_stack()?;
+                Ok(true)
+            }
+
+            Opcode::Proc => {
+                // Save the caller's frame and start a new one
+                self.push_stack(self.amx.frm)?;
+                self.amx.frm = self.amx.stk;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::Retn => {
+                // Above the frame lie the saved FRM, the return address and
+                // the size in bytes of the arguments over it
+                let args = self.read_cell(self.amx.stk + 2 * CELL_SIZE)?;
+                let stk = self.amx.stk + 3 * CELL_SIZE + args;
+                if args < 0 || stk > self.amx.stp {
+                    return Err(AmxRuntimeError::StackUnderflow);
+                }
+                self.amx.frm = self.read_cell(self.amx.stk)?;
+                self.amx.cip = self.read_cell(self.amx.stk + CELL_SIZE)?;
+                self.amx.stk = stk;
+                Ok(true)
+            }
+
+            Opcode::PushPri => {
+                self.push_stack(self.amx.pri)?;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::PopPri => {
+                self.amx.pri = self.pop_stack()?;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::PushAlt => {
+                self.push_stack(self.amx.alt)?;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::PopAlt => {
+                self.amx.alt = self.pop_stack()?;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::PushC => {
+                self.push_stack(instruction.operand)?;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::PushAddr => {
+                self.push_stack(self.amx.frm + instruction.operand)?;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            // LOAD and STOR address data directly; their .S forms address
+            // the frame, with negative offsets for locals and positive ones
+            // for arguments
+            Opcode::LoadPri => {
+                self.amx.pri = self.read_cell(instruction.operand)?;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::LoadAlt => {
+                self.amx.alt = self.read_cell(instruction.operand)?;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::LoadSPri => {
+                let addr = self.amx.frm + instruction.operand;
+                self.amx.pri = self.read_cell(addr)?;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::LoadSAlt => {
+                let addr = self.amx.frm + instruction.operand;
+                self.amx.alt = self.read_cell(addr)?;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::StorPri => {
+                self.write_cell(instruction.operand, self.amx.pri)?;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::StorAlt => {
+                self.write_cell(instruction.operand, self.amx.alt)?;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::StorSPri => {
+                let addr = self.amx.frm + instruction.operand;
+                self.write_cell(addr, self.amx.pri)?;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::StorSAlt => {
+                let addr = self.amx.frm + instruction.operand;
+                self.write_cell(addr, self.amx.alt)?;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::LoadI => {
+                self.amx.pri = self.read_cell(self.amx.pri)?;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::StorI => {
+                self.write_cell(self.amx.alt, self.amx.pri)?;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::LodbI => {
+                let width = Self::byte_access_size(instruction.operand, self.amx.cip)?;
+                self.amx.pri = self.read_bytes(self.amx.pri, width)?;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::StrbI => {
+                let width = Self::byte_access_size(instruction.operand, self.amx.cip)?;
+                self.write_bytes(self.amx.alt, width, self.amx.pri)?;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::AddrPri => {
+                self.amx.pri = self.amx.frm + instruction.operand;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::AddrAlt => {
+                self.amx.alt = self.amx.frm + instruction.operand;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::Lidx => {
+                let addr = self
+                    .amx
+                    .alt
+                    .wrapping_add(self.amx.pri.wrapping_mul(CELL_SIZE));
+                self.amx.pri = self.read_cell(addr)?;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::Idxaddr => {
+                self.amx.pri = self
+                    .amx
+                    .alt
+                    .wrapping_add(self.amx.pri.wrapping_mul(CELL_SIZE));
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::Bounds => {
+                // Negative indices wrap to large unsigned values
+                if !self.amx.flags.no_checks && self.amx.pri as UCell > instruction.operand as UCell
+                {
+                    return Err(AmxRuntimeError::ArrayBounds);
+                }
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::Stack => {
+                let stk = self.amx.stk + instruction.operand;
+                // Growing the frame must leave room for the pushes that follow
+                if instruction.operand < 0 && self.collides_with_heap(stk) {
+                    return Err(AmxRuntimeError::StackOverflow);
+                }
+                if stk > self.amx.frm {
+                    return Err(AmxRuntimeError::StackUnderflow);
+                }
+                self.amx.alt = self.amx.stk;
+                self.amx.stk = stk;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::MovePri => {
+                self.amx.pri = self.amx.alt;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::MoveAlt => {
+                self.amx.alt = self.amx.pri;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::Xchg => {
+                std::mem::swap(&mut self.amx.pri, &mut self.amx.alt);
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            // Special registers by index: 0 COD, 1 DAT, 2 HEA, 3 STP, 4 STK,
+            // 5 FRM, 6 CIP
+            Opcode::Lctrl => {
+                self.amx.pri = match instruction.operand {
+                    0 => self.header.cod as Cell,
+                    1 => self.header.dat as Cell,
+                    2 => self.amx.hea,
+                    3 => self.amx.stp,
+                    4 => self.amx.stk,
+                    5 => self.amx.frm,
+                    6 => self.amx.cip,
+                    _ => return Err(AmxRuntimeError::InvalidInstruction(self.amx.cip as usize)),
+                };
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            // COD, DAT and STP are read-only
+            Opcode::Sctrl => {
+                match instruction.operand {
+                    2 => self.amx.hea = self.amx.pri,
+                    4 => self.amx.stk = self.amx.pri,
+                    5 => self.amx.frm = self.amx.pri,
+                    6 => {
+                        self.amx.cip = self.amx.pri;
+                        return Ok(true);
+                    }
+                    _ => return Err(AmxRuntimeError::InvalidInstruction(self.amx.cip as usize)),
+                }
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::ZeroPri => {
+                self.amx.pri = 0;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::ZeroAlt => {
+                self.amx.alt = 0;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::Zero => {
+                self.write_cell(instruction.operand, 0)?;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::ZeroS => {
+                self.write_cell(self.amx.frm + instruction.operand, 0)?;
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            Opcode::Sysreq => {
+                let native_index = instruction.operand as usize;
+                if native_index >= self.natives.len() {
+                    return Err(AmxRuntimeError::NativeNotFound(format!(
+                        "native_{}",
+                        native_index
+                    )));
+                }
+
+                let params = self.native_params()?;
+                let argc = params[0] as usize / CELL_SIZE as usize;
+                // Borrow the native and the AMX separately, so a closure
+                // native can be called mutably while it stays registered
+                let native = &mut self.natives[native_index];
+                if let Some(arity) = native.arity
+                    && argc != arity
+                {
+                    return Err(AmxRuntimeError::ParameterError(format!(
+                        "{} expects {} argument(s), got {}",
+                        native.name, arity, argc
+                    )));
+                }
+
+                self.amx.pri = native.func.call(&mut self.amx, &params);
+                self.amx.cip += size;
+                Ok(true)
+            }
+
+            _ => {
+                // Unimplemented instruction
+                if !self.unsupported_opcodes.contains(&instruction.opcode) {
+                    self.unsupported_opcodes.push(instruction.opcode);
+                }
+                if self.strict_opcodes {
+                    return Err(AmxRuntimeError::InvalidInstruction(self.amx.cip as usize));
+                }
+                self.amx.cip += size;
+                Ok(true)
+            }
+        }
+    }
+
+    /// Collect the parameters of a native call from the stack
+    ///
+    /// The caller pushes the arguments last to first followed by their size
+    /// in bytes. The returned slice starts with that size, as natives expect.
+    fn native_params(&self) -> AmxResult<Vec<Cell>> {
+        let size = self.read_cell(self.amx.stk)?;
+        if size < 0 || size % CELL_SIZE != 0 {
+            return Err(AmxRuntimeError::ParameterError(format!(
+                "invalid native argument size {}",
+                size
+            )));
+        }
+
+        let mut params = vec![size];
+        for index in 1..=size / CELL_SIZE {
+            params.push(self.read_cell(self.amx.stk + index * CELL_SIZE)?);
+        }
+        Ok(params)
+    }
+
+    /// Result of an arithmetic instruction under the overflow mode, given its
+    /// checked and wrapped values
+    fn overflow_checked(&self, checked: Option<Cell>, wrapped: Cell) -> AmxResult<Cell> {
+        match (self.overflow_mode, checked) {
+            (OverflowMode::Trap, None) => {
+                Err(AmxRuntimeError::DomainError("integer overflow".to_string()))
+            }
+            (_, value) => Ok(value.unwrap_or(wrapped)),
+        }
+    }
+
+    /// Whether the stack reaching down to `stk` would run into the heap,
+    /// keeping `STKMARGIN` bytes between them
+    fn collides_with_heap(&self, stk: Cell) -> bool {
+        stk - STKMARGIN < self.amx.hea
+    }
+
+    /// Push value to stack
+    fn push_stack(&mut self, value: Cell) -> AmxResult<()> {
+        let stk = self.amx.stk - CELL_SIZE;
+        if self.collides_with_heap(stk) {
+            return Err(AmxRuntimeError::StackOverflow);
+        }
+
+        self.write_cell(stk, value)?;
+        self.amx.stk = stk;
+        Ok(())
+    }
+
+    /// Pop value from stack
+    fn pop_stack(&mut self) -> AmxResult<Cell> {
+        if self.amx.stk >= self.amx.frm {
+            return Err(AmxRuntimeError::StackUnderflow);
+        }
+
+        let value = self.read_cell(self.amx.stk)?;
+        self.amx.stk += CELL_SIZE;
+        Ok(value)
+    }
+
+    /// Read cell from memory (address relative to DAT)
+    fn read_cell(&self, addr: Cell) -> AmxResult<Cell> {
+        self.amx.read_cell(addr)
+    }
+
+    /// Write cell to memory (address relative to DAT)
+    fn write_cell(&mut self, addr: Cell, value: Cell) -> AmxResult<()> {
+        self.write_bytes(addr, CELL_SIZE as usize, value)
+    }
+
+    /// Read 1, 2 or 4 little-endian bytes from memory, zero-extended
+    fn read_bytes(&self, addr: Cell, size: usize) -> AmxResult<Cell> {
+        let data = self.amx.data.as_deref().unwrap_or_default();
+        let offset = data_offset(data, addr, size)?;
+
+        let mut bytes = [0u8; CELL_BYTES];
+        bytes[..size].copy_from_slice(&data[offset..offset + size]);
+        Ok(Cell::from_le_bytes(bytes))
+    }
+
+    /// Write the low 1, 2 or 4 bytes of `value` to memory
+    fn write_bytes(&mut self, addr: Cell, size: usize, value: Cell) -> AmxResult<()> {
+        let data = self.amx.data.as_deref_mut().unwrap_or_default();
+        let offset = data_offset(data, addr, size)?;
+
+        data[offset..offset + size].copy_from_slice(&value.to_le_bytes()[..size]);
+        Ok(())
+    }
+
+    /// Validate the operand of `LODB.I`/`STRB.I`
+    fn byte_access_size(operand: Cell, cip: Cell) -> AmxResult<usize> {
+        match operand {
+            1 | 2 | 4 => Ok(operand as usize),
+            _ => Err(AmxRuntimeError::InvalidInstruction(cip as usize)),
+        }
+    }
+
+    /// Load public functions from header
+    fn load_publics(&mut self, header: &AmxHeader) -> AmxResult<()> {
+        if header.publics == 0 {
+            return Ok(());
+        }
+
+        let entries = header
+            .table_entries(&self.amx.base, header.publics, header.natives)
+            .ok_or(AmxRuntimeError::InvalidFormat)?;
+        for (address, name) in entries {
+            self.publics.insert(
+                name.to_string(),
+                FuncStub::new(address as UCell, name.to_string()),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Load native functions from header
+    fn load_natives(&mut self, header: &AmxHeader) -> AmxResult<()> {
+        if header.natives == 0 {
+            return Ok(());
+        }
+
+        // Natives registered before init keep their implementation but move
+        // to the index the program's table assigns them
+        let mut registered = std::mem::take(&mut self.natives);
+        let entries = header
+            .table_entries(&self.amx.base, header.natives, header.libraries)
+            .ok_or(AmxRuntimeError::InvalidFormat)?;
+        for (_, name) in entries {
+            let native = match registered.iter().position(|native| native.name == name) {
+                Some(index) => registered.remove(index),
+                // Placeholder until the host registers an implementation
+                None => NativeInfo::new(name.to_string(), |_amx, _params| 0),
+            };
+            self.natives.push(native);
+        }
+        self.natives.extend(registered);
+
+        Ok(())
+    }
+
+    /// Load public variables from header
+    fn load_pubvars(&mut self, header: &AmxHeader) -> AmxResult<()> {
+        if header.pubvars == 0 {
+            return Ok(());
+        }
+
+        let entries = header
+            .table_entries(&self.amx.base, header.pubvars, header.tags)
+            .ok_or(AmxRuntimeError::InvalidFormat)?;
+        for (address, name) in entries {
+            self.pubvars.insert(
+                name.to_string(),
+                PubVar::new(address as UCell, name.to_string()),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Load tags from header
+    fn load_tags(&mut self, header: &AmxHeader) -> AmxResult<()> {
+        if header.tags == 0 {
+            return Ok(());
+        }
+
+        let entries = header
+            .table_entries(&self.amx.base, header.tags, header.nametable)
+            .ok_or(AmxRuntimeError::InvalidFormat)?;
+        for (tag_id, name) in entries {
+            self.tags.insert(
+                name.to_string(),
+                TagInfo::new(tag_id as i32 as Cell, name.to_string()),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Read `cells` consecutive cells starting at data address `start`
+    ///
+    /// The dump stops early at the end of the data region, so it never fails.
+    pub fn dump_memory(&self, start: Cell, cells: usize) -> Vec<Cell> {
+        (0..cells as Cell)
+            .map_while(|index| self.read_cell(start.wrapping_add(index * CELL_SIZE)).ok())
+            .collect()
+    }
+
+    /// Cells currently on the stack, oldest first
+    pub fn dump_stack(&self) -> Vec<Cell> {
+        let mut cells = self.dump_memory(self.amx.stk, self.stack_depth());
+        cells.reverse();
+        cells
+    }
+
+    /// Number of cells on the stack
+    fn stack_depth(&self) -> usize {
+        ((self.amx.reset_stk - self.amx.stk) / CELL_SIZE).max(0) as usize
+    }
+
+    /// Line table loaded from the debug information
+    pub fn debug_lines(&self) -> &[(Cell, u32)] {
+        &self.debug_lines
+    }
+
+    /// Source line of the instruction at `cip`, if debug info is loaded
+    pub fn current_line(&self) -> Option<u32> {
+        let index = self
+            .debug_lines
+            .partition_point(|&(address, _)| address <= self.amx.cip);
+        index.checked_sub(1).map(|index| self.debug_lines[index].1)
+    }
+
+    /// Translate an AMX address to an offset into the data region
+    pub fn get_addr(&self, amx_addr: Cell) -> AmxResult<usize> {
+        self.amx.get_addr(amx_addr)
+    }
+
+    /// Read the cell at an AMX address, such as a reference argument
+    pub fn read_cell_at(&self, amx_addr: Cell) -> AmxResult<Cell> {
+        self.amx.read_cell(amx_addr)
+    }
+
+    /// Write the cell at an AMX address, such as a reference argument
+    pub fn write_cell_at(&mut self, amx_addr: Cell, value: Cell) -> AmxResult<()> {
+        self.amx.write_cell(amx_addr, value)
+    }
+
+    /// Read `count` consecutive cells at an AMX address, such as an array
+    /// argument
+    pub fn read_cells(&self, amx_addr: Cell, count: usize) -> AmxResult<Vec<Cell>> {
+        self.amx.read_cells(amx_addr, count)
+    }
+
+    /// Write consecutive cells at an AMX address, such as an array argument
+    pub fn write_cells(&mut self, amx_addr: Cell, values: &[Cell]) -> AmxResult<()> {
+        self.amx.write_cells(amx_addr, values)
+    }
+
+    /// Read the zero-terminated string at an AMX address
+    pub fn get_string(&self, amx_addr: Cell) -> AmxResult<String> {
+        self.amx.get_string(amx_addr)
+    }
+
+    /// Write a string into the buffer at an AMX address, truncated to
+    /// `max_cells` cells including the terminator
+    pub fn set_string(
+        &mut self,
+        amx_addr: Cell,
+        s: &str,
+        packed: bool,
+        max_cells: usize,
+    ) -> AmxResult<usize> {
+        self.amx.set_string(amx_addr, s, packed, max_cells)
+    }
+
+    /// Register a native function accepting any number of arguments
+    pub fn register_native(&mut self, name: String, func: NativeFunction) {
+        self.add_native(NativeInfo::new(name, func));
+    }
+
+    /// Register a native function that must receive exactly `arity` arguments
+    pub fn register_native_typed(&mut self, name: String, arity: usize, func: NativeFunction) {
+        self.add_native(NativeInfo::with_arity(name, arity, func));
+    }
+
+    /// Register a native backed by a closure, which may capture host state
+    pub fn register_native_closure<F>(&mut self, name: String, func: F)
+    where
+        F: FnMut(&mut Amx, &[Cell]) -> Cell + 'static,
+    {
+        self.add_native(NativeInfo::closure(name, Box::new(func)));
+    }
+
+    /// Register a native that reaches the runtime through a
+    /// [`NativeContext`], for reading and writing its string and array
+    /// arguments
+    pub fn register_native_with_context<F>(&mut self, name: String, func: F)
+    where
+        F: FnMut(&mut NativeContext, &[Cell]) -> Cell + 'static,
+    {
+        self.add_native(NativeInfo::with_context(name, Box::new(func)));
+    }
+
+    /// Add a native, replacing one of the same name in place
+    fn add_native(&mut self, native: NativeInfo) {
+        match self.natives.iter_mut().find(|n| n.name == native.name) {
+            Some(existing) => *existing = native,
+            None => self.natives.push(native),
+        }
+    }
+
+    /// Find public function by name
+    pub fn find_public(&self, name: &str) -> Option<&FuncStub> {
+        self.publics.get(name)
+    }
+
+    /// Find native function by name
+    pub fn find_native(&self, name: &str) -> Option<&NativeInfo> {
+        self.natives.iter().find(|native| native.name == name)
+    }
+
+    /// Find public variable by name
+    pub fn find_pubvar(&self, name: &str) -> Option<&PubVar> {
+        self.pubvars.get(name)
+    }
+
+    /// Read the value of a public variable
+    pub fn get_pubvar(&self, name: &str) -> AmxResult<Cell> {
+        let pubvar = self
+            .pubvars
+            .get(name)
+            .ok_or_else(|| AmxRuntimeError::PubVarNotFound(name.to_string()))?;
+        self.read_cell(pubvar.address as Cell)
+    }
+
+    /// Write the value of a public variable
+    pub fn set_pubvar(&mut self, name: &str, value: Cell) -> AmxResult<()> {
+        let address = self
+            .pubvars
+            .get(name)
+            .ok_or_else(|| AmxRuntimeError::PubVarNotFound(name.to_string()))?
+            .address;
+        self.write_cell(address as Cell, value)
+    }
+
+    /// Find tag by name
+    pub fn find_tag(&self, name: &str) -> Option<&TagInfo> {
+        self.tags.get(name)
+    }
+}
+
+/// The runtime as seen by a native while it runs
+///
+/// Arguments passed by reference arrive as AMX addresses; the helpers here
+/// read and write the memory behind them.
+pub struct NativeContext<'a> {
+    amx: &'a mut Amx,
+}
+
+impl<'a> NativeContext<'a> {
+    pub fn new(amx: &'a mut Amx) -> Self {
+        Self { amx }
+    }
+
+    /// The abstract machine the native was called from
+    pub fn amx(&mut self) -> &mut Amx {
+        self.amx
+    }
+
+    /// Translate an AMX address to an offset into the data region
+    pub fn get_addr(&self, amx_addr: Cell) -> AmxResult<usize> {
+        self.amx.get_addr(amx_addr)
+    }
+
+    /// Read the cell at an AMX address
+    pub fn read_cell(&self, amx_addr: Cell) -> AmxResult<Cell> {
+        self.amx.read_cell(amx_addr)
+    }
+
+    /// Write the cell at an AMX address
+    pub fn write_cell(&mut self, amx_addr: Cell, value: Cell) -> AmxResult<()> {
+        self.amx.write_cell(amx_addr, value)
+    }
+
+    /// Read `count` consecutive cells at an AMX address
+    pub fn read_cells(&self, amx_addr: Cell, count: usize) -> AmxResult<Vec<Cell>> {
+        self.amx.read_cells(amx_addr, count)
+    }
+
+    /// Write consecutive cells at an AMX address
+    pub fn write_cells(&mut self, amx_addr: Cell, values: &[Cell]) -> AmxResult<()> {
+        self.amx.write_cells(amx_addr, values)
+    }
+
+    /// Read the zero-terminated string at an AMX address
+    pub fn get_string(&self, amx_addr: Cell) -> AmxResult<String> {
+        self.amx.get_string(amx_addr)
+    }
+
+    /// Write a string into the buffer at an AMX address, truncated to
+    /// `max_cells` cells including the terminator
+    pub fn set_string(
+        &mut self,
+        amx_addr: Cell,
+        s: &str,
+        packed: bool,
+        max_cells: usize,
+    ) -> AmxResult<usize> {
+        self.amx.set_string(amx_addr, s, packed, max_cells)
+    }
+}
+
+impl Amx {
+    /// Translate an AMX address to an offset into the data region
+    ///
+    /// Natives receive reference and array arguments as AMX addresses; the
+    /// whole cell at the address must lie inside the data region.
+    pub fn get_addr(&self, addr: Cell) -> AmxResult<usize> {
+        data_offset(
+            self.data.as_deref().unwrap_or_default(),
+            addr,
+            CELL_SIZE as usize,
+        )
+    }
+
+    /// Read a cell from the data region (address relative to DAT)
+    pub fn read_cell(&self, addr: Cell) -> AmxResult<Cell> {
+        let offset = self.get_addr(addr)?;
+        let data = self.data.as_deref().unwrap_or_default();
+
+        let mut bytes = [0u8; CELL_BYTES];
+        bytes.copy_from_slice(&data[offset..offset + CELL_BYTES]);
+        Ok(Cell::from_le_bytes(bytes))
+    }
+
+    /// Write a cell to the data region (address relative to DAT)
+    pub fn write_cell(&mut self, addr: Cell, value: Cell) -> AmxResult<()> {
+        let offset = self.get_addr(addr)?;
+        let data = self.data.as_deref_mut().unwrap_or_default();
+
+        data[offset..offset + CELL_BYTES].copy_from_slice(&value.to_le_bytes());
+        Ok(())
+    }
+
+    /// Read `count` consecutive cells starting at `addr`
+    ///
+    /// Fails without reading anything unless the whole range lies inside the
+    /// data region.
+    pub fn read_cells(&self, addr: Cell, count: usize) -> AmxResult<Vec<Cell>> {
+        let data = self.data.as_deref().unwrap_or_default();
+        let size = count
+            .checked_mul(CELL_BYTES)
+            .ok_or(AmxRuntimeError::InvalidMemoryAccess(addr as usize))?;
+        let offset = data_offset(data, addr, size)?;
+        Ok(data[offset..offset + size]
+            .chunks_exact(CELL_BYTES)
+            .map(|chunk| {
+                let mut bytes = [0u8; CELL_BYTES];
+                bytes.copy_from_slice(chunk);
+                Cell::from_le_bytes(bytes)
+            })
+            .collect())
+    }
+
+    /// Write `values` to consecutive cells starting at `addr`
+    ///
+    /// Fails without writing anything unless the whole range lies inside the
+    /// data region.
+    pub fn write_cells(&mut self, addr: Cell, values: &[Cell]) -> AmxResult<()> {
+        let data = self.data.as_deref_mut().unwrap_or_default();
+        let size = values
+            .len()
+            .checked_mul(CELL_BYTES)
+            .ok_or(AmxRuntimeError::InvalidMemoryAccess(addr as usize))?;
+        let offset = data_offset(data, addr, size)?;
+        for (index, value) in values.iter().enumerate() {
+            let start = offset + index * CELL_BYTES;
+            data[start..start + CELL_BYTES].copy_from_slice(&value.to_le_bytes());
+        }
+        Ok(())
+    }
+
+    /// Read character `index` of the packed string at `addr`
+    ///
+    /// Packed strings hold four characters per cell with the first character
+    /// in the most significant byte, as the reference compiler stores them.
+    pub fn packed_char(&self, addr: Cell, index: Cell) -> AmxResult<u8> {
+        let cell = self.read_cell(addr.wrapping_add(index / CELL_SIZE * CELL_SIZE))?;
+        let shift = (CELL_SIZE - 1 - index % CELL_SIZE) * 8;
+        Ok((cell >> shift) as u8)
+    }
+
+    /// Read the zero-terminated string at `addr`
+    ///
+    /// Packed strings are recognized by a first cell above the largest
+    /// unpacked character, as `amx_StrLen` does.
+    pub fn get_string(&self, addr: Cell) -> AmxResult<String> {
+        let mut text = String::new();
+        if self.read_cell(addr)? as UCell > UNPACKED_MAX {
+            for index in 0.. {
+                match self.packed_char(addr, index)? {
+                    0 => break,
+                    byte => text.push(byte as char),
+                }
+            }
+        } else {
+            for index in 0.. {
+                match self.read_cell(addr.wrapping_add(index * CELL_SIZE))? {
+                    0 => break,
+                    cell => text.push(char::from_u32(cell as u32).unwrap_or('\u{fffd}')),
+                }
+            }
+        }
+        Ok(text)
+    }
+
+    /// Write `s` as a zero-terminated string into the buffer at `addr`
+    ///
+    /// Unpacked strings hold one character per cell. Packed strings hold one
+    /// byte per character, so characters above U+00FF are written as `?`.
+    /// The string is truncated to fit `max_cells` cells including the
+    /// terminator. Returns the number of characters written.
+    pub fn set_string(
+        &mut self,
+        addr: Cell,
+        s: &str,
+        packed: bool,
+        max_cells: usize,
+    ) -> AmxResult<usize> {
+        let capacity = if packed {
+            max_cells.saturating_mul(CELL_SIZE as usize)
+        } else {
+            max_cells
+        };
+        if capacity == 0 {
+            return Ok(0);
+        }
+        let chars: Vec<char> = s.chars().take(capacity - 1).collect();
+
+        let cells: Vec<Cell> = if packed {
+            let bytes: Vec<u8> = chars
+                .iter()
+                .map(|&c| u8::try_from(c).unwrap_or(b'?'))
+                .collect();
+            let mut cells: Vec<Cell> = bytes
+                .chunks(CELL_SIZE as usize)
+                .map(|chunk| {
+                    let mut word = [0u8; CELL_SIZE as usize];
+                    word[..chunk.len()].copy_from_slice(chunk);
+                    Cell::from_be_bytes(word)
+                })
+                .collect();
+            // A full last cell leaves no room for the terminator byte
+            if bytes.len().is_multiple_of(CELL_SIZE as usize) {
+                cells.push(0);
+            }
+            cells
+        } else {
+            chars.iter().map(|&c| c as Cell).chain([0]).collect()
+        };
+
+        self.write_cells(addr, &cells)?;
+        Ok(chars.len())
+    }
+}
+
+/// Translate a data-relative address to an offset into the data region
+fn data_offset(data: &[u8], addr: Cell, size: usize) -> AmxResult<usize> {
+    let offset = addr as usize;
+    match offset.checked_add(size) {
+        Some(end) if addr >= 0 && end <= data.len() => Ok(offset),
+        _ => Err(AmxRuntimeError::InvalidMemoryAccess(offset)),
+    }
+}
+
+impl Default for AmxRuntime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,113 @@
+//! AMX file writer
+//!
+//! Lays a program out in pawncc's section order: the header, the publics,
+//! natives, libraries, public variables and tags tables, the name table,
+//! then the code and data sections. Table entries are `FuncStubNt` records
+//! whose names live in the name table. Code uses the reference opcode
+//! numbering with cell-sized operands, as `amx_Init` expects.
+
+use crate::header::*;
+use crate::types::*;
+
+/// Longest symbol name pawncc allows, stored at the start of the name table
+const NAME_MAX: u16 = 31;
+
+/// Contents of a program to be written as an AMX file
+#[derive(Debug, Clone, Default)]
+pub struct AmxProgram {
+    pub flags: AmxFlags,
+    /// Code section; addresses in it are relative to its start
+    pub code: Vec<u8>,
+    /// Initialized data section
+    pub data: Vec<u8>,
+    /// Bytes reserved for the heap and stack after the data
+    pub stack_heap_size: i32,
+    /// Code address of `main`
+    pub cip: Cell,
+    /// Public functions and their code addresses
+    pub publics: Vec<(String, UCell)>,
+    /// Natives, in the order SYSREQ operands refer to them
+    pub natives: Vec<String>,
+    pub libraries: Vec<String>,
+    /// Public variables and their data addresses
+    pub pubvars: Vec<(String, UCell)>,
+    /// Tag names and their identifiers
+    pub tags: Vec<(String, Cell)>,
+}
+
+/// Serialize a program to an AMX file
+///
+/// Publics and public variables are sorted by name, as pawncc sorts them.
+pub fn write_amx(program: &AmxProgram) -> Vec<u8> {
+    let mut publics = program.publics.clone();
+    publics.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut pubvars = program.pubvars.clone();
+    pubvars.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let natives: Vec<(String, UCell)> = program
+        .natives
+        .iter()
+        .map(|name| (name.clone(), 0))
+        .collect();
+    let libraries: Vec<(String, UCell)> = program
+        .libraries
+        .iter()
+        .map(|name| (name.clone(), 0))
+        .collect();
+    let tags: Vec<(String, UCell)> = program
+        .tags
+        .iter()
+        .map(|(name, id)| (name.clone(), *id as UCell))
+        .collect();
+    let tables = [&publics, &natives, &libraries, &pubvars, &tags];
+
+    let defsize = FUNCSTUB_NT_BYTES as i32;
+    let mut header = AmxHeader::new();
+    header.defsize = defsize as i16;
+    header.flags = program.flags.to_bits();
+
+    // Each table starts where the previous one ends
+    let mut offset = AMX_HEADER_BYTES as i32;
+    for (field, table) in [
+        &mut header.publics,
+        &mut header.natives,
+        &mut header.libraries,
+        &mut header.pubvars,
+        &mut header.tags,
+    ]
+    .into_iter()
+    .zip(tables)
+    {
+        *field = offset;
+        offset += table.len() as i32 * defsize;
+    }
+    header.nametable = offset;
+
+    let names_size: usize = tables
+        .iter()
+        .flat_map(|table| table.iter())
+        .map(|(name, _)| name.len() + 1)
+        .sum();
+    header.cod = header.nametable + (std::mem::size_of::<u16>() + names_size) as i32;
+    header.dat = header.cod + program.code.len() as i32;
+    header.hea = header.dat + program.data.len() as i32;
+    header.stp = header.hea + program.stack_heap_size;
+    header.cip = program.cip as _;
+    header.size = header.hea;
+
+    let mut bytes = write_header(&header);
+    let mut names = NAME_MAX.to_le_bytes().to_vec();
+    let mut name_offset = header.nametable as u32 + std::mem::size_of::<u16>() as u32;
+    for (name, address) in tables.iter().flat_map(|table| table.iter()) {
+        // Table entries keep a 32-bit address whatever the cell size
+        bytes.extend_from_slice(&address.to_le_bytes()[..4]);
+        bytes.extend_from_slice(&name_offset.to_le_bytes());
+        names.extend_from_slice(name.as_bytes());
+        names.push(0);
+        name_offset += name.len() as u32 + 1;
+    }
+    bytes.extend_from_slice(&names);
+    bytes.extend_from_slice(&program.code);
+    bytes.extend_from_slice(&program.data);
+    bytes
+}
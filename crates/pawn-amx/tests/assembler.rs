@@ -0,0 +1,174 @@
+use pawn_amx::instructions::Opcode;
+use pawn_amx::{AMX_EXEC_MAIN, AmxError, AmxRuntime, AmxRuntimeError, CELL_BYTES, Cell, assemble};
+
+fn run(text: &str) -> AmxRuntime {
+    let bytecode = assemble(text).expect("assembly should succeed");
+    let mut runtime = AmxRuntime::new();
+    runtime
+        .init(&bytecode)
+        .expect("runtime init should succeed");
+    runtime.exec(AMX_EXEC_MAIN).expect("exec should succeed");
+    runtime
+}
+
+#[test]
+fn assembles_straight_line_program() {
+    let runtime = run("const.pri 20; const.alt 22\nadd\nhalt");
+    assert_eq!(runtime.amx.pri, 42);
+    assert_eq!(runtime.amx.alt, 22);
+}
+
+#[test]
+fn resolves_forward_and_backward_labels() {
+    let counter = -2 * CELL_BYTES as i32;
+    let total = -(CELL_BYTES as i32);
+    let runtime = run(&format!(
+        r#"
+        # Sum 10 five times, keeping the counter and total in the frame
+            stack {counter}
+            const.pri 5
+            stor.s.pri {counter}
+            const.pri 0
+            stor.s.pri {total}
+        loop:
+            load.s.pri {total}
+            const.alt 10
+            add
+            stor.s.pri {total}
+            load.s.pri {counter}
+            const.alt 1
+            sub
+            stor.s.pri {counter}
+            jnz loop
+            jump done
+            const.pri -1
+        done: load.s.pri {total}
+            halt
+    "#
+    ));
+    assert_eq!(runtime.amx.pri, 50);
+}
+
+#[test]
+fn reports_unknown_mnemonic_with_line_number() {
+    let error = assemble("const.pri 1\n\nfrobnicate 2\nhalt").unwrap_err();
+    assert!(matches!(
+        error,
+        AmxRuntimeError::AssemblyError { line: 3, ref message } if message.contains("frobnicate")
+    ));
+}
+
+#[test]
+fn reports_undefined_label() {
+    let error = assemble("jump nowhere\nhalt").unwrap_err();
+    assert!(matches!(
+        error,
+        AmxRuntimeError::AssemblyError { line: 1, .. }
+    ));
+}
+
+#[test]
+fn register_moves_and_exchange() {
+    let runtime = run("const.pri 3; const.alt 7; xchg; halt");
+    assert_eq!((runtime.amx.pri, runtime.amx.alt), (7, 3));
+
+    let runtime = run("const.pri 3; const.alt 7; move.pri; halt");
+    assert_eq!((runtime.amx.pri, runtime.amx.alt), (7, 7));
+
+    let runtime = run("const.pri 3; const.alt 7; move.alt; halt");
+    assert_eq!((runtime.amx.pri, runtime.amx.alt), (3, 3));
+}
+
+#[test]
+fn lctrl_reads_special_registers() {
+    // Index 3 is STP in the standard control-register numbering
+    let runtime = run("lctrl 3; halt");
+    assert_eq!(runtime.amx.pri, runtime.amx.stp);
+
+    let runtime = run("lctrl 2; halt");
+    assert_eq!(runtime.amx.pri, runtime.amx.hea);
+}
+
+#[test]
+fn sctrl_writes_registers_and_rejects_read_only_ones() {
+    let runtime = run("const.pri 64; sctrl 5; halt");
+    assert_eq!(runtime.amx.frm, 64);
+
+    let bytecode = assemble("const.pri 64; sctrl 3; halt").unwrap();
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+    let error = runtime.exec(AMX_EXEC_MAIN).unwrap_err();
+    assert_eq!(AmxError::from(error), AmxError::InvInstr);
+}
+
+#[test]
+fn retn_pops_arguments_and_restores_callers_stack() {
+    let baseline = run("halt").amx.stk;
+    let cell = CELL_BYTES;
+    let (args, first, second) = (2 * cell, 3 * cell, 4 * cell);
+    let runtime = run(&format!(
+        r#"
+            push.c 22
+            push.c 20
+            push.c {args}
+            call sum
+            halt
+        # Above the frame: saved FRM, return address, argument size, then
+        # the arguments first to last
+        sum:
+            proc
+            load.s.pri {first}
+            load.s.alt {second}
+            add
+            retn
+    "#
+    ));
+    assert_eq!(runtime.amx.pri, 42);
+    assert_eq!(runtime.amx.stk, baseline);
+}
+
+#[test]
+fn strict_mode_traps_unimplemented_opcodes() {
+    let bytecode = assemble("const.pri 7; lref.s.alt 0; halt").unwrap();
+
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+    assert_eq!(runtime.exec(AMX_EXEC_MAIN).unwrap(), 7);
+    assert_eq!(runtime.unsupported_opcodes(), vec![Opcode::LrefSAlt]);
+
+    runtime.init(&bytecode).unwrap();
+    assert!(runtime.unsupported_opcodes().is_empty());
+    runtime.set_strict_opcodes(true);
+    let error = runtime.exec(AMX_EXEC_MAIN).unwrap_err();
+    assert_eq!(AmxError::from(error), AmxError::InvInstr);
+    assert_eq!(runtime.unsupported_opcodes(), vec![Opcode::LrefSAlt]);
+}
+
+#[test]
+fn compare_jumps_branch_only_when_the_comparison_holds() {
+    let cases = [
+        ("jeq", 3, 3, true),
+        ("jeq", 3, 4, false),
+        ("jneq", 3, 4, true),
+        ("jneq", 3, 3, false),
+        ("jsless", -1, 1, true),
+        ("jsless", 1, 1, false),
+        ("jsleq", 1, 1, true),
+        ("jsleq", 2, 1, false),
+        ("jsgrtr", 1, -1, true),
+        ("jsgrtr", 1, 1, false),
+        ("jsgeq", 1, 1, true),
+        ("jsgeq", -1, 1, false),
+        ("jless", 1, -1, true),
+        ("jless", -1, 1, false),
+        ("jgeq", -1, 1, true),
+        ("jgeq", 1, -1, false),
+    ];
+    for (jump, pri, alt, taken) in cases {
+        let runtime = run(&format!(
+            "const.pri {}\nconst.alt {}\n{} yes\nconst.pri 0\nhalt\nyes: const.pri 1\nhalt",
+            pri, alt, jump
+        ));
+        assert_eq!(runtime.amx.pri, taken as Cell, "{} {} {}", jump, pri, alt);
+    }
+}
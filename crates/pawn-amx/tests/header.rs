@@ -0,0 +1,171 @@
+use pawn_amx::instructions::Instruction;
+use pawn_amx::{
+    AMX_HEADER_BYTES, AMX_MAGIC, AMX_MAGIC_32, AMX_MAGIC_64, AmxError, AmxHeader, AmxProgram,
+    AmxRuntime, FUNCSTUB_BYTES, FUNCSTUB_NT_BYTES, read_header, try_read_header, write_amx,
+    write_header,
+};
+
+const HEADER_SIZE: i32 = AMX_HEADER_BYTES as i32;
+
+fn minimal_file() -> Vec<u8> {
+    let mut header = AmxHeader::new();
+    header.cod = HEADER_SIZE;
+    header.dat = HEADER_SIZE + Instruction::halt().size() as i32;
+    header.hea = header.dat;
+    header.stp = header.hea + 64;
+    header.cip = 0;
+    header.size = header.dat;
+
+    let mut bytes = write_header(&header);
+    bytes.extend_from_slice(&Instruction::halt().to_bytes());
+    bytes
+}
+
+#[test]
+fn accepts_consistent_header() {
+    let bytes = minimal_file();
+    let header = read_header(&bytes).expect("header should be valid");
+    assert_eq!(header.size as usize, bytes.len());
+    assert_eq!(write_header(&header).len(), AMX_HEADER_BYTES);
+}
+
+#[test]
+fn rejects_file_for_another_cell_size() {
+    let other = if AMX_MAGIC == AMX_MAGIC_32 {
+        AMX_MAGIC_64
+    } else {
+        AMX_MAGIC_32
+    };
+    let mut bytes = minimal_file();
+    bytes[4..6].copy_from_slice(&other.to_le_bytes());
+    assert_eq!(read_header(&bytes).unwrap_err(), AmxError::Version);
+
+    let mut runtime = AmxRuntime::new();
+    let error = runtime.init(&bytes).unwrap_err();
+    assert_eq!(AmxError::from(error), AmxError::Version);
+
+    // An unknown magic is not an AMX file at all
+    bytes[4..6].copy_from_slice(&0x1234u16.to_le_bytes());
+    assert_eq!(read_header(&bytes).unwrap_err(), AmxError::Format);
+}
+
+#[test]
+fn rejects_truncated_buffer() {
+    let bytes = minimal_file();
+    let truncated = &bytes[..bytes.len() - 2];
+    assert_eq!(read_header(truncated).unwrap_err(), AmxError::Format);
+
+    let mut runtime = AmxRuntime::new();
+    assert!(runtime.init(truncated).is_err());
+}
+
+#[test]
+fn rejects_table_offset_past_eof() {
+    let mut bytes = minimal_file();
+    let mut header = read_header(&bytes).unwrap();
+    header.defsize = 8;
+    header.publics = header.size + 100;
+    bytes[..HEADER_SIZE as usize].copy_from_slice(&write_header(&header));
+
+    assert_eq!(read_header(&bytes).unwrap_err(), AmxError::Format);
+
+    let mut runtime = AmxRuntime::new();
+    assert!(runtime.init(&bytes).is_err());
+}
+
+#[test]
+fn rejects_unordered_sections() {
+    let mut bytes = minimal_file();
+    let mut header = read_header(&bytes).unwrap();
+    header.dat = header.cod - 1;
+    bytes[..HEADER_SIZE as usize].copy_from_slice(&write_header(&header));
+
+    assert_eq!(read_header(&bytes).unwrap_err(), AmxError::Format);
+}
+
+/// Header whose publics table holds `count` records of `defsize` bytes
+fn header_with_publics(defsize: usize, count: usize) -> AmxHeader {
+    let mut header = AmxHeader::new();
+    header.defsize = defsize as i16;
+    header.publics = HEADER_SIZE;
+    header.natives = HEADER_SIZE + (count * defsize) as i32;
+    header
+}
+
+#[test]
+fn name_table_format_is_detected_from_defsize() {
+    let header = header_with_publics(FUNCSTUB_NT_BYTES, 3);
+    assert!(header.uses_name_table());
+    assert_eq!(header.num_entries(header.publics, header.natives), 3);
+
+    let header = header_with_publics(FUNCSTUB_BYTES, 2);
+    assert!(!header.uses_name_table());
+    assert_eq!(header.num_entries(header.publics, header.natives), 2);
+
+    let mut entry = 0x40u32.to_le_bytes().to_vec();
+    entry.extend_from_slice(b"main\0");
+    entry.resize(FUNCSTUB_BYTES, 0);
+    assert_eq!(header.get_entry_name(&[], &entry), Some("main"));
+
+    // A missing table size must not divide by zero
+    let header = header_with_publics(0, 0);
+    assert_eq!(header.num_entries(header.publics, header.natives), 0);
+}
+
+#[test]
+fn header_round_trips_through_write_and_read() {
+    let bytes = write_amx(&AmxProgram {
+        code: Instruction::halt().to_bytes(),
+        publics: vec![("main".to_string(), 0)],
+        natives: vec!["printf".to_string()],
+        ..AmxProgram::default()
+    });
+    let header = read_header(&bytes).unwrap();
+    assert_eq!(header.defsize as usize, FUNCSTUB_NT_BYTES);
+
+    let mut rewritten = write_header(&header);
+    rewritten.extend_from_slice(&bytes[rewritten.len()..]);
+    assert_eq!(rewritten, bytes);
+    assert_eq!(header.num_entries(header.publics, header.natives), 1);
+    assert_eq!(header.num_entries(header.natives, header.libraries), 1);
+}
+
+#[test]
+fn malformed_files_are_rejected_without_panicking() {
+    let valid = write_amx(&AmxProgram {
+        code: Instruction::halt().to_bytes(),
+        publics: vec![("main".to_string(), 0)],
+        natives: vec!["printf".to_string()],
+        ..AmxProgram::default()
+    });
+    let header = read_header(&valid).unwrap();
+    let mut buffers: Vec<Vec<u8>> = (0..valid.len()).map(|len| valid[..len].to_vec()).collect();
+
+    // A name offset pointing past the end of the file
+    let mut bad_name = valid.clone();
+    let entry = header.publics as usize;
+    bad_name[entry + 4..entry + 8].copy_from_slice(&u32::MAX.to_le_bytes());
+    buffers.push(bad_name);
+
+    // Pseudo-random bytes behind a header that passes the magic and version
+    // checks, so the table offsets are what gets exercised
+    let mut state = 0x2545_f491_u32;
+    for _ in 0..256 {
+        let mut bytes: Vec<u8> = (0..valid.len())
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect();
+        bytes[..8].copy_from_slice(&valid[..8]);
+        buffers.push(bytes);
+    }
+
+    for bytes in &buffers {
+        assert!(try_read_header(bytes).is_err(), "{:?}", bytes);
+        assert!(AmxRuntime::new().init(bytes).is_err(), "{:?}", bytes);
+    }
+    assert!(AmxRuntime::new().init(&valid).is_ok());
+}
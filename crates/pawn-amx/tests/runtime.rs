@@ -0,0 +1,867 @@
+use pawn_amx::instructions::{Instruction, Opcode};
+use pawn_amx::{
+    AMX_EXEC_CONT, AMX_EXEC_MAIN, AMX_HEADER_BYTES, AmxError, AmxFlags, AmxHeader, AmxProgram,
+    AmxRuntime, AmxRuntimeError, CELL_BYTES, Cell, OverflowMode, STKMARGIN, assemble, write_amx,
+    write_header,
+};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const HEADER_SIZE: i32 = AMX_HEADER_BYTES as i32;
+const CELL: Cell = CELL_BYTES as Cell;
+
+/// Lay out cells as a data section
+fn cells(values: &[Cell]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+/// Size in bytes of a run of instructions, which is also the code address
+/// of the instruction following them
+fn code_size(code: &[Instruction]) -> i32 {
+    code.iter().map(Instruction::size).sum::<usize>() as i32
+}
+
+/// Build a minimal AMX file from a list of instructions and initial data
+fn program(code: &[Instruction], data: &[u8]) -> Vec<u8> {
+    let mut header = AmxHeader::new();
+    header.cod = HEADER_SIZE;
+    header.dat = header.cod + code_size(code);
+    header.hea = header.dat + data.len() as i32;
+    header.stp = header.hea + 1024;
+    header.cip = 0;
+    header.size = header.hea;
+
+    let mut bytes = write_header(&header);
+    for instruction in code {
+        bytes.extend_from_slice(&instruction.to_bytes());
+    }
+    bytes.extend_from_slice(data);
+    bytes
+}
+
+#[test]
+fn code_is_kept_separate_from_data() {
+    let bytecode = program(
+        &[
+            Instruction::new(Opcode::ConstPri, 20),
+            Instruction::new(Opcode::PushPri, 0),
+            Instruction::new(Opcode::ConstPri, 22),
+            Instruction::new(Opcode::PopAlt, 0),
+            Instruction::new(Opcode::Add, 0),
+            Instruction::new(Opcode::Halt, 0),
+        ],
+        &[1, 0, 0, 0],
+    );
+
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+
+    let data = runtime.amx.data.as_ref().expect("data region allocated");
+    assert_eq!(&data[..4], &[1, 0, 0, 0]);
+    assert_eq!(runtime.amx.stp as usize, data.len());
+
+    assert_eq!(runtime.exec(AMX_EXEC_MAIN).unwrap(), 42);
+    assert_eq!(runtime.amx.base, bytecode);
+}
+
+#[test]
+fn init_rejects_headers_asking_for_too_much_memory() {
+    let mut bytecode = program(&[Instruction::new(Opcode::Halt, 0)], &[]);
+    let mut header = AmxHeader::new();
+    header.cod = HEADER_SIZE;
+    header.dat = HEADER_SIZE + CELL_BYTES as i32 * 2;
+    header.hea = header.dat;
+    header.stp = i32::MAX;
+    header.size = header.hea;
+    bytecode.splice(..AMX_HEADER_BYTES, write_header(&header));
+
+    let mut runtime = AmxRuntime::new();
+    assert!(matches!(
+        runtime.init(&bytecode),
+        Err(AmxRuntimeError::InvalidFormat)
+    ));
+
+    header.stp = header.hea + 1024;
+    bytecode.splice(..AMX_HEADER_BYTES, write_header(&header));
+    runtime.set_max_memory(512);
+    assert!(matches!(
+        runtime.init(&bytecode),
+        Err(AmxRuntimeError::InvalidFormat)
+    ));
+    runtime.set_max_memory(1024);
+    runtime.init(&bytecode).unwrap();
+}
+
+#[test]
+fn executes_twice_after_resetting_registers() {
+    let bytecode = program(
+        &[
+            Instruction::new(Opcode::ConstPri, 5),
+            Instruction::new(Opcode::PushPri, 0),
+            Instruction::new(Opcode::ConstPri, 7),
+            Instruction::new(Opcode::PopAlt, 0),
+            Instruction::new(Opcode::Smul, 0),
+            Instruction::new(Opcode::Halt, 0),
+        ],
+        &[],
+    );
+
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+    let first = runtime.exec(AMX_EXEC_MAIN).unwrap();
+
+    runtime.amx.stk = runtime.amx.reset_stk;
+    runtime.amx.frm = runtime.amx.reset_stk;
+    runtime.amx.hea = runtime.amx.reset_hea;
+    let second = runtime.exec(AMX_EXEC_MAIN).unwrap();
+
+    assert_eq!(first, 35);
+    assert_eq!(first, second);
+}
+
+/// Build an AMX file exposing a single public variable at data address 0
+fn program_with_pubvar(name: &str, code: &[Instruction], initial: Cell) -> Vec<u8> {
+    let pubvars = HEADER_SIZE;
+    let nametable = pubvars + 8;
+    let cod = nametable + name.len() as i32 + 1;
+
+    let mut header = AmxHeader::new();
+    header.defsize = 8;
+    header.pubvars = pubvars;
+    header.tags = nametable;
+    header.nametable = nametable;
+    header.cod = cod;
+    header.dat = cod + code_size(code);
+    header.hea = header.dat + CELL_BYTES as i32;
+    header.stp = header.hea + 1024;
+    header.cip = 0;
+    header.size = header.hea;
+
+    let mut bytes = write_header(&header);
+    bytes.extend_from_slice(&0u32.to_le_bytes());
+    bytes.extend_from_slice(&(nametable as u32).to_le_bytes());
+    bytes.extend_from_slice(name.as_bytes());
+    bytes.push(0);
+    for instruction in code {
+        bytes.extend_from_slice(&instruction.to_bytes());
+    }
+    bytes.extend_from_slice(&initial.to_le_bytes());
+    bytes
+}
+
+/// Increments the public variable `g_Counter`, which starts at 10, and
+/// returns its new value
+fn counter_program() -> Vec<u8> {
+    program_with_pubvar(
+        "g_Counter",
+        &[
+            Instruction::new(Opcode::LoadPri, 0),
+            Instruction::new(Opcode::ConstAlt, 1),
+            Instruction::new(Opcode::Add, 0),
+            Instruction::new(Opcode::StorPri, 0),
+            Instruction::new(Opcode::Halt, 0),
+        ],
+        10,
+    )
+}
+
+#[test]
+fn reset_restores_initial_state() {
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&counter_program()).unwrap();
+    let initial = runtime.snapshot();
+
+    let first = runtime.exec(AMX_EXEC_MAIN).unwrap();
+    assert_eq!(first, 11);
+    runtime.set_pubvar("g_Counter", 40).unwrap();
+    assert_eq!(runtime.exec(AMX_EXEC_MAIN).unwrap(), 41);
+
+    runtime.reset();
+    assert_eq!(runtime.get_pubvar("g_Counter").unwrap(), 10);
+    assert_eq!(runtime.amx.data.as_ref().unwrap(), &initial.data);
+    assert_eq!(runtime.amx.pri, 0);
+    assert_eq!(runtime.exec(AMX_EXEC_MAIN).unwrap(), first);
+}
+
+#[test]
+fn snapshot_and_restore_round_trip() {
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&counter_program()).unwrap();
+
+    assert_eq!(runtime.exec(AMX_EXEC_MAIN).unwrap(), 11);
+    let checkpoint = runtime.snapshot();
+    assert_eq!(runtime.exec(AMX_EXEC_MAIN).unwrap(), 12);
+    assert_eq!(runtime.exec(AMX_EXEC_MAIN).unwrap(), 13);
+
+    runtime.restore(&checkpoint);
+    assert_eq!(runtime.amx.pri, 11);
+    assert_eq!(runtime.get_pubvar("g_Counter").unwrap(), 11);
+    assert_eq!(runtime.exec(AMX_EXEC_MAIN).unwrap(), 12);
+}
+
+#[test]
+fn host_injects_public_variable() {
+    let name = "g_MaxPlayers";
+    // Returns 1 when g_MaxPlayers is non-zero, 2 otherwise; every
+    // instruction takes an operand, so each is two cells long
+    let code = [
+        Instruction::new(Opcode::LoadPri, 0),
+        Instruction::new(Opcode::Jzer, 4 * 2 * CELL),
+        Instruction::new(Opcode::ConstPri, 1),
+        Instruction::new(Opcode::Halt, 0),
+        Instruction::new(Opcode::ConstPri, 2),
+        Instruction::new(Opcode::Halt, 0),
+    ];
+    let bytecode = program_with_pubvar(name, &code, 0);
+
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+    assert_eq!(runtime.get_pubvar(name).unwrap(), 0);
+    assert_eq!(runtime.exec(AMX_EXEC_MAIN).unwrap(), 2);
+
+    runtime.set_pubvar(name, 32).unwrap();
+    assert_eq!(runtime.get_pubvar(name).unwrap(), 32);
+    assert_eq!(runtime.exec(AMX_EXEC_MAIN).unwrap(), 1);
+
+    assert!(matches!(
+        runtime.get_pubvar("g_Missing"),
+        Err(AmxRuntimeError::PubVarNotFound(_))
+    ));
+    assert!(runtime.set_pubvar("g_Missing", 1).is_err());
+}
+
+#[test]
+fn forward_jump_skips_instruction() {
+    let bytecode = program(
+        &[
+            Instruction::new(Opcode::ConstPri, 1),
+            Instruction::new(Opcode::Jump, 3 * 2 * CELL),
+            Instruction::new(Opcode::ConstPri, 99),
+            Instruction::new(Opcode::Halt, 0),
+        ],
+        &[],
+    );
+
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+    assert_eq!(runtime.exec(AMX_EXEC_MAIN).unwrap(), 1);
+}
+
+#[test]
+fn indirect_call_returns_to_caller() {
+    let bytecode = program(
+        &[
+            // The subroutine follows seven cells of code
+            Instruction::new(Opcode::ConstPri, 7 * CELL),
+            Instruction::new(Opcode::CallI, 0),
+            Instruction::new(Opcode::ConstAlt, 1),
+            Instruction::new(Opcode::Halt, 0),
+            // subroutine
+            Instruction::new(Opcode::ConstPri, 41),
+            Instruction::new(Opcode::Ret, 0),
+        ],
+        &[],
+    );
+
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+    assert_eq!(runtime.exec(AMX_EXEC_MAIN).unwrap(), 41);
+    assert_eq!(runtime.amx.alt, 1);
+}
+
+#[test]
+fn bounds_rejects_negative_and_too_large_indices() {
+    let bounds_check = |index: Cell| {
+        let bytecode = program(
+            &[
+                Instruction::new(Opcode::ConstPri, index),
+                Instruction::new(Opcode::Bounds, 4),
+                Instruction::new(Opcode::Halt, 0),
+            ],
+            &[],
+        );
+        let mut runtime = AmxRuntime::new();
+        runtime.init(&bytecode).unwrap();
+        runtime.exec(AMX_EXEC_MAIN)
+    };
+
+    assert_eq!(bounds_check(4).unwrap(), 4);
+    assert!(matches!(
+        bounds_check(5).unwrap_err().kind(),
+        AmxRuntimeError::ArrayBounds
+    ));
+    assert!(matches!(
+        bounds_check(-1).unwrap_err().kind(),
+        AmxRuntimeError::ArrayBounds
+    ));
+}
+
+#[test]
+fn byte_access_reads_and_writes_single_bytes() {
+    let run = |text: &str| {
+        let mut runtime = AmxRuntime::new();
+        runtime.init(&assemble(text).unwrap()).unwrap();
+        runtime.exec(AMX_EXEC_MAIN)
+    };
+    let store = "const.pri 0x11223344; const.alt 0; stor.i";
+
+    assert_eq!(
+        run(&format!("{store}; const.pri 0; lodb.i 1; halt")).unwrap(),
+        0x44
+    );
+    assert_eq!(
+        run(&format!("{store}; const.pri 2; lodb.i 2; halt")).unwrap(),
+        0x1122
+    );
+    assert_eq!(
+        run(&format!(
+            "{store}; const.pri 0xAB; const.alt 1; strb.i 1; const.pri 0; load.i; halt"
+        ))
+        .unwrap(),
+        0x1122AB44
+    );
+    assert!(matches!(
+        run(&format!("{store}; const.pri 0; lodb.i 3; halt"))
+            .unwrap_err()
+            .kind(),
+        AmxRuntimeError::InvalidInstruction(_)
+    ));
+}
+
+#[test]
+fn sdiv_floors_the_quotient_and_matches_the_remainder() {
+    let run = |text: &str| {
+        let mut runtime = AmxRuntime::new();
+        runtime.init(&assemble(text).unwrap()).unwrap();
+        runtime.exec(AMX_EXEC_MAIN)
+    };
+    let divide = |dividend: Cell, divisor: Cell| {
+        let text = format!("const.pri {dividend}; const.alt {divisor}; sdiv");
+        (
+            run(&format!("{text}; halt")).unwrap(),
+            run(&format!("{text}; move.pri; halt")).unwrap(),
+        )
+    };
+
+    assert_eq!(divide(7, 2), (3, 1));
+    assert_eq!(divide(-7, 2), (-4, 1));
+    assert_eq!(divide(7, -2), (-4, -1));
+    assert_eq!(divide(-7, -2), (3, -1));
+    assert_eq!(divide(-8, 4), (-2, 0));
+    assert!(matches!(
+        run("const.pri 1; const.alt 0; sdiv; halt")
+            .unwrap_err()
+            .kind(),
+        AmxRuntimeError::AmxError(AmxError::Divide)
+    ));
+}
+
+#[test]
+fn stack_reserves_frame_and_zero_clears_it() {
+    let run = |text: &str| {
+        let mut runtime = AmxRuntime::new();
+        runtime.init(&assemble(text).unwrap()).unwrap();
+        runtime.exec(AMX_EXEC_MAIN)
+    };
+
+    assert_eq!(run("const.pri 5; zero.pri; halt").unwrap(), 0);
+    assert_eq!(
+        run(&format!(
+            "stack {}; const.pri 5; stor.s.pri {local}; zero.s {local}; load.s.pri {local}; halt",
+            -2 * CELL,
+            local = -CELL
+        ))
+        .unwrap(),
+        0
+    );
+    assert!(matches!(
+        run(&format!("stack {CELL}; halt")).unwrap_err().kind(),
+        AmxRuntimeError::StackUnderflow
+    ));
+    // The whole stack would leave no margin for pushes
+    assert!(matches!(
+        run("stack -4096; halt").unwrap_err().kind(),
+        AmxRuntimeError::StackOverflow
+    ));
+}
+
+#[test]
+fn runaway_recursion_stops_before_the_heap() {
+    let bytecode = assemble(
+        "    push.c 0
+             call deeper
+             halt
+         deeper:
+             proc
+             push.c 0
+             call deeper
+             retn",
+    )
+    .unwrap();
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+
+    let error = runtime.exec(AMX_EXEC_MAIN).unwrap_err();
+    assert!(matches!(error.kind(), AmxRuntimeError::StackOverflow));
+    assert_eq!(AmxError::from(error), AmxError::StackErr);
+    assert_eq!(runtime.amx.error, AmxError::StackErr as i32);
+    // The stack grew down from the top and kept its margin above the heap
+    assert!(runtime.amx.stk < runtime.amx.stp);
+    assert!(runtime.amx.stk - STKMARGIN >= runtime.amx.hea);
+}
+
+#[test]
+fn load_addresses_data_and_load_s_addresses_the_frame() {
+    // A global at data address 0 and a local just below the frame
+    let bytecode = program(
+        &[
+            Instruction::new(Opcode::Stack, -CELL),
+            Instruction::new(Opcode::ConstPri, 9),
+            Instruction::new(Opcode::StorSPri, -CELL),
+            Instruction::new(Opcode::LoadPri, 0),
+            Instruction::new(Opcode::LoadSAlt, -CELL),
+            Instruction::new(Opcode::Halt, 0),
+        ],
+        &cells(&[5]),
+    );
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+
+    assert_eq!(runtime.exec(AMX_EXEC_MAIN).unwrap(), 5);
+    assert_eq!(runtime.amx.alt, 9);
+    assert_eq!(runtime.read_cell_at(runtime.amx.frm - CELL).unwrap(), 9);
+    assert_eq!(runtime.read_cell_at(0).unwrap(), 5);
+}
+
+#[test]
+fn sleep_yields_to_host_and_resumes() {
+    let bytecode = assemble(
+        "const.pri 7; push.pri; halt 12
+         pop.alt; const.pri 35; add; halt",
+    )
+    .unwrap();
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+
+    assert!(matches!(
+        runtime.exec(AMX_EXEC_MAIN),
+        Err(AmxRuntimeError::Sleep(7))
+    ));
+    assert_eq!(runtime.exec(AMX_EXEC_CONT).unwrap(), 42);
+}
+
+/// Call native 0 with the given arguments and return its result
+fn call_native(runtime: &mut AmxRuntime, args: &[Cell]) -> Result<Cell, AmxRuntimeError> {
+    let mut text = String::new();
+    for arg in args.iter().rev() {
+        text.push_str(&format!("const.pri {arg}; push.pri; "));
+    }
+    let size = args.len() as Cell * CELL;
+    text.push_str(&format!(
+        "const.pri {size}; push.pri; sysreq 0; stack {}; halt",
+        size + CELL
+    ));
+
+    runtime.init(&assemble(&text).unwrap()).unwrap();
+    runtime.exec(AMX_EXEC_MAIN)
+}
+
+#[test]
+fn fixed_arity_native_rejects_wrong_argument_count() {
+    let mut runtime = AmxRuntime::new();
+    runtime.register_native_typed("sub".to_string(), 2, |_amx, params| params[1] - params[2]);
+
+    assert_eq!(call_native(&mut runtime, &[10, 3]).unwrap(), 7);
+    assert!(matches!(
+        call_native(&mut runtime, &[10]).unwrap_err().kind(),
+        AmxRuntimeError::ParameterError(_)
+    ));
+}
+
+#[test]
+fn variadic_native_accepts_any_argument_count() {
+    let mut runtime = AmxRuntime::new();
+    runtime.register_native("count".to_string(), |_amx, params| params[0] / CELL);
+
+    assert_eq!(call_native(&mut runtime, &[]).unwrap(), 0);
+    assert_eq!(call_native(&mut runtime, &[1, 2, 3]).unwrap(), 3);
+}
+
+#[test]
+fn closure_native_updates_host_state() {
+    let counter = Rc::new(RefCell::new(0));
+    let mut runtime = AmxRuntime::new();
+    let captured = Rc::clone(&counter);
+    runtime.register_native_closure("tick".to_string(), move |_amx, params| {
+        *captured.borrow_mut() += params[1];
+        *captured.borrow()
+    });
+
+    assert_eq!(call_native(&mut runtime, &[2]).unwrap(), 2);
+    assert_eq!(call_native(&mut runtime, &[3]).unwrap(), 5);
+    assert_eq!(*counter.borrow(), 5);
+}
+
+#[test]
+fn native_writes_back_through_reference() {
+    let bytecode = assemble(&format!(
+        "const.pri 41; push.pri
+         addr.pri {}; push.pri; const.pri {CELL}; push.pri
+         sysreq 0; stack {}
+         pop.pri; halt",
+        -CELL,
+        2 * CELL
+    ))
+    .unwrap();
+    let mut runtime = AmxRuntime::new();
+    runtime.register_native_typed("increment".to_string(), 1, |amx, params| {
+        match amx.read_cell(params[1]) {
+            Ok(value) => amx.write_cell(params[1], value + 1).map_or(0, |_| 1),
+            Err(_) => 0,
+        }
+    });
+    runtime.init(&bytecode).unwrap();
+
+    assert_eq!(runtime.exec(AMX_EXEC_MAIN).unwrap(), 42);
+
+    let local = runtime.amx.frm - CELL;
+    assert_eq!(runtime.get_addr(local).unwrap(), local as usize);
+    runtime.write_cell_at(local, 7).unwrap();
+    assert_eq!(runtime.read_cell_at(local).unwrap(), 7);
+    assert!(matches!(
+        runtime.get_addr(-CELL),
+        Err(AmxRuntimeError::InvalidMemoryAccess(_))
+    ));
+}
+
+/// `strcpy(dest[], const src[], size)` built on the string helpers
+fn strcpy(amx: &mut pawn_amx::Amx, params: &[Cell]) -> Cell {
+    match amx.get_string(params[2]) {
+        Ok(source) => amx
+            .set_string(params[1], &source, false, params[3] as usize)
+            .map_or(-1, |written| written as Cell),
+        Err(_) => -1,
+    }
+}
+
+/// Address of the buffer `run_strcpy` copies into, just past "hello"
+const BUFFER: Cell = 6 * CELL;
+
+/// A buffer cell `run_strcpy` did not write to
+const SENTINEL: Cell = Cell::from_le_bytes([0x7f; CELL_BYTES]);
+
+/// Copy the unpacked string "hello" into an 8-cell buffer with `size` cells
+fn run_strcpy(size: Cell) -> (Cell, AmxRuntime) {
+    // "hello" at address 0, followed by a buffer filled with a sentinel
+    let mut data: Vec<u8> = "hello\0"
+        .bytes()
+        .flat_map(|byte| (byte as Cell).to_le_bytes())
+        .collect();
+    data.extend(std::iter::repeat_n(0x7f, 8 * CELL_BYTES));
+
+    let bytecode = program(
+        &[
+            Instruction::new(Opcode::ConstPri, size),
+            Instruction::new(Opcode::PushPri, 0),
+            Instruction::new(Opcode::ConstPri, 0),
+            Instruction::new(Opcode::PushPri, 0),
+            Instruction::new(Opcode::ConstPri, BUFFER),
+            Instruction::new(Opcode::PushPri, 0),
+            Instruction::new(Opcode::ConstPri, 3 * CELL),
+            Instruction::new(Opcode::PushPri, 0),
+            Instruction::new(Opcode::Sysreq, 0),
+            Instruction::new(Opcode::Stack, 4 * CELL),
+            Instruction::new(Opcode::Halt, 0),
+        ],
+        &data,
+    );
+    let mut runtime = AmxRuntime::new();
+    runtime.register_native_typed("strcpy".to_string(), 3, strcpy);
+    runtime.init(&bytecode).unwrap();
+    (runtime.exec(AMX_EXEC_MAIN).unwrap(), runtime)
+}
+
+#[test]
+fn native_copies_string_into_caller_buffer() {
+    let (written, runtime) = run_strcpy(8);
+    assert_eq!(written, 5);
+    assert_eq!(runtime.get_string(BUFFER).unwrap(), "hello");
+    // Cells past the terminator are left alone
+    assert_eq!(runtime.read_cell_at(BUFFER + 6 * CELL).unwrap(), SENTINEL);
+}
+
+#[test]
+fn native_string_copy_truncates_to_buffer_size() {
+    let (written, runtime) = run_strcpy(3);
+    assert_eq!(written, 2);
+    assert_eq!(runtime.get_string(BUFFER).unwrap(), "he");
+    assert_eq!(runtime.read_cell_at(BUFFER + 3 * CELL).unwrap(), SENTINEL);
+}
+
+#[test]
+fn context_native_reverses_string_in_place() {
+    let data: Vec<u8> = "pawn\0"
+        .bytes()
+        .flat_map(|byte| (byte as Cell).to_le_bytes())
+        .collect();
+    let bytecode = program(
+        &[
+            Instruction::new(Opcode::ConstPri, 0),
+            Instruction::new(Opcode::PushPri, 0),
+            Instruction::new(Opcode::ConstPri, CELL),
+            Instruction::new(Opcode::PushPri, 0),
+            Instruction::new(Opcode::Sysreq, 0),
+            Instruction::new(Opcode::Stack, 2 * CELL),
+            Instruction::new(Opcode::Halt, 0),
+        ],
+        &data,
+    );
+    let mut runtime = AmxRuntime::new();
+    runtime.register_native_with_context("strrev".to_string(), |ctx, params| {
+        let Ok(text) = ctx.get_string(params[1]) else {
+            return -1;
+        };
+        let reversed: String = text.chars().rev().collect();
+        ctx.set_string(params[1], &reversed, false, reversed.len() + 1)
+            .map_or(-1, |written| written as Cell)
+    });
+    runtime.init(&bytecode).unwrap();
+
+    assert_eq!(runtime.exec(AMX_EXEC_MAIN).unwrap(), 4);
+    assert_eq!(runtime.get_string(0).unwrap(), "nwap");
+}
+
+#[test]
+fn set_string_checks_destination_bounds() {
+    let (_, mut runtime) = run_strcpy(8);
+    let end = runtime.amx.stp;
+
+    assert!(matches!(
+        runtime.set_string(end - 8, "too long", false, 16),
+        Err(AmxRuntimeError::InvalidMemoryAccess(_))
+    ));
+    assert_eq!(runtime.set_string(BUFFER, "packed", true, 2).unwrap(), 6);
+    assert_eq!(runtime.get_string(BUFFER).unwrap(), "packed");
+}
+
+#[test]
+fn set_string_round_trips_non_ascii_characters() {
+    let (_, mut runtime) = run_strcpy(8);
+
+    assert_eq!(runtime.set_string(BUFFER, "héllo", false, 8).unwrap(), 5);
+    assert_eq!(runtime.get_string(BUFFER).unwrap(), "héllo");
+    // Truncation keeps whole characters
+    assert_eq!(runtime.set_string(BUFFER, "héllo", false, 3).unwrap(), 2);
+    assert_eq!(runtime.get_string(BUFFER).unwrap(), "hé");
+
+    assert_eq!(runtime.set_string(BUFFER, "héllo", true, 2).unwrap(), 5);
+    assert_eq!(runtime.get_string(BUFFER).unwrap(), "héllo");
+    // Packed strings hold Latin-1 only
+    assert_eq!(runtime.set_string(BUFFER, "€uro", true, 2).unwrap(), 4);
+    assert_eq!(runtime.get_string(BUFFER).unwrap(), "?uro");
+}
+
+/// `const.pri 300; const.alt -2; jump skip; const.pri 0; skip: smul; halt`
+/// in the compact encoding; the jump target is an offset into this stream
+const COMPACT_CODE: [u8; 14] = [
+    0x0B, 0x82, 0x2C, // const.pri 300
+    0x0C, 0x7E, // const.alt -2
+    0x33, 0x09, // jump 9
+    0x0B, 0x00, // const.pri 0
+    0x80, 0x48, // smul
+    0x80, 0x78, 0x00, // halt 0
+];
+
+#[test]
+fn compact_decode_matches_fixed_width() {
+    let expected = [
+        Instruction::new(Opcode::ConstPri, 300),
+        Instruction::new(Opcode::ConstAlt, -2),
+        Instruction::new(Opcode::Jump, 9),
+        Instruction::new(Opcode::ConstPri, 0),
+        Instruction::new(Opcode::Smul, 0),
+        Instruction::new(Opcode::Halt, 0),
+    ];
+
+    let mut offset = 0;
+    for instruction in &expected {
+        let (compact, size) = Instruction::from_bytes_compact(&COMPACT_CODE, offset).unwrap();
+        let fixed = Instruction::from_bytes(&instruction.to_bytes(), 0).unwrap();
+        assert_eq!(
+            (compact.opcode, compact.operand),
+            (fixed.opcode, fixed.operand)
+        );
+        offset += size;
+    }
+    assert_eq!(offset, COMPACT_CODE.len());
+}
+
+/// Encode a cell in the compact encoding, most significant group first
+fn compact_cell(value: Cell) -> Vec<u8> {
+    let mut groups = vec![(value & 0x7f) as u8];
+    let mut rest = value >> 7;
+    // Stop once the remaining bits only repeat the sign in bit 6
+    while !(rest == 0 && groups[groups.len() - 1] & 0x40 == 0
+        || rest == -1 && groups[groups.len() - 1] & 0x40 != 0)
+    {
+        groups.push((rest & 0x7f) as u8);
+        rest >>= 7;
+    }
+    groups.reverse();
+    let last = groups.len() - 1;
+    for group in &mut groups[..last] {
+        *group |= 0x80;
+    }
+    groups
+}
+
+#[test]
+fn compact_decode_covers_full_cell_range() {
+    for value in [Cell::MIN, Cell::MIN + 1, -1, 0, 1, Cell::MAX - 1, Cell::MAX] {
+        let mut code = compact_cell(Opcode::ConstPri.to_byte() as Cell);
+        code.extend(compact_cell(value));
+        let (instruction, size) = Instruction::from_bytes_compact(&code, 0).unwrap();
+        assert_eq!(instruction.operand, value);
+        assert_eq!(size, code.len());
+    }
+
+    // One continuation group past the width of a cell is malformed
+    let mut code = compact_cell(Opcode::ConstPri.to_byte() as Cell);
+    code.extend(vec![0x80; CELL_BYTES * 8 / 7 + 1]);
+    code.push(0x00);
+    assert!(matches!(
+        Instruction::from_bytes_compact(&code, 0),
+        Err(AmxRuntimeError::InvalidInstruction(_))
+    ));
+}
+
+#[test]
+fn runtime_executes_compact_code() {
+    let bytecode = write_amx(&AmxProgram {
+        flags: AmxFlags {
+            compact: true,
+            ..AmxFlags::new()
+        },
+        code: COMPACT_CODE.to_vec(),
+        stack_heap_size: 1024,
+        ..AmxProgram::default()
+    });
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+
+    assert_eq!(runtime.exec(AMX_EXEC_MAIN).unwrap(), -600);
+}
+
+#[test]
+fn dump_stack_returns_pushed_values_in_order() {
+    let bytecode = program(
+        &[
+            Instruction::new(Opcode::ConstPri, 7),
+            Instruction::new(Opcode::PushPri, 0),
+            Instruction::new(Opcode::ConstPri, -1),
+            Instruction::new(Opcode::PushPri, 0),
+            Instruction::new(Opcode::ConstPri, 42),
+            Instruction::new(Opcode::PushPri, 0),
+            Instruction::new(Opcode::Halt, 0),
+        ],
+        &cells(&[5, 6]),
+    );
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+    assert!(runtime.dump_stack().is_empty());
+
+    runtime.exec(AMX_EXEC_MAIN).unwrap();
+    assert_eq!(runtime.dump_stack(), vec![7, -1, 42]);
+    assert_eq!(runtime.dump_memory(0, 2), vec![5, 6]);
+    // Dumps stop at the end of the data region
+    let stp = runtime.amx.stp;
+    assert_eq!(runtime.dump_memory(stp - CELL, 3).len(), 1);
+}
+
+#[test]
+fn overflow_wraps_by_default_and_traps_on_request() {
+    // Operands are 32 bits wide, so the largest cell comes from the data
+    let bytecode = program(
+        &[
+            Instruction::new(Opcode::LoadPri, 0),
+            Instruction::new(Opcode::ConstAlt, 1),
+            Instruction::new(Opcode::Add, 0),
+            Instruction::new(Opcode::Halt, 0),
+        ],
+        &cells(&[Cell::MAX]),
+    );
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+    assert_eq!(runtime.exec(AMX_EXEC_MAIN).unwrap(), Cell::MIN);
+
+    let mut runtime = AmxRuntime::new();
+    runtime.set_overflow_mode(OverflowMode::Trap);
+    runtime.init(&bytecode).unwrap();
+    let error = runtime.exec(AMX_EXEC_MAIN).unwrap_err();
+    assert!(
+        matches!(error.kind(), AmxRuntimeError::DomainError(message) if message == "integer overflow")
+    );
+}
+
+#[test]
+fn bulk_cell_access_checks_the_whole_range() {
+    let bytecode = program(&[Instruction::new(Opcode::Halt, 0)], &cells(&[0; 5]));
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+
+    runtime.write_cells(0, &[1, -2, 3, 4, 5]).unwrap();
+    assert_eq!(runtime.read_cells(0, 5).unwrap(), vec![1, -2, 3, 4, 5]);
+    assert_eq!(runtime.read_cells(2 * CELL, 2).unwrap(), vec![3, 4]);
+
+    let stp = runtime.amx.stp;
+    assert!(matches!(
+        runtime.read_cells(stp - 2 * CELL, 3),
+        Err(AmxRuntimeError::InvalidMemoryAccess(_))
+    ));
+    // A failed write leaves memory untouched
+    assert!(runtime.write_cells(stp - CELL, &[9, 9]).is_err());
+    assert_eq!(runtime.read_cells(stp - CELL, 1).unwrap(), vec![0]);
+}
+
+#[test]
+fn bulk_cell_access_rejects_overflowing_ranges() {
+    let bytecode = program(&[Instruction::new(Opcode::Halt, 0)], &cells(&[0; 5]));
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+
+    // The byte size of the range overflows
+    assert!(matches!(
+        runtime.read_cells(CELL, usize::MAX / 2),
+        Err(AmxRuntimeError::InvalidMemoryAccess(_))
+    ));
+    // The end of the range overflows
+    assert!(matches!(
+        runtime.read_cells(CELL, usize::MAX / CELL_BYTES),
+        Err(AmxRuntimeError::InvalidMemoryAccess(_))
+    ));
+}
+
+#[test]
+fn stack_grows_down_from_stp_and_pops_last_in_first_out() {
+    let mut runtime = AmxRuntime::new();
+    runtime
+        .init(&assemble("push.c 1; push.c 2; push.c 3; halt").unwrap())
+        .unwrap();
+    let stp = runtime.amx.stp;
+    assert_eq!((runtime.amx.stk, runtime.amx.frm), (stp, stp));
+
+    runtime.exec(AMX_EXEC_MAIN).unwrap();
+    assert_eq!(runtime.amx.stk, stp - 3 * CELL);
+    assert_eq!(runtime.read_cell_at(stp - CELL).unwrap(), 1);
+    assert_eq!(runtime.read_cell_at(runtime.amx.stk).unwrap(), 3);
+
+    runtime
+        .init(&assemble("push.c 1; push.c 2; pop.pri; pop.alt; halt").unwrap())
+        .unwrap();
+    runtime.exec(AMX_EXEC_MAIN).unwrap();
+    assert_eq!((runtime.amx.pri, runtime.amx.alt), (2, 1));
+    assert_eq!(runtime.amx.stk, stp);
+}
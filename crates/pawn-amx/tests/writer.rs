@@ -0,0 +1,130 @@
+use pawn_amx::instructions::{Instruction, Opcode};
+use pawn_amx::{AMX_EXEC_MAIN, AmxProgram, AmxRuntime, CELL_BYTES, Cell, read_header, write_amx};
+
+const CELL: Cell = CELL_BYTES as Cell;
+
+fn sample_program() -> AmxProgram {
+    let code: Vec<u8> = [
+        Instruction::new(Opcode::ConstPri, 5),
+        Instruction::new(Opcode::PushPri, 0),
+        Instruction::new(Opcode::ConstPri, CELL),
+        Instruction::new(Opcode::PushPri, 0),
+        Instruction::new(Opcode::Sysreq, 1),
+        Instruction::new(Opcode::Stack, -2 * CELL),
+        Instruction::new(Opcode::Halt, 0),
+    ]
+    .iter()
+    .flat_map(|instruction| instruction.to_bytes())
+    .collect();
+
+    AmxProgram {
+        code,
+        data: (7 as Cell).to_le_bytes().to_vec(),
+        stack_heap_size: 1024,
+        publics: vec![("OnInit".to_string(), 10), ("OnExit".to_string(), 20)],
+        natives: vec!["print".to_string(), "double".to_string()],
+        libraries: vec!["core".to_string()],
+        pubvars: vec![("version".to_string(), 0)],
+        tags: vec![("Float".to_string(), 0x4000_0001)],
+        ..AmxProgram::default()
+    }
+}
+
+#[test]
+fn sections_follow_pawncc_order() {
+    let bytes = write_amx(&sample_program());
+    let header = read_header(&bytes).unwrap();
+
+    assert_eq!(header.defsize, 8);
+    assert!(header.uses_name_table());
+    assert_eq!(header.publics, 56);
+    assert_eq!(header.natives, header.publics + 2 * 8);
+    assert_eq!(header.libraries, header.natives + 2 * 8);
+    assert_eq!(header.pubvars, header.libraries + 8);
+    assert_eq!(header.tags, header.pubvars + 8);
+    assert_eq!(header.nametable, header.tags + 8);
+    assert!(header.cod > header.nametable);
+    // Five instructions with an operand and two without
+    assert_eq!(header.dat, header.cod + (5 * 2 + 2) * CELL_BYTES as i32);
+    assert_eq!(header.hea, header.dat + CELL_BYTES as i32);
+    assert_eq!(header.size as usize, bytes.len());
+    assert_eq!(header.stp, header.hea + 1024);
+
+    // Publics are sorted for the reference loader's binary search
+    let first = header.get_entry(&bytes, header.publics, 0).unwrap();
+    assert_eq!(header.get_entry_name(&bytes, first), Some("OnExit"));
+    let library = header.get_entry(&bytes, header.libraries, 0).unwrap();
+    assert_eq!(header.get_entry_name(&bytes, library), Some("core"));
+}
+
+#[test]
+fn runtime_loads_every_table() {
+    let mut runtime = AmxRuntime::new();
+    runtime.register_native("double".to_string(), |_amx, params| params[1] * 2);
+    runtime.init(&write_amx(&sample_program())).unwrap();
+
+    assert_eq!(runtime.find_public("OnInit").unwrap().address, 10);
+    assert_eq!(runtime.find_public("OnExit").unwrap().address, 20);
+    assert!(runtime.find_native("print").is_some());
+    assert_eq!(runtime.get_pubvar("version").unwrap(), 7);
+    assert_eq!(runtime.find_tag("Float").unwrap().tag_id, 0x4000_0001);
+
+    // SYSREQ 1 reaches the second native of the table
+    assert_eq!(runtime.exec(AMX_EXEC_MAIN).unwrap(), 10);
+}
+
+#[test]
+fn named_constructors_encode_like_new() {
+    assert_eq!(Instruction::const_pri(5).to_bytes(), cells(&[11, 5]));
+    assert_eq!(
+        Instruction::stack(-8).to_bytes(),
+        Instruction::new(Opcode::Stack, -8).to_bytes()
+    );
+    let halt = Instruction::halt();
+    assert_eq!((halt.opcode, halt.operand), (Opcode::Halt, 0));
+    assert_eq!(halt.size(), 2 * CELL_BYTES);
+    assert_eq!(Instruction::jump(40).size(), halt.to_bytes().len());
+}
+
+#[test]
+fn instructions_use_reference_opcodes_and_cell_operands() {
+    // OP_PUSH_PRI, OP_PROC, OP_SHL, OP_SMUL, OP_EQ and OP_INC_PRI, which
+    // the reference amx_Init checks its numbering against
+    for (opcode, number) in [
+        (Opcode::PushPri, 36),
+        (Opcode::Proc, 46),
+        (Opcode::Shl, 65),
+        (Opcode::Smul, 72),
+        (Opcode::Eq, 95),
+        (Opcode::Incr, 107),
+        (Opcode::Halt, 120),
+        (Opcode::Sysreq, 123),
+    ] {
+        assert_eq!(opcode.to_byte(), number, "{:?}", opcode);
+    }
+
+    // Opcodes without an operand are a single cell
+    assert_eq!(Instruction::push_pri().to_bytes(), cells(&[36]));
+    assert_eq!(Instruction::push_pri().size(), CELL_BYTES);
+    assert_eq!(Instruction::sysreq(1).to_bytes(), cells(&[123, 1]));
+
+    let code = [
+        Instruction::const_pri(-1),
+        Instruction::add(),
+        Instruction::jump(0),
+    ]
+    .iter()
+    .flat_map(Instruction::to_bytes)
+    .collect::<Vec<u8>>();
+    assert_eq!(code, cells(&[11, -1, 78, 51, 0]));
+    let add = Instruction::from_bytes(&code, 2 * CELL_BYTES).unwrap();
+    assert_eq!((add.opcode, add.size()), (Opcode::Add, CELL_BYTES));
+}
+
+/// Lay out cells the way the code section stores them
+fn cells(values: &[Cell]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
@@ -1,277 +1,588 @@
-//! CLI interface for Pawn compiler
-
-use pawn_amx::*;
-use pawn_compiler::{LintIssue, compile as compile_lib, format_source, lint_source, load_config};
-use std::fs;
-use std::path::PathBuf;
-
-use clap::{Arg, ArgAction, Command};
-
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let matches = Command::new("pawnc")
-        .about("Pawn Compiler (Rust MVP)")
-        .arg(Arg::new("input").required(false))
-        .arg(Arg::new("output").required(false))
-        .arg(
-            Arg::new("check")
-                .long("check")
-                .help("Run linter on input")
-                .action(ArgAction::SetTrue),
-        )
-        .arg(
-            Arg::new("fix")
-                .long("fix")
-                .help("Format input (writes back)")
-                .action(ArgAction::SetTrue),
-        )
-        .arg(
-            Arg::new("config")
-                .long("config")
-                .num_args(1)
-                .help("Path to rustpwn.json"),
-        )
-        .get_matches();
-
-    let input_file = matches.get_one::<String>("input").map(|s| s.to_string());
-    let output_file = matches
-        .get_one::<String>("output")
-        .map(|s| s.as_str())
-        .unwrap_or("output.amx");
-
-    let cfg_path = matches
-        .get_one::<String>("config")
-        .map(PathBuf::from)
-        .unwrap_or_else(|| PathBuf::from("rustpwn.json"));
-
-    let cfg = load_config(&cfg_path);
-
-    let flag_check = matches.get_flag("check");
-    let flag_fix = matches.get_flag("fix");
-
-    if input_file.is_none() && (flag_check || flag_fix) {
-        // Project-wide check/fix
-        let root = std::env::current_dir()?;
-        let files = collect_pawn_files(&root, &cfg);
-        if files.is_empty() {
-            println!("No Pawn files found.");
-            return Ok(());
-        }
-        let mut had_issues = false;
-        for path in files {
-            let content = match fs::read_to_string(&path) {
-                Ok(s) => s,
-                Err(_) => continue,
-            };
-            if flag_check {
-                let issues: Vec<LintIssue> = lint_source(&content, &cfg);
-                for i in issues {
-                    had_issues = true;
-                    eprintln!("{}:{}: {} ({})", path.display(), i.line, i.message, i.rule);
-                }
-            } else if flag_fix {
-                let formatted = format_source(&content, &cfg);
-                if formatted != content {
-                    let _ = fs::write(&path, formatted);
-                    println!("Formatted {}", path.display());
-                }
-            }
-        }
-        if flag_check && had_issues {
-            std::process::exit(1);
-        }
-        return Ok(());
-    }
-
-    let input_file = match input_file {
-        Some(s) => s,
-        None => {
-            println!("Usage: pawnc [--check|--fix] [--config <path>] <input_file> [output_file]");
-            return Ok(());
-        }
-    };
-
-    // Read input file
-    let source_code = fs::read_to_string(&input_file)?;
-
-    if flag_check {
-        let issues: Vec<LintIssue> = lint_source(&source_code, &cfg);
-        if issues.is_empty() {
-            println!("No issues found.");
-            return Ok(());
-        } else {
-            for i in issues {
-                eprintln!("{}:{}: {} ({})", &input_file, i.line, i.message, i.rule);
-            }
-            std::process::exit(1);
-        }
-    }
-
-    if flag_fix {
-        let formatted = format_source(&source_code, &cfg);
-        if formatted != source_code {
-            fs::write(&input_file, formatted)?;
-            println!("Formatted {}", &input_file);
-        } else {
-            println!("Already formatted: {}", &input_file);
-        }
-        return Ok(());
-    }
-
-    // Compile
-    println!("Compiling {} to {}", input_file, output_file);
-    let preprocessed = preprocess(&source_code);
-    match compile_lib(&preprocessed) {
-        Ok(bytecode) => {
-            // Write bytecode to file
-            fs::write(output_file, &bytecode)?;
-            println!("Compilation successful! Output written to {}", output_file);
-
-            // For MVP, also try to run the bytecode
-            if let Err(e) = run_bytecode(&bytecode) {
-                println!("Warning: Could not run bytecode: {}", e);
-            }
-        }
-        Err(e) => {
-            eprintln!("Compilation failed: {}", e);
-            std::process::exit(1);
-        }
-    }
-
-    Ok(())
-}
-
-// legacy usage function kept for reference; not used with clap
-#[allow(dead_code)]
-fn print_usage() {}
-
-// Minimal preprocessor for MVP: drop lines starting with #include and strip trailing semicolonsless printf forms
-fn preprocess(input: &str) -> String {
-    let mut out = String::new();
-    for line in input.lines() {
-        let trimmed = line.trim_start();
-        if trimmed.starts_with("#include") {
-            continue;
-        }
-        out.push_str(line);
-        out.push('\n');
-    }
-    out
-}
-
-fn run_bytecode(bytecode: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
-    // Create AMX runtime
-    let mut runtime = AmxRuntime::new();
-
-    // Initialize with bytecode
-    runtime.init(bytecode)?;
-
-    // Register printf native
-    runtime.register_native("printf".to_string(), |_amx, params| {
-        if let Some(format_string) = params.get(0) {
-            // For MVP, just print the string
-            println!("{}", format_string);
-        }
-        0
-    });
-
-    // Execute
-    let result = runtime.exec(AMX_EXEC_MAIN)?;
-    println!("Execution completed with result: {}", result);
-
-    Ok(())
-}
-
-fn collect_pawn_files(
-    root: &std::path::Path,
-    cfg: &pawn_compiler::Config,
-) -> Vec<std::path::PathBuf> {
-    let mut out = Vec::new();
-    let mut stack = vec![root.to_path_buf()];
-    let include_globs = &cfg.files.include_globs;
-    let exclude_globs = &cfg.files.exclude_globs;
-    while let Some(dir) = stack.pop() {
-        let Ok(read) = std::fs::read_dir(&dir) else {
-            continue;
-        };
-        for entry in read.flatten() {
-            let path = entry.path();
-            let name = entry.file_name();
-            let name = name.to_string_lossy();
-            if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
-                if name == ".git"
-                    || name == "node_modules"
-                    || name == "dist"
-                    || name.starts_with("dist-")
-                    || name == "target"
-                    || name == ".turbo"
-                    || name == ".vercel"
-                    || (name == "styles" && path.join("dist").is_dir())
-                {
-                    continue;
-                }
-                stack.push(path);
-            } else {
-                if file_matches(&path, include_globs, exclude_globs) {
-                    out.push(path);
-                }
-            }
-        }
-    }
-    out
-}
-
-fn file_matches(path: &std::path::Path, includes: &[String], excludes: &[String]) -> bool {
-    // very rough glob matching supporting ** and suffix extension checks commonly used here
-    let rel = path.to_string_lossy();
-    if excludes.iter().any(|g| glob_match(&rel, g)) {
-        return false;
-    }
-    if includes.iter().any(|g| glob_match(&rel, g)) {
-        return true;
-    }
-    // default: allow only pawn extensions
-    let ext = path
-        .extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("")
-        .to_ascii_lowercase();
-    ext == "p" || ext == "pwn" || ext == "inc"
-}
-
-fn glob_match(text: &str, pat: &str) -> bool {
-    // minimal: "**" matches any, "*" matches within a segment. If pat has no wildcard and is a directory, check prefix
-    if pat == "**" {
-        return true;
-    }
-    if pat.contains("*") {
-        // naive: replace ** with .* and * with [^/]*
-        let mut regex = String::new();
-        let mut chars = pat.chars().peekable();
-        while let Some(c) = chars.next() {
-            match c {
-                '.' => regex.push_str("\\."),
-                '?' => regex.push('.'),
-                '*' => {
-                    if chars.peek() == Some(&'*') {
-                        chars.next();
-                        regex.push_str(".*");
-                    } else {
-                        regex.push_str("[^/]*");
-                    }
-                }
-                '/' | '\\' => regex.push_str("[/\\]"),
-                _ => regex.push(c),
-            }
-        }
-        return regex::Regex::new(&format!("^{}$", regex))
-            .map(|r| r.is_match(text))
-            .unwrap_or(false);
-    }
-    // No wildcard case: exact or prefix match
-    if pat.ends_with('/') {
-        text.replace('\\', "/")
-            .starts_with(&pat.trim_end_matches('/'))
-    } else {
-        text.ends_with(pat)
-    }
-}
+//! CLI interface for Pawn compiler
+
+use pawn_amx::*;
+use pawn_compiler::{
+    FileLintIssue, LintIssue, LintSummary, Parser, compile_all_with_config, emit_asm_with_config,
+    format_ast, format_source, issues_to_json, lint_source, load_config, render_diagnostic,
+};
+use std::fs;
+use std::path::PathBuf;
+
+use clap::{Arg, ArgAction, Command};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let matches = Command::new("pawnc")
+        .about("Pawn Compiler (Rust MVP)")
+        .arg(Arg::new("input").required(false))
+        .arg(Arg::new("output").required(false))
+        .arg(
+            Arg::new("check")
+                .long("check")
+                .help("Run linter on input; exits 1 on warnings and 2 on errors")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("fix")
+                .long("fix")
+                .help("Format input (writes back)")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("check-format")
+                .long("check-format")
+                .help("Fail if input is not formatted, without writing")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("run")
+                .long("run")
+                .help("Run input as AMX bytecode without compiling")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("dump-ast")
+                .long("dump-ast")
+                .help("Print the parsed syntax tree without compiling")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("dump-header")
+                .long("dump-header")
+                .help("Print the header and symbol tables of an AMX file")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("disasm")
+                .long("disasm")
+                .help("Print a full listing of an AMX file: header, code and data")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("emit")
+                .long("emit")
+                .num_args(1)
+                .value_parser(["asm"])
+                .help("Print generated code in another form instead of writing bytecode"),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .num_args(1)
+                .value_parser(["text", "json"])
+                .help("Output format for --check diagnostics"),
+        )
+        .arg(
+            Arg::new("library")
+                .long("library")
+                .help("Compile a library, such as an include file, that needs no main()")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .num_args(1)
+                .help("Path to rustpwn.json"),
+        )
+        .get_matches();
+
+    let input_file = matches.get_one::<String>("input").map(|s| s.to_string());
+    let output_file = matches
+        .get_one::<String>("output")
+        .map(|s| s.as_str())
+        .unwrap_or("output.amx");
+
+    let cfg_path = matches
+        .get_one::<String>("config")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("rustpwn.json"));
+
+    let mut cfg = load_config(&cfg_path);
+    cfg.pawn.library |= matches.get_flag("library");
+
+    let flag_check = matches.get_flag("check");
+    let flag_fix = matches.get_flag("fix");
+    let flag_check_format = matches.get_flag("check-format");
+    let flag_dump_ast = matches.get_flag("dump-ast");
+    let flag_run = matches.get_flag("run");
+    let flag_dump_header = matches.get_flag("dump-header");
+    let flag_disasm = matches.get_flag("disasm");
+    let emit_asm = matches
+        .get_one::<String>("emit")
+        .is_some_and(|e| e == "asm");
+    let json_output = matches
+        .get_one::<String>("format")
+        .is_some_and(|f| f == "json");
+
+    if input_file.is_none() && (flag_check || flag_fix || flag_check_format) {
+        // Project-wide check/fix
+        let root = std::env::current_dir()?;
+        let files = collect_pawn_files(&root, &cfg);
+        if files.is_empty() {
+            println!("No Pawn files found.");
+            return Ok(());
+        }
+        let mut summary = LintSummary::default();
+        let mut unformatted = 0;
+        let mut all_issues: Vec<(String, LintIssue)> = Vec::new();
+        for path in files {
+            let content = match fs::read_to_string(&path) {
+                Ok(s) => s,
+                Err(_) => continue,
+            };
+            if flag_check {
+                let issues: Vec<LintIssue> = lint_source(&content, &cfg);
+                summary.add_file(&issues);
+                for i in issues {
+                    if json_output {
+                        all_issues.push((path.display().to_string(), i));
+                    } else {
+                        eprintln!(
+                            "{}:{}:{}: {} ({})",
+                            path.display(),
+                            i.line,
+                            i.column,
+                            i.message,
+                            i.rule
+                        );
+                    }
+                }
+            } else if flag_check_format {
+                if !check_formatted(&path.display().to_string(), &content, &cfg) {
+                    unformatted += 1;
+                }
+            } else if flag_fix {
+                let formatted = format_source(&content, &cfg);
+                if formatted != content {
+                    let _ = fs::write(&path, formatted);
+                    println!("Formatted {}", path.display());
+                }
+            }
+        }
+        if flag_check && json_output {
+            let entries: Vec<FileLintIssue> = all_issues
+                .iter()
+                .map(|(file, issue)| FileLintIssue { file, issue })
+                .collect();
+            println!("{}", issues_to_json(&entries));
+        }
+        if flag_check && summary.issues > 0 {
+            eprintln!("{}", summary);
+            std::process::exit(summary.exit_code());
+        }
+        if unformatted > 0 {
+            eprintln!("{} file(s) need formatting", unformatted);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let input_file = match input_file {
+        Some(s) => s,
+        None => {
+            println!(
+                "Usage: pawnc [--check|--check-format|--fix|--dump-ast|--dump-header|--run] [--format text|json] [--config <path>] <input_file> [output_file]"
+            );
+            return Ok(());
+        }
+    };
+
+    // Read input file; precompiled AMX files are executed directly
+    let input = fs::read(&input_file)?;
+    if flag_dump_header {
+        if !is_amx(&input) {
+            eprintln!(
+                "{}: not an AMX file; compile it first and dump the output",
+                input_file
+            );
+            std::process::exit(1);
+        }
+        match dump_header(&input) {
+            Ok(dump) => print!("{}", dump),
+            Err(e) => {
+                eprintln!("{}: invalid AMX header: {}", input_file, e);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+    if flag_disasm {
+        if !is_amx(&input) {
+            eprintln!(
+                "{}: not an AMX file; compile it first and disassemble the output",
+                input_file
+            );
+            std::process::exit(1);
+        }
+        match disassemble_full(&input) {
+            Ok(listing) => print!("{}", listing),
+            Err(e) => {
+                eprintln!("{}: {}", input_file, e);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+    if flag_run || is_amx(&input) {
+        if let Err(e) = run_bytecode(&input) {
+            eprintln!("{}: {}", input_file, e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    let source_code = String::from_utf8(input)?;
+
+    if flag_check {
+        let issues: Vec<LintIssue> = lint_source(&source_code, &cfg);
+        let mut summary = LintSummary::default();
+        summary.add_file(&issues);
+        if json_output {
+            let entries: Vec<FileLintIssue> = issues
+                .iter()
+                .map(|issue| FileLintIssue {
+                    file: &input_file,
+                    issue,
+                })
+                .collect();
+            println!("{}", issues_to_json(&entries));
+            if !issues.is_empty() {
+                eprintln!("{}", summary);
+                std::process::exit(summary.exit_code());
+            }
+            return Ok(());
+        }
+        if issues.is_empty() {
+            println!("No issues found.");
+            return Ok(());
+        } else {
+            for i in issues {
+                eprintln!(
+                    "{}:{}:{}: {} ({})",
+                    &input_file, i.line, i.column, i.message, i.rule
+                );
+            }
+            eprintln!("{}", summary);
+            std::process::exit(summary.exit_code());
+        }
+    }
+
+    if flag_check_format {
+        if !check_formatted(&input_file, &source_code, &cfg) {
+            eprintln!("{} needs formatting", &input_file);
+            std::process::exit(1);
+        }
+        println!("Already formatted: {}", &input_file);
+        return Ok(());
+    }
+
+    if flag_fix {
+        let formatted = format_source(&source_code, &cfg);
+        if formatted != source_code {
+            fs::write(&input_file, formatted)?;
+            println!("Formatted {}", &input_file);
+        } else {
+            println!("Already formatted: {}", &input_file);
+        }
+        return Ok(());
+    }
+
+    if flag_dump_ast {
+        let mut parser = Parser::new(&preprocess(&source_code))?;
+        let ast = parser.parse_program()?;
+        print!("{}", format_ast(&ast));
+        return Ok(());
+    }
+
+    if emit_asm {
+        let preprocessed = preprocess(&source_code);
+        match emit_asm_with_config(&preprocessed, &cfg) {
+            Ok(asm) => print!("{}", asm),
+            Err(errors) => {
+                for e in &errors {
+                    eprint!("{}: {}", input_file, render_diagnostic(&preprocessed, e));
+                }
+                eprintln!("Compilation failed with {} error(s)", errors.len());
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    // Compile
+    println!("Compiling {} to {}", input_file, output_file);
+    let preprocessed = preprocess(&source_code);
+    match compile_all_with_config(&preprocessed, &cfg) {
+        Ok(bytecode) => {
+            // Write bytecode to file
+            fs::write(output_file, &bytecode)?;
+            println!("Compilation successful! Output written to {}", output_file);
+
+            // For MVP, also try to run the bytecode
+            if let Err(e) = run_bytecode(&bytecode) {
+                println!("Warning: Could not run bytecode: {}", e);
+            }
+        }
+        Err(errors) => {
+            for e in &errors {
+                eprint!("{}: {}", input_file, render_diagnostic(&preprocessed, e));
+            }
+            eprintln!("Compilation failed with {} error(s)", errors.len());
+            std::process::exit(1);
+        }
+    }
+
+    Ok(())
+}
+
+/// Differing lines shown per file by `--check-format`
+const MAX_DIFF_LINES: usize = 5;
+
+/// Whether `content` is already formatted; if not, print the first lines
+/// that formatting would change
+fn check_formatted(file: &str, content: &str, cfg: &pawn_compiler::Config) -> bool {
+    let formatted = format_source(content, cfg);
+    if formatted == content {
+        return true;
+    }
+
+    println!("--- {}", file);
+    println!("+++ {} (formatted)", file);
+    let original: Vec<&str> = content.lines().collect();
+    let expected: Vec<&str> = formatted.lines().collect();
+    let mut shown = 0;
+    for line in 0..original.len().max(expected.len()) {
+        let (old, new) = (original.get(line), expected.get(line));
+        if old == new {
+            continue;
+        }
+        if shown == MAX_DIFF_LINES {
+            println!("...");
+            break;
+        }
+        println!("@@ line {} @@", line + 1);
+        if let Some(old) = old {
+            println!("-{}", old);
+        }
+        if let Some(new) = new {
+            println!("+{}", new);
+        }
+        shown += 1;
+    }
+    false
+}
+
+/// Whether `bytes` start with an AMX header rather than Pawn source
+fn is_amx(bytes: &[u8]) -> bool {
+    bytes.len() >= AMX_HEADER_BYTES && u16::from_le_bytes([bytes[4], bytes[5]]) == AMX_MAGIC
+}
+
+/// Describe an AMX file: its header followed by the names in each of its
+/// symbol tables
+fn dump_header(bytes: &[u8]) -> Result<String, AmxError> {
+    use std::fmt::Write;
+
+    let header = read_header(bytes)?;
+    let mut out = header.to_string();
+    for (title, table, next) in [
+        ("Publics", header.publics, header.natives),
+        ("Natives", header.natives, header.libraries),
+        ("Libraries", header.libraries, header.pubvars),
+        ("Pub Vars", header.pubvars, header.tags),
+        ("Tags", header.tags, header.nametable),
+    ] {
+        let entries = if table == 0 {
+            Vec::new()
+        } else {
+            header
+                .table_entries(bytes, table, next)
+                .ok_or(AmxError::Format)?
+        };
+        let _ = writeln!(out, "{} ({}):", title, entries.len());
+        for (_, name) in entries {
+            let _ = writeln!(out, "  {}", name);
+        }
+    }
+    Ok(out)
+}
+
+// legacy usage function kept for reference; not used with clap
+#[allow(dead_code)]
+fn print_usage() {}
+
+// Minimal preprocessor for MVP: drop lines starting with #include and strip trailing semicolonsless printf forms
+fn preprocess(input: &str) -> String {
+    let mut out = String::new();
+    for line in input.lines() {
+        let trimmed = line.trim_start();
+        // Keep an empty line, so diagnostics point at the right line
+        if trimmed.starts_with("#include") {
+            out.push('\n');
+            continue;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+fn run_bytecode(bytecode: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+    // Create AMX runtime
+    let mut runtime = AmxRuntime::new();
+
+    // Initialize with bytecode
+    runtime.init(bytecode)?;
+
+    // Register printf native; params[0] holds the argument size in bytes
+    runtime.register_native("printf".to_string(), |amx, params| {
+        let Some(format) = params.get(1).and_then(|&addr| amx.get_string(addr).ok()) else {
+            return 0;
+        };
+        let text = format_cells(amx, &format, &params[2..]);
+        print!("{}", text);
+        let _ = std::io::Write::flush(&mut std::io::stdout());
+        text.chars().count() as Cell
+    });
+
+    // Execute
+    let result = runtime.exec(AMX_EXEC_MAIN)?;
+    println!("Execution completed with result: {}", result);
+
+    Ok(())
+}
+
+/// Expand a printf format string with the cell arguments that follow it
+///
+/// `%d`, `%i`, `%x`, `%c` and `%f` take the argument's value, `%s` the
+/// string at its address, and `%%` is a percent sign. Other specifiers, and
+/// those without an argument left, are printed as written.
+fn format_cells(amx: &Amx, format: &str, args: &[Cell]) -> String {
+    let mut out = String::new();
+    let mut args = args.iter().copied();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        let Some(spec) = chars.next() else {
+            out.push('%');
+            break;
+        };
+        if spec == '%' {
+            out.push('%');
+            continue;
+        }
+        if !matches!(spec, 'd' | 'i' | 'x' | 'c' | 'f' | 's') {
+            out.push('%');
+            out.push(spec);
+            continue;
+        }
+        let Some(value) = args.next() else {
+            out.push('%');
+            out.push(spec);
+            continue;
+        };
+        match spec {
+            'd' | 'i' => out.push_str(&value.to_string()),
+            'x' => out.push_str(&format!("{:x}", value)),
+            'c' => out.push(char::from_u32(value as u32).unwrap_or('\u{fffd}')),
+            'f' => out.push_str(&format!("{:.6}", f32::from_bits(value as u32))),
+            _ => out.push_str(&amx.get_string(value).unwrap_or_default()),
+        }
+    }
+    out
+}
+
+fn collect_pawn_files(
+    root: &std::path::Path,
+    cfg: &pawn_compiler::Config,
+) -> Vec<std::path::PathBuf> {
+    let mut out = Vec::new();
+    let mut stack = vec![root.to_path_buf()];
+    let include_globs = &cfg.files.include_globs;
+    let exclude_globs = &cfg.files.exclude_globs;
+    while let Some(dir) = stack.pop() {
+        let Ok(read) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in read.flatten() {
+            let path = entry.path();
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+                if name == ".git"
+                    || name == "node_modules"
+                    || name == "dist"
+                    || name.starts_with("dist-")
+                    || name == "target"
+                    || name == ".turbo"
+                    || name == ".vercel"
+                    || (name == "styles" && path.join("dist").is_dir())
+                {
+                    continue;
+                }
+                stack.push(path);
+            } else {
+                if file_matches(&path, include_globs, exclude_globs) {
+                    out.push(path);
+                }
+            }
+        }
+    }
+    out
+}
+
+fn file_matches(path: &std::path::Path, includes: &[String], excludes: &[String]) -> bool {
+    // very rough glob matching supporting ** and suffix extension checks commonly used here
+    let rel = path.to_string_lossy();
+    if excludes.iter().any(|g| glob_match(&rel, g)) {
+        return false;
+    }
+    if includes.iter().any(|g| glob_match(&rel, g)) {
+        return true;
+    }
+    // default: allow only pawn extensions
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    ext == "p" || ext == "pwn" || ext == "inc"
+}
+
+fn glob_match(text: &str, pat: &str) -> bool {
+    // minimal: "**" matches any, "*" matches within a segment. If pat has no wildcard and is a directory, check prefix
+    if pat == "**" {
+        return true;
+    }
+    if pat.contains("*") {
+        // naive: replace ** with .* and * with [^/]*
+        let mut regex = String::new();
+        let mut chars = pat.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '.' => regex.push_str("\\."),
+                '?' => regex.push('.'),
+                '*' => {
+                    if chars.peek() == Some(&'*') {
+                        chars.next();
+                        regex.push_str(".*");
+                    } else {
+                        regex.push_str("[^/]*");
+                    }
+                }
+                '/' | '\\' => regex.push_str("[/\\]"),
+                _ => regex.push(c),
+            }
+        }
+        return regex::Regex::new(&format!("^{}$", regex))
+            .map(|r| r.is_match(text))
+            .unwrap_or(false);
+    }
+    // No wildcard case: exact or prefix match
+    if pat.ends_with('/') {
+        text.replace('\\', "/")
+            .starts_with(pat.trim_end_matches('/'))
+    } else {
+        text.ends_with(pat)
+    }
+}
@@ -0,0 +1,142 @@
+use std::process::Command;
+
+/// Write `contents` to a file in the temp directory unique to this test
+fn temp_file(name: &str, contents: &[u8]) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("pawncc-{}-{}", std::process::id(), name));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn runs_precompiled_amx_without_compiling() {
+    let bytecode = pawn_amx::assemble("const.pri 20; const.alt 22; add; halt").unwrap();
+    let path = temp_file("prebuilt.amx", &bytecode);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pawncc"))
+        .arg(&path)
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).ok();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(!stdout.contains("Compiling"));
+    assert!(stdout.contains("Execution completed with result: 42"));
+}
+
+#[test]
+fn check_format_fails_without_writing() {
+    let source = b"main()\n{\nreturn 0;   \n}\n";
+    let path = temp_file("unformatted.pwn", source);
+    let config = temp_file("format.json", br#"{"formatter": {"enabled": true}}"#);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pawncc"))
+        .arg("--check-format")
+        .arg("--config")
+        .arg(&config)
+        .arg(&path)
+        .output()
+        .unwrap();
+    let contents = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+    std::fs::remove_file(&config).ok();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success());
+    assert!(stdout.contains("@@ line 3 @@"), "{}", stdout);
+    assert!(stdout.contains("-return 0;   "));
+    assert!(stdout.contains("+    return 0;"));
+    assert_eq!(contents, source);
+}
+
+#[test]
+fn check_format_keeps_braces_off_with_the_rule() {
+    let path = temp_file("braceless.pwn", b"main()\n    return;\n");
+    let config = concat!(env!("CARGO_MANIFEST_DIR"), "/../../pwnrust.json");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pawncc"))
+        .arg("--check-format")
+        .arg("--config")
+        .arg(config)
+        .arg(&path)
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).ok();
+
+    // `pwnrust.json` turns `style.addMissingBraces` off
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("+main(){"), "{}", stdout);
+}
+
+#[test]
+fn dump_header_lists_natives() {
+    let source = temp_file("natives.pwn", b"main()\n{\n    printf(\"hi\");\n}\n");
+    let amx = std::env::temp_dir().join(format!("pawncc-{}-natives.amx", std::process::id()));
+    let compiled = Command::new(env!("CARGO_BIN_EXE_pawncc"))
+        .arg(&source)
+        .arg(&amx)
+        .output()
+        .unwrap();
+    assert!(compiled.status.success());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pawncc"))
+        .arg("--dump-header")
+        .arg(&amx)
+        .output()
+        .unwrap();
+    let source_dump = Command::new(env!("CARGO_BIN_EXE_pawncc"))
+        .arg("--dump-header")
+        .arg(&source)
+        .output()
+        .unwrap();
+    std::fs::remove_file(&source).ok();
+    std::fs::remove_file(&amx).ok();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("AMX Header:"));
+    assert!(stdout.contains("Natives (1):\n  printf\n"), "{}", stdout);
+
+    assert!(!source_dump.status.success());
+    assert!(String::from_utf8_lossy(&source_dump.stderr).contains("not an AMX file"));
+}
+
+#[test]
+fn printf_formats_its_arguments() {
+    let source = temp_file(
+        "printf.pwn",
+        b"main()\n{\n    printf(\"x=%d %s %c%%\", 42, \"ok\", 121);\n}\n",
+    );
+    let amx = std::env::temp_dir().join(format!("pawncc-{}-printf.amx", std::process::id()));
+    let output = Command::new(env!("CARGO_BIN_EXE_pawncc"))
+        .arg(&source)
+        .arg(&amx)
+        .output()
+        .unwrap();
+    std::fs::remove_file(&source).ok();
+    std::fs::remove_file(&amx).ok();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("x=42 ok y%"), "{}", stdout);
+}
+
+#[test]
+fn printf_formats_floats_with_six_decimals() {
+    let source = temp_file(
+        "printf-float.pwn",
+        b"main()\n{\n    printf(\"x=%f\\n\", 1.5);\n}\n",
+    );
+    let amx = std::env::temp_dir().join(format!("pawncc-{}-printf-float.amx", std::process::id()));
+    let output = Command::new(env!("CARGO_BIN_EXE_pawncc"))
+        .arg(&source)
+        .arg(&amx)
+        .output()
+        .unwrap();
+    std::fs::remove_file(&source).ok();
+    std::fs::remove_file(&amx).ok();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("x=1.500000\n"), "{}", stdout);
+}
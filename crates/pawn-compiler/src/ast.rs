@@ -1,490 +1,1042 @@
-//! Abstract Syntax Tree for Pawn
-
-use crate::error::*;
-
-/// AST node types
-#[derive(Debug, Clone, PartialEq)]
-pub enum AstNode {
-    // Program structure
-    Program(Vec<AstNode>),
-
-    // Function definitions
-    Function {
-        name: String,
-        parameters: Vec<Parameter>,
-        return_type: Option<String>,
-        body: Vec<AstNode>,
-        is_public: bool,
-        is_native: bool,
-        is_forward: bool,
-    },
-
-    // Variable declarations
-    VariableDeclaration {
-        name: String,
-        var_type: String,
-        initializer: Option<Box<AstNode>>,
-        is_const: bool,
-        is_static: bool,
-    },
-
-    // Statements
-    Block(Vec<AstNode>),
-    Expression(Box<AstNode>),
-    If {
-        condition: Box<AstNode>,
-        then_branch: Box<AstNode>,
-        else_branch: Option<Box<AstNode>>,
-    },
-    While {
-        condition: Box<AstNode>,
-        body: Box<AstNode>,
-    },
-    For {
-        init: Option<Box<AstNode>>,
-        condition: Option<Box<AstNode>>,
-        update: Option<Box<AstNode>>,
-        body: Box<AstNode>,
-    },
-    Return(Option<Box<AstNode>>),
-    Break,
-    Continue,
-
-    // Expressions
-    BinaryOp {
-        left: Box<AstNode>,
-        operator: BinaryOperator,
-        right: Box<AstNode>,
-    },
-    UnaryOp {
-        operator: UnaryOperator,
-        operand: Box<AstNode>,
-    },
-    Assignment {
-        target: Box<AstNode>,
-        value: Box<AstNode>,
-    },
-    FunctionCall {
-        name: String,
-        arguments: Vec<AstNode>,
-    },
-    ArrayAccess {
-        array: Box<AstNode>,
-        index: Box<AstNode>,
-    },
-    MemberAccess {
-        object: Box<AstNode>,
-        member: String,
-    },
-
-    // Literals
-    Integer(i32),
-    Float(f32),
-    String(String),
-    Character(char),
-    Boolean(bool),
-    Identifier(String),
-
-    // Type definitions
-    TypeDefinition {
-        name: String,
-        definition: TypeDefinition,
-    },
-
-    // Enum definitions
-    EnumDefinition {
-        name: String,
-        variants: Vec<EnumVariant>,
-    },
-}
-
-/// Function parameter
-#[derive(Debug, Clone, PartialEq)]
-pub struct Parameter {
-    pub name: String,
-    pub param_type: String,
-    pub is_reference: bool,
-    pub default_value: Option<Box<AstNode>>,
-}
-
-/// Binary operators
-#[derive(Debug, Clone, PartialEq)]
-pub enum BinaryOperator {
-    // Arithmetic
-    Add,
-    Subtract,
-    Multiply,
-    Divide,
-    Modulo,
-
-    // Comparison
-    Equal,
-    NotEqual,
-    Less,
-    LessEqual,
-    Greater,
-    GreaterEqual,
-
-    // Logical
-    LogicalAnd,
-    LogicalOr,
-
-    // Bitwise
-    BitwiseAnd,
-    BitwiseOr,
-    BitwiseXor,
-    LeftShift,
-    RightShift,
-
-    // Assignment
-    Assign,
-    AddAssign,
-    SubtractAssign,
-    MultiplyAssign,
-    DivideAssign,
-    ModuloAssign,
-    AndAssign,
-    OrAssign,
-    XorAssign,
-    LeftShiftAssign,
-    RightShiftAssign,
-}
-
-/// Unary operators
-#[derive(Debug, Clone, PartialEq)]
-pub enum UnaryOperator {
-    Plus,
-    Minus,
-    LogicalNot,
-    BitwiseNot,
-    Increment,
-    Decrement,
-    AddressOf,
-    Dereference,
-}
-
-/// Type definitions
-#[derive(Debug, Clone, PartialEq)]
-pub enum TypeDefinition {
-    Primitive(String),
-    Array {
-        element_type: Box<TypeDefinition>,
-        size: Option<Box<AstNode>>,
-    },
-    Pointer(Box<TypeDefinition>),
-    Struct {
-        fields: Vec<StructField>,
-    },
-    Union {
-        fields: Vec<StructField>,
-    },
-    Enum {
-        variants: Vec<EnumVariant>,
-    },
-    Function {
-        parameters: Vec<Parameter>,
-        return_type: Option<String>,
-    },
-}
-
-/// Struct field
-#[derive(Debug, Clone, PartialEq)]
-pub struct StructField {
-    pub name: String,
-    pub field_type: TypeDefinition,
-}
-
-/// Enum variant
-#[derive(Debug, Clone, PartialEq)]
-pub struct EnumVariant {
-    pub name: String,
-    pub value: Option<Box<AstNode>>,
-}
-
-/// AST visitor trait
-pub trait AstVisitor<T> {
-    fn visit_program(&mut self, nodes: &[AstNode]) -> CompilerResult<T>;
-    fn visit_function(
-        &mut self,
-        name: &str,
-        parameters: &[Parameter],
-        return_type: &Option<String>,
-        body: &[AstNode],
-        is_public: bool,
-        is_native: bool,
-        is_forward: bool,
-    ) -> CompilerResult<T>;
-    fn visit_variable_declaration(
-        &mut self,
-        name: &str,
-        var_type: &str,
-        initializer: &Option<Box<AstNode>>,
-        is_const: bool,
-        is_static: bool,
-    ) -> CompilerResult<T>;
-    fn visit_block(&mut self, statements: &[AstNode]) -> CompilerResult<T>;
-    fn visit_if(
-        &mut self,
-        condition: &AstNode,
-        then_branch: &AstNode,
-        else_branch: &Option<Box<AstNode>>,
-    ) -> CompilerResult<T>;
-    fn visit_while(&mut self, condition: &AstNode, body: &AstNode) -> CompilerResult<T>;
-    fn visit_for(
-        &mut self,
-        init: &Option<Box<AstNode>>,
-        condition: &Option<Box<AstNode>>,
-        update: &Option<Box<AstNode>>,
-        body: &AstNode,
-    ) -> CompilerResult<T>;
-    fn visit_return(&mut self, value: &Option<Box<AstNode>>) -> CompilerResult<T>;
-    fn visit_break(&mut self) -> CompilerResult<T>;
-    fn visit_continue(&mut self) -> CompilerResult<T>;
-    fn visit_binary_op(
-        &mut self,
-        left: &AstNode,
-        operator: &BinaryOperator,
-        right: &AstNode,
-    ) -> CompilerResult<T>;
-    fn visit_unary_op(&mut self, operator: &UnaryOperator, operand: &AstNode) -> CompilerResult<T>;
-    fn visit_assignment(&mut self, target: &AstNode, value: &AstNode) -> CompilerResult<T>;
-    fn visit_function_call(&mut self, name: &str, arguments: &[AstNode]) -> CompilerResult<T>;
-    fn visit_array_access(&mut self, array: &AstNode, index: &AstNode) -> CompilerResult<T>;
-    fn visit_member_access(&mut self, object: &AstNode, member: &str) -> CompilerResult<T>;
-    fn visit_integer(&mut self, value: i32) -> CompilerResult<T>;
-    fn visit_float(&mut self, value: f32) -> CompilerResult<T>;
-    fn visit_string(&mut self, value: &str) -> CompilerResult<T>;
-    fn visit_character(&mut self, value: char) -> CompilerResult<T>;
-    fn visit_boolean(&mut self, value: bool) -> CompilerResult<T>;
-    fn visit_identifier(&mut self, name: &str) -> CompilerResult<T>;
-    fn visit_type_definition(
-        &mut self,
-        name: &str,
-        definition: &TypeDefinition,
-    ) -> CompilerResult<T>;
-    fn visit_enum_definition(&mut self, name: &str, variants: &[EnumVariant]) -> CompilerResult<T>;
-}
-
-impl AstNode {
-    /// Accept a visitor
-    pub fn accept<T>(&self, visitor: &mut dyn AstVisitor<T>) -> CompilerResult<T> {
-        match self {
-            AstNode::Program(nodes) => visitor.visit_program(nodes),
-            AstNode::Function {
-                name,
-                parameters,
-                return_type,
-                body,
-                is_public,
-                is_native,
-                is_forward,
-            } => visitor.visit_function(
-                name,
-                parameters,
-                return_type,
-                body,
-                *is_public,
-                *is_native,
-                *is_forward,
-            ),
-            AstNode::VariableDeclaration {
-                name,
-                var_type,
-                initializer,
-                is_const,
-                is_static,
-            } => visitor.visit_variable_declaration(
-                name,
-                var_type,
-                initializer,
-                *is_const,
-                *is_static,
-            ),
-            AstNode::Block(statements) => visitor.visit_block(statements),
-            AstNode::Expression(expr) => expr.accept(visitor),
-            AstNode::If {
-                condition,
-                then_branch,
-                else_branch,
-            } => visitor.visit_if(condition, then_branch, else_branch),
-            AstNode::While { condition, body } => visitor.visit_while(condition, body),
-            AstNode::For {
-                init,
-                condition,
-                update,
-                body,
-            } => visitor.visit_for(init, condition, update, body),
-            AstNode::Return(value) => visitor.visit_return(value),
-            AstNode::Break => visitor.visit_break(),
-            AstNode::Continue => visitor.visit_continue(),
-            AstNode::BinaryOp {
-                left,
-                operator,
-                right,
-            } => visitor.visit_binary_op(left, operator, right),
-            AstNode::UnaryOp { operator, operand } => visitor.visit_unary_op(operator, operand),
-            AstNode::Assignment { target, value } => visitor.visit_assignment(target, value),
-            AstNode::FunctionCall { name, arguments } => {
-                visitor.visit_function_call(name, arguments)
-            }
-            AstNode::ArrayAccess { array, index } => visitor.visit_array_access(array, index),
-            AstNode::MemberAccess { object, member } => visitor.visit_member_access(object, member),
-            AstNode::Integer(value) => visitor.visit_integer(*value),
-            AstNode::Float(value) => visitor.visit_float(*value),
-            AstNode::String(value) => visitor.visit_string(value),
-            AstNode::Character(value) => visitor.visit_character(*value),
-            AstNode::Boolean(value) => visitor.visit_boolean(*value),
-            AstNode::Identifier(name) => visitor.visit_identifier(name),
-            AstNode::TypeDefinition { name, definition } => {
-                visitor.visit_type_definition(name, definition)
-            }
-            AstNode::EnumDefinition { name, variants } => {
-                visitor.visit_enum_definition(name, variants)
-            }
-        }
-    }
-}
-
-/// Default implementation for AstVisitor
-impl<T> AstVisitor<T> for Box<dyn AstVisitor<T>> {
-    fn visit_program(&mut self, nodes: &[AstNode]) -> CompilerResult<T> {
-        self.as_mut().visit_program(nodes)
-    }
-
-    fn visit_function(
-        &mut self,
-        name: &str,
-        parameters: &[Parameter],
-        return_type: &Option<String>,
-        body: &[AstNode],
-        is_public: bool,
-        is_native: bool,
-        is_forward: bool,
-    ) -> CompilerResult<T> {
-        self.as_mut().visit_function(
-            name,
-            parameters,
-            return_type,
-            body,
-            is_public,
-            is_native,
-            is_forward,
-        )
-    }
-
-    fn visit_variable_declaration(
-        &mut self,
-        name: &str,
-        var_type: &str,
-        initializer: &Option<Box<AstNode>>,
-        is_const: bool,
-        is_static: bool,
-    ) -> CompilerResult<T> {
-        self.as_mut()
-            .visit_variable_declaration(name, var_type, initializer, is_const, is_static)
-    }
-
-    fn visit_block(&mut self, statements: &[AstNode]) -> CompilerResult<T> {
-        self.as_mut().visit_block(statements)
-    }
-
-    fn visit_if(
-        &mut self,
-        condition: &AstNode,
-        then_branch: &AstNode,
-        else_branch: &Option<Box<AstNode>>,
-    ) -> CompilerResult<T> {
-        self.as_mut().visit_if(condition, then_branch, else_branch)
-    }
-
-    fn visit_while(&mut self, condition: &AstNode, body: &AstNode) -> CompilerResult<T> {
-        self.as_mut().visit_while(condition, body)
-    }
-
-    fn visit_for(
-        &mut self,
-        init: &Option<Box<AstNode>>,
-        condition: &Option<Box<AstNode>>,
-        update: &Option<Box<AstNode>>,
-        body: &AstNode,
-    ) -> CompilerResult<T> {
-        self.as_mut().visit_for(init, condition, update, body)
-    }
-
-    fn visit_return(&mut self, value: &Option<Box<AstNode>>) -> CompilerResult<T> {
-        self.as_mut().visit_return(value)
-    }
-
-    fn visit_break(&mut self) -> CompilerResult<T> {
-        self.as_mut().visit_break()
-    }
-
-    fn visit_continue(&mut self) -> CompilerResult<T> {
-        self.as_mut().visit_continue()
-    }
-
-    fn visit_binary_op(
-        &mut self,
-        left: &AstNode,
-        operator: &BinaryOperator,
-        right: &AstNode,
-    ) -> CompilerResult<T> {
-        self.as_mut().visit_binary_op(left, operator, right)
-    }
-
-    fn visit_unary_op(&mut self, operator: &UnaryOperator, operand: &AstNode) -> CompilerResult<T> {
-        self.as_mut().visit_unary_op(operator, operand)
-    }
-
-    fn visit_assignment(&mut self, target: &AstNode, value: &AstNode) -> CompilerResult<T> {
-        self.as_mut().visit_assignment(target, value)
-    }
-
-    fn visit_function_call(&mut self, name: &str, arguments: &[AstNode]) -> CompilerResult<T> {
-        self.as_mut().visit_function_call(name, arguments)
-    }
-
-    fn visit_array_access(&mut self, array: &AstNode, index: &AstNode) -> CompilerResult<T> {
-        self.as_mut().visit_array_access(array, index)
-    }
-
-    fn visit_member_access(&mut self, object: &AstNode, member: &str) -> CompilerResult<T> {
-        self.as_mut().visit_member_access(object, member)
-    }
-
-    fn visit_integer(&mut self, value: i32) -> CompilerResult<T> {
-        self.as_mut().visit_integer(value)
-    }
-
-    fn visit_float(&mut self, value: f32) -> CompilerResult<T> {
-        self.as_mut().visit_float(value)
-    }
-
-    fn visit_string(&mut self, value: &str) -> CompilerResult<T> {
-        self.as_mut().visit_string(value)
-    }
-
-    fn visit_character(&mut self, value: char) -> CompilerResult<T> {
-        self.as_mut().visit_character(value)
-    }
-
-    fn visit_boolean(&mut self, value: bool) -> CompilerResult<T> {
-        self.as_mut().visit_boolean(value)
-    }
-
-    fn visit_identifier(&mut self, name: &str) -> CompilerResult<T> {
-        self.as_mut().visit_identifier(name)
-    }
-
-    fn visit_type_definition(
-        &mut self,
-        name: &str,
-        definition: &TypeDefinition,
-    ) -> CompilerResult<T> {
-        self.as_mut().visit_type_definition(name, definition)
-    }
-
-    fn visit_enum_definition(&mut self, name: &str, variants: &[EnumVariant]) -> CompilerResult<T> {
-        self.as_mut().visit_enum_definition(name, variants)
-    }
-}
+//! Abstract Syntax Tree for Pawn
+
+use crate::error::*;
+
+/// Position of a node in the source, 1-based
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// AST node types
+#[derive(Debug, Clone, PartialEq)]
+pub enum AstNode {
+    // Program structure
+    Program(Vec<AstNode>),
+
+    // Source location of a statement or declaration
+    Located {
+        span: Span,
+        node: Box<AstNode>,
+    },
+
+    // Function definitions
+    Function {
+        name: String,
+        parameters: Vec<Parameter>,
+        return_type: Option<String>,
+        body: Vec<AstNode>,
+        is_public: bool,
+        is_native: bool,
+        is_forward: bool,
+        /// States the function is defined for, from a `<automaton:state>`
+        /// suffix
+        states: Option<FunctionStates>,
+    },
+
+    // Variable declarations
+    VariableDeclaration {
+        name: String,
+        /// Tag, such as `Float` in `new Float:x`; empty when untagged
+        var_type: String,
+        /// Number of cells for array declarations (`new arr[5]`)
+        array_size: Option<Box<AstNode>>,
+        initializer: Option<Box<AstNode>>,
+        is_const: bool,
+        is_static: bool,
+    },
+
+    // Statements
+    Block(Vec<AstNode>),
+    Expression(Box<AstNode>),
+    If {
+        condition: Box<AstNode>,
+        then_branch: Box<AstNode>,
+        else_branch: Option<Box<AstNode>>,
+    },
+    While {
+        condition: Box<AstNode>,
+        body: Box<AstNode>,
+    },
+    /// Post-test loop `do body while (condition);`
+    DoWhile {
+        body: Box<AstNode>,
+        condition: Box<AstNode>,
+    },
+    For {
+        init: Option<Box<AstNode>>,
+        condition: Option<Box<AstNode>>,
+        update: Option<Box<AstNode>>,
+        body: Box<AstNode>,
+    },
+    Return(Option<Box<AstNode>>),
+    /// `assert expr;`, aborting the script when `expr` is false
+    Assert(Box<AstNode>),
+    Break,
+    Continue,
+    /// `state name;` or `state automaton:name;`, switching an automaton to
+    /// another state
+    StateChange {
+        /// Automaton name; `None` for the default automaton
+        automaton: Option<String>,
+        state: String,
+    },
+
+    // Expressions
+    BinaryOp {
+        left: Box<AstNode>,
+        operator: BinaryOperator,
+        right: Box<AstNode>,
+    },
+    UnaryOp {
+        operator: UnaryOperator,
+        operand: Box<AstNode>,
+    },
+    Assignment {
+        target: Box<AstNode>,
+        value: Box<AstNode>,
+    },
+    Ternary {
+        condition: Box<AstNode>,
+        then_value: Box<AstNode>,
+        else_value: Box<AstNode>,
+    },
+    FunctionCall {
+        name: String,
+        arguments: Vec<AstNode>,
+    },
+    ArrayAccess {
+        array: Box<AstNode>,
+        index: Box<AstNode>,
+    },
+    MemberAccess {
+        object: Box<AstNode>,
+        member: String,
+    },
+
+    // Literals
+    Integer(i32),
+    Float(f32),
+    String(String),
+    /// Packed string literal `!"..."`, four characters per cell
+    PackedString(String),
+    Character(char),
+    Boolean(bool),
+    Identifier(String),
+
+    // Type definitions
+    TypeDefinition {
+        name: String,
+        definition: TypeDefinition,
+    },
+
+    // Enum definitions
+    EnumDefinition {
+        name: String,
+        variants: Vec<EnumVariant>,
+    },
+}
+
+/// Function parameter
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    pub param_type: String,
+    pub is_reference: bool,
+    /// Declared `const`: the function may not modify it
+    pub is_const: bool,
+    /// Array dimensions, each with its size if one is declared, as in
+    /// `list[]` or `grid[3][4]`
+    pub dimensions: Vec<Option<Box<AstNode>>>,
+    pub default_value: Option<Box<AstNode>>,
+}
+
+/// State suffix of a function, as in `OnUpdate() <game:running, paused>`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionStates {
+    /// Automaton name; `None` for the default automaton
+    pub automaton: Option<String>,
+    /// States the function applies to; empty for the fallback `<>`
+    pub states: Vec<String>,
+}
+
+impl std::fmt::Display for FunctionStates {
+    /// The suffix without its angle brackets, as written in source
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(automaton) = &self.automaton {
+            write!(f, "{}:", automaton)?;
+        }
+        write!(f, "{}", self.states.join(", "))
+    }
+}
+
+/// Binary operators
+#[derive(Debug, Clone, PartialEq)]
+pub enum BinaryOperator {
+    // Arithmetic
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+
+    // Comparison
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+
+    // Logical
+    LogicalAnd,
+    LogicalOr,
+
+    // Bitwise
+    BitwiseAnd,
+    BitwiseOr,
+    BitwiseXor,
+    LeftShift,
+    RightShift,
+
+    // Assignment
+    Assign,
+    AddAssign,
+    SubtractAssign,
+    MultiplyAssign,
+    DivideAssign,
+    ModuloAssign,
+    AndAssign,
+    OrAssign,
+    XorAssign,
+    LeftShiftAssign,
+    RightShiftAssign,
+}
+
+/// Unary operators
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnaryOperator {
+    Plus,
+    Minus,
+    LogicalNot,
+    BitwiseNot,
+    /// `++x`, yielding the new value
+    Increment,
+    /// `--x`, yielding the new value
+    Decrement,
+    /// `x++`, yielding the original value
+    PostIncrement,
+    /// `x--`, yielding the original value
+    PostDecrement,
+    AddressOf,
+    Dereference,
+    /// `sizeof x`, the number of cells declared for the variable operand
+    Sizeof,
+    /// `tagof x`, the identifier of the variable operand's tag
+    Tagof,
+}
+
+/// Type definitions
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeDefinition {
+    Primitive(String),
+    Array {
+        element_type: Box<TypeDefinition>,
+        size: Option<Box<AstNode>>,
+    },
+    Pointer(Box<TypeDefinition>),
+    Struct {
+        fields: Vec<StructField>,
+    },
+    Union {
+        fields: Vec<StructField>,
+    },
+    Enum {
+        variants: Vec<EnumVariant>,
+    },
+    Function {
+        parameters: Vec<Parameter>,
+        return_type: Option<String>,
+    },
+}
+
+/// Struct field
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructField {
+    pub name: String,
+    pub field_type: TypeDefinition,
+}
+
+/// Enum variant
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumVariant {
+    pub name: String,
+    pub value: Option<Box<AstNode>>,
+}
+
+/// AST visitor trait
+pub trait AstVisitor<T> {
+    fn visit_program(&mut self, nodes: &[AstNode]) -> CompilerResult<T>;
+    fn visit_located(&mut self, span: Span, node: &AstNode) -> CompilerResult<T>;
+    #[allow(clippy::too_many_arguments)]
+    fn visit_function(
+        &mut self,
+        name: &str,
+        parameters: &[Parameter],
+        return_type: &Option<String>,
+        body: &[AstNode],
+        is_public: bool,
+        is_native: bool,
+        is_forward: bool,
+    ) -> CompilerResult<T>;
+    fn visit_variable_declaration(
+        &mut self,
+        name: &str,
+        var_type: &str,
+        array_size: &Option<Box<AstNode>>,
+        initializer: &Option<Box<AstNode>>,
+        is_const: bool,
+        is_static: bool,
+    ) -> CompilerResult<T>;
+    fn visit_block(&mut self, statements: &[AstNode]) -> CompilerResult<T>;
+    fn visit_if(
+        &mut self,
+        condition: &AstNode,
+        then_branch: &AstNode,
+        else_branch: &Option<Box<AstNode>>,
+    ) -> CompilerResult<T>;
+    fn visit_while(&mut self, condition: &AstNode, body: &AstNode) -> CompilerResult<T>;
+    fn visit_do_while(&mut self, body: &AstNode, condition: &AstNode) -> CompilerResult<T>;
+    fn visit_for(
+        &mut self,
+        init: &Option<Box<AstNode>>,
+        condition: &Option<Box<AstNode>>,
+        update: &Option<Box<AstNode>>,
+        body: &AstNode,
+    ) -> CompilerResult<T>;
+    fn visit_return(&mut self, value: &Option<Box<AstNode>>) -> CompilerResult<T>;
+    fn visit_assert(&mut self, condition: &AstNode) -> CompilerResult<T>;
+    fn visit_break(&mut self) -> CompilerResult<T>;
+    fn visit_continue(&mut self) -> CompilerResult<T>;
+    fn visit_state_change(&mut self, automaton: &Option<String>, state: &str) -> CompilerResult<T>;
+    fn visit_binary_op(
+        &mut self,
+        left: &AstNode,
+        operator: &BinaryOperator,
+        right: &AstNode,
+    ) -> CompilerResult<T>;
+    fn visit_unary_op(&mut self, operator: &UnaryOperator, operand: &AstNode) -> CompilerResult<T>;
+    fn visit_assignment(&mut self, target: &AstNode, value: &AstNode) -> CompilerResult<T>;
+    fn visit_ternary(
+        &mut self,
+        condition: &AstNode,
+        then_value: &AstNode,
+        else_value: &AstNode,
+    ) -> CompilerResult<T>;
+    fn visit_function_call(&mut self, name: &str, arguments: &[AstNode]) -> CompilerResult<T>;
+    fn visit_array_access(&mut self, array: &AstNode, index: &AstNode) -> CompilerResult<T>;
+    fn visit_member_access(&mut self, object: &AstNode, member: &str) -> CompilerResult<T>;
+    fn visit_integer(&mut self, value: i32) -> CompilerResult<T>;
+    fn visit_float(&mut self, value: f32) -> CompilerResult<T>;
+    fn visit_string(&mut self, value: &str) -> CompilerResult<T>;
+    fn visit_character(&mut self, value: char) -> CompilerResult<T>;
+    fn visit_boolean(&mut self, value: bool) -> CompilerResult<T>;
+    fn visit_identifier(&mut self, name: &str) -> CompilerResult<T>;
+    fn visit_type_definition(
+        &mut self,
+        name: &str,
+        definition: &TypeDefinition,
+    ) -> CompilerResult<T>;
+    fn visit_enum_definition(&mut self, name: &str, variants: &[EnumVariant]) -> CompilerResult<T>;
+}
+
+impl AstNode {
+    /// The node without its source location wrapper
+    pub fn inner(&self) -> &AstNode {
+        match self {
+            AstNode::Located { node, .. } => node.inner(),
+            node => node,
+        }
+    }
+
+    /// Source location of the node, if the parser recorded one
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            AstNode::Located { span, .. } => Some(*span),
+            _ => None,
+        }
+    }
+
+    /// A copy of the tree with every source location wrapper removed
+    ///
+    /// Two parses of equivalent source differ only in their spans, so
+    /// comparing stripped trees compares their structure.
+    pub fn without_locations(&self) -> AstNode {
+        let strip = |node: &AstNode| Box::new(node.without_locations());
+        let strip_all = |nodes: &[AstNode]| nodes.iter().map(AstNode::without_locations).collect();
+        match self {
+            AstNode::Program(nodes) => AstNode::Program(strip_all(nodes)),
+            AstNode::Located { node, .. } => node.without_locations(),
+            AstNode::Function {
+                name,
+                parameters,
+                return_type,
+                body,
+                is_public,
+                is_native,
+                is_forward,
+                states,
+            } => AstNode::Function {
+                name: name.clone(),
+                parameters: parameters
+                    .iter()
+                    .map(|param| Parameter {
+                        dimensions: param
+                            .dimensions
+                            .iter()
+                            .map(|size| size.as_deref().map(strip))
+                            .collect(),
+                        default_value: param.default_value.as_deref().map(strip),
+                        ..param.clone()
+                    })
+                    .collect(),
+                return_type: return_type.clone(),
+                body: strip_all(body),
+                is_public: *is_public,
+                is_native: *is_native,
+                is_forward: *is_forward,
+                states: states.clone(),
+            },
+            AstNode::VariableDeclaration {
+                name,
+                var_type,
+                array_size,
+                initializer,
+                is_const,
+                is_static,
+            } => AstNode::VariableDeclaration {
+                name: name.clone(),
+                var_type: var_type.clone(),
+                array_size: array_size.as_deref().map(strip),
+                initializer: initializer.as_deref().map(strip),
+                is_const: *is_const,
+                is_static: *is_static,
+            },
+            AstNode::Block(statements) => AstNode::Block(strip_all(statements)),
+            AstNode::Expression(expr) => AstNode::Expression(strip(expr)),
+            AstNode::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => AstNode::If {
+                condition: strip(condition),
+                then_branch: strip(then_branch),
+                else_branch: else_branch.as_deref().map(strip),
+            },
+            AstNode::While { condition, body } => AstNode::While {
+                condition: strip(condition),
+                body: strip(body),
+            },
+            AstNode::DoWhile { body, condition } => AstNode::DoWhile {
+                body: strip(body),
+                condition: strip(condition),
+            },
+            AstNode::For {
+                init,
+                condition,
+                update,
+                body,
+            } => AstNode::For {
+                init: init.as_deref().map(strip),
+                condition: condition.as_deref().map(strip),
+                update: update.as_deref().map(strip),
+                body: strip(body),
+            },
+            AstNode::Return(value) => AstNode::Return(value.as_deref().map(strip)),
+            AstNode::Assert(condition) => AstNode::Assert(strip(condition)),
+            AstNode::BinaryOp {
+                left,
+                operator,
+                right,
+            } => AstNode::BinaryOp {
+                left: strip(left),
+                operator: operator.clone(),
+                right: strip(right),
+            },
+            AstNode::UnaryOp { operator, operand } => AstNode::UnaryOp {
+                operator: operator.clone(),
+                operand: strip(operand),
+            },
+            AstNode::Assignment { target, value } => AstNode::Assignment {
+                target: strip(target),
+                value: strip(value),
+            },
+            AstNode::Ternary {
+                condition,
+                then_value,
+                else_value,
+            } => AstNode::Ternary {
+                condition: strip(condition),
+                then_value: strip(then_value),
+                else_value: strip(else_value),
+            },
+            AstNode::FunctionCall { name, arguments } => AstNode::FunctionCall {
+                name: name.clone(),
+                arguments: strip_all(arguments),
+            },
+            AstNode::ArrayAccess { array, index } => AstNode::ArrayAccess {
+                array: strip(array),
+                index: strip(index),
+            },
+            AstNode::MemberAccess { object, member } => AstNode::MemberAccess {
+                object: strip(object),
+                member: member.clone(),
+            },
+            AstNode::EnumDefinition { name, variants } => AstNode::EnumDefinition {
+                name: name.clone(),
+                variants: variants
+                    .iter()
+                    .map(|variant| EnumVariant {
+                        name: variant.name.clone(),
+                        value: variant.value.as_deref().map(strip),
+                    })
+                    .collect(),
+            },
+            node => node.clone(),
+        }
+    }
+
+    /// Accept a visitor
+    pub fn accept<T>(&self, visitor: &mut dyn AstVisitor<T>) -> CompilerResult<T> {
+        match self {
+            AstNode::Program(nodes) => visitor.visit_program(nodes),
+            AstNode::Located { span, node } => visitor.visit_located(*span, node),
+            AstNode::Function {
+                name,
+                parameters,
+                return_type,
+                body,
+                is_public,
+                is_native,
+                is_forward,
+                ..
+            } => visitor.visit_function(
+                name,
+                parameters,
+                return_type,
+                body,
+                *is_public,
+                *is_native,
+                *is_forward,
+            ),
+            AstNode::VariableDeclaration {
+                name,
+                var_type,
+                array_size,
+                initializer,
+                is_const,
+                is_static,
+            } => visitor.visit_variable_declaration(
+                name,
+                var_type,
+                array_size,
+                initializer,
+                *is_const,
+                *is_static,
+            ),
+            AstNode::Block(statements) => visitor.visit_block(statements),
+            AstNode::Expression(expr) => expr.accept(visitor),
+            AstNode::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => visitor.visit_if(condition, then_branch, else_branch),
+            AstNode::While { condition, body } => visitor.visit_while(condition, body),
+            AstNode::DoWhile { body, condition } => visitor.visit_do_while(body, condition),
+            AstNode::For {
+                init,
+                condition,
+                update,
+                body,
+            } => visitor.visit_for(init, condition, update, body),
+            AstNode::Return(value) => visitor.visit_return(value),
+            AstNode::Assert(condition) => visitor.visit_assert(condition),
+            AstNode::Break => visitor.visit_break(),
+            AstNode::Continue => visitor.visit_continue(),
+            AstNode::StateChange { automaton, state } => {
+                visitor.visit_state_change(automaton, state)
+            }
+            AstNode::BinaryOp {
+                left,
+                operator,
+                right,
+            } => visitor.visit_binary_op(left, operator, right),
+            AstNode::UnaryOp { operator, operand } => visitor.visit_unary_op(operator, operand),
+            AstNode::Assignment { target, value } => visitor.visit_assignment(target, value),
+            AstNode::Ternary {
+                condition,
+                then_value,
+                else_value,
+            } => visitor.visit_ternary(condition, then_value, else_value),
+            AstNode::FunctionCall { name, arguments } => {
+                visitor.visit_function_call(name, arguments)
+            }
+            AstNode::ArrayAccess { array, index } => visitor.visit_array_access(array, index),
+            AstNode::MemberAccess { object, member } => visitor.visit_member_access(object, member),
+            AstNode::Integer(value) => visitor.visit_integer(*value),
+            AstNode::Float(value) => visitor.visit_float(*value),
+            AstNode::String(value) | AstNode::PackedString(value) => visitor.visit_string(value),
+            AstNode::Character(value) => visitor.visit_character(*value),
+            AstNode::Boolean(value) => visitor.visit_boolean(*value),
+            AstNode::Identifier(name) => visitor.visit_identifier(name),
+            AstNode::TypeDefinition { name, definition } => {
+                visitor.visit_type_definition(name, definition)
+            }
+            AstNode::EnumDefinition { name, variants } => {
+                visitor.visit_enum_definition(name, variants)
+            }
+        }
+    }
+}
+
+/// Default implementation for AstVisitor
+impl<T> AstVisitor<T> for Box<dyn AstVisitor<T>> {
+    fn visit_program(&mut self, nodes: &[AstNode]) -> CompilerResult<T> {
+        self.as_mut().visit_program(nodes)
+    }
+
+    fn visit_located(&mut self, span: Span, node: &AstNode) -> CompilerResult<T> {
+        self.as_mut().visit_located(span, node)
+    }
+
+    fn visit_function(
+        &mut self,
+        name: &str,
+        parameters: &[Parameter],
+        return_type: &Option<String>,
+        body: &[AstNode],
+        is_public: bool,
+        is_native: bool,
+        is_forward: bool,
+    ) -> CompilerResult<T> {
+        self.as_mut().visit_function(
+            name,
+            parameters,
+            return_type,
+            body,
+            is_public,
+            is_native,
+            is_forward,
+        )
+    }
+
+    fn visit_variable_declaration(
+        &mut self,
+        name: &str,
+        var_type: &str,
+        array_size: &Option<Box<AstNode>>,
+        initializer: &Option<Box<AstNode>>,
+        is_const: bool,
+        is_static: bool,
+    ) -> CompilerResult<T> {
+        self.as_mut().visit_variable_declaration(
+            name,
+            var_type,
+            array_size,
+            initializer,
+            is_const,
+            is_static,
+        )
+    }
+
+    fn visit_block(&mut self, statements: &[AstNode]) -> CompilerResult<T> {
+        self.as_mut().visit_block(statements)
+    }
+
+    fn visit_if(
+        &mut self,
+        condition: &AstNode,
+        then_branch: &AstNode,
+        else_branch: &Option<Box<AstNode>>,
+    ) -> CompilerResult<T> {
+        self.as_mut().visit_if(condition, then_branch, else_branch)
+    }
+
+    fn visit_while(&mut self, condition: &AstNode, body: &AstNode) -> CompilerResult<T> {
+        self.as_mut().visit_while(condition, body)
+    }
+
+    fn visit_do_while(&mut self, body: &AstNode, condition: &AstNode) -> CompilerResult<T> {
+        self.as_mut().visit_do_while(body, condition)
+    }
+
+    fn visit_for(
+        &mut self,
+        init: &Option<Box<AstNode>>,
+        condition: &Option<Box<AstNode>>,
+        update: &Option<Box<AstNode>>,
+        body: &AstNode,
+    ) -> CompilerResult<T> {
+        self.as_mut().visit_for(init, condition, update, body)
+    }
+
+    fn visit_return(&mut self, value: &Option<Box<AstNode>>) -> CompilerResult<T> {
+        self.as_mut().visit_return(value)
+    }
+
+    fn visit_assert(&mut self, condition: &AstNode) -> CompilerResult<T> {
+        self.as_mut().visit_assert(condition)
+    }
+
+    fn visit_break(&mut self) -> CompilerResult<T> {
+        self.as_mut().visit_break()
+    }
+
+    fn visit_continue(&mut self) -> CompilerResult<T> {
+        self.as_mut().visit_continue()
+    }
+
+    fn visit_state_change(&mut self, automaton: &Option<String>, state: &str) -> CompilerResult<T> {
+        self.as_mut().visit_state_change(automaton, state)
+    }
+
+    fn visit_binary_op(
+        &mut self,
+        left: &AstNode,
+        operator: &BinaryOperator,
+        right: &AstNode,
+    ) -> CompilerResult<T> {
+        self.as_mut().visit_binary_op(left, operator, right)
+    }
+
+    fn visit_unary_op(&mut self, operator: &UnaryOperator, operand: &AstNode) -> CompilerResult<T> {
+        self.as_mut().visit_unary_op(operator, operand)
+    }
+
+    fn visit_assignment(&mut self, target: &AstNode, value: &AstNode) -> CompilerResult<T> {
+        self.as_mut().visit_assignment(target, value)
+    }
+
+    fn visit_ternary(
+        &mut self,
+        condition: &AstNode,
+        then_value: &AstNode,
+        else_value: &AstNode,
+    ) -> CompilerResult<T> {
+        self.as_mut()
+            .visit_ternary(condition, then_value, else_value)
+    }
+
+    fn visit_function_call(&mut self, name: &str, arguments: &[AstNode]) -> CompilerResult<T> {
+        self.as_mut().visit_function_call(name, arguments)
+    }
+
+    fn visit_array_access(&mut self, array: &AstNode, index: &AstNode) -> CompilerResult<T> {
+        self.as_mut().visit_array_access(array, index)
+    }
+
+    fn visit_member_access(&mut self, object: &AstNode, member: &str) -> CompilerResult<T> {
+        self.as_mut().visit_member_access(object, member)
+    }
+
+    fn visit_integer(&mut self, value: i32) -> CompilerResult<T> {
+        self.as_mut().visit_integer(value)
+    }
+
+    fn visit_float(&mut self, value: f32) -> CompilerResult<T> {
+        self.as_mut().visit_float(value)
+    }
+
+    fn visit_string(&mut self, value: &str) -> CompilerResult<T> {
+        self.as_mut().visit_string(value)
+    }
+
+    fn visit_character(&mut self, value: char) -> CompilerResult<T> {
+        self.as_mut().visit_character(value)
+    }
+
+    fn visit_boolean(&mut self, value: bool) -> CompilerResult<T> {
+        self.as_mut().visit_boolean(value)
+    }
+
+    fn visit_identifier(&mut self, name: &str) -> CompilerResult<T> {
+        self.as_mut().visit_identifier(name)
+    }
+
+    fn visit_type_definition(
+        &mut self,
+        name: &str,
+        definition: &TypeDefinition,
+    ) -> CompilerResult<T> {
+        self.as_mut().visit_type_definition(name, definition)
+    }
+
+    fn visit_enum_definition(&mut self, name: &str, variants: &[EnumVariant]) -> CompilerResult<T> {
+        self.as_mut().visit_enum_definition(name, variants)
+    }
+}
+
+/// Render an AST as an indented tree, one node per line
+///
+/// Source locations are shown after the node they wrap and `None` fields are
+/// left out, so the output stays close to the shape of the source.
+pub fn format_ast(node: &AstNode) -> String {
+    let mut out = String::new();
+    write_node(&mut out, node, 0, "");
+    out
+}
+
+/// Append one line at `depth` levels of indentation
+fn write_line(out: &mut String, depth: usize, line: &str) {
+    out.push_str(&"  ".repeat(depth));
+    out.push_str(line);
+    out.push('\n');
+}
+
+/// Append `node` and its children, with `suffix` after the node's own line
+fn write_node(out: &mut String, node: &AstNode, depth: usize, suffix: &str) {
+    // Label and child for fields whose role is not obvious from the node kind
+    let labeled = |out: &mut String, label: &str, child: &AstNode| {
+        write_line(out, depth + 1, label);
+        write_node(out, child, depth + 2, "");
+    };
+
+    match node {
+        AstNode::Program(nodes) => {
+            write_line(out, depth, &format!("Program{}", suffix));
+            for child in nodes {
+                write_node(out, child, depth + 1, "");
+            }
+        }
+        AstNode::Located { span, node } => {
+            write_node(
+                out,
+                node,
+                depth,
+                &format!(" @{}:{}", span.line, span.column),
+            );
+        }
+        AstNode::Function {
+            name,
+            parameters,
+            return_type,
+            body,
+            is_public,
+            is_native,
+            is_forward,
+            states,
+        } => {
+            let mut line = String::from("Function ");
+            for (flag, keyword) in [
+                (*is_public, "public "),
+                (*is_native, "native "),
+                (*is_forward, "forward "),
+            ] {
+                if flag {
+                    line.push_str(keyword);
+                }
+            }
+            if let Some(tag) = return_type {
+                line.push_str(&format!("{}:", tag));
+            }
+            line.push_str(name);
+            if let Some(states) = states {
+                line.push_str(&format!(" <{}>", states));
+            }
+            write_line(out, depth, &format!("{}{}", line, suffix));
+            for param in parameters {
+                write_parameter(out, param, depth + 1);
+            }
+            for stmt in body {
+                write_node(out, stmt, depth + 1, "");
+            }
+        }
+        AstNode::VariableDeclaration {
+            name,
+            var_type,
+            array_size,
+            initializer,
+            is_const,
+            is_static,
+        } => {
+            let mut line = String::from("VariableDeclaration ");
+            if *is_const {
+                line.push_str("const ");
+            }
+            if *is_static {
+                line.push_str("static ");
+            }
+            if !var_type.is_empty() {
+                line.push_str(&format!("{}:", var_type));
+            }
+            line.push_str(name);
+            write_line(out, depth, &format!("{}{}", line, suffix));
+            if let Some(size) = array_size {
+                labeled(out, "size", size);
+            }
+            if let Some(init) = initializer {
+                labeled(out, "init", init);
+            }
+        }
+        AstNode::Block(statements) => {
+            write_line(out, depth, &format!("Block{}", suffix));
+            for stmt in statements {
+                write_node(out, stmt, depth + 1, "");
+            }
+        }
+        AstNode::Expression(expr) => write_node(out, expr, depth, suffix),
+        AstNode::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            write_line(out, depth, &format!("If{}", suffix));
+            labeled(out, "condition", condition);
+            labeled(out, "then", then_branch);
+            if let Some(else_stmt) = else_branch {
+                labeled(out, "else", else_stmt);
+            }
+        }
+        AstNode::While { condition, body } => {
+            write_line(out, depth, &format!("While{}", suffix));
+            labeled(out, "condition", condition);
+            labeled(out, "body", body);
+        }
+        AstNode::DoWhile { body, condition } => {
+            write_line(out, depth, &format!("DoWhile{}", suffix));
+            labeled(out, "body", body);
+            labeled(out, "condition", condition);
+        }
+        AstNode::For {
+            init,
+            condition,
+            update,
+            body,
+        } => {
+            write_line(out, depth, &format!("For{}", suffix));
+            for (label, field) in [("init", init), ("condition", condition), ("update", update)] {
+                if let Some(child) = field {
+                    labeled(out, label, child);
+                }
+            }
+            labeled(out, "body", body);
+        }
+        AstNode::Return(value) => {
+            write_line(out, depth, &format!("Return{}", suffix));
+            if let Some(val) = value {
+                write_node(out, val, depth + 1, "");
+            }
+        }
+        AstNode::Assert(condition) => {
+            write_line(out, depth, &format!("Assert{}", suffix));
+            write_node(out, condition, depth + 1, "");
+        }
+        AstNode::Break => write_line(out, depth, &format!("Break{}", suffix)),
+        AstNode::Continue => write_line(out, depth, &format!("Continue{}", suffix)),
+        AstNode::StateChange { automaton, state } => {
+            let target = match automaton {
+                Some(automaton) => format!("{}:{}", automaton, state),
+                None => state.clone(),
+            };
+            write_line(out, depth, &format!("StateChange {}{}", target, suffix));
+        }
+        AstNode::BinaryOp {
+            left,
+            operator,
+            right,
+        } => {
+            write_line(out, depth, &format!("BinaryOp {:?}{}", operator, suffix));
+            write_node(out, left, depth + 1, "");
+            write_node(out, right, depth + 1, "");
+        }
+        AstNode::UnaryOp { operator, operand } => {
+            write_line(out, depth, &format!("UnaryOp {:?}{}", operator, suffix));
+            write_node(out, operand, depth + 1, "");
+        }
+        AstNode::Assignment { target, value } => {
+            write_line(out, depth, &format!("Assignment{}", suffix));
+            write_node(out, target, depth + 1, "");
+            write_node(out, value, depth + 1, "");
+        }
+        AstNode::Ternary {
+            condition,
+            then_value,
+            else_value,
+        } => {
+            write_line(out, depth, &format!("Ternary{}", suffix));
+            write_node(out, condition, depth + 1, "");
+            write_node(out, then_value, depth + 1, "");
+            write_node(out, else_value, depth + 1, "");
+        }
+        AstNode::FunctionCall { name, arguments } => {
+            write_line(out, depth, &format!("FunctionCall {}{}", name, suffix));
+            for arg in arguments {
+                write_node(out, arg, depth + 1, "");
+            }
+        }
+        AstNode::ArrayAccess { array, index } => {
+            write_line(out, depth, &format!("ArrayAccess{}", suffix));
+            write_node(out, array, depth + 1, "");
+            write_node(out, index, depth + 1, "");
+        }
+        AstNode::MemberAccess { object, member } => {
+            write_line(out, depth, &format!("MemberAccess .{}{}", member, suffix));
+            write_node(out, object, depth + 1, "");
+        }
+        AstNode::Integer(value) => write_line(out, depth, &format!("Integer {}{}", value, suffix)),
+        AstNode::Float(value) => write_line(out, depth, &format!("Float {:?}{}", value, suffix)),
+        AstNode::String(value) => write_line(out, depth, &format!("String {:?}{}", value, suffix)),
+        AstNode::PackedString(value) => {
+            write_line(out, depth, &format!("PackedString {:?}{}", value, suffix))
+        }
+        AstNode::Character(value) => {
+            write_line(out, depth, &format!("Character {:?}{}", value, suffix))
+        }
+        AstNode::Boolean(value) => write_line(out, depth, &format!("Boolean {}{}", value, suffix)),
+        AstNode::Identifier(name) => {
+            write_line(out, depth, &format!("Identifier {}{}", name, suffix))
+        }
+        AstNode::TypeDefinition { name, definition } => {
+            write_line(
+                out,
+                depth,
+                &format!("TypeDefinition {} = {:?}{}", name, definition, suffix),
+            );
+        }
+        AstNode::EnumDefinition { name, variants } => {
+            write_line(out, depth, &format!("EnumDefinition {}{}", name, suffix));
+            for variant in variants {
+                write_line(out, depth + 1, &format!("Variant {}", variant.name));
+                if let Some(value) = &variant.value {
+                    write_node(out, value, depth + 2, "");
+                }
+            }
+        }
+    }
+}
+
+/// Append a function parameter with its array sizes and default value, if
+/// any
+fn write_parameter(out: &mut String, param: &Parameter, depth: usize) {
+    let mut line = String::from("Parameter ");
+    if param.is_const {
+        line.push_str("const ");
+    }
+    if param.is_reference {
+        line.push('&');
+    }
+    if !param.param_type.is_empty() {
+        line.push_str(&format!("{}:", param.param_type));
+    }
+    line.push_str(&param.name);
+    line.push_str(&"[]".repeat(param.dimensions.len()));
+    write_line(out, depth, &line);
+    for size in param.dimensions.iter().flatten() {
+        write_line(out, depth + 1, "size");
+        write_node(out, size, depth + 2, "");
+    }
+    if let Some(default) = &param.default_value {
+        write_node(out, default, depth + 1, "");
+    }
+}
@@ -1,269 +1,1591 @@
-//! Code generation from AST to AMX bytecode
-
-use crate::ast::*;
-use crate::error::*;
-use pawn_amx::instructions::{Instruction, Opcode};
-use pawn_amx::*;
-use std::collections::HashMap;
-
-/// Code generator for AMX bytecode
-pub struct CodeGenerator {
-    instructions: Vec<Instruction>,
-    data: Vec<u8>,
-    strings: Vec<String>,
-    string_map: HashMap<String, usize>,
-    label_map: HashMap<String, usize>,
-    next_label: usize,
-}
-
-impl CodeGenerator {
-    /// Create a new code generator
-    pub fn new() -> Self {
-        Self {
-            instructions: Vec::new(),
-            data: Vec::new(),
-            strings: Vec::new(),
-            string_map: HashMap::new(),
-            label_map: HashMap::new(),
-            next_label: 0,
-        }
-    }
-
-    /// Generate AMX bytecode from AST
-    pub fn generate(&mut self, ast: &AstNode) -> CompilerResult<Vec<u8>> {
-        self.instructions.clear();
-        self.data.clear();
-        self.strings.clear();
-        self.string_map.clear();
-        self.label_map.clear();
-        self.next_label = 0;
-
-        // Generate code for the AST
-        self.generate_node(ast)?;
-
-        // Add halt instruction
-        self.instructions.push(Instruction::new(Opcode::Halt, 0));
-
-        // Create AMX header
-        let mut header = AmxHeader::new();
-        header.size = (std::mem::size_of::<AmxHeader>()
-            + self.instructions.len() * 5
-            + self.data.len()) as i32;
-        header.cod = std::mem::size_of::<AmxHeader>() as i32;
-        header.dat = header.cod + (self.instructions.len() * 5) as i32;
-        header.hea = header.dat + self.data.len() as i32;
-        header.stp = header.hea;
-        // Start execution at the beginning of the code section
-        header.cip = header.cod;
-
-        // Build final bytecode
-        let mut bytecode = Vec::new();
-        bytecode.extend_from_slice(&write_header(&header));
-
-        // Add instructions
-        for instruction in &self.instructions {
-            bytecode.extend_from_slice(&instruction.to_bytes());
-        }
-
-        // Add data
-        bytecode.extend_from_slice(&self.data);
-
-        Ok(bytecode)
-    }
-
-    /// Generate code for an AST node
-    fn generate_node(&mut self, node: &AstNode) -> CompilerResult<()> {
-        match node {
-            AstNode::Program(statements) => {
-                for stmt in statements {
-                    self.generate_node(stmt)?;
-                }
-            }
-
-            AstNode::Function { name, body, .. } => {
-                if name == "main" {
-                    for stmt in body {
-                        self.generate_node(stmt)?;
-                    }
-                }
-            }
-
-            AstNode::FunctionCall { name, arguments } => {
-                if name == "printf" {
-                    self.generate_printf(arguments)?;
-                } else {
-                    return Err(CompilerError::SemanticError(format!(
-                        "Unknown function: {}",
-                        name
-                    )));
-                }
-            }
-
-            AstNode::String(s) => {
-                // Store string in data section
-                let string_id = self.add_string(s);
-                self.instructions
-                    .push(Instruction::new(Opcode::ConstPri, string_id as i32));
-            }
-
-            AstNode::Integer(n) => {
-                self.instructions
-                    .push(Instruction::new(Opcode::ConstPri, *n));
-            }
-
-            AstNode::Float(f) => {
-                // Convert float to integer representation for now
-                let int_val = *f as i32;
-                self.instructions
-                    .push(Instruction::new(Opcode::ConstPri, int_val));
-            }
-
-            AstNode::BinaryOp {
-                left,
-                operator,
-                right,
-            } => {
-                self.generate_node(left)?;
-                self.instructions.push(Instruction::new(Opcode::PushPri, 0));
-                self.generate_node(right)?;
-                self.instructions.push(Instruction::new(Opcode::PopAlt, 0));
-
-                match operator {
-                    BinaryOperator::Add => {
-                        self.instructions.push(Instruction::new(Opcode::Add, 0));
-                    }
-                    BinaryOperator::Subtract => {
-                        self.instructions.push(Instruction::new(Opcode::Sub, 0));
-                    }
-                    BinaryOperator::Multiply => {
-                        self.instructions.push(Instruction::new(Opcode::Smul, 0));
-                    }
-                    BinaryOperator::Divide => {
-                        self.instructions.push(Instruction::new(Opcode::Sdiv, 0));
-                    }
-                    BinaryOperator::Equal => {
-                        self.instructions.push(Instruction::new(Opcode::Eq, 0));
-                    }
-                    BinaryOperator::NotEqual => {
-                        self.instructions.push(Instruction::new(Opcode::Neq, 0));
-                    }
-                    BinaryOperator::Less => {
-                        self.instructions.push(Instruction::new(Opcode::Less, 0));
-                    }
-                    BinaryOperator::LessEqual => {
-                        self.instructions.push(Instruction::new(Opcode::Leq, 0));
-                    }
-                    BinaryOperator::Greater => {
-                        self.instructions.push(Instruction::new(Opcode::Grtr, 0));
-                    }
-                    BinaryOperator::GreaterEqual => {
-                        self.instructions.push(Instruction::new(Opcode::Geq, 0));
-                    }
-                    _ => {
-                        return Err(CompilerError::SemanticError(format!(
-                            "Unsupported operator: {:?}",
-                            operator
-                        )));
-                    }
-                }
-            }
-
-            AstNode::UnaryOp { operator, operand } => {
-                self.generate_node(operand)?;
-                match operator {
-                    UnaryOperator::Plus => {
-                        // No operation needed
-                    }
-                    UnaryOperator::Minus => {
-                        self.instructions.push(Instruction::new(Opcode::Neg, 0));
-                    }
-                    UnaryOperator::LogicalNot => {
-                        // For now, just negate the value
-                        self.instructions.push(Instruction::new(Opcode::Eq, 0));
-                    }
-                    _ => {
-                        return Err(CompilerError::SemanticError(format!(
-                            "Unsupported unary operator: {:?}",
-                            operator
-                        )));
-                    }
-                }
-            }
-
-            _ => {
-                return Err(CompilerError::SemanticError(format!(
-                    "Unsupported AST node: {:?}",
-                    node
-                )));
-            }
-        }
-
-        Ok(())
-    }
-
-    /// Generate printf function call
-    fn generate_printf(&mut self, arguments: &[AstNode]) -> CompilerResult<()> {
-        if arguments.is_empty() {
-            return Err(CompilerError::SemanticError(
-                "printf requires at least one argument".to_string(),
-            ));
-        }
-
-        // For now, just print the first argument as a string
-        if let AstNode::String(s) = &arguments[0] {
-            // In a real implementation, we would call a native printf function
-            // For MVP, we'll just simulate it by storing the string
-            let string_id = self.add_string(s);
-            self.instructions
-                .push(Instruction::new(Opcode::ConstPri, string_id as i32));
-            // Call printf native (index 0 for now)
-            self.instructions.push(Instruction::new(Opcode::Sysreq, 0));
-        } else {
-            return Err(CompilerError::SemanticError(
-                "printf first argument must be a string".to_string(),
-            ));
-        }
-
-        Ok(())
-    }
-
-    /// Add a string to the data section
-    fn add_string(&mut self, s: &str) -> usize {
-        if let Some(&id) = self.string_map.get(s) {
-            return id;
-        }
-
-        let id = self.strings.len();
-        self.strings.push(s.to_string());
-        self.string_map.insert(s.to_string(), id);
-
-        // Store string in data section
-        let string_bytes = s.as_bytes();
-        let _start_offset = self.data.len();
-        self.data.extend_from_slice(string_bytes);
-        self.data.push(0); // Null terminator
-
-        id
-    }
-
-    /// Create a new label
-    #[allow(dead_code)]
-    fn create_label(&mut self) -> String {
-        let label = format!("label_{}", self.next_label);
-        self.next_label += 1;
-        label
-    }
-
-    /// Set label position
-    #[allow(dead_code)]
-    fn set_label(&mut self, label: &str) {
-        self.label_map
-            .insert(label.to_string(), self.instructions.len());
-    }
-
-    /// Get label address
-    #[allow(dead_code)]
-    fn get_label_address(&self, label: &str) -> Option<i32> {
-        self.label_map.get(label).map(|&addr| addr as i32)
-    }
-}
+//! Code generation from AST to AMX bytecode
+
+use crate::ast::*;
+use crate::config::CodegenConfig;
+use crate::error::*;
+use pawn_amx::instructions::{Instruction, Opcode};
+use pawn_amx::*;
+use std::collections::{HashMap, HashSet};
+
+/// Size of the combined stack and heap area reserved after the data section,
+/// matching the pawncc default of 4096 cells
+const STACK_HEAP_SIZE: i32 = 4096 * CELL_BYTES as i32;
+
+/// Size of a cell in bytes
+const CELL_SIZE: Cell = CELL_BYTES as Cell;
+
+/// Flag set on the identifiers of tags starting with an uppercase letter,
+/// which pawncc calls strong or fixed tags
+const FIXED_TAG: Cell = 0x4000_0000;
+
+/// Storage of a variable known to the code generator
+#[derive(Debug, Clone, Copy)]
+struct Variable {
+    /// DAT-relative address for globals, frame offset for locals
+    address: Cell,
+    is_local: bool,
+    /// Number of cells, for arrays; 0 when an array parameter leaves its
+    /// size open
+    array_size: Option<Cell>,
+    /// Whether the variable's cell holds the address of the array, as for
+    /// array parameters
+    by_address: bool,
+    /// Identifier of the declared tag, as `tagof` yields it
+    tag: Cell,
+}
+
+/// Jump targets of an enclosing loop
+#[derive(Debug, Clone)]
+struct LoopContext {
+    /// Label `continue` jumps to
+    continue_label: String,
+    /// Label `break` jumps to
+    break_label: String,
+}
+
+/// Code generator for AMX bytecode
+pub struct CodeGenerator {
+    instructions: Vec<Instruction>,
+    data: Vec<u8>,
+    /// Data section address of each string literal, so each is stored once
+    string_map: HashMap<String, Cell>,
+    label_map: HashMap<String, usize>,
+    /// Jump/call instructions whose operand refers to a label
+    fixups: Vec<(usize, String)>,
+    next_label: usize,
+    /// Global variables, stored in the data section
+    globals: HashMap<String, Variable>,
+    /// Local variable scopes of the function being generated
+    locals: Vec<HashMap<String, Variable>>,
+    /// Bytes of the frame taken by locals currently in scope
+    frame_size: Cell,
+    /// Bytes reserved for locals by the function prologue
+    frame_reserved: Cell,
+    /// Loops enclosing the statement being generated, innermost last
+    loops: Vec<LoopContext>,
+    /// Natives called by the program, in SYSREQ index order
+    natives: Vec<String>,
+    /// Functions with a body in the program; calls to them jump to a label
+    /// named after the function
+    functions: HashSet<String>,
+    /// Natives declared in the program and their parameters
+    native_functions: HashMap<String, Vec<Parameter>>,
+    /// Natives provided by the host without a declaration in the program
+    host_natives: Vec<String>,
+    /// Tags declared in the program and their identifiers, in order of
+    /// first use
+    tags: Vec<(String, Cell)>,
+    /// Public functions and their code addresses
+    publics: Vec<(String, UCell)>,
+    /// Code address of `main`, if the program has one
+    main_address: Option<Cell>,
+    /// Whether the function being generated is `main`, which returns to the
+    /// host by halting
+    in_main: bool,
+    /// Whether to emit debug information
+    debug: bool,
+    /// Whether to leave out array bounds checks
+    no_checks: bool,
+    /// Whether to run the peephole optimizer before relocation
+    optimize: bool,
+    /// Alignment in bytes the data section is padded to; 0 leaves it as is
+    cell_alignment: usize,
+    /// Instruction index of each statement and its source line
+    line_table: Vec<(usize, u32)>,
+}
+
+impl CodeGenerator {
+    /// Create a new code generator
+    pub fn new() -> Self {
+        Self {
+            instructions: Vec::new(),
+            data: Vec::new(),
+            string_map: HashMap::new(),
+            label_map: HashMap::new(),
+            fixups: Vec::new(),
+            next_label: 0,
+            globals: HashMap::new(),
+            locals: Vec::new(),
+            frame_size: 0,
+            frame_reserved: 0,
+            loops: Vec::new(),
+            natives: Vec::new(),
+            functions: HashSet::new(),
+            native_functions: HashMap::new(),
+            host_natives: Vec::new(),
+            tags: Vec::new(),
+            publics: Vec::new(),
+            main_address: None,
+            in_main: false,
+            debug: false,
+            no_checks: false,
+            optimize: false,
+            cell_alignment: 0,
+            line_table: Vec::new(),
+        }
+    }
+
+    /// Create a code generator with the options of a `codegen` config section
+    pub fn with_config(config: &CodegenConfig) -> Self {
+        let mut codegen = Self::new();
+        codegen.set_debug(config.emit_debug);
+        codegen.set_optimize(config.optimize);
+        codegen.cell_alignment = config.cell_alignment;
+        codegen
+    }
+
+    /// Enable or disable emitting debug information with a line table
+    pub fn set_debug(&mut self, debug: bool) {
+        self.debug = debug;
+    }
+
+    /// Leave out `BOUNDS` checks on array indices and mark the program as
+    /// unchecked
+    pub fn set_no_checks(&mut self, no_checks: bool) {
+        self.no_checks = no_checks;
+    }
+
+    /// Treat each name as a native provided by the host, called through
+    /// `SYSREQ` without a `native` declaration
+    pub fn set_globals(&mut self, globals: &[String]) {
+        self.host_natives = globals.to_vec();
+    }
+
+    /// Enable or disable the peephole optimizer
+    pub fn set_optimize(&mut self, optimize: bool) {
+        self.optimize = optimize;
+    }
+
+    /// Size in bytes of the data section generated so far
+    pub fn data_len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Instructions produced by the last call to `generate`
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    /// Render the generated instructions as assembly text
+    ///
+    /// Jump and call targets are shown as their labels rather than byte
+    /// offsets, and `SYSREQ` operands are annotated with the native's name.
+    /// The output is accepted by [`pawn_amx::assemble`].
+    pub fn to_asm(&self) -> String {
+        let mut labels: Vec<(usize, &str)> = self
+            .label_map
+            .iter()
+            .map(|(label, &index)| (index, label.as_str()))
+            .collect();
+        labels.sort();
+        let targets: HashMap<usize, &str> = self
+            .fixups
+            .iter()
+            .map(|(index, label)| (*index, label.as_str()))
+            .collect();
+
+        let mut out = String::new();
+        let mut labels = labels.into_iter().peekable();
+        for (index, instruction) in self.instructions.iter().enumerate() {
+            while let Some((_, label)) = labels.next_if(|&(at, _)| at == index) {
+                out.push_str(&format!("{}:\n", label));
+            }
+            out.push_str("    ");
+            out.push_str(instruction.opcode.name());
+            if let Some(label) = targets.get(&index) {
+                out.push_str(&format!(" {}", label));
+            } else if instruction.operand != 0 || instruction.opcode == Opcode::Sysreq {
+                out.push_str(&format!(" {}", instruction.operand));
+            }
+            if instruction.opcode == Opcode::Sysreq
+                && let Some(native) = self.natives.get(instruction.operand as usize)
+            {
+                out.push_str(&format!(" # {}", native));
+            }
+            out.push('\n');
+        }
+        for (_, label) in labels {
+            out.push_str(&format!("{}:\n", label));
+        }
+        out
+    }
+
+    /// Generate AMX bytecode from AST
+    pub fn generate(&mut self, ast: &AstNode) -> CompilerResult<Vec<u8>> {
+        self.instructions.clear();
+        self.data.clear();
+        self.string_map.clear();
+        self.label_map.clear();
+        self.fixups.clear();
+        self.next_label = 0;
+        self.globals.clear();
+        self.locals.clear();
+        self.frame_size = 0;
+        self.frame_reserved = 0;
+        self.loops.clear();
+        self.natives.clear();
+        self.publics.clear();
+        self.functions.clear();
+        self.native_functions.clear();
+        for name in &self.host_natives {
+            self.native_functions.insert(name.clone(), Vec::new());
+        }
+        self.tags.clear();
+        if let AstNode::Program(items) = ast.inner() {
+            for item in items {
+                match item.inner() {
+                    AstNode::Function {
+                        name,
+                        parameters,
+                        is_native: true,
+                        ..
+                    } => {
+                        self.native_functions
+                            .insert(name.clone(), parameters.clone());
+                    }
+                    AstNode::Function {
+                        name,
+                        is_forward: false,
+                        ..
+                    } => {
+                        self.functions.insert(name.clone());
+                    }
+                    _ => {}
+                }
+            }
+        }
+        self.main_address = None;
+        self.line_table.clear();
+
+        // Generate code for the AST
+        self.generate_node(ast)?;
+
+        // Add halt instruction
+        self.instructions.push(Instruction::halt());
+
+        if self.optimize {
+            self.peephole();
+        }
+        if self.cell_alignment > 0 {
+            let padded = self.data.len().div_ceil(self.cell_alignment) * self.cell_alignment;
+            self.data.resize(padded, 0);
+        }
+
+        // Resolve label references to their final code addresses
+        self.relocate()?;
+
+        // Lay out the file as pawncc does. CIP and all jump/call targets are
+        // relative to the start of the code section; without a `main` there
+        // is no entry point
+        let program = AmxProgram {
+            flags: AmxFlags {
+                debug: self.debug,
+                no_checks: self.no_checks,
+                ..AmxFlags::new()
+            },
+            code: self
+                .instructions
+                .iter()
+                .flat_map(|instruction| instruction.to_bytes())
+                .collect(),
+            data: self.data.clone(),
+            stack_heap_size: STACK_HEAP_SIZE,
+            cip: self.main_address.unwrap_or(-1),
+            publics: self.publics.clone(),
+            natives: self.natives.clone(),
+            tags: self.tags.clone(),
+            ..AmxProgram::default()
+        };
+        let mut bytecode = write_amx(&program);
+
+        // Debug information follows the program image
+        if self.debug {
+            let addresses = self.code_addresses();
+            let lines: Vec<(Cell, u32)> = self
+                .line_table
+                .iter()
+                .map(|&(index, line)| (addresses[index], line))
+                .collect();
+            bytecode.extend_from_slice(&write_debug_info(&lines));
+        }
+
+        Ok(bytecode)
+    }
+
+    /// Generate code for an AST node
+    fn generate_node(&mut self, node: &AstNode) -> CompilerResult<()> {
+        match node {
+            AstNode::Program(statements) => {
+                for stmt in statements {
+                    self.generate_node(stmt)?;
+                }
+            }
+
+            AstNode::Located { span, node } => {
+                if self.debug {
+                    self.record_line(span.line as u32);
+                }
+                self.generate_node(node)?;
+            }
+
+            AstNode::Function {
+                name,
+                parameters,
+                body,
+                is_public,
+                is_native,
+                is_forward,
+                states,
+                ..
+            } => {
+                if states.is_some() {
+                    return Err(CompilerError::SemanticError(format!(
+                        "States are not yet supported (function '{}')",
+                        name
+                    )));
+                }
+                // Natives and forward declarations have no code
+                if !is_native && !is_forward {
+                    self.generate_function(name, parameters, body, *is_public)?;
+                }
+            }
+
+            AstNode::Block(statements) => {
+                let frame_size = self.frame_size;
+                self.locals.push(HashMap::new());
+                for stmt in statements {
+                    self.generate_node(stmt)?;
+                }
+                self.exit_scope(frame_size);
+            }
+
+            AstNode::VariableDeclaration {
+                name,
+                var_type,
+                array_size,
+                initializer,
+                ..
+            } => {
+                let tag = self.tag_id(var_type);
+                self.generate_variable_declaration(name, tag, array_size, initializer)?;
+            }
+
+            AstNode::Return(value) => {
+                if let Some(value) = value {
+                    self.generate_node(value)?;
+                }
+                self.emit_return();
+            }
+
+            // A failed assertion halts with AMX_ERR_ASSERT, as pawncc does
+            AstNode::Assert(condition) => {
+                self.generate_node(condition)?;
+                let passed = self.create_label();
+                self.emit_jump(Opcode::Jnz, &passed);
+                self.instructions
+                    .push(Instruction::new(Opcode::Halt, AmxError::Assert as Cell));
+                self.set_label(&passed);
+            }
+
+            AstNode::Identifier(name) => {
+                let var = self.lookup_variable(name)?;
+                match (var.is_local, var.array_size) {
+                    (true, None) => {
+                        self.instructions.push(Instruction::load_s_pri(var.address));
+                    }
+                    // Arrays evaluate to their address, which an array
+                    // parameter holds
+                    (true, Some(_)) if var.by_address => {
+                        self.instructions.push(Instruction::load_s_pri(var.address));
+                    }
+                    (true, Some(_)) => {
+                        self.instructions.push(Instruction::addr_pri(var.address));
+                    }
+                    (false, None) => {
+                        self.instructions.push(Instruction::load_pri(var.address));
+                    }
+                    (false, Some(_)) => {
+                        self.instructions.push(Instruction::const_pri(var.address));
+                    }
+                }
+            }
+
+            AstNode::ArrayAccess { array, index } => {
+                self.generate_index(array, index)?;
+                self.instructions.push(Instruction::lidx());
+            }
+
+            AstNode::Assignment { target, value } => {
+                self.generate_assignment(target, value)?;
+            }
+
+            AstNode::Ternary {
+                condition,
+                then_value,
+                else_value,
+            } => {
+                self.generate_conditional(condition, then_value, Some(else_value))?;
+            }
+
+            AstNode::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.generate_conditional(condition, then_branch, else_branch.as_deref())?;
+            }
+
+            AstNode::While { condition, body } => {
+                let start_label = self.create_label();
+                let end_label = self.create_label();
+
+                self.set_label(&start_label);
+                self.generate_branch(condition, false, &end_label)?;
+                self.generate_loop_body(body, &start_label, &end_label)?;
+                self.emit_jump(Opcode::Jump, &start_label);
+                self.set_label(&end_label);
+            }
+
+            AstNode::For {
+                init,
+                condition,
+                update,
+                body,
+            } => {
+                // A variable declared by the initializer is scoped to the loop
+                let frame_size = self.frame_size;
+                self.locals.push(HashMap::new());
+                if let Some(init) = init {
+                    self.generate_node(init)?;
+                }
+
+                let start_label = self.create_label();
+                let update_label = self.create_label();
+                let end_label = self.create_label();
+
+                self.set_label(&start_label);
+                if let Some(condition) = condition {
+                    self.generate_branch(condition, false, &end_label)?;
+                }
+                self.generate_loop_body(body, &update_label, &end_label)?;
+                self.set_label(&update_label);
+                if let Some(update) = update {
+                    self.generate_node(update)?;
+                }
+                self.emit_jump(Opcode::Jump, &start_label);
+                self.set_label(&end_label);
+                self.exit_scope(frame_size);
+            }
+
+            AstNode::DoWhile { body, condition } => {
+                let start_label = self.create_label();
+                let condition_label = self.create_label();
+                let end_label = self.create_label();
+
+                self.set_label(&start_label);
+                self.generate_loop_body(body, &condition_label, &end_label)?;
+                self.set_label(&condition_label);
+                self.generate_branch(condition, true, &start_label)?;
+                self.set_label(&end_label);
+            }
+
+            AstNode::Break => {
+                let target = self.enclosing_loop("break")?;
+                self.emit_jump(Opcode::Jump, &target.break_label);
+            }
+
+            AstNode::Continue => {
+                let target = self.enclosing_loop("continue")?;
+                self.emit_jump(Opcode::Jump, &target.continue_label);
+            }
+
+            AstNode::FunctionCall { name, arguments } => {
+                if name == "printf" {
+                    self.generate_printf(arguments)?;
+                } else if self.functions.contains(name) {
+                    self.generate_call(name, arguments)?;
+                } else if let Some(parameters) = self.native_functions.get(name).cloned() {
+                    self.generate_native_call(name, &parameters, arguments)?;
+                } else {
+                    return Err(CompilerError::SemanticError(format!(
+                        "Unknown function: {}",
+                        name
+                    )));
+                }
+            }
+
+            AstNode::String(s) => {
+                // Store string in data section
+                let address = self.add_string(s);
+                self.instructions.push(Instruction::const_pri(address));
+            }
+
+            AstNode::PackedString(s) => {
+                let address = self.add_packed_string(s);
+                self.instructions.push(Instruction::const_pri(address));
+            }
+
+            AstNode::Integer(n) => {
+                self.instructions.push(Instruction::const_pri(*n as Cell));
+            }
+
+            AstNode::Boolean(value) => {
+                self.instructions
+                    .push(Instruction::const_pri(*value as Cell));
+            }
+
+            AstNode::Float(f) => {
+                // Floats are stored as their IEEE 754 bits, as the float
+                // natives expect
+                self.instructions
+                    .push(Instruction::const_pri(f.to_bits() as Cell));
+            }
+
+            AstNode::BinaryOp {
+                left,
+                operator,
+                right,
+            } => {
+                if let Some(operator) = Self::compound_operator(operator) {
+                    self.generate_compound_assignment(left, operator, right)?;
+                } else if matches!(
+                    operator,
+                    BinaryOperator::LogicalAnd | BinaryOperator::LogicalOr
+                ) {
+                    self.generate_logical(left, operator, right)?;
+                } else {
+                    self.generate_node(left)?;
+                    self.generate_right_operand(right)?;
+                    self.generate_operator(operator)?;
+                }
+            }
+
+            AstNode::UnaryOp {
+                operator:
+                    operator @ (UnaryOperator::Increment
+                    | UnaryOperator::Decrement
+                    | UnaryOperator::PostIncrement
+                    | UnaryOperator::PostDecrement),
+                operand,
+            } => {
+                self.generate_increment(operand, operator)?;
+            }
+
+            AstNode::UnaryOp {
+                operator: operator @ (UnaryOperator::Sizeof | UnaryOperator::Tagof),
+                operand,
+            } => {
+                let AstNode::Identifier(name) = operand.inner() else {
+                    return Err(CompilerError::SemanticError(format!(
+                        "{:?} needs a variable operand",
+                        operator
+                    )));
+                };
+                let var = self.lookup_variable(name)?;
+                let value = if *operator == UnaryOperator::Sizeof {
+                    var.array_size.unwrap_or(1)
+                } else {
+                    var.tag
+                };
+                self.instructions.push(Instruction::const_pri(value));
+            }
+
+            AstNode::UnaryOp { operator, operand } => {
+                self.generate_node(operand)?;
+                match operator {
+                    UnaryOperator::Plus => {
+                        // No operation needed
+                    }
+                    UnaryOperator::Minus => {
+                        self.instructions.push(Instruction::neg());
+                    }
+                    UnaryOperator::LogicalNot => {
+                        self.instructions.push(Instruction::not());
+                    }
+                    UnaryOperator::BitwiseNot => {
+                        self.instructions.push(Instruction::invert());
+                    }
+                    _ => {
+                        return Err(CompilerError::SemanticError(format!(
+                            "Unsupported unary operator: {:?}",
+                            operator
+                        )));
+                    }
+                }
+            }
+
+            AstNode::StateChange { .. } => {
+                return Err(CompilerError::SemanticError(
+                    "States are not yet supported".to_string(),
+                ));
+            }
+
+            _ => {
+                return Err(CompilerError::SemanticError(format!(
+                    "Unsupported AST node: {:?}",
+                    node
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Generate a function: its prologue, body and a return for falling off
+    /// the end
+    ///
+    /// Functions other than `main` are entered with `CALL`, so they save the
+    /// caller's frame with `PROC` and return with `RETN`. The caller pushes
+    /// the arguments last to first, then their size in bytes.
+    fn generate_function(
+        &mut self,
+        name: &str,
+        parameters: &[Parameter],
+        body: &[AstNode],
+        is_public: bool,
+    ) -> CompilerResult<()> {
+        let address = self.code_addresses()[self.instructions.len()];
+        self.set_label(name);
+        if is_public {
+            self.publics.push((name.to_string(), address as UCell));
+        }
+        self.in_main = name == "main";
+        if self.in_main {
+            self.main_address = Some(address);
+        } else {
+            self.instructions.push(Instruction::proc());
+        }
+
+        // The saved FRM, the return address and the argument size lie
+        // between the frame and the first argument, above it
+        let mut arguments = HashMap::new();
+        for (index, parameter) in parameters.iter().enumerate() {
+            if parameter.is_reference {
+                return Err(CompilerError::SemanticError(format!(
+                    "Reference parameters are not supported yet: {}",
+                    parameter.name
+                )));
+            }
+            // Arrays are passed as the address of their first cell
+            let array_size = match parameter.dimensions.as_slice() {
+                [] => None,
+                [size] => Some(size.as_deref().and_then(Self::constant_value).unwrap_or(0)),
+                _ => {
+                    return Err(CompilerError::SemanticError(format!(
+                        "Multi-dimensional array parameters are not supported: {}",
+                        parameter.name
+                    )));
+                }
+            };
+            let tag = self.tag_id(&parameter.param_type);
+            arguments.insert(
+                parameter.name.clone(),
+                Variable {
+                    address: (3 + index as Cell) * CELL_SIZE,
+                    is_local: true,
+                    array_size,
+                    by_address: array_size.is_some(),
+                    tag,
+                },
+            );
+        }
+
+        // Reserve the frame for every local up front
+        self.frame_size = 0;
+        self.frame_reserved = Self::frame_bytes(body);
+        if self.frame_reserved > 0 {
+            self.instructions
+                .push(Instruction::stack(-self.frame_reserved));
+        }
+        self.locals.push(arguments);
+        for stmt in body {
+            self.generate_node(stmt)?;
+        }
+        self.exit_scope(0);
+
+        if !self.in_main {
+            self.instructions.push(Instruction::zero_pri());
+        }
+        self.emit_return();
+        Ok(())
+    }
+
+    /// Release the frame and return the value in PRI; `main` returns to the
+    /// host by halting
+    fn emit_return(&mut self) {
+        if self.frame_reserved > 0 {
+            self.instructions
+                .push(Instruction::stack(self.frame_reserved));
+        }
+        let opcode = if self.in_main {
+            Opcode::Halt
+        } else {
+            Opcode::Retn
+        };
+        self.instructions.push(Instruction::new(opcode, 0));
+    }
+
+    /// Call a function defined in the program
+    ///
+    /// Arguments are pushed last to first, followed by their size in bytes;
+    /// the callee's `RETN` removes them again.
+    fn generate_call(&mut self, name: &str, arguments: &[AstNode]) -> CompilerResult<()> {
+        self.push_arguments(arguments, &[])?;
+        self.emit_jump(Opcode::Call, name);
+        Ok(())
+    }
+
+    /// Call a native declared in the program
+    ///
+    /// Arguments for reference parameters are passed as the address of the
+    /// variable, so the native can write to it.
+    fn generate_native_call(
+        &mut self,
+        name: &str,
+        parameters: &[Parameter],
+        arguments: &[AstNode],
+    ) -> CompilerResult<()> {
+        let size = self.push_arguments(arguments, parameters)?;
+        let index = self.native_index(name);
+        self.instructions.push(Instruction::sysreq(index));
+        self.instructions.push(Instruction::stack(size + CELL_SIZE));
+        Ok(())
+    }
+
+    /// Push call arguments last to first, then their size in bytes, which
+    /// is returned
+    ///
+    /// Constants are pushed with `PUSH.C` and addresses of locals with
+    /// `PUSH.ADDR`; other values are computed into PRI and pushed from there.
+    fn push_arguments(
+        &mut self,
+        arguments: &[AstNode],
+        parameters: &[Parameter],
+    ) -> CompilerResult<Cell> {
+        for (index, argument) in arguments.iter().enumerate().rev() {
+            let by_reference = parameters
+                .get(index)
+                .is_some_and(|parameter| parameter.is_reference);
+            self.push_argument(argument, by_reference)?;
+        }
+        let size = arguments.len() as Cell * CELL_SIZE;
+        self.instructions.push(Instruction::push_c(size));
+        Ok(size)
+    }
+
+    /// Push one call argument
+    fn push_argument(&mut self, argument: &AstNode, by_reference: bool) -> CompilerResult<()> {
+        match argument.inner() {
+            AstNode::Identifier(name) => {
+                let var = self.lookup_variable(name)?;
+                // The value of an array parameter is already the address
+                let array = var.array_size.is_some() && !var.by_address;
+                match (var.is_local, by_reference || array) {
+                    (true, true) => {
+                        self.instructions.push(Instruction::push_addr(var.address));
+                    }
+                    (false, true) => {
+                        self.instructions.push(Instruction::push_c(var.address));
+                    }
+                    (_, false) => {
+                        self.generate_node(argument)?;
+                        self.instructions.push(Instruction::push_pri());
+                    }
+                }
+            }
+            _ if by_reference => {
+                return Err(CompilerError::SemanticError(
+                    "Argument passed by reference must be a variable".to_string(),
+                ));
+            }
+            AstNode::Integer(value) => {
+                self.instructions.push(Instruction::push_c(*value as Cell));
+            }
+            AstNode::String(text) => {
+                let address = self.add_string(text);
+                self.instructions.push(Instruction::push_c(address));
+            }
+            _ => {
+                self.generate_node(argument)?;
+                self.instructions.push(Instruction::push_pri());
+            }
+        }
+        Ok(())
+    }
+
+    /// Generate printf function call
+    fn generate_printf(&mut self, arguments: &[AstNode]) -> CompilerResult<()> {
+        if arguments.is_empty() {
+            return Err(CompilerError::SemanticError(
+                "printf requires at least one argument".to_string(),
+            ));
+        }
+
+        if !matches!(arguments[0], AstNode::String(_)) {
+            return Err(CompilerError::SemanticError(
+                "printf first argument must be a string".to_string(),
+            ));
+        }
+
+        let size = self.push_arguments(arguments, &[])?;
+        let index = self.native_index("printf");
+        self.instructions.push(Instruction::sysreq(index));
+        self.instructions.push(Instruction::stack(size + CELL_SIZE));
+
+        Ok(())
+    }
+
+    /// Generate storage for a variable declaration
+    ///
+    /// Globals are placed in the data section and must have a constant
+    /// initializer. Locals live in the frame reserved by the prologue, after
+    /// the locals still in scope; they are zeroed unless initialized.
+    fn generate_variable_declaration(
+        &mut self,
+        name: &str,
+        tag: Cell,
+        array_size: &Option<Box<AstNode>>,
+        initializer: &Option<Box<AstNode>>,
+    ) -> CompilerResult<()> {
+        let array_size = match array_size {
+            Some(size) => Some(
+                Self::constant_value(size)
+                    .filter(|&cells| cells > 0)
+                    .ok_or_else(|| {
+                        CompilerError::SemanticError(format!(
+                            "Array size of '{}' must be a positive constant",
+                            name
+                        ))
+                    })?,
+            ),
+            None => None,
+        };
+        if array_size.is_some() && initializer.is_some() {
+            return Err(CompilerError::SemanticError(format!(
+                "Array initializers are not supported yet: {}",
+                name
+            )));
+        }
+        let cells = array_size.unwrap_or(1);
+
+        if self.locals.is_empty() {
+            let value = match initializer {
+                Some(init) => Self::constant_value(init).ok_or_else(|| {
+                    CompilerError::SemanticError(format!(
+                        "Initializer of global '{}' must be constant",
+                        name
+                    ))
+                })?,
+                None => 0,
+            };
+
+            // Keep cells aligned after byte-sized string data
+            self.data
+                .resize(self.data.len().next_multiple_of(CELL_SIZE as usize), 0);
+            let address = self.data.len() as Cell;
+            self.data.extend_from_slice(&value.to_le_bytes());
+            self.data.resize(
+                self.data.len() + (cells - 1) as usize * CELL_SIZE as usize,
+                0,
+            );
+
+            self.globals.insert(
+                name.to_string(),
+                Variable {
+                    address,
+                    is_local: false,
+                    array_size,
+                    by_address: false,
+                    tag,
+                },
+            );
+        } else {
+            // Locals lie below the frame, each starting at its lowest cell
+            self.frame_size += cells * CELL_SIZE;
+            let address = -self.frame_size;
+            let addresses = (0..cells).map(|cell| address + cell * CELL_SIZE);
+            match initializer {
+                Some(init) => {
+                    self.generate_node(init)?;
+                    for cell in addresses {
+                        self.instructions.push(Instruction::stor_s_pri(cell));
+                    }
+                }
+                None => {
+                    for cell in addresses {
+                        self.instructions.push(Instruction::zero_s(cell));
+                    }
+                }
+            }
+            if let Some(scope) = self.locals.last_mut() {
+                scope.insert(
+                    name.to_string(),
+                    Variable {
+                        address,
+                        is_local: true,
+                        array_size,
+                        by_address: false,
+                        tag,
+                    },
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Leave a local scope, so later locals can reuse its frame space
+    fn exit_scope(&mut self, frame_size: Cell) {
+        self.locals.pop();
+        self.frame_size = frame_size;
+    }
+
+    /// Bytes of frame needed by the locals of a function body
+    ///
+    /// Sibling blocks share their space, so this is the deepest nesting of
+    /// declarations rather than their total.
+    fn frame_bytes(statements: &[AstNode]) -> Cell {
+        let mut in_scope = 0;
+        let mut peak = 0;
+        for statement in statements {
+            let nested = Self::nested_frame_bytes(statement);
+            peak = peak.max(in_scope + nested);
+            if let AstNode::VariableDeclaration { array_size, .. } = statement.inner() {
+                let cells = array_size
+                    .as_deref()
+                    .and_then(Self::constant_value)
+                    .unwrap_or(1);
+                in_scope += cells.max(1) * CELL_SIZE;
+                peak = peak.max(in_scope);
+            }
+        }
+        peak
+    }
+
+    /// Frame bytes needed by the blocks nested in a statement
+    fn nested_frame_bytes(statement: &AstNode) -> Cell {
+        match statement.inner() {
+            AstNode::Block(statements) => Self::frame_bytes(statements),
+            AstNode::If {
+                then_branch,
+                else_branch,
+                ..
+            } => Self::nested_frame_bytes(then_branch)
+                .max(else_branch.as_deref().map_or(0, Self::nested_frame_bytes)),
+            AstNode::While { body, .. } | AstNode::DoWhile { body, .. } => {
+                Self::nested_frame_bytes(body)
+            }
+            AstNode::For { init, body, .. } => {
+                let init_bytes = init
+                    .as_deref()
+                    .map_or(0, |init| Self::frame_bytes(std::slice::from_ref(init)));
+                init_bytes + Self::nested_frame_bytes(body)
+            }
+            _ => 0,
+        }
+    }
+
+    /// SYSREQ index of a native, adding it to the native table on first use
+    fn native_index(&mut self, name: &str) -> Cell {
+        let index = match self.natives.iter().position(|native| native == name) {
+            Some(index) => index,
+            None => {
+                self.natives.push(name.to_string());
+                self.natives.len() - 1
+            }
+        };
+        index as Cell
+    }
+
+    /// Generate a loop body with `continue` and `break` bound to the given labels
+    fn generate_loop_body(
+        &mut self,
+        body: &AstNode,
+        continue_label: &str,
+        break_label: &str,
+    ) -> CompilerResult<()> {
+        self.loops.push(LoopContext {
+            continue_label: continue_label.to_string(),
+            break_label: break_label.to_string(),
+        });
+        let result = self.generate_node(body);
+        self.loops.pop();
+        result
+    }
+
+    /// The innermost loop, or an error naming the misplaced `statement`
+    fn enclosing_loop(&self, statement: &str) -> CompilerResult<LoopContext> {
+        self.loops.last().cloned().ok_or_else(|| {
+            CompilerError::SemanticError(format!("'{}' outside of a loop", statement))
+        })
+    }
+
+    /// Find a variable, searching the innermost scope first
+    fn lookup_variable(&self, name: &str) -> CompilerResult<Variable> {
+        self.locals
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .or_else(|| self.globals.get(name))
+            .copied()
+            .ok_or_else(|| CompilerError::SemanticError(format!("Undefined variable: {}", name)))
+    }
+
+    /// Identifier of a tag, assigning the next free one on first use
+    ///
+    /// Untagged values have identifier 0.
+    fn tag_id(&mut self, tag: &str) -> Cell {
+        if tag.is_empty() || tag == "_" {
+            return 0;
+        }
+        if let Some(&(_, id)) = self.tags.iter().find(|(name, _)| name == tag) {
+            return id;
+        }
+        let mut id = self.tags.len() as Cell + 1;
+        if tag.starts_with(|c: char| c.is_ascii_uppercase()) {
+            id |= FIXED_TAG;
+        }
+        self.tags.push((tag.to_string(), id));
+        id
+    }
+
+    /// Evaluate a constant expression such as an array size
+    fn constant_value(node: &AstNode) -> Option<Cell> {
+        match node {
+            AstNode::Integer(n) => Some(*n as Cell),
+            AstNode::Boolean(value) => Some(*value as Cell),
+            AstNode::Character(c) => Some(*c as Cell),
+            AstNode::UnaryOp {
+                operator: UnaryOperator::Minus,
+                operand,
+            } => Self::constant_value(operand).map(Cell::wrapping_neg),
+            AstNode::BinaryOp {
+                left,
+                operator,
+                right,
+            } => {
+                let (left, right) = (Self::constant_value(left)?, Self::constant_value(right)?);
+                match operator {
+                    BinaryOperator::Add => Some(left.wrapping_add(right)),
+                    BinaryOperator::Subtract => Some(left.wrapping_sub(right)),
+                    BinaryOperator::Multiply => Some(left.wrapping_mul(right)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Compute a bounds-checked array index into PRI and the array base into ALT
+    fn generate_index(&mut self, array: &AstNode, index: &AstNode) -> CompilerResult<()> {
+        let AstNode::Identifier(name) = array else {
+            return Err(CompilerError::SemanticError(
+                "Only named arrays can be indexed".to_string(),
+            ));
+        };
+        let var = self.lookup_variable(name)?;
+        let Some(size) = var.array_size else {
+            return Err(CompilerError::SemanticError(format!(
+                "'{}' is not an array",
+                name
+            )));
+        };
+
+        self.generate_node(index)?;
+        // Array parameters of open size are not bounds-checked
+        if !self.no_checks && size > 0 {
+            self.instructions.push(Instruction::bounds(size - 1));
+        }
+        let opcode = if var.by_address {
+            Opcode::LoadSAlt
+        } else if var.is_local {
+            Opcode::AddrAlt
+        } else {
+            Opcode::ConstAlt
+        };
+        self.instructions
+            .push(Instruction::new(opcode, var.address));
+
+        Ok(())
+    }
+
+    /// Generate an if/else statement or a conditional expression
+    fn generate_conditional(
+        &mut self,
+        condition: &AstNode,
+        then_branch: &AstNode,
+        else_branch: Option<&AstNode>,
+    ) -> CompilerResult<()> {
+        let else_label = self.create_label();
+
+        self.generate_branch(condition, false, &else_label)?;
+        self.generate_node(then_branch)?;
+        match else_branch {
+            Some(else_branch) => {
+                let end_label = self.create_label();
+                self.emit_jump(Opcode::Jump, &end_label);
+                self.set_label(&else_label);
+                self.generate_node(else_branch)?;
+                self.set_label(&end_label);
+            }
+            None => self.set_label(&else_label),
+        }
+
+        Ok(())
+    }
+
+    /// Jump to `label` when `condition` evaluates to `when`
+    ///
+    /// A comparison branches on PRI and ALT directly rather than computing
+    /// 0 or 1 and testing that.
+    fn generate_branch(
+        &mut self,
+        condition: &AstNode,
+        when: bool,
+        label: &str,
+    ) -> CompilerResult<()> {
+        if let AstNode::BinaryOp {
+            left,
+            operator,
+            right,
+        } = condition.inner()
+            && let Some((taken, not_taken)) = Self::compare_jumps(operator)
+        {
+            self.generate_node(left)?;
+            self.generate_right_operand(right)?;
+            self.emit_jump(if when { taken } else { not_taken }, label);
+        } else {
+            self.generate_node(condition)?;
+            self.emit_jump(if when { Opcode::Jnz } else { Opcode::Jzer }, label);
+        }
+        Ok(())
+    }
+
+    /// The jumps taken when a comparison holds and when it does not
+    fn compare_jumps(operator: &BinaryOperator) -> Option<(Opcode, Opcode)> {
+        match operator {
+            BinaryOperator::Equal => Some((Opcode::Jeq, Opcode::Jneq)),
+            BinaryOperator::NotEqual => Some((Opcode::Jneq, Opcode::Jeq)),
+            BinaryOperator::Less => Some((Opcode::Jsless, Opcode::Jsgeq)),
+            BinaryOperator::LessEqual => Some((Opcode::Jsleq, Opcode::Jsgrtr)),
+            BinaryOperator::Greater => Some((Opcode::Jsgrtr, Opcode::Jsleq)),
+            BinaryOperator::GreaterEqual => Some((Opcode::Jsgeq, Opcode::Jsless)),
+            _ => None,
+        }
+    }
+
+    /// Generate `&&` or `||`, leaving 0 or 1 in PRI
+    ///
+    /// The right operand is skipped once the left one decides the result.
+    fn generate_logical(
+        &mut self,
+        left: &AstNode,
+        operator: &BinaryOperator,
+        right: &AstNode,
+    ) -> CompilerResult<()> {
+        // `&&` stops at the first false operand, `||` at the first true one
+        let (jump, decided, undecided) = if *operator == BinaryOperator::LogicalAnd {
+            (Opcode::Jzer, 0, 1)
+        } else {
+            (Opcode::Jnz, 1, 0)
+        };
+        let decided_label = self.create_label();
+        let end_label = self.create_label();
+
+        self.generate_node(left)?;
+        self.emit_jump(jump, &decided_label);
+        self.generate_node(right)?;
+        self.emit_jump(jump, &decided_label);
+        self.instructions.push(Instruction::const_pri(undecided));
+        self.emit_jump(Opcode::Jump, &end_label);
+        self.set_label(&decided_label);
+        self.instructions.push(Instruction::const_pri(decided));
+        self.set_label(&end_label);
+
+        Ok(())
+    }
+
+    /// Generate an assignment, leaving the assigned value in PRI
+    fn generate_assignment(&mut self, target: &AstNode, value: &AstNode) -> CompilerResult<()> {
+        match target {
+            AstNode::Identifier(name) => {
+                let var = self.lookup_variable(name)?;
+                if var.array_size.is_some() {
+                    return Err(CompilerError::SemanticError(format!(
+                        "Cannot assign to array '{}'",
+                        name
+                    )));
+                }
+                self.generate_node(value)?;
+                self.store_variable(&var);
+            }
+            AstNode::ArrayAccess { array, index } => {
+                self.generate_index(array, index)?;
+                self.instructions.push(Instruction::idxaddr());
+                self.instructions.push(Instruction::push_pri());
+                self.generate_node(value)?;
+                self.instructions.push(Instruction::pop_alt());
+                self.instructions.push(Instruction::stor_i());
+            }
+            _ => {
+                return Err(CompilerError::SemanticError(format!(
+                    "Invalid assignment target: {:?}",
+                    target
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Evaluate the right operand into ALT, keeping the left operand in PRI
+    fn generate_right_operand(&mut self, right: &AstNode) -> CompilerResult<()> {
+        self.instructions.push(Instruction::push_pri());
+        self.generate_node(right)?;
+        self.instructions.push(Instruction::move_alt());
+        self.instructions.push(Instruction::pop_pri());
+        Ok(())
+    }
+
+    /// Emit the instructions computing `PRI = PRI <operator> ALT`
+    fn generate_operator(&mut self, operator: &BinaryOperator) -> CompilerResult<()> {
+        let opcode = match operator {
+            BinaryOperator::Add => Opcode::Add,
+            BinaryOperator::Subtract => Opcode::Sub,
+            BinaryOperator::Multiply => Opcode::Smul,
+            BinaryOperator::Divide => Opcode::Sdiv,
+            // SDIV leaves the remainder in ALT
+            BinaryOperator::Modulo => {
+                self.instructions.push(Instruction::sdiv());
+                self.instructions.push(Instruction::move_pri());
+                return Ok(());
+            }
+            BinaryOperator::BitwiseAnd => Opcode::And,
+            BinaryOperator::BitwiseOr => Opcode::Or,
+            BinaryOperator::BitwiseXor => Opcode::Xor,
+            BinaryOperator::LeftShift => Opcode::Shl,
+            BinaryOperator::RightShift => Opcode::Sshr,
+            BinaryOperator::Equal => Opcode::Eq,
+            BinaryOperator::NotEqual => Opcode::Neq,
+            BinaryOperator::Less => Opcode::Sless,
+            BinaryOperator::LessEqual => Opcode::Sleq,
+            BinaryOperator::Greater => Opcode::Sgrtr,
+            BinaryOperator::GreaterEqual => Opcode::Sgeq,
+            _ => {
+                return Err(CompilerError::SemanticError(format!(
+                    "Unsupported operator: {:?}",
+                    operator
+                )));
+            }
+        };
+        self.instructions.push(Instruction::new(opcode, 0));
+        Ok(())
+    }
+
+    /// Map a compound assignment operator to the arithmetic it applies
+    fn compound_operator(operator: &BinaryOperator) -> Option<BinaryOperator> {
+        match operator {
+            BinaryOperator::AddAssign => Some(BinaryOperator::Add),
+            BinaryOperator::SubtractAssign => Some(BinaryOperator::Subtract),
+            BinaryOperator::MultiplyAssign => Some(BinaryOperator::Multiply),
+            BinaryOperator::DivideAssign => Some(BinaryOperator::Divide),
+            BinaryOperator::ModuloAssign => Some(BinaryOperator::Modulo),
+            BinaryOperator::AndAssign => Some(BinaryOperator::BitwiseAnd),
+            BinaryOperator::OrAssign => Some(BinaryOperator::BitwiseOr),
+            BinaryOperator::XorAssign => Some(BinaryOperator::BitwiseXor),
+            BinaryOperator::LeftShiftAssign => Some(BinaryOperator::LeftShift),
+            BinaryOperator::RightShiftAssign => Some(BinaryOperator::RightShift),
+            _ => None,
+        }
+    }
+
+    /// Generate `target op= value`, leaving the stored value in PRI. The
+    /// address of an array element is computed once, so the index
+    /// expression is only evaluated a single time.
+    fn generate_compound_assignment(
+        &mut self,
+        target: &AstNode,
+        operator: BinaryOperator,
+        value: &AstNode,
+    ) -> CompilerResult<()> {
+        match target {
+            AstNode::Identifier(name) => {
+                let var = self.lookup_variable(name)?;
+                if var.array_size.is_some() {
+                    return Err(CompilerError::SemanticError(format!(
+                        "Cannot assign to array '{}'",
+                        name
+                    )));
+                }
+                self.generate_node(target)?;
+                self.generate_right_operand(value)?;
+                self.generate_operator(&operator)?;
+                self.store_variable(&var);
+            }
+            AstNode::ArrayAccess { array, index } => {
+                self.generate_index(array, index)?;
+                self.instructions.push(Instruction::idxaddr());
+                self.instructions.push(Instruction::push_pri());
+                self.instructions.push(Instruction::load_i());
+                self.generate_right_operand(value)?;
+                self.generate_operator(&operator)?;
+                self.instructions.push(Instruction::pop_alt());
+                self.instructions.push(Instruction::stor_i());
+            }
+            _ => {
+                return Err(CompilerError::SemanticError(format!(
+                    "Invalid assignment target: {:?}",
+                    target
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Generate `++`/`--` on a variable or array element, leaving the new
+    /// value in PRI for the prefix forms and the original one for postfix
+    fn generate_increment(
+        &mut self,
+        target: &AstNode,
+        operator: &UnaryOperator,
+    ) -> CompilerResult<()> {
+        let (adjust, postfix) = match operator {
+            UnaryOperator::Increment => (Opcode::Incr, false),
+            UnaryOperator::Decrement => (Opcode::Decr, false),
+            UnaryOperator::PostIncrement => (Opcode::Incr, true),
+            _ => (Opcode::Decr, true),
+        };
+        let var = match target {
+            AstNode::Identifier(name) => {
+                let var = self.lookup_variable(name)?;
+                if var.array_size.is_some() {
+                    return Err(CompilerError::SemanticError(format!(
+                        "Cannot assign to array '{}'",
+                        name
+                    )));
+                }
+                self.generate_node(target)?;
+                Some(var)
+            }
+            AstNode::ArrayAccess { array, index } => {
+                // Keep the element address in ALT for the store
+                self.generate_index(array, index)?;
+                self.instructions.push(Instruction::idxaddr());
+                self.instructions.push(Instruction::move_alt());
+                self.instructions.push(Instruction::load_i());
+                None
+            }
+            _ => {
+                return Err(CompilerError::SemanticError(format!(
+                    "Invalid assignment target: {:?}",
+                    target
+                )));
+            }
+        };
+
+        if postfix {
+            self.instructions.push(Instruction::push_pri());
+        }
+        self.instructions.push(Instruction::new(adjust, 0));
+        match var {
+            Some(var) => self.store_variable(&var),
+            None => self.instructions.push(Instruction::stor_i()),
+        }
+        if postfix {
+            self.instructions.push(Instruction::pop_pri());
+        }
+        Ok(())
+    }
+
+    /// Store PRI into a scalar variable
+    fn store_variable(&mut self, var: &Variable) {
+        if var.is_local {
+            self.instructions.push(Instruction::stor_s_pri(var.address));
+        } else {
+            self.instructions.push(Instruction::stor_pri(var.address));
+        }
+    }
+
+    /// Map the next instruction to a source line
+    fn record_line(&mut self, line: u32) {
+        let index = self.instructions.len();
+        // A statement nested in another one at the same address wins
+        if let Some(last) = self.line_table.last_mut()
+            && last.0 == index
+        {
+            last.1 = line;
+            return;
+        }
+        self.line_table.push((index, line));
+    }
+
+    /// Add a string to the data section and return its address
+    ///
+    /// The string is stored unpacked, one character per cell followed by a
+    /// zero terminator. Identical literals share a single copy.
+    fn add_string(&mut self, s: &str) -> Cell {
+        if let Some(&address) = self.string_map.get(s) {
+            return address;
+        }
+
+        self.data
+            .resize(self.data.len().next_multiple_of(CELL_SIZE as usize), 0);
+        let address = self.data.len() as Cell;
+        for ch in s.chars().chain(std::iter::once('\0')) {
+            self.data.extend_from_slice(&(ch as Cell).to_le_bytes());
+        }
+        self.string_map.insert(s.to_string(), address);
+
+        address
+    }
+
+    /// Add a packed string to the data section and return its address
+    ///
+    /// Characters are packed four per cell with the first one in the most
+    /// significant byte, followed by a zero terminator.
+    fn add_packed_string(&mut self, s: &str) -> Cell {
+        self.data
+            .resize(self.data.len().next_multiple_of(CELL_SIZE as usize), 0);
+        let address = self.data.len() as Cell;
+
+        let mut bytes = s.as_bytes().to_vec();
+        bytes.push(0);
+        for chunk in bytes.chunks(CELL_SIZE as usize) {
+            let mut cell = [0u8; CELL_SIZE as usize];
+            cell[..chunk.len()].copy_from_slice(chunk);
+            self.data
+                .extend_from_slice(&UCell::from_be_bytes(cell).to_le_bytes());
+        }
+
+        address
+    }
+
+    /// Create a new label
+    fn create_label(&mut self) -> String {
+        let label = format!("label_{}", self.next_label);
+        self.next_label += 1;
+        label
+    }
+
+    /// Set label position
+    fn set_label(&mut self, label: &str) {
+        self.label_map
+            .insert(label.to_string(), self.instructions.len());
+    }
+
+    /// Get label address (instruction index)
+    fn get_label_address(&self, label: &str) -> Option<usize> {
+        self.label_map.get(label).copied()
+    }
+
+    /// Emit a jump or call to a label, resolved later by `relocate`
+    fn emit_jump(&mut self, opcode: Opcode, label: &str) {
+        self.fixups
+            .push((self.instructions.len(), label.to_string()));
+        self.instructions.push(Instruction::new(opcode, 0));
+    }
+
+    /// Rewrite short instruction sequences into cheaper equivalents
+    ///
+    /// Only sequences no label points into are rewritten. Labels, jumps, the
+    /// line table and function addresses are moved to the new instruction
+    /// indices.
+    fn peephole(&mut self) {
+        let targets: HashSet<usize> = self.label_map.values().copied().collect();
+        let old = std::mem::take(&mut self.instructions);
+        // New index of each old instruction, plus one past the end
+        let mut remap = Vec::with_capacity(old.len() + 1);
+        let mut index = 0;
+        while index < old.len() {
+            let window_free = |len: usize| {
+                index + len <= old.len() && (index + 1..index + len).all(|i| !targets.contains(&i))
+            };
+            let at = self.instructions.len();
+            // PUSH.pri; <load pri>; MOVE.alt; POP.pri loads alt directly
+            if window_free(4)
+                && old[index].opcode == Opcode::PushPri
+                && old[index + 2].opcode == Opcode::MoveAlt
+                && old[index + 3].opcode == Opcode::PopPri
+                && let Some(opcode) = load_into_alt(old[index + 1].opcode)
+            {
+                self.instructions
+                    .push(Instruction::new(opcode, old[index + 1].operand));
+                remap.extend([at; 4]);
+                index += 4;
+                continue;
+            }
+            // Adjacent stack adjustments combine into one
+            if window_free(2)
+                && old[index].opcode == Opcode::Stack
+                && old[index + 1].opcode == Opcode::Stack
+            {
+                let bytes = old[index].operand + old[index + 1].operand;
+                self.instructions.push(Instruction::stack(bytes));
+                remap.extend([at; 2]);
+                index += 2;
+                continue;
+            }
+            self.instructions.push(old[index].clone());
+            remap.push(at);
+            index += 1;
+        }
+        remap.push(self.instructions.len());
+
+        for target in self.label_map.values_mut() {
+            *target = remap[*target];
+        }
+        for (index, _) in &mut self.fixups {
+            *index = remap[*index];
+        }
+        for (index, _) in &mut self.line_table {
+            *index = remap[*index];
+        }
+        let addresses = self.code_addresses();
+        for (name, address) in &mut self.publics {
+            *address = addresses[self.label_map[name.as_str()]] as UCell;
+        }
+        if self.main_address.is_some() {
+            self.main_address = Some(addresses[self.label_map["main"]]);
+        }
+    }
+
+    /// Rewrite every label reference to its cod-relative byte offset
+    fn relocate(&mut self) -> CompilerResult<()> {
+        let addresses = self.code_addresses();
+        for (index, label) in &self.fixups {
+            let target = self.get_label_address(label).ok_or_else(|| {
+                CompilerError::InternalError(format!("Undefined label: {}", label))
+            })?;
+            self.instructions[*index].operand = addresses[target];
+        }
+        Ok(())
+    }
+
+    /// Cod-relative byte offset of each instruction, plus one past the last
+    fn code_addresses(&self) -> Vec<Cell> {
+        let mut addresses = Vec::with_capacity(self.instructions.len() + 1);
+        let mut address = 0;
+        addresses.push(address);
+        for instruction in &self.instructions {
+            address += instruction.size() as Cell;
+            addresses.push(address);
+        }
+        addresses
+    }
+}
+
+/// The instruction loading the same value into ALT as `opcode` loads into PRI
+fn load_into_alt(opcode: Opcode) -> Option<Opcode> {
+    match opcode {
+        Opcode::ConstPri => Some(Opcode::ConstAlt),
+        Opcode::LoadPri => Some(Opcode::LoadAlt),
+        Opcode::LoadSPri => Some(Opcode::LoadSAlt),
+        Opcode::AddrPri => Some(Opcode::AddrAlt),
+        _ => None,
+    }
+}
+
+impl Default for CodeGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,110 +1,413 @@
-use std::fs;
-use std::path::Path;
-
-#[derive(Debug, Clone, Default)]
-pub struct FormatterConfig {
-    pub enabled: bool,
-    pub line_width: usize,
-    pub trim_trailing_whitespace: bool,
-    pub insert_final_newline: bool,
-    pub add_missing_braces: bool,
-}
-
-#[derive(Debug, Clone, Default)]
-pub struct LinterConfig {
-    pub enabled: bool,
-    pub check_trailing_whitespace: bool,
-    pub check_duplicate_includes: bool,
-    pub check_missing_braces: bool,
-    pub check_newline_eof: bool,
-}
-
-#[derive(Debug, Clone, Default)]
-pub struct PawnConfig {
-    pub globals: Vec<String>,
-}
-
-#[derive(Debug, Clone, Default)]
-pub struct Config {
-    pub formatter: FormatterConfig,
-    pub linter: LinterConfig,
-    pub pawn: PawnConfig,
-    pub files: FilesConfig,
-}
-
-#[derive(Debug, Clone, Default)]
-pub struct FilesConfig {
-    pub include_globs: Vec<String>,
-    pub exclude_globs: Vec<String>,
-}
-
-pub fn load_config(path: &Path) -> Config {
-    let text = fs::read_to_string(path).unwrap_or_default();
-    // Minimal detection without regex/serde
-    let enabled_formatter = text.contains("\"formatter\"") && text.contains("\"enabled\": true");
-    let enabled_linter = text.contains("\"linter\"") && text.contains("\"enabled\": true");
-
-    fn rule_off(text: &str, key: &str) -> bool {
-        let a = format!("\"{}\": \"off\"", key);
-        let b = format!("\"{}\":\"off\"", key);
-        text.contains(&a) || text.contains(&b)
-    }
-    let check_missing_braces = if text.contains("\"addMissingBraces\"") {
-        !rule_off(&text, "addMissingBraces")
-    } else {
-        true
-    };
-    let check_trailing_whitespace = !rule_off(&text, "noTrailingWhitespace");
-    let check_duplicate_includes = !rule_off(&text, "duplicateInclude");
-    let check_newline_eof = !rule_off(&text, "newlineAtEndOfFile");
-
-    // Parse files.includes minimal support: collect entries and split into include/exclude by '!'
-    let mut include_globs: Vec<String> = Vec::new();
-    let mut exclude_globs: Vec<String> = Vec::new();
-    if let Some(start) = text.find("\"includes\"") {
-        if let Some(arr_start) = text[start..].find('[') {
-            let rest = &text[start + arr_start + 1..];
-            if let Some(arr_end) = rest.find(']') {
-                let array = &rest[..arr_end];
-                for raw in array.split(',') {
-                    let s = raw.trim().trim_matches('"');
-                    if s.is_empty() {
-                        continue;
-                    }
-                    if s.starts_with('!') {
-                        exclude_globs.push(s[1..].to_string());
-                    } else {
-                        include_globs.push(s.to_string());
-                    }
-                }
-            }
-        }
-    }
-    if include_globs.is_empty() {
-        include_globs.push("**".to_string());
-    }
-
-    Config {
-        formatter: FormatterConfig {
-            enabled: enabled_formatter,
-            line_width: 100,
-            trim_trailing_whitespace: check_trailing_whitespace,
-            insert_final_newline: check_newline_eof,
-            add_missing_braces: check_missing_braces,
-        },
-        linter: LinterConfig {
-            enabled: enabled_linter,
-            check_trailing_whitespace: check_trailing_whitespace,
-            check_duplicate_includes: check_duplicate_includes,
-            check_missing_braces: check_missing_braces,
-            check_newline_eof,
-        },
-        pawn: PawnConfig {
-            globals: vec!["printf".into()],
-        },
-        files: FilesConfig {
-            include_globs,
-            exclude_globs,
-        },
-    }
-}
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatterConfig {
+    pub enabled: bool,
+    pub line_width: usize,
+    pub trim_trailing_whitespace: bool,
+    pub insert_final_newline: bool,
+    pub add_missing_braces: bool,
+    /// Spaces per nesting level when reindenting; 0 leaves indentation alone
+    pub indent_width: usize,
+    /// Indent with one tab per nesting level instead of spaces
+    pub use_tabs: bool,
+    /// Put single spaces around binary and assignment operators
+    pub space_around_operators: bool,
+    /// Longest run of blank lines to keep; 0 leaves blank lines alone
+    pub max_consecutive_blank_lines: usize,
+    /// Columns between tab stops when measuring indentation
+    pub tab_width: usize,
+}
+
+impl Default for FormatterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            line_width: 100,
+            trim_trailing_whitespace: true,
+            insert_final_newline: true,
+            add_missing_braces: true,
+            indent_width: 4,
+            use_tabs: false,
+            space_around_operators: true,
+            max_consecutive_blank_lines: 1,
+            tab_width: 4,
+        }
+    }
+}
+
+/// How seriously a lint issue should be treated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    #[default]
+    Warning,
+    Info,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinterConfig {
+    pub enabled: bool,
+    pub check_trailing_whitespace: bool,
+    pub check_duplicate_includes: bool,
+    pub check_missing_braces: bool,
+    pub check_newline_eof: bool,
+    pub check_unreachable_code: bool,
+    pub check_unused_variables: bool,
+    pub check_unused_parameters: bool,
+    /// Flag lines indented with tabs and spaces mixed, or against the file's dominant style
+    pub check_indentation: bool,
+    /// Flag conditions that are a bare assignment such as `if (x = 0)`
+    pub check_assignment_in_condition: bool,
+    /// Flag relational comparisons chained like `a < b < c`
+    pub check_comparison_chaining: bool,
+    /// Flag numeric literals that should be named constants; off unless the
+    /// rule is given a level
+    pub check_magic_numbers: bool,
+    /// Numbers allowed as literals besides -1, 0 and 1
+    pub magic_number_allowlist: Vec<i32>,
+    /// Severity per rule id (e.g. `style.noTrailingWhitespace`); unlisted rules are warnings
+    pub severities: HashMap<String, Severity>,
+}
+
+impl Default for LinterConfig {
+    /// Disabled, with each rule on or off as when the configuration does not
+    /// mention it
+    fn default() -> Self {
+        let mut linter = Self {
+            enabled: false,
+            check_trailing_whitespace: false,
+            check_duplicate_includes: false,
+            check_missing_braces: false,
+            check_newline_eof: false,
+            check_unreachable_code: false,
+            check_unused_variables: false,
+            check_unused_parameters: false,
+            check_indentation: false,
+            check_assignment_in_condition: false,
+            check_comparison_chaining: false,
+            check_magic_numbers: false,
+            magic_number_allowlist: Vec::new(),
+            severities: HashMap::new(),
+        };
+        for (_, default_on, check) in LINT_RULES {
+            *check(&mut linter) = default_on;
+        }
+        linter
+    }
+}
+
+impl LinterConfig {
+    /// Severity configured for a rule
+    pub fn severity_for(&self, rule: &str) -> Severity {
+        self.severities.get(rule).copied().unwrap_or_default()
+    }
+}
+
+/// Options for code generation
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CodegenConfig {
+    /// Run the peephole optimizer over the generated instructions
+    pub optimize: bool,
+    /// Append debug information with a line table to the output
+    pub emit_debug: bool,
+    /// Alignment in bytes the data section is padded to; 0 leaves it unpadded
+    pub cell_alignment: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PawnConfig {
+    pub globals: Vec<String>,
+    /// Compile a library, such as an include file, that needs no `main`
+    pub library: bool,
+}
+
+impl Default for PawnConfig {
+    fn default() -> Self {
+        Self {
+            globals: vec!["printf".into()],
+            library: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    pub formatter: FormatterConfig,
+    pub linter: LinterConfig,
+    pub pawn: PawnConfig,
+    pub files: FilesConfig,
+    pub codegen: CodegenConfig,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilesConfig {
+    pub include_globs: Vec<String>,
+    pub exclude_globs: Vec<String>,
+}
+
+impl Default for FilesConfig {
+    /// Every file included
+    fn default() -> Self {
+        Self {
+            include_globs: vec!["**".to_string()],
+            exclude_globs: Vec::new(),
+        }
+    }
+}
+
+/// The `files` section as written: one `includes` array in which excluded
+/// globs carry a `!` prefix
+#[derive(Serialize, Deserialize, Default)]
+struct FilesSection {
+    #[serde(default)]
+    includes: Vec<String>,
+}
+
+impl Serialize for FilesConfig {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let excludes = self.exclude_globs.iter().map(|glob| format!("!{}", glob));
+        FilesSection {
+            includes: self.include_globs.iter().cloned().chain(excludes).collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for FilesConfig {
+    /// Split `includes` into included and excluded globs; everything is
+    /// included when no glob is
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let section = FilesSection::deserialize(deserializer)?;
+        let (excludes, mut includes): (Vec<String>, Vec<String>) = section
+            .includes
+            .into_iter()
+            .partition(|glob| glob.starts_with('!'));
+        if includes.is_empty() {
+            includes.push("**".to_string());
+        }
+        Ok(FilesConfig {
+            include_globs: includes,
+            exclude_globs: excludes
+                .into_iter()
+                .map(|glob| glob[1..].to_string())
+                .collect(),
+        })
+    }
+}
+
+/// Lint rules configurable under `linter.rules`, whether each is on when the
+/// configuration does not mention it, and the flag it controls
+type RuleFlag = fn(&mut LinterConfig) -> &mut bool;
+const LINT_RULES: [(&str, bool, RuleFlag); 11] = [
+    ("style.noTrailingWhitespace", true, |linter| {
+        &mut linter.check_trailing_whitespace
+    }),
+    ("suspicious.duplicateInclude", true, |linter| {
+        &mut linter.check_duplicate_includes
+    }),
+    ("style.addMissingBraces", true, |linter| {
+        &mut linter.check_missing_braces
+    }),
+    ("style.newlineAtEndOfFile", true, |linter| {
+        &mut linter.check_newline_eof
+    }),
+    ("suspicious.unreachableCode", true, |linter| {
+        &mut linter.check_unreachable_code
+    }),
+    ("suspicious.unusedVariable", true, |linter| {
+        &mut linter.check_unused_variables
+    }),
+    ("suspicious.unusedParameter", true, |linter| {
+        &mut linter.check_unused_parameters
+    }),
+    ("style.consistentIndentation", true, |linter| {
+        &mut linter.check_indentation
+    }),
+    ("suspicious.assignmentInCondition", true, |linter| {
+        &mut linter.check_assignment_in_condition
+    }),
+    ("suspicious.comparisonChaining", true, |linter| {
+        &mut linter.check_comparison_chaining
+    }),
+    ("suspicious.magicNumber", false, |linter| {
+        &mut linter.check_magic_numbers
+    }),
+];
+
+/// Rule levels as written in the configuration
+const LEVELS: [(&str, Severity); 3] = [
+    ("error", Severity::Error),
+    ("warn", Severity::Warning),
+    ("info", Severity::Info),
+];
+
+impl Config {
+    /// Read a configuration in the `pwnrust.json` format
+    ///
+    /// Missing sections and keys take their values from [`Config::default`].
+    /// A rule set to `"on"` is enabled at the default severity. Unless
+    /// `formatter.addMissingBraces` is given, the formatter adds braces only
+    /// while the `style.addMissingBraces` rule is on.
+    pub fn from_json(text: &str) -> Result<Config, serde_json::Error> {
+        let root: Value = serde_json::from_str(text)?;
+        let flag = |section: &str, key: &str, default: bool| {
+            root[section][key].as_bool().unwrap_or(default)
+        };
+        let number = |section: &str, key: &str, default: usize| {
+            root[section][key]
+                .as_u64()
+                .map_or(default, |value| value as usize)
+        };
+
+        let defaults = Config::default();
+        let (formatter, codegen) = (&defaults.formatter, &defaults.codegen);
+        let mut config = Config {
+            formatter: FormatterConfig {
+                enabled: flag("formatter", "enabled", formatter.enabled),
+                line_width: number("formatter", "lineWidth", formatter.line_width),
+                trim_trailing_whitespace: flag(
+                    "formatter",
+                    "trimTrailingWhitespace",
+                    formatter.trim_trailing_whitespace,
+                ),
+                insert_final_newline: flag(
+                    "formatter",
+                    "insertFinalNewline",
+                    formatter.insert_final_newline,
+                ),
+                add_missing_braces: flag(
+                    "formatter",
+                    "addMissingBraces",
+                    formatter.add_missing_braces,
+                ),
+                indent_width: number("formatter", "indentWidth", formatter.indent_width),
+                use_tabs: root["formatter"]["indentStyle"] == "tab",
+                space_around_operators: flag(
+                    "formatter",
+                    "spaceAroundOperators",
+                    formatter.space_around_operators,
+                ),
+                max_consecutive_blank_lines: number(
+                    "formatter",
+                    "maxConsecutiveBlankLines",
+                    formatter.max_consecutive_blank_lines,
+                ),
+                tab_width: number("formatter", "tabWidth", formatter.tab_width),
+            },
+            linter: LinterConfig {
+                enabled: flag("linter", "enabled", defaults.linter.enabled),
+                magic_number_allowlist: root["linter"]["magicNumberAllowlist"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|value| value.as_i64().map(|value| value as i32))
+                    .collect(),
+                ..LinterConfig::default()
+            },
+            pawn: PawnConfig {
+                globals: match root["pawn"]["globals"].as_array() {
+                    Some(globals) => globals
+                        .iter()
+                        .filter_map(|global| global.as_str().map(String::from))
+                        .collect(),
+                    None => defaults.pawn.globals.clone(),
+                },
+                library: flag("pawn", "library", defaults.pawn.library),
+            },
+            files: FilesConfig::deserialize(root.get("files").unwrap_or(&json!({})))?,
+            codegen: CodegenConfig {
+                optimize: flag("codegen", "optimize", codegen.optimize),
+                emit_debug: flag("codegen", "emitDebug", codegen.emit_debug),
+                cell_alignment: number("codegen", "cellAlignment", codegen.cell_alignment),
+            },
+        };
+
+        for (rule, default_on, check) in LINT_RULES {
+            let (group, name) = rule.split_once('.').unwrap_or_default();
+            let setting = &root["linter"]["rules"][group][name];
+            // Rules with options give their level as `{ "level": ... }`
+            let level = setting.get("level").unwrap_or(setting).as_str();
+            *check(&mut config.linter) = level.map_or(default_on, |level| level != "off");
+            if let Some((_, severity)) = LEVELS.iter().find(|(name, _)| Some(*name) == level) {
+                config.linter.severities.insert(rule.to_string(), *severity);
+            }
+        }
+        // Without a key of its own, brace insertion follows the lint rule
+        if root["formatter"]["addMissingBraces"].is_null() {
+            config.formatter.add_missing_braces = config.linter.check_missing_braces;
+        }
+        Ok(config)
+    }
+
+    /// Write the configuration in the `pwnrust.json` format, pretty-printed
+    ///
+    /// [`Config::from_json`] reads the result back into an equal `Config`,
+    /// except that disabled rules are written as `"off"` and lose any
+    /// severity set for them.
+    pub fn to_json(&self) -> String {
+        let mut linter = self.linter.clone();
+        let mut rules = json!({});
+        for (rule, _, check) in LINT_RULES {
+            let (group, name) = rule.split_once('.').unwrap_or_default();
+            let severity = self.linter.severities.get(rule);
+            let level = match (*check(&mut linter), severity) {
+                (false, _) => "off",
+                (true, Some(severity)) => LEVELS
+                    .iter()
+                    .find(|(_, level)| level == severity)
+                    .map_or("on", |(name, _)| name),
+                (true, None) => "on",
+            };
+            rules[group][name] = json!(level);
+        }
+
+        let formatter = &self.formatter;
+        let value = json!({
+            "files": self.files,
+            "formatter": {
+                "enabled": formatter.enabled,
+                "indentStyle": if formatter.use_tabs { "tab" } else { "space" },
+                "indentWidth": formatter.indent_width,
+                "tabWidth": formatter.tab_width,
+                "lineWidth": formatter.line_width,
+                "trimTrailingWhitespace": formatter.trim_trailing_whitespace,
+                "insertFinalNewline": formatter.insert_final_newline,
+                "addMissingBraces": formatter.add_missing_braces,
+                "spaceAroundOperators": formatter.space_around_operators,
+                "maxConsecutiveBlankLines": formatter.max_consecutive_blank_lines,
+            },
+            "linter": {
+                "enabled": self.linter.enabled,
+                "rules": rules,
+                "magicNumberAllowlist": self.linter.magic_number_allowlist,
+            },
+            "pawn": {
+                "globals": self.pawn.globals,
+                "library": self.pawn.library,
+            },
+            "codegen": {
+                "optimize": self.codegen.optimize,
+                "emitDebug": self.codegen.emit_debug,
+                "cellAlignment": self.codegen.cell_alignment,
+            },
+        });
+        serde_json::to_string_pretty(&value).unwrap_or_default()
+    }
+}
+
+/// Read the configuration file at `path`
+///
+/// A missing file, or one that is not valid JSON, gives the defaults; use
+/// [`Config::from_json`] to see why a file was rejected.
+pub fn load_config(path: &Path) -> Config {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|text| Config::from_json(&text).ok())
+        .unwrap_or_default()
+}
@@ -0,0 +1,207 @@
+//! Compile-time evaluation of constant expressions
+//!
+//! Operators whose operands are literals are replaced by the literal they
+//! produce. Integer arithmetic wraps like the AMX instructions do, and a
+//! division by zero is left in place so it still fails at run time. Float
+//! operators are left alone, since the generated code has no float
+//! arithmetic to match.
+
+use crate::ast::*;
+use pawn_amx::Cell;
+
+/// Fold every constant expression in the tree
+pub fn fold_constants(node: AstNode) -> AstNode {
+    let fold = |node: Box<AstNode>| Box::new(fold_constants(*node));
+    let fold_all = |nodes: Vec<AstNode>| nodes.into_iter().map(fold_constants).collect();
+
+    match node {
+        AstNode::Program(nodes) => AstNode::Program(fold_all(nodes)),
+        AstNode::Located { span, node } => AstNode::Located {
+            span,
+            node: fold(node),
+        },
+        AstNode::Function {
+            name,
+            parameters,
+            return_type,
+            body,
+            is_public,
+            is_native,
+            is_forward,
+            states,
+        } => AstNode::Function {
+            name,
+            parameters,
+            return_type,
+            body: fold_all(body),
+            is_public,
+            is_native,
+            is_forward,
+            states,
+        },
+        AstNode::VariableDeclaration {
+            name,
+            var_type,
+            array_size,
+            initializer,
+            is_const,
+            is_static,
+        } => AstNode::VariableDeclaration {
+            name,
+            var_type,
+            array_size: array_size.map(fold),
+            initializer: initializer.map(fold),
+            is_const,
+            is_static,
+        },
+        AstNode::Block(statements) => AstNode::Block(fold_all(statements)),
+        AstNode::Expression(expr) => AstNode::Expression(fold(expr)),
+        AstNode::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => AstNode::If {
+            condition: fold(condition),
+            then_branch: fold(then_branch),
+            else_branch: else_branch.map(fold),
+        },
+        AstNode::While { condition, body } => AstNode::While {
+            condition: fold(condition),
+            body: fold(body),
+        },
+        AstNode::DoWhile { body, condition } => AstNode::DoWhile {
+            body: fold(body),
+            condition: fold(condition),
+        },
+        AstNode::For {
+            init,
+            condition,
+            update,
+            body,
+        } => AstNode::For {
+            init: init.map(fold),
+            condition: condition.map(fold),
+            update: update.map(fold),
+            body: fold(body),
+        },
+        AstNode::Return(value) => AstNode::Return(value.map(fold)),
+        AstNode::Assert(condition) => AstNode::Assert(fold(condition)),
+        AstNode::BinaryOp {
+            left,
+            operator,
+            right,
+        } => {
+            let (left, right) = (fold(left), fold(right));
+            match fold_binary(&left, &operator, &right) {
+                Some(value) => value,
+                None => AstNode::BinaryOp {
+                    left,
+                    operator,
+                    right,
+                },
+            }
+        }
+        AstNode::UnaryOp { operator, operand } => {
+            let operand = fold(operand);
+            match fold_unary(&operator, &operand) {
+                Some(value) => value,
+                None => AstNode::UnaryOp { operator, operand },
+            }
+        }
+        AstNode::Assignment { target, value } => AstNode::Assignment {
+            target,
+            value: fold(value),
+        },
+        AstNode::Ternary {
+            condition,
+            then_value,
+            else_value,
+        } => AstNode::Ternary {
+            condition: fold(condition),
+            then_value: fold(then_value),
+            else_value: fold(else_value),
+        },
+        AstNode::FunctionCall { name, arguments } => AstNode::FunctionCall {
+            name,
+            arguments: fold_all(arguments),
+        },
+        AstNode::ArrayAccess { array, index } => AstNode::ArrayAccess {
+            array,
+            index: fold(index),
+        },
+        AstNode::MemberAccess { object, member } => AstNode::MemberAccess {
+            object: fold(object),
+            member,
+        },
+        node => node,
+    }
+}
+
+/// Value of an expression made only of integer literals, or `None` if it
+/// depends on anything else
+pub fn eval_constant(node: &AstNode) -> Option<Cell> {
+    match fold_constants(node.clone()).inner() {
+        AstNode::Integer(value) => Some(*value as Cell),
+        _ => None,
+    }
+}
+
+/// Evaluate a binary operator applied to two literals
+fn fold_binary(left: &AstNode, operator: &BinaryOperator, right: &AstNode) -> Option<AstNode> {
+    match (left, right) {
+        (AstNode::Integer(a), AstNode::Integer(b)) => fold_integer(*a, operator, *b),
+        _ => None,
+    }
+}
+
+/// Integer arithmetic with the wrapping behavior of the AMX
+fn fold_integer(a: i32, operator: &BinaryOperator, b: i32) -> Option<AstNode> {
+    let value = match operator {
+        BinaryOperator::Add => a.wrapping_add(b),
+        BinaryOperator::Subtract => a.wrapping_sub(b),
+        BinaryOperator::Multiply => a.wrapping_mul(b),
+        // Division by zero must still raise at run time
+        BinaryOperator::Divide if b != 0 => floored_div_rem(a, b).0,
+        BinaryOperator::Modulo if b != 0 => floored_div_rem(a, b).1,
+        BinaryOperator::Equal => (a == b) as i32,
+        BinaryOperator::NotEqual => (a != b) as i32,
+        BinaryOperator::Less => (a < b) as i32,
+        BinaryOperator::LessEqual => (a <= b) as i32,
+        BinaryOperator::Greater => (a > b) as i32,
+        BinaryOperator::GreaterEqual => (a >= b) as i32,
+        BinaryOperator::LogicalAnd => (a != 0 && b != 0) as i32,
+        BinaryOperator::LogicalOr => (a != 0 || b != 0) as i32,
+        BinaryOperator::BitwiseAnd => a & b,
+        BinaryOperator::BitwiseOr => a | b,
+        BinaryOperator::BitwiseXor => a ^ b,
+        BinaryOperator::LeftShift => a.wrapping_shl(b as u32),
+        BinaryOperator::RightShift => a.wrapping_shr(b as u32),
+        _ => return None,
+    };
+    Some(AstNode::Integer(value))
+}
+
+/// Quotient rounded toward negative infinity and the matching remainder,
+/// which takes the sign of the divisor as `SDIV` computes them
+fn floored_div_rem(a: i32, b: i32) -> (i32, i32) {
+    let (quotient, remainder) = (a.wrapping_div(b), a.wrapping_rem(b));
+    if remainder != 0 && (remainder ^ b) < 0 {
+        (quotient.wrapping_sub(1), remainder + b)
+    } else {
+        (quotient, remainder)
+    }
+}
+
+/// Evaluate a unary operator applied to a literal
+fn fold_unary(operator: &UnaryOperator, operand: &AstNode) -> Option<AstNode> {
+    match (operator, operand) {
+        (UnaryOperator::Plus, AstNode::Integer(_) | AstNode::Float(_)) => Some(operand.clone()),
+        (UnaryOperator::Minus, AstNode::Integer(a)) => Some(AstNode::Integer(a.wrapping_neg())),
+        (UnaryOperator::Minus, AstNode::Float(a)) => Some(AstNode::Float(-a)),
+        (UnaryOperator::LogicalNot, AstNode::Integer(a)) => {
+            Some(AstNode::Integer((*a == 0) as i32))
+        }
+        (UnaryOperator::BitwiseNot, AstNode::Integer(a)) => Some(AstNode::Integer(!a)),
+        _ => None,
+    }
+}
@@ -1,37 +1,132 @@
-//! Error handling for the Pawn compiler
-
-use thiserror::Error;
-
-/// Compiler error types
-#[derive(Error, Debug, Clone)]
-pub enum CompilerError {
-    #[error("Lexical error: {0}")]
-    LexicalError(String),
-
-    #[error("Syntax error: {0}")]
-    SyntaxError(String),
-
-    #[error("Parser error: {0}")]
-    ParserError(String),
-
-    #[error("Semantic error: {0}")]
-    SemanticError(String),
-
-    #[error("Type error: {0}")]
-    TypeError(String),
-
-    #[error("Symbol error: {0}")]
-    SymbolError(String),
-
-    #[error("Code generation error: {0}")]
-    CodeGenError(String),
-
-    #[error("File error: {0}")]
-    FileError(String),
-
-    #[error("Internal error: {0}")]
-    InternalError(String),
-}
-
-/// Result type for compiler operations
-pub type CompilerResult<T> = Result<T, CompilerError>;
+//! Error handling for the Pawn compiler
+
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Compiler error types
+#[derive(Error, Debug, Clone)]
+pub enum CompilerError {
+    #[error("Lexical error: {0}")]
+    LexicalError(String),
+
+    #[error("Syntax error: {0}")]
+    SyntaxError(String),
+
+    #[error("Parser error: {0}")]
+    ParserError(String),
+
+    #[error("Semantic error: {0}")]
+    SemanticError(String),
+
+    #[error("Type error: {0}")]
+    TypeError(String),
+
+    #[error("Symbol error: {0}")]
+    SymbolError(String),
+
+    #[error("Code generation error: {0}")]
+    CodeGenError(String),
+
+    #[error("File error: {0}")]
+    FileError(String),
+
+    #[error("Internal error: {0}")]
+    InternalError(String),
+
+    /// An error tied to the source text it was found in
+    #[error("{line}:{column}: {error}")]
+    Spanned {
+        error: Box<CompilerError>,
+        line: usize,
+        column: usize,
+        /// Number of characters the error covers
+        len: usize,
+    },
+
+    /// A located error in a file other than the one being compiled directly
+    #[error("{}:{error}", path.display())]
+    InFile {
+        error: Box<CompilerError>,
+        path: PathBuf,
+    },
+}
+
+impl CompilerError {
+    /// Attach a source location, unless the error already has one
+    pub fn at(self, line: usize, column: usize, len: usize) -> Self {
+        match self {
+            CompilerError::Spanned { .. } | CompilerError::InFile { .. } => self,
+            error => CompilerError::Spanned {
+                error: Box::new(error),
+                line,
+                column,
+                len,
+            },
+        }
+    }
+
+    /// The underlying error, without its source location
+    pub fn kind(&self) -> &CompilerError {
+        match self {
+            CompilerError::Spanned { error, .. } => error,
+            CompilerError::InFile { error, .. } => error.kind(),
+            error => error,
+        }
+    }
+
+    /// Line, column and length of the source text the error refers to
+    pub fn span(&self) -> Option<(usize, usize, usize)> {
+        match self {
+            CompilerError::Spanned {
+                line, column, len, ..
+            } => Some((*line, *column, *len)),
+            CompilerError::InFile { error, .. } => error.span(),
+            _ => None,
+        }
+    }
+
+    /// The file the error was found in, when it is not the main source
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            CompilerError::InFile { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+}
+
+/// Render an error the way rustc does, quoting the offending source line
+/// and underlining the span with carets
+pub fn render_diagnostic(source: &str, err: &CompilerError) -> String {
+    let mut out = format!("error: {}\n", err.kind());
+    let Some((line, column, len)) = err.span() else {
+        return out;
+    };
+    let Some(text) = source.lines().nth(line.saturating_sub(1)) else {
+        return out;
+    };
+
+    // Keep tabs before the span, so the carets line up with the source
+    let indent: String = text
+        .chars()
+        .take(column.saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let gutter = " ".repeat(line.to_string().len());
+    let file = err
+        .path()
+        .map(|path| format!("{}:", path.display()))
+        .unwrap_or_default();
+    out.push_str(&format!("{} --> {}{}:{}\n", gutter, file, line, column));
+    out.push_str(&format!("{} |\n", gutter));
+    out.push_str(&format!("{} | {}\n", line, text));
+    out.push_str(&format!(
+        "{} | {}{}\n",
+        gutter,
+        indent,
+        "^".repeat(len.max(1))
+    ));
+    out
+}
+
+/// Result type for compiler operations
+pub type CompilerResult<T> = Result<T, CompilerError>;
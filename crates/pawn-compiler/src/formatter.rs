@@ -1,87 +1,381 @@
-use crate::config::Config;
-
-pub fn format_source(source: &str, cfg: &Config) -> String {
-    if !cfg.formatter.enabled {
-        return source.to_string();
-    }
-
-    // Optional pass to add missing braces for simple function bodies like:
-    // main()\n    printf "Hello"\n -> becomes main(){\n    printf "Hello"\n}
-    let mut text = source.to_string();
-    if cfg.formatter.add_missing_braces {
-        text = add_missing_braces(&text);
-    }
-
-    // Whitespace normalization
-    let mut out = String::with_capacity(text.len());
-    for line in text.lines() {
-        let mut trimmed = line.to_string();
-        if cfg.formatter.trim_trailing_whitespace {
-            while trimmed.ends_with(' ') || trimmed.ends_with('\t') {
-                trimmed.pop();
-            }
-        }
-        out.push_str(&trimmed);
-        out.push('\n');
-    }
-    if cfg.formatter.insert_final_newline && !out.ends_with('\n') {
-        out.push('\n');
-    }
-    out
-}
-
-fn add_missing_braces(input: &str) -> String {
-    #[allow(unused_mut)]
-    let mut lines: Vec<&str> = input.lines().collect();
-    let mut output: Vec<String> = Vec::with_capacity(lines.len() + 2);
-    let mut i = 0;
-    while i < lines.len() {
-        let line = lines[i];
-        let trimmed = line.trim_end();
-        // Detect function header without opening brace on same line
-        let is_header = {
-            let t = trimmed.trim_start();
-            t.ends_with("()") || t.ends_with(")") && !t.contains('{') && !t.starts_with("#")
-        };
-        if is_header {
-            // Lookahead: if next non-empty line is indented more than this line, wrap with braces
-            let indent_curr = leading_whitespace(line);
-            let mut j = i + 1;
-            while j < lines.len() && lines[j].trim().is_empty() {
-                j += 1;
-            }
-            if j < lines.len() {
-                let next_line = lines[j];
-                let indent_next = leading_whitespace(next_line);
-                if indent_next > indent_curr && !trimmed.ends_with('{') {
-                    // Insert opening brace at end of header line
-                    output.push(format!("{}{{", trimmed));
-                    // Emit body lines until indentation returns to header level or EOF
-                    i += 1;
-                    while i < lines.len() {
-                        let body_line = lines[i];
-                        let body_indent = leading_whitespace(body_line);
-                        if !body_line.trim().is_empty() && body_indent <= indent_curr {
-                            break;
-                        }
-                        output.push(body_line.to_string());
-                        i += 1;
-                    }
-                    // Insert closing brace aligned with header
-                    output.push(format!("{}{}", " ".repeat(indent_curr), "}"));
-                    continue; // skip the regular push at loop end
-                }
-            }
-        }
-        output.push(trimmed.to_string());
-        i += 1;
-    }
-    output.join("\n")
-}
-
-fn leading_whitespace(s: &str) -> usize {
-    s.chars()
-        .take_while(|c| *c == ' ' || *c == '\t')
-        .map(|c| if c == '\t' { 4 } else { 1 })
-        .sum()
-}
+use crate::config::Config;
+use crate::lexer::{Lexer, Token};
+use crate::text::indent_width;
+
+/// Format `source` according to `cfg.formatter`
+///
+/// Formatting is idempotent: running it over its own output changes nothing.
+pub fn format_source(source: &str, cfg: &Config) -> String {
+    if !cfg.formatter.enabled {
+        return source.to_string();
+    }
+
+    // Optional pass to add missing braces for simple function bodies like:
+    // main()\n    printf "Hello"\n -> becomes main(){\n    printf "Hello"\n}
+    let mut text = source.to_string();
+    if cfg.formatter.add_missing_braces {
+        text = add_missing_braces(&text, cfg.formatter.tab_width);
+    }
+
+    if cfg.formatter.space_around_operators {
+        text = space_operators(&text);
+    }
+
+    if cfg.formatter.indent_width > 0 || cfg.formatter.use_tabs {
+        text = reindent(&text, cfg);
+    }
+
+    if cfg.formatter.max_consecutive_blank_lines > 0 {
+        text = collapse_blank_lines(&text, cfg.formatter.max_consecutive_blank_lines);
+    }
+
+    // Whitespace normalization
+    let mut out = String::with_capacity(text.len());
+    for line in text.lines() {
+        let mut trimmed = line.to_string();
+        if cfg.formatter.trim_trailing_whitespace {
+            while trimmed.ends_with(' ') || trimmed.ends_with('\t') {
+                trimmed.pop();
+            }
+        }
+        out.push_str(&trimmed);
+        out.push('\n');
+    }
+    if cfg.formatter.insert_final_newline && !out.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+fn add_missing_braces(input: &str, tab_width: usize) -> String {
+    #[allow(unused_mut)]
+    let mut lines: Vec<&str> = input.lines().collect();
+    let mut output: Vec<String> = Vec::with_capacity(lines.len() + 2);
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim_end();
+        // Detect function header without opening brace on same line
+        let is_header = is_function_header(trimmed.trim_start());
+        if is_header {
+            // Lookahead: if next non-empty line is indented more than this line, wrap with braces
+            let indent_curr = indent_width(line, tab_width);
+            let mut j = i + 1;
+            while j < lines.len() && lines[j].trim().is_empty() {
+                j += 1;
+            }
+            if j < lines.len() {
+                let next_line = lines[j];
+                let indent_next = indent_width(next_line, tab_width);
+                // A body that already opens with `{` is braced; leave it alone
+                if indent_next > indent_curr && !next_line.trim_start().starts_with('{') {
+                    // Insert opening brace at end of header line
+                    output.push(format!("{}{{", trimmed));
+                    // Emit body lines until indentation returns to header level or EOF
+                    i += 1;
+                    while i < lines.len() {
+                        let body_line = lines[i];
+                        let body_indent = indent_width(body_line, tab_width);
+                        if !body_line.trim().is_empty() && body_indent <= indent_curr {
+                            break;
+                        }
+                        output.push(body_line.to_string());
+                        i += 1;
+                    }
+                    // Insert closing brace aligned with header
+                    output.push(format!("{}{}", " ".repeat(indent_curr), "}"));
+                    continue; // skip the regular push at loop end
+                }
+            }
+        }
+        output.push(trimmed.to_string());
+        i += 1;
+    }
+    output.join("\n")
+}
+
+/// Whether `line` looks like a function header such as `public Foo(a, b)`
+///
+/// Control statements (`if (x)`, `while (x)`, ...) and calls ending in `;`
+/// are not headers, so their unbraced bodies are left as written.
+fn is_function_header(line: &str) -> bool {
+    if !line.ends_with(')') || line.contains('{') || line.starts_with('#') {
+        return false;
+    }
+    let Some(open) = line.find('(') else {
+        return false;
+    };
+    let head = line[..open].trim_end();
+    let Some(name) = head.split_whitespace().last() else {
+        return false;
+    };
+    let name = name.rsplit(':').next().unwrap_or(name);
+    let is_ident = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '@');
+    is_ident
+        && !matches!(
+            head.split_whitespace().next(),
+            Some("if" | "while" | "for" | "switch" | "else" | "return" | "do" | "case")
+        )
+}
+
+/// Rewrite leading whitespace to match brace depth
+///
+/// Braces inside string and character literals and comments are ignored, and
+/// lines that continue a multi-line comment are left untouched. Preprocessor
+/// directives are kept at column 0.
+fn reindent(input: &str, cfg: &Config) -> String {
+    let unit = if cfg.formatter.use_tabs {
+        "\t".to_string()
+    } else {
+        " ".repeat(cfg.formatter.indent_width)
+    };
+
+    let mut output: Vec<String> = Vec::new();
+    let mut depth = 0usize;
+    let mut in_comment = false;
+    for line in input.lines() {
+        if in_comment {
+            output.push(line.to_string());
+            in_comment = scan_braces(line, true, &mut depth);
+            continue;
+        }
+
+        let content = line.trim_start();
+        if content.is_empty() || content.starts_with('#') {
+            output.push(content.to_string());
+            continue;
+        }
+
+        let closers = content.chars().take_while(|c| *c == '}').count();
+        let level = depth.saturating_sub(closers);
+        output.push(format!("{}{}", unit.repeat(level), content));
+        in_comment = scan_braces(content, false, &mut depth);
+    }
+
+    let mut out = output.join("\n");
+    if input.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+/// Shorten runs of more than `max` blank lines and drop blank lines at EOF
+///
+/// Blank lines inside a block comment or a string continued with a trailing
+/// backslash are kept. The final newline itself is preserved.
+fn collapse_blank_lines(input: &str, max: usize) -> String {
+    let mut output: Vec<&str> = Vec::new();
+    let mut blanks = 0;
+    let mut in_comment = false;
+    let mut continued = false;
+    for line in input.lines() {
+        if in_comment || continued {
+            blanks = 0;
+        } else if line.trim().is_empty() {
+            blanks += 1;
+            if blanks > max {
+                continue;
+            }
+        } else {
+            blanks = 0;
+        }
+        output.push(line);
+        in_comment = scan_braces(line, in_comment, &mut 0);
+        continued = line.ends_with('\\');
+    }
+    while blanks > 0 && output.pop_if(|line| line.trim().is_empty()).is_some() {
+        blanks -= 1;
+    }
+
+    let mut out = output.join("\n");
+    if input.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+/// Put single spaces around binary and assignment operators
+///
+/// Each line is tokenized with the lexer so literals and comments are copied
+/// verbatim; everything but the whitespace next to an operator is preserved.
+/// Lines the lexer rejects, directives and comment continuations are left
+/// unchanged.
+fn space_operators(input: &str) -> String {
+    let mut output: Vec<String> = Vec::new();
+    let mut in_comment = false;
+    for line in input.lines() {
+        let starts_in_comment = in_comment;
+        in_comment = scan_braces(line, in_comment, &mut 0);
+        if starts_in_comment || line.trim_start().starts_with('#') {
+            output.push(line.to_string());
+            continue;
+        }
+        output.push(space_operators_in_line(line).unwrap_or_else(|| line.to_string()));
+    }
+
+    let mut out = output.join("\n");
+    if input.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+fn space_operators_in_line(line: &str) -> Option<String> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut lexer = Lexer::new(line);
+    let mut out = String::with_capacity(line.len());
+    let mut previous: Option<Token> = None;
+    let mut previous_binary = false;
+    let mut end = 0;
+    // A function header's state suffix, as in `OnTick() <auto:idle>`, holds
+    // angle brackets that are not comparisons
+    let mut header = true;
+    let mut in_state = false;
+    let mut depth = 0usize;
+
+    loop {
+        let token = lexer.next_token().ok()?;
+        if token == Token::EndOfFile {
+            break;
+        }
+        let token_end = lexer.column() - 1;
+        let text: String = chars[end..token_end].iter().collect();
+        let gap = &text[..text.len() - text.trim_start().len()];
+
+        if header && depth == 0 && !in_state {
+            in_state = token == Token::Less && previous == Some(Token::RightParen);
+            header = in_state || is_header_token(&token);
+        }
+        let binary =
+            !in_state && is_binary_operator(&token) && previous.as_ref().is_some_and(ends_operand);
+        if previous.is_some() && (binary || previous_binary) {
+            out.push(' ');
+        } else {
+            out.push_str(gap);
+        }
+        out.push_str(text.trim_start());
+
+        match token {
+            Token::LeftParen => depth += 1,
+            Token::RightParen => depth = depth.saturating_sub(1),
+            Token::Greater if in_state => in_state = false,
+            _ => {}
+        }
+        previous_binary = binary;
+        previous = Some(token);
+        end = token_end;
+    }
+    out.extend(&chars[end..]);
+
+    Some(out)
+}
+
+fn is_binary_operator(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Plus
+            | Token::Minus
+            | Token::Multiply
+            | Token::Divide
+            | Token::Modulo
+            | Token::Assign
+            | Token::PlusAssign
+            | Token::MinusAssign
+            | Token::MultiplyAssign
+            | Token::DivideAssign
+            | Token::ModuloAssign
+            | Token::AndAssign
+            | Token::OrAssign
+            | Token::XorAssign
+            | Token::LeftShiftAssign
+            | Token::RightShiftAssign
+            | Token::Equal
+            | Token::NotEqual
+            | Token::Less
+            | Token::LessEqual
+            | Token::Greater
+            | Token::GreaterEqual
+            | Token::LogicalAnd
+            | Token::LogicalOr
+            | Token::BitwiseAnd
+            | Token::BitwiseOr
+            | Token::BitwiseXor
+            | Token::LeftShift
+            | Token::RightShift
+    )
+}
+
+/// Whether this token can appear at the top level of a function header
+/// before its state suffix
+fn is_header_token(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Public
+            | Token::Static
+            | Token::Forward
+            | Token::Native
+            | Token::Identifier(_)
+            | Token::Colon
+            | Token::LeftParen
+            | Token::RightParen
+    )
+}
+
+/// Whether an operator after this token is binary rather than unary
+fn ends_operand(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Number(_)
+            | Token::Float(_)
+            | Token::String(_)
+            | Token::PackedString(_)
+            | Token::Character(_)
+            | Token::Identifier(_)
+            | Token::RightParen
+            | Token::RightBracket
+            | Token::Increment
+            | Token::Decrement
+    )
+}
+
+/// Track brace depth across a line, skipping literals and comments
+///
+/// Returns whether the line ends inside a block comment.
+fn scan_braces(line: &str, mut in_comment: bool, depth: &mut usize) -> bool {
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_comment {
+            if c == '*' && chars.peek() == Some(&'/') {
+                chars.next();
+                in_comment = false;
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => {
+                while let Some(inner) = chars.next() {
+                    if inner == '\\' {
+                        chars.next();
+                    } else if inner == c {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => break,
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                in_comment = true;
+            }
+            '{' => *depth += 1,
+            '}' => *depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    in_comment
+}
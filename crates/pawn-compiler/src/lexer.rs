@@ -1,676 +1,928 @@
-//! Lexical analysis for Pawn source code
-
-use crate::error::*;
-use std::collections::HashMap;
-
-/// Token types for Pawn
-#[derive(Debug, Clone, PartialEq)]
-pub enum Token {
-    // Literals
-    Number(i32),
-    Float(f32),
-    String(String),
-    Character(char),
-
-    // Identifiers
-    Identifier(String),
-
-    // Keywords
-    Main,
-    Public,
-    Native,
-    Forward,
-    Static,
-    Const,
-    New,
-    Delete,
-    If,
-    Else,
-    While,
-    For,
-    Do,
-    Switch,
-    Case,
-    Default,
-    Break,
-    Continue,
-    Return,
-    Goto,
-    Enum,
-    Struct,
-    Union,
-    Typedef,
-    Extern,
-    Auto,
-    Register,
-    Volatile,
-    Inline,
-    Restrict,
-
-    // Operators
-    Plus,           // +
-    Minus,          // -
-    Multiply,       // *
-    Divide,         // /
-    Modulo,         // %
-    Assign,         // =
-    PlusAssign,     // +=
-    MinusAssign,    // -=
-    MultiplyAssign, // *=
-    DivideAssign,   // /=
-    ModuloAssign,   // %=
-    Equal,          // ==
-    NotEqual,       // !=
-    Less,           // <
-    LessEqual,      // <=
-    Greater,        // >
-    GreaterEqual,   // >=
-    LogicalAnd,     // &&
-    LogicalOr,      // ||
-    LogicalNot,     // !
-    BitwiseAnd,     // &
-    BitwiseOr,      // |
-    BitwiseXor,     // ^
-    BitwiseNot,     // ~
-    LeftShift,      // <<
-    RightShift,     // >>
-    Increment,      // ++
-    Decrement,      // --
-
-    // Delimiters
-    LeftParen,    // (
-    RightParen,   // )
-    LeftBracket,  // [
-    RightBracket, // ]
-    LeftBrace,    // {
-    RightBrace,   // }
-    Semicolon,    // ;
-    Comma,        // ,
-    Dot,          // .
-    Arrow,        // ->
-    Colon,        // :
-    Question,     // ?
-
-    // Special
-    EndOfFile,
-    Newline,
-    Comment(String),
-}
-
-/// Lexer for Pawn source code
-pub struct Lexer {
-    input: Vec<char>,
-    position: usize,
-    line: usize,
-    column: usize,
-    keywords: HashMap<String, Token>,
-}
-
-impl Lexer {
-    /// Create a new lexer
-    pub fn new(input: &str) -> Self {
-        let mut lexer = Self {
-            input: input.chars().collect(),
-            position: 0,
-            line: 1,
-            column: 1,
-            keywords: HashMap::new(),
-        };
-
-        lexer.init_keywords();
-        lexer
-    }
-
-    /// Initialize keyword mapping
-    fn init_keywords(&mut self) {
-        self.keywords.insert("main".to_string(), Token::Main);
-        self.keywords.insert("public".to_string(), Token::Public);
-        self.keywords.insert("native".to_string(), Token::Native);
-        self.keywords.insert("forward".to_string(), Token::Forward);
-        self.keywords.insert("static".to_string(), Token::Static);
-        self.keywords.insert("const".to_string(), Token::Const);
-        self.keywords.insert("new".to_string(), Token::New);
-        self.keywords.insert("delete".to_string(), Token::Delete);
-        self.keywords.insert("if".to_string(), Token::If);
-        self.keywords.insert("else".to_string(), Token::Else);
-        self.keywords.insert("while".to_string(), Token::While);
-        self.keywords.insert("for".to_string(), Token::For);
-        self.keywords.insert("do".to_string(), Token::Do);
-        self.keywords.insert("switch".to_string(), Token::Switch);
-        self.keywords.insert("case".to_string(), Token::Case);
-        self.keywords.insert("default".to_string(), Token::Default);
-        self.keywords.insert("break".to_string(), Token::Break);
-        self.keywords
-            .insert("continue".to_string(), Token::Continue);
-        self.keywords.insert("return".to_string(), Token::Return);
-        self.keywords.insert("goto".to_string(), Token::Goto);
-        self.keywords.insert("enum".to_string(), Token::Enum);
-        self.keywords.insert("struct".to_string(), Token::Struct);
-        self.keywords.insert("union".to_string(), Token::Union);
-        self.keywords.insert("typedef".to_string(), Token::Typedef);
-        self.keywords.insert("extern".to_string(), Token::Extern);
-        self.keywords.insert("auto".to_string(), Token::Auto);
-        self.keywords
-            .insert("register".to_string(), Token::Register);
-        self.keywords
-            .insert("volatile".to_string(), Token::Volatile);
-        self.keywords.insert("inline".to_string(), Token::Inline);
-        self.keywords
-            .insert("restrict".to_string(), Token::Restrict);
-    }
-
-    /// Get the current character
-    fn current_char(&self) -> Option<char> {
-        self.input.get(self.position).copied()
-    }
-
-    /// Peek at the next character
-    #[allow(dead_code)]
-    fn peek_char(&self) -> Option<char> {
-        self.input.get(self.position + 1).copied()
-    }
-
-    /// Advance to the next character
-    fn advance(&mut self) {
-        if let Some(ch) = self.current_char() {
-            if ch == '\n' {
-                self.line += 1;
-                self.column = 1;
-            } else {
-                self.column += 1;
-            }
-        }
-        self.position += 1;
-    }
-
-    /// Skip whitespace
-    fn skip_whitespace(&mut self) {
-        while let Some(ch) = self.current_char() {
-            if ch.is_whitespace() && ch != '\n' {
-                self.advance();
-            } else {
-                break;
-            }
-        }
-    }
-
-    /// Read a number
-    fn read_number(&mut self) -> CompilerResult<Token> {
-        let mut value = String::new();
-        let mut is_float = false;
-
-        while let Some(ch) = self.current_char() {
-            if ch.is_ascii_digit() {
-                value.push(ch);
-                self.advance();
-            } else if ch == '.' && !is_float {
-                is_float = true;
-                value.push(ch);
-                self.advance();
-            } else {
-                break;
-            }
-        }
-
-        if is_float {
-            let float_val = value.parse::<f32>().map_err(|_| {
-                CompilerError::LexicalError(format!("Invalid float literal: {}", value))
-            })?;
-            Ok(Token::Float(float_val))
-        } else {
-            let int_val = value.parse::<i32>().map_err(|_| {
-                CompilerError::LexicalError(format!("Invalid integer literal: {}", value))
-            })?;
-            Ok(Token::Number(int_val))
-        }
-    }
-
-    /// Read a string literal
-    fn read_string(&mut self) -> CompilerResult<Token> {
-        let mut value = String::new();
-        self.advance(); // Skip opening quote
-
-        while let Some(ch) = self.current_char() {
-            if ch == '"' {
-                self.advance(); // Skip closing quote
-                break;
-            } else if ch == '\\' {
-                self.advance();
-                if let Some(escaped) = self.current_char() {
-                    let escaped_char = match escaped {
-                        'n' => '\n',
-                        't' => '\t',
-                        'r' => '\r',
-                        '\\' => '\\',
-                        '"' => '"',
-                        _ => escaped,
-                    };
-                    value.push(escaped_char);
-                    self.advance();
-                }
-            } else {
-                value.push(ch);
-                self.advance();
-            }
-        }
-
-        Ok(Token::String(value))
-    }
-
-    /// Read a character literal
-    fn read_character(&mut self) -> CompilerResult<Token> {
-        let mut value = '\0';
-        self.advance(); // Skip opening quote
-
-        if let Some(ch) = self.current_char() {
-            if ch == '\\' {
-                self.advance();
-                if let Some(escaped) = self.current_char() {
-                    value = match escaped {
-                        'n' => '\n',
-                        't' => '\t',
-                        'r' => '\r',
-                        '\\' => '\\',
-                        '\'' => '\'',
-                        _ => escaped,
-                    };
-                    self.advance();
-                }
-            } else {
-                value = ch;
-                self.advance();
-            }
-        }
-
-        if let Some(ch) = self.current_char() {
-            if ch == '\'' {
-                self.advance(); // Skip closing quote
-            }
-        }
-
-        Ok(Token::Character(value))
-    }
-
-    /// Read an identifier or keyword
-    fn read_identifier(&mut self) -> CompilerResult<Token> {
-        let mut value = String::new();
-
-        while let Some(ch) = self.current_char() {
-            if ch.is_ascii_alphanumeric() || ch == '_' {
-                value.push(ch);
-                self.advance();
-            } else {
-                break;
-            }
-        }
-
-        // Check if it's a keyword
-        if let Some(keyword) = self.keywords.get(&value) {
-            Ok(keyword.clone())
-        } else {
-            Ok(Token::Identifier(value))
-        }
-    }
-
-    /// Read a comment
-    fn read_comment(&mut self) -> CompilerResult<Token> {
-        let mut value = String::new();
-        self.advance(); // Skip first /
-
-        if let Some(ch) = self.current_char() {
-            if ch == '/' {
-                // Single-line comment
-                self.advance();
-                while let Some(ch) = self.current_char() {
-                    if ch == '\n' {
-                        break;
-                    }
-                    value.push(ch);
-                    self.advance();
-                }
-            } else if ch == '*' {
-                // Multi-line comment
-                self.advance();
-                while let Some(ch) = self.current_char() {
-                    if ch == '*' {
-                        self.advance();
-                        if let Some(next_ch) = self.current_char() {
-                            if next_ch == '/' {
-                                self.advance();
-                                break;
-                            }
-                        }
-                    }
-                    value.push(ch);
-                    self.advance();
-                }
-            }
-        }
-
-        Ok(Token::Comment(value))
-    }
-
-    /// Get the next token
-    pub fn next_token(&mut self) -> CompilerResult<Token> {
-        self.skip_whitespace();
-
-        if self.position >= self.input.len() {
-            return Ok(Token::EndOfFile);
-        }
-
-        let Some(ch) = self.current_char() else {
-            return Ok(Token::EndOfFile);
-        };
-
-        match ch {
-            '\n' => {
-                self.advance();
-                Ok(Token::Newline)
-            }
-
-            '+' => {
-                self.advance();
-                if let Some(next_ch) = self.current_char() {
-                    match next_ch {
-                        '+' => {
-                            self.advance();
-                            Ok(Token::Increment)
-                        }
-                        '=' => {
-                            self.advance();
-                            Ok(Token::PlusAssign)
-                        }
-                        _ => Ok(Token::Plus),
-                    }
-                } else {
-                    Ok(Token::Plus)
-                }
-            }
-
-            '-' => {
-                self.advance();
-                if let Some(next_ch) = self.current_char() {
-                    match next_ch {
-                        '-' => {
-                            self.advance();
-                            Ok(Token::Decrement)
-                        }
-                        '=' => {
-                            self.advance();
-                            Ok(Token::MinusAssign)
-                        }
-                        '>' => {
-                            self.advance();
-                            Ok(Token::Arrow)
-                        }
-                        _ => Ok(Token::Minus),
-                    }
-                } else {
-                    Ok(Token::Minus)
-                }
-            }
-
-            '*' => {
-                self.advance();
-                if let Some(next_ch) = self.current_char() {
-                    if next_ch == '=' {
-                        self.advance();
-                        Ok(Token::MultiplyAssign)
-                    } else {
-                        Ok(Token::Multiply)
-                    }
-                } else {
-                    Ok(Token::Multiply)
-                }
-            }
-
-            '/' => {
-                self.advance();
-                if let Some(next_ch) = self.current_char() {
-                    match next_ch {
-                        '/' | '*' => self.read_comment(),
-                        '=' => {
-                            self.advance();
-                            Ok(Token::DivideAssign)
-                        }
-                        _ => Ok(Token::Divide),
-                    }
-                } else {
-                    Ok(Token::Divide)
-                }
-            }
-
-            '%' => {
-                self.advance();
-                if let Some(next_ch) = self.current_char() {
-                    if next_ch == '=' {
-                        self.advance();
-                        Ok(Token::ModuloAssign)
-                    } else {
-                        Ok(Token::Modulo)
-                    }
-                } else {
-                    Ok(Token::Modulo)
-                }
-            }
-
-            '=' => {
-                self.advance();
-                if let Some(next_ch) = self.current_char() {
-                    if next_ch == '=' {
-                        self.advance();
-                        Ok(Token::Equal)
-                    } else {
-                        Ok(Token::Assign)
-                    }
-                } else {
-                    Ok(Token::Assign)
-                }
-            }
-
-            '!' => {
-                self.advance();
-                if let Some(next_ch) = self.current_char() {
-                    if next_ch == '=' {
-                        self.advance();
-                        Ok(Token::NotEqual)
-                    } else if next_ch == '"' {
-                        // Packed string literal, treat same as normal string for MVP
-                        self.read_string()
-                    } else {
-                        Ok(Token::LogicalNot)
-                    }
-                } else {
-                    Ok(Token::LogicalNot)
-                }
-            }
-
-            '<' => {
-                self.advance();
-                if let Some(next_ch) = self.current_char() {
-                    match next_ch {
-                        '<' => {
-                            self.advance();
-                            Ok(Token::LeftShift)
-                        }
-                        '=' => {
-                            self.advance();
-                            Ok(Token::LessEqual)
-                        }
-                        _ => Ok(Token::Less),
-                    }
-                } else {
-                    Ok(Token::Less)
-                }
-            }
-
-            '>' => {
-                self.advance();
-                if let Some(next_ch) = self.current_char() {
-                    match next_ch {
-                        '>' => {
-                            self.advance();
-                            Ok(Token::RightShift)
-                        }
-                        '=' => {
-                            self.advance();
-                            Ok(Token::GreaterEqual)
-                        }
-                        _ => Ok(Token::Greater),
-                    }
-                } else {
-                    Ok(Token::Greater)
-                }
-            }
-
-            '&' => {
-                self.advance();
-                if let Some(next_ch) = self.current_char() {
-                    if next_ch == '&' {
-                        self.advance();
-                        Ok(Token::LogicalAnd)
-                    } else {
-                        Ok(Token::BitwiseAnd)
-                    }
-                } else {
-                    Ok(Token::BitwiseAnd)
-                }
-            }
-
-            '|' => {
-                self.advance();
-                if let Some(next_ch) = self.current_char() {
-                    if next_ch == '|' {
-                        self.advance();
-                        Ok(Token::LogicalOr)
-                    } else {
-                        Ok(Token::BitwiseOr)
-                    }
-                } else {
-                    Ok(Token::BitwiseOr)
-                }
-            }
-
-            '^' => {
-                self.advance();
-                Ok(Token::BitwiseXor)
-            }
-
-            '~' => {
-                self.advance();
-                Ok(Token::BitwiseNot)
-            }
-
-            '(' => {
-                self.advance();
-                Ok(Token::LeftParen)
-            }
-
-            ')' => {
-                self.advance();
-                Ok(Token::RightParen)
-            }
-
-            '[' => {
-                self.advance();
-                Ok(Token::LeftBracket)
-            }
-
-            ']' => {
-                self.advance();
-                Ok(Token::RightBracket)
-            }
-
-            '{' => {
-                self.advance();
-                Ok(Token::LeftBrace)
-            }
-
-            '}' => {
-                self.advance();
-                Ok(Token::RightBrace)
-            }
-
-            ';' => {
-                self.advance();
-                Ok(Token::Semicolon)
-            }
-
-            ',' => {
-                self.advance();
-                Ok(Token::Comma)
-            }
-
-            '.' => {
-                self.advance();
-                Ok(Token::Dot)
-            }
-
-            ':' => {
-                self.advance();
-                Ok(Token::Colon)
-            }
-
-            '?' => {
-                self.advance();
-                Ok(Token::Question)
-            }
-
-            '"' => self.read_string(),
-
-            '\'' => self.read_character(),
-
-            // Preprocessor directive, skip to end of line
-            '#' => {
-                // consume '#'
-                self.advance();
-                let mut txt = String::new();
-                while let Some(c) = self.current_char() {
-                    if c == '\n' {
-                        break;
-                    }
-                    txt.push(c);
-                    self.advance();
-                }
-                Ok(Token::Comment(txt))
-            }
-
-            // Event labels like @timer, @keypressed — treat as comment for MVP
-            '@' => {
-                self.advance();
-                let mut txt = String::new();
-                while let Some(c) = self.current_char() {
-                    if c == '\n' {
-                        break;
-                    }
-                    txt.push(c);
-                    self.advance();
-                }
-                Ok(Token::Comment(txt))
-            }
-
-            _ => {
-                if ch.is_ascii_alphabetic() || ch == '_' {
-                    self.read_identifier()
-                } else if ch.is_ascii_digit() {
-                    self.read_number()
-                } else {
-                    Err(CompilerError::LexicalError(format!(
-                        "Unexpected character: {}",
-                        ch
-                    )))
-                }
-            }
-        }
-    }
-
-    /// Get current line number
-    pub fn line(&self) -> usize {
-        self.line
-    }
-
-    /// Get current column number
-    pub fn column(&self) -> usize {
-        self.column
-    }
-}
+//! Lexical analysis for Pawn source code
+
+use crate::ast::Span;
+use crate::error::*;
+use std::collections::HashMap;
+
+/// Token types for Pawn
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    // Literals
+    Number(i32),
+    Float(f32),
+    String(String),
+    PackedString(String), // !"..."
+    Character(char),
+
+    // Identifiers
+    Identifier(String),
+
+    // Keywords
+    Main,
+    Public,
+    Native,
+    Forward,
+    Static,
+    Const,
+    New,
+    Delete,
+    If,
+    Else,
+    While,
+    For,
+    Do,
+    Switch,
+    Case,
+    Default,
+    Break,
+    Continue,
+    Return,
+    Assert,
+    Goto,
+    Enum,
+    Struct,
+    Union,
+    Typedef,
+    Extern,
+    Auto,
+    Register,
+    Volatile,
+    Inline,
+    Restrict,
+    Tagof,
+    Sizeof,
+    State,
+    Emit, // Only with `Lexer::with_keywords`
+
+    // Operators
+    Plus,             // +
+    Minus,            // -
+    Multiply,         // *
+    Divide,           // /
+    Modulo,           // %
+    Assign,           // =
+    PlusAssign,       // +=
+    MinusAssign,      // -=
+    MultiplyAssign,   // *=
+    DivideAssign,     // /=
+    ModuloAssign,     // %=
+    AndAssign,        // &=
+    OrAssign,         // |=
+    XorAssign,        // ^=
+    LeftShiftAssign,  // <<=
+    RightShiftAssign, // >>=
+    Equal,            // ==
+    NotEqual,         // !=
+    Less,             // <
+    LessEqual,        // <=
+    Greater,          // >
+    GreaterEqual,     // >=
+    LogicalAnd,       // &&
+    LogicalOr,        // ||
+    LogicalNot,       // !
+    BitwiseAnd,       // &
+    BitwiseOr,        // |
+    BitwiseXor,       // ^
+    BitwiseNot,       // ~
+    LeftShift,        // <<
+    RightShift,       // >>
+    Increment,        // ++
+    Decrement,        // --
+
+    // Delimiters
+    LeftParen,    // (
+    RightParen,   // )
+    LeftBracket,  // [
+    RightBracket, // ]
+    LeftBrace,    // {
+    RightBrace,   // }
+    Semicolon,    // ;
+    Comma,        // ,
+    Dot,          // .
+    Arrow,        // ->
+    Colon,        // :
+    Question,     // ?
+
+    // Special
+    EndOfFile,
+    Newline,
+    Comment(String),
+}
+
+/// Lexer for Pawn source code
+pub struct Lexer {
+    input: Vec<char>,
+    position: usize,
+    line: usize,
+    column: usize,
+    /// Position where the most recently returned token starts
+    token_line: usize,
+    token_column: usize,
+    token_start: usize,
+    keywords: HashMap<String, Token>,
+    /// Whether `/* */` comments nest; Pawn itself ends a comment at the
+    /// first `*/`
+    nested_block_comments: bool,
+}
+
+impl Lexer {
+    /// Create a new lexer
+    ///
+    /// A leading UTF-8 byte order mark is skipped.
+    pub fn new(input: &str) -> Self {
+        let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+        let mut lexer = Self {
+            input: input.chars().collect(),
+            position: 0,
+            line: 1,
+            column: 1,
+            token_line: 1,
+            token_column: 1,
+            token_start: 0,
+            keywords: HashMap::new(),
+            nested_block_comments: false,
+        };
+
+        lexer.init_keywords();
+        lexer.skip_line_continuations();
+        lexer
+    }
+
+    /// Create a lexer that also recognizes the `extra` keywords
+    ///
+    /// Some dialects reserve words beyond the built-in set, such as `emit`;
+    /// an extra entry replaces a built-in keyword of the same spelling.
+    pub fn with_keywords(input: &str, extra: &[(&str, Token)]) -> Self {
+        let mut lexer = Self::new(input);
+        for (word, token) in extra {
+            lexer.keywords.insert(word.to_string(), token.clone());
+        }
+        lexer
+    }
+
+    /// Stop recognizing `word` as a keyword, so it lexes as an identifier
+    pub fn remove_keyword(&mut self, word: &str) {
+        self.keywords.remove(word);
+    }
+
+    /// Initialize keyword mapping
+    fn init_keywords(&mut self) {
+        self.keywords.insert("main".to_string(), Token::Main);
+        self.keywords.insert("public".to_string(), Token::Public);
+        self.keywords.insert("native".to_string(), Token::Native);
+        self.keywords.insert("forward".to_string(), Token::Forward);
+        self.keywords.insert("static".to_string(), Token::Static);
+        self.keywords.insert("const".to_string(), Token::Const);
+        self.keywords.insert("new".to_string(), Token::New);
+        self.keywords.insert("delete".to_string(), Token::Delete);
+        self.keywords.insert("if".to_string(), Token::If);
+        self.keywords.insert("else".to_string(), Token::Else);
+        self.keywords.insert("while".to_string(), Token::While);
+        self.keywords.insert("for".to_string(), Token::For);
+        self.keywords.insert("do".to_string(), Token::Do);
+        self.keywords.insert("switch".to_string(), Token::Switch);
+        self.keywords.insert("case".to_string(), Token::Case);
+        self.keywords.insert("default".to_string(), Token::Default);
+        self.keywords.insert("break".to_string(), Token::Break);
+        self.keywords
+            .insert("continue".to_string(), Token::Continue);
+        self.keywords.insert("return".to_string(), Token::Return);
+        self.keywords.insert("assert".to_string(), Token::Assert);
+        self.keywords.insert("sizeof".to_string(), Token::Sizeof);
+        self.keywords.insert("tagof".to_string(), Token::Tagof);
+        self.keywords.insert("state".to_string(), Token::State);
+        self.keywords.insert("goto".to_string(), Token::Goto);
+        self.keywords.insert("enum".to_string(), Token::Enum);
+        self.keywords.insert("struct".to_string(), Token::Struct);
+        self.keywords.insert("union".to_string(), Token::Union);
+        self.keywords.insert("typedef".to_string(), Token::Typedef);
+        self.keywords.insert("extern".to_string(), Token::Extern);
+        self.keywords.insert("auto".to_string(), Token::Auto);
+        self.keywords
+            .insert("register".to_string(), Token::Register);
+        self.keywords
+            .insert("volatile".to_string(), Token::Volatile);
+        self.keywords.insert("inline".to_string(), Token::Inline);
+        self.keywords
+            .insert("restrict".to_string(), Token::Restrict);
+    }
+
+    /// Get the current character
+    fn current_char(&self) -> Option<char> {
+        self.input.get(self.position).copied()
+    }
+
+    /// Peek at the next character
+    fn peek_char(&self) -> Option<char> {
+        self.input.get(self.position + 1).copied()
+    }
+
+    /// Let `/* */` comments nest, ending only at the matching `*/`
+    pub fn set_nested_block_comments(&mut self, nested: bool) {
+        self.nested_block_comments = nested;
+    }
+
+    /// Byte offset of the current character in the source
+    fn byte_offset(&self) -> usize {
+        self.input[..self.position.min(self.input.len())]
+            .iter()
+            .map(|ch| ch.len_utf8())
+            .sum()
+    }
+
+    /// Advance to the next character
+    fn advance(&mut self) {
+        self.step();
+        self.skip_line_continuations();
+    }
+
+    /// Advance to the next character, leaving a line continuation there in
+    /// place, as after an escaping backslash
+    fn step(&mut self) {
+        if let Some(ch) = self.current_char() {
+            if ch == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+        self.position += 1;
+    }
+
+    /// Splice lines ending in a backslash onto the following line
+    ///
+    /// The backslash and line break are dropped everywhere, including inside
+    /// string literals and directives, as if the line had not been broken.
+    fn skip_line_continuations(&mut self) {
+        while self.current_char() == Some('\\') {
+            let newline = match (self.peek_char(), self.input.get(self.position + 2)) {
+                (Some('\n'), _) => 2,
+                (Some('\r'), Some('\n')) => 3,
+                _ => return,
+            };
+            self.position += newline;
+            self.line += 1;
+            self.column = 1;
+        }
+    }
+
+    /// Skip whitespace
+    fn skip_whitespace(&mut self) {
+        while let Some(ch) = self.current_char() {
+            if ch.is_whitespace() && ch != '\n' {
+                self.advance();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Read a number
+    fn read_number(&mut self) -> CompilerResult<Token> {
+        let mut value = String::new();
+        let mut is_float = false;
+
+        while let Some(ch) = self.current_char() {
+            if ch.is_ascii_digit() {
+                value.push(ch);
+                self.advance();
+            } else if ch == '.' && !is_float {
+                is_float = true;
+                value.push(ch);
+                self.advance();
+            } else {
+                break;
+            }
+        }
+
+        if is_float {
+            let float_val = value.parse::<f32>().map_err(|_| {
+                CompilerError::LexicalError(format!("Invalid float literal: {}", value))
+            })?;
+            Ok(Token::Float(float_val))
+        } else {
+            // Any 32-bit pattern is a valid cell, so values above i32::MAX
+            // wrap around the way an unsigned cell would be stored
+            match value.parse::<i64>() {
+                Ok(int_val) if (i32::MIN as i64..=u32::MAX as i64).contains(&int_val) => {
+                    Ok(Token::Number(int_val as u32 as i32))
+                }
+                _ => Err(CompilerError::LexicalError(format!(
+                    "Integer literal {} is out of range; a cell holds {} to {}",
+                    value,
+                    i32::MIN,
+                    u32::MAX
+                ))),
+            }
+        }
+    }
+
+    /// Read a string literal, which is packed when prefixed with `!`
+    ///
+    /// A string must be closed on the line it starts on, unless the line
+    /// ends in a backslash continuing it on the next one.
+    fn read_string(&mut self, packed: bool) -> CompilerResult<Token> {
+        let mut value = String::new();
+        self.advance(); // Skip opening quote
+
+        loop {
+            let Some(ch) = self.current_char() else {
+                return Err(self.unterminated_string());
+            };
+            if ch == '"' {
+                self.advance(); // Skip closing quote
+                break;
+            } else if ch == '\n' {
+                return Err(self.unterminated_string());
+            } else if ch == '\\' {
+                // The escaped character is taken as is, so a `\\` ending
+                // the line does not continue it
+                self.step();
+                if let Some(escaped) = self.current_char() {
+                    let escaped_char = match escaped {
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        '\\' => '\\',
+                        '"' => '"',
+                        _ => escaped,
+                    };
+                    value.push(escaped_char);
+                    self.advance();
+                }
+            } else {
+                value.push(ch);
+                self.advance();
+            }
+        }
+
+        if packed {
+            Ok(Token::PackedString(value))
+        } else {
+            Ok(Token::String(value))
+        }
+    }
+
+    fn unterminated_string(&self) -> CompilerError {
+        CompilerError::LexicalError(format!(
+            "Unterminated string literal starting on line {}",
+            self.token_line
+        ))
+    }
+
+    /// Read a character literal
+    fn read_character(&mut self) -> CompilerResult<Token> {
+        let mut value = '\0';
+        self.advance(); // Skip opening quote
+
+        if let Some(ch) = self.current_char() {
+            if ch == '\\' {
+                self.step();
+                if let Some(escaped) = self.current_char() {
+                    value = match escaped {
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        '\\' => '\\',
+                        '\'' => '\'',
+                        _ => escaped,
+                    };
+                    self.advance();
+                }
+            } else {
+                value = ch;
+                self.advance();
+            }
+        }
+
+        if let Some(ch) = self.current_char()
+            && ch == '\''
+        {
+            self.advance(); // Skip closing quote
+        }
+
+        Ok(Token::Character(value))
+    }
+
+    /// Read an identifier or keyword
+    fn read_identifier(&mut self) -> CompilerResult<Token> {
+        let mut value = String::new();
+
+        while let Some(ch) = self.current_char() {
+            if ch.is_ascii_alphanumeric() || ch == '_' {
+                value.push(ch);
+                self.advance();
+            } else {
+                break;
+            }
+        }
+
+        // Check if it's a keyword
+        if let Some(keyword) = self.keywords.get(&value) {
+            Ok(keyword.clone())
+        } else {
+            Ok(Token::Identifier(value))
+        }
+    }
+
+    /// Read a comment
+    fn read_comment(&mut self) -> CompilerResult<Token> {
+        let mut value = String::new();
+        self.advance(); // Skip first /
+
+        if let Some(ch) = self.current_char() {
+            if ch == '/' {
+                // Single-line comment
+                self.advance();
+                while let Some(ch) = self.current_char() {
+                    if ch == '\n' {
+                        break;
+                    }
+                    value.push(ch);
+                    self.advance();
+                }
+            } else if ch == '*' {
+                // Multi-line comment
+                self.advance();
+                let mut depth = 1;
+                loop {
+                    match (self.current_char(), self.peek_char()) {
+                        (None, _) => {
+                            return Err(CompilerError::LexicalError(format!(
+                                "Unterminated block comment starting on line {}",
+                                self.token_line
+                            )));
+                        }
+                        (Some('*'), Some('/')) => {
+                            self.advance();
+                            self.advance();
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                            value.push_str("*/");
+                        }
+                        (Some('/'), Some('*')) if self.nested_block_comments => {
+                            self.advance();
+                            self.advance();
+                            depth += 1;
+                            value.push_str("/*");
+                        }
+                        (Some(ch), _) => {
+                            value.push(ch);
+                            self.advance();
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(Token::Comment(value))
+    }
+
+    /// Get the next token
+    pub fn next_token(&mut self) -> CompilerResult<Token> {
+        self.skip_whitespace();
+        self.token_line = self.line;
+        self.token_column = self.column;
+        self.token_start = self.position;
+
+        if self.position >= self.input.len() {
+            return Ok(Token::EndOfFile);
+        }
+
+        let Some(ch) = self.current_char() else {
+            return Ok(Token::EndOfFile);
+        };
+
+        match ch {
+            '\n' => {
+                self.advance();
+                Ok(Token::Newline)
+            }
+
+            '+' => {
+                self.advance();
+                if let Some(next_ch) = self.current_char() {
+                    match next_ch {
+                        '+' => {
+                            self.advance();
+                            Ok(Token::Increment)
+                        }
+                        '=' => {
+                            self.advance();
+                            Ok(Token::PlusAssign)
+                        }
+                        _ => Ok(Token::Plus),
+                    }
+                } else {
+                    Ok(Token::Plus)
+                }
+            }
+
+            '-' => {
+                self.advance();
+                if let Some(next_ch) = self.current_char() {
+                    match next_ch {
+                        '-' => {
+                            self.advance();
+                            Ok(Token::Decrement)
+                        }
+                        '=' => {
+                            self.advance();
+                            Ok(Token::MinusAssign)
+                        }
+                        '>' => {
+                            self.advance();
+                            Ok(Token::Arrow)
+                        }
+                        _ => Ok(Token::Minus),
+                    }
+                } else {
+                    Ok(Token::Minus)
+                }
+            }
+
+            '*' => {
+                self.advance();
+                if let Some(next_ch) = self.current_char() {
+                    if next_ch == '=' {
+                        self.advance();
+                        Ok(Token::MultiplyAssign)
+                    } else {
+                        Ok(Token::Multiply)
+                    }
+                } else {
+                    Ok(Token::Multiply)
+                }
+            }
+
+            '/' => {
+                if matches!(self.peek_char(), Some('/' | '*')) {
+                    return self.read_comment();
+                }
+                self.advance();
+                if let Some(next_ch) = self.current_char() {
+                    match next_ch {
+                        '=' => {
+                            self.advance();
+                            Ok(Token::DivideAssign)
+                        }
+                        _ => Ok(Token::Divide),
+                    }
+                } else {
+                    Ok(Token::Divide)
+                }
+            }
+
+            '%' => {
+                self.advance();
+                if let Some(next_ch) = self.current_char() {
+                    if next_ch == '=' {
+                        self.advance();
+                        Ok(Token::ModuloAssign)
+                    } else {
+                        Ok(Token::Modulo)
+                    }
+                } else {
+                    Ok(Token::Modulo)
+                }
+            }
+
+            '=' => {
+                self.advance();
+                if let Some(next_ch) = self.current_char() {
+                    if next_ch == '=' {
+                        self.advance();
+                        Ok(Token::Equal)
+                    } else {
+                        Ok(Token::Assign)
+                    }
+                } else {
+                    Ok(Token::Assign)
+                }
+            }
+
+            '!' => {
+                self.advance();
+                if let Some(next_ch) = self.current_char() {
+                    if next_ch == '=' {
+                        self.advance();
+                        Ok(Token::NotEqual)
+                    } else if next_ch == '"' {
+                        self.read_string(true)
+                    } else {
+                        Ok(Token::LogicalNot)
+                    }
+                } else {
+                    Ok(Token::LogicalNot)
+                }
+            }
+
+            '<' => {
+                self.advance();
+                if let Some(next_ch) = self.current_char() {
+                    match next_ch {
+                        '<' => {
+                            self.advance();
+                            if self.current_char() == Some('=') {
+                                self.advance();
+                                Ok(Token::LeftShiftAssign)
+                            } else {
+                                Ok(Token::LeftShift)
+                            }
+                        }
+                        '=' => {
+                            self.advance();
+                            Ok(Token::LessEqual)
+                        }
+                        _ => Ok(Token::Less),
+                    }
+                } else {
+                    Ok(Token::Less)
+                }
+            }
+
+            '>' => {
+                self.advance();
+                if let Some(next_ch) = self.current_char() {
+                    match next_ch {
+                        '>' => {
+                            self.advance();
+                            if self.current_char() == Some('=') {
+                                self.advance();
+                                Ok(Token::RightShiftAssign)
+                            } else {
+                                Ok(Token::RightShift)
+                            }
+                        }
+                        '=' => {
+                            self.advance();
+                            Ok(Token::GreaterEqual)
+                        }
+                        _ => Ok(Token::Greater),
+                    }
+                } else {
+                    Ok(Token::Greater)
+                }
+            }
+
+            '&' => {
+                self.advance();
+                if let Some(next_ch) = self.current_char() {
+                    if next_ch == '&' {
+                        self.advance();
+                        Ok(Token::LogicalAnd)
+                    } else if next_ch == '=' {
+                        self.advance();
+                        Ok(Token::AndAssign)
+                    } else {
+                        Ok(Token::BitwiseAnd)
+                    }
+                } else {
+                    Ok(Token::BitwiseAnd)
+                }
+            }
+
+            '|' => {
+                self.advance();
+                if let Some(next_ch) = self.current_char() {
+                    if next_ch == '|' {
+                        self.advance();
+                        Ok(Token::LogicalOr)
+                    } else if next_ch == '=' {
+                        self.advance();
+                        Ok(Token::OrAssign)
+                    } else {
+                        Ok(Token::BitwiseOr)
+                    }
+                } else {
+                    Ok(Token::BitwiseOr)
+                }
+            }
+
+            '^' => {
+                self.advance();
+                if self.current_char() == Some('=') {
+                    self.advance();
+                    Ok(Token::XorAssign)
+                } else {
+                    Ok(Token::BitwiseXor)
+                }
+            }
+
+            '~' => {
+                self.advance();
+                Ok(Token::BitwiseNot)
+            }
+
+            '(' => {
+                self.advance();
+                Ok(Token::LeftParen)
+            }
+
+            ')' => {
+                self.advance();
+                Ok(Token::RightParen)
+            }
+
+            '[' => {
+                self.advance();
+                Ok(Token::LeftBracket)
+            }
+
+            ']' => {
+                self.advance();
+                Ok(Token::RightBracket)
+            }
+
+            '{' => {
+                self.advance();
+                Ok(Token::LeftBrace)
+            }
+
+            '}' => {
+                self.advance();
+                Ok(Token::RightBrace)
+            }
+
+            ';' => {
+                self.advance();
+                Ok(Token::Semicolon)
+            }
+
+            ',' => {
+                self.advance();
+                Ok(Token::Comma)
+            }
+
+            '.' => {
+                self.advance();
+                Ok(Token::Dot)
+            }
+
+            ':' => {
+                self.advance();
+                Ok(Token::Colon)
+            }
+
+            '?' => {
+                self.advance();
+                Ok(Token::Question)
+            }
+
+            '"' => self.read_string(false),
+
+            '\'' => self.read_character(),
+
+            // Preprocessor directive, skip to end of line
+            '#' => {
+                // consume '#'
+                self.advance();
+                let mut txt = String::new();
+                while let Some(c) = self.current_char() {
+                    if c == '\n' {
+                        break;
+                    }
+                    txt.push(c);
+                    self.advance();
+                }
+                Ok(Token::Comment(txt))
+            }
+
+            // Event labels like @timer, @keypressed — treat as comment for MVP
+            '@' => {
+                self.advance();
+                let mut txt = String::new();
+                while let Some(c) = self.current_char() {
+                    if c == '\n' {
+                        break;
+                    }
+                    txt.push(c);
+                    self.advance();
+                }
+                Ok(Token::Comment(txt))
+            }
+
+            _ => {
+                if ch.is_ascii_alphabetic() || ch == '_' {
+                    self.read_identifier()
+                } else if ch.is_ascii_digit() {
+                    self.read_number()
+                } else {
+                    let offset = self.byte_offset();
+                    // Skip the offending character so lexing can resume after it
+                    self.advance();
+                    Err(CompilerError::LexicalError(if ch.is_alphanumeric() {
+                        format!(
+                            "Non-ASCII character '{}' (U+{:04X}) at byte {} cannot be used in an identifier",
+                            ch, ch as u32, offset
+                        )
+                    } else {
+                        format!(
+                            "Unexpected character '{}' (U+{:04X}) at byte {}",
+                            ch.escape_default(),
+                            ch as u32,
+                            offset
+                        )
+                    }))
+                }
+            }
+        }
+    }
+
+    /// Get the next token, returning a lexical error as a diagnostic
+    ///
+    /// On invalid input the error comes back located at the offending text,
+    /// without a token, and the text is skipped so the following call
+    /// continues after it. Unlike [`Lexer::next_token`], repeated calls
+    /// always reach the end of file.
+    pub fn next_token_recovering(&mut self) -> (Option<Token>, Option<CompilerError>) {
+        match self.next_token() {
+            Ok(token) => (Some(token), None),
+            Err(error) => {
+                if self.position == self.token_start {
+                    self.advance();
+                }
+                let len = self.position - self.token_start;
+                let error = error.at(self.token_line, self.token_column, len);
+                (None, Some(error))
+            }
+        }
+    }
+
+    /// Lex the whole input, collecting lexical errors instead of stopping
+    /// at the first one
+    ///
+    /// The end of file is not included in the tokens.
+    pub fn lex_all(&mut self) -> (Vec<Token>, Vec<CompilerError>) {
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        loop {
+            match self.next_token_recovering() {
+                (Some(Token::EndOfFile), _) => return (tokens, errors),
+                (Some(token), _) => tokens.push(token),
+                (None, error) => errors.extend(error),
+            }
+        }
+    }
+
+    /// Consume the lexer as an iterator over its tokens
+    ///
+    /// The final item is `Token::EndOfFile`; lexical errors are yielded in
+    /// place and lexing continues after the offending input.
+    pub fn tokens(mut self) -> impl Iterator<Item = CompilerResult<Token>> {
+        let mut finished = false;
+        std::iter::from_fn(move || {
+            if finished {
+                return None;
+            }
+            let token = self.next_token();
+            finished = matches!(token, Ok(Token::EndOfFile));
+            Some(token)
+        })
+    }
+
+    /// Lex the whole input, keeping comments and line breaks
+    ///
+    /// Each token comes with the position it starts at and the source text
+    /// it was read from. Spaces and tabs between tokens are not returned, so
+    /// the input can only be rebuilt up to the whitespace within a line. The
+    /// end of file is not included.
+    pub fn lex_all_with_trivia(&mut self) -> CompilerResult<Vec<(Token, Span, String)>> {
+        let mut tokens = Vec::new();
+        loop {
+            let token = self.next_token()?;
+            if token == Token::EndOfFile {
+                return Ok(tokens);
+            }
+            let span = Span {
+                line: self.token_line,
+                column: self.token_column,
+            };
+            let text = self.input[self.token_start..self.position].iter().collect();
+            tokens.push((token, span, text));
+        }
+    }
+
+    /// Get current line number
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Get current column number
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// Get the line on which the last returned token starts
+    pub fn token_line(&self) -> usize {
+        self.token_line
+    }
+
+    /// Get the column at which the last returned token starts
+    pub fn token_column(&self) -> usize {
+        self.token_column
+    }
+}
@@ -1,52 +1,129 @@
-//! Pawn compiler implementation
-//!
-//! This crate provides the core compiler functionality for parsing Pawn source code
-//! and generating AMX bytecode.
-
-pub mod ast;
-pub mod codegen;
-pub mod config;
-pub mod error;
-pub mod formatter;
-pub mod lexer;
-pub mod linter;
-pub mod parser;
-pub mod symbol_table;
-
-pub use ast::*;
-pub use codegen::*;
-pub use config::*;
-pub use error::*;
-pub use formatter::*;
-pub use lexer::*;
-pub use linter::*;
-pub use parser::*;
-pub use symbol_table::*;
-
-/// Compile Pawn source code to AMX bytecode
-pub fn compile(source_code: &str) -> CompilerResult<Vec<u8>> {
-    // Lexical analysis
-    let mut lexer = Lexer::new(source_code);
-    let mut tokens = Vec::new();
-    loop {
-        let token = lexer.next_token()?;
-        match token {
-            Token::EndOfFile => break,
-            _ => tokens.push(token),
-        }
-    }
-
-    // Parsing
-    let mut parser = Parser::new(source_code)?;
-    let ast = parser.parse_program()?;
-
-    // Symbol table analysis
-    let mut symbol_visitor = SymbolTableVisitor::new();
-    symbol_visitor.analyze(&ast)?;
-
-    // Code generation
-    let mut codegen = CodeGenerator::new();
-    let bytecode = codegen.generate(&ast)?;
-
-    Ok(bytecode)
-}
+//! Pawn compiler implementation
+//!
+//! This crate provides the core compiler functionality for parsing Pawn source code
+//! and generating AMX bytecode.
+
+pub mod ast;
+pub mod codegen;
+pub mod config;
+pub mod constant_folding;
+pub mod error;
+pub mod formatter;
+pub mod lexer;
+pub mod linter;
+pub mod parser;
+pub mod project;
+pub mod symbol_table;
+pub mod tag_checker;
+pub mod text;
+pub mod unparse;
+
+pub use ast::*;
+pub use codegen::*;
+pub use config::*;
+pub use constant_folding::*;
+pub use error::*;
+pub use formatter::*;
+pub use lexer::*;
+pub use linter::*;
+pub use parser::*;
+pub use project::*;
+pub use symbol_table::*;
+pub use tag_checker::*;
+pub use text::visual_width;
+pub use unparse::unparse;
+
+/// Parse a standalone expression, outside of any function
+///
+/// Useful for evaluators and debuggers; pair it with [`eval_constant`] to
+/// compute constant expressions without generating code.
+pub fn parse_expression(input: &str) -> CompilerResult<AstNode> {
+    Parser::new(input)?.parse_standalone_expression()
+}
+
+/// Compile Pawn source code to AMX bytecode
+pub fn compile(source_code: &str) -> CompilerResult<Vec<u8>> {
+    compile_with_config(source_code, &Config::default())
+}
+
+/// Compile Pawn source code to AMX bytecode using the natives in `config`
+pub fn compile_with_config(source_code: &str, config: &Config) -> CompilerResult<Vec<u8>> {
+    compile_all_with_config(source_code, config).map_err(|errors| {
+        errors
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| CompilerError::InternalError("Compilation failed".to_string()))
+    })
+}
+
+/// Compile Pawn source code to AMX bytecode, collecting every diagnostic
+///
+/// Lexical, syntax and semantic errors are accumulated rather than stopping
+/// at the first one, so all of them can be reported in a single run.
+pub fn compile_all(source_code: &str) -> Result<Vec<u8>, Vec<CompilerError>> {
+    compile_all_with_config(source_code, &Config::default())
+}
+
+/// Compile Pawn source code to AMX bytecode, collecting every diagnostic
+///
+/// Names in `config.pawn.globals` are known to the symbol table as host
+/// natives, so calling them is not an error.
+pub fn compile_all_with_config(
+    source_code: &str,
+    config: &Config,
+) -> Result<Vec<u8>, Vec<CompilerError>> {
+    let ast = analyze(source_code, config)?;
+
+    // Code generation
+    let mut codegen = CodeGenerator::with_config(&config.codegen);
+    codegen.set_globals(&config.pawn.globals);
+    codegen.generate(&ast).map_err(|e| vec![e])
+}
+
+/// Compile Pawn source code to assembly text instead of bytecode
+///
+/// Shows the instructions as code generation produced them, with symbolic
+/// jump labels; see [`CodeGenerator::to_asm`].
+pub fn emit_asm_with_config(
+    source_code: &str,
+    config: &Config,
+) -> Result<String, Vec<CompilerError>> {
+    let ast = analyze(source_code, config)?;
+
+    let mut codegen = CodeGenerator::with_config(&config.codegen);
+    codegen.set_globals(&config.pawn.globals);
+    codegen.generate(&ast).map_err(|e| vec![e])?;
+    Ok(codegen.to_asm())
+}
+
+/// Parse and check a program, returning its constant-folded syntax tree
+fn analyze(source_code: &str, config: &Config) -> Result<AstNode, Vec<CompilerError>> {
+    // Lexical analysis and parsing
+    let mut parser = Parser::new(source_code).map_err(|e| vec![e])?;
+    let (ast, mut errors) = parser.parse_program_recovering();
+
+    // Symbol table analysis
+    let mut symbol_visitor = SymbolTableVisitor::new();
+    symbol_visitor.set_globals(&config.pawn.globals);
+    symbol_visitor.set_library(config.pawn.library);
+    if let Err(e) = symbol_visitor.analyze(&ast) {
+        if symbol_visitor.get_errors().is_empty() {
+            errors.push(e);
+        } else {
+            errors.extend_from_slice(symbol_visitor.get_errors());
+        }
+    }
+
+    // Tag checking
+    let mut tag_checker = TagChecker::new();
+    if tag_checker.check(&ast).is_err() {
+        errors.extend_from_slice(tag_checker.get_errors());
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    // Evaluate constant expressions at compile time
+    Ok(fold_constants(ast))
+}
@@ -1,94 +1,557 @@
-use crate::config::Config;
-
-#[derive(Debug, Clone)]
-pub struct LintIssue {
-    pub rule: &'static str,
-    pub message: String,
-    pub line: usize,
-}
-
-pub fn lint_source(source: &str, cfg: &Config) -> Vec<LintIssue> {
-    if !cfg.linter.enabled {
-        return Vec::new();
-    }
-    let mut issues = Vec::new();
-    let mut seen_includes = std::collections::HashSet::new();
-    // Missing braces: detect function headers not followed by '{' while body is indented
-    let mut previous_header: Option<(usize, usize)> = None; // (line_no, indent)
-    for (idx, raw_line) in source.lines().enumerate() {
-        let line_no = idx + 1;
-        let line = raw_line;
-        if cfg.linter.check_trailing_whitespace {
-            if line.ends_with(' ') || line.ends_with('\t') {
-                issues.push(LintIssue {
-                    rule: "style.noTrailingWhitespace",
-                    message: "Trailing whitespace".into(),
-                    line: line_no,
-                });
-            }
-        }
-
-        if cfg.linter.check_duplicate_includes {
-            let trimmed = line.trim_start();
-            if trimmed.starts_with("#include") {
-                // naive extract between quotes or after space
-                let token = trimmed.split_whitespace().nth(1).unwrap_or("");
-                if !token.is_empty() {
-                    if !seen_includes.insert(token.to_string()) {
-                        issues.push(LintIssue {
-                            rule: "suspicious.duplicateInclude",
-                            message: format!("Duplicate include: {}", token),
-                            line: line_no,
-                        });
-                    }
-                }
-            }
-        }
-
-        if cfg.linter.check_missing_braces {
-            let trimmed = line.trim_end();
-            let tstart = trimmed.trim_start();
-            let is_header = (tstart.ends_with("()") || tstart.ends_with(")"))
-                && !tstart.contains('{')
-                && !tstart.starts_with('#');
-            if is_header {
-                let indent = leading_whitespace(line);
-                previous_header = Some((line_no, indent));
-                continue;
-            }
-            if let Some((hdr_line, hdr_indent)) = previous_header {
-                if !tstart.is_empty() {
-                    let indent = leading_whitespace(line);
-                    if indent > hdr_indent {
-                        issues.push(LintIssue {
-                            rule: "style.addMissingBraces",
-                            message: "Function-like header without braces around body".into(),
-                            line: hdr_line,
-                        });
-                    }
-                    previous_header = None;
-                }
-            }
-        }
-    }
-    // final newline check
-    if cfg.linter.enabled
-        && cfg.linter.check_newline_eof
-        && !source.is_empty()
-        && !source.ends_with('\n')
-    {
-        issues.push(LintIssue {
-            rule: "style.newlineAtEndOfFile",
-            message: "File should end with a newline".into(),
-            line: source.lines().count(),
-        });
-    }
-    issues
-}
-
-fn leading_whitespace(s: &str) -> usize {
-    s.chars()
-        .take_while(|c| *c == ' ' || *c == '\t')
-        .map(|c| if c == '\t' { 4 } else { 1 })
-        .sum()
-}
+use crate::ast::{AstNode, BinaryOperator, Span, UnaryOperator};
+use crate::config::{Config, Severity};
+use crate::parser::Parser;
+use crate::symbol_table::SymbolTableVisitor;
+use crate::text::indent_width;
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LintIssue {
+    pub rule: &'static str,
+    pub message: String,
+    pub line: usize,
+    /// 1-based column of the offending text
+    pub column: usize,
+    pub severity: Severity,
+}
+
+/// A lint issue paired with the file it was reported for
+#[derive(Debug, Clone, Serialize)]
+pub struct FileLintIssue<'a> {
+    pub file: &'a str,
+    #[serde(flatten)]
+    pub issue: &'a LintIssue,
+}
+
+/// Serialize lint issues as a JSON array for editor integrations
+pub fn issues_to_json(issues: &[FileLintIssue]) -> String {
+    serde_json::to_string_pretty(issues).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Issue counts over every file a lint run checked
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintSummary {
+    pub issues: usize,
+    /// Files with at least one issue
+    pub files: usize,
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+impl LintSummary {
+    /// Count the issues reported for one file
+    pub fn add_file(&mut self, issues: &[LintIssue]) {
+        if issues.is_empty() {
+            return;
+        }
+        self.files += 1;
+        self.issues += issues.len();
+        for issue in issues {
+            match issue.severity {
+                Severity::Error => self.errors += 1,
+                Severity::Warning => self.warnings += 1,
+                Severity::Info => {}
+            }
+        }
+    }
+
+    /// Process exit code for `--check`: 2 if any error was reported, 1 if
+    /// only warnings were, and 0 otherwise
+    pub fn exit_code(&self) -> i32 {
+        if self.errors > 0 {
+            2
+        } else if self.warnings > 0 {
+            1
+        } else {
+            0
+        }
+    }
+}
+
+impl fmt::Display for LintSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} issues in {} files ({} errors, {} warnings)",
+            self.issues, self.files, self.errors, self.warnings
+        )
+    }
+}
+
+fn issue(
+    cfg: &Config,
+    rule: &'static str,
+    message: String,
+    line: usize,
+    column: usize,
+) -> LintIssue {
+    LintIssue {
+        rule,
+        message,
+        line,
+        column,
+        severity: cfg.linter.severity_for(rule),
+    }
+}
+
+pub fn lint_source(source: &str, cfg: &Config) -> Vec<LintIssue> {
+    if !cfg.linter.enabled {
+        return Vec::new();
+    }
+    let mut issues = Vec::new();
+    let mut seen_includes = std::collections::HashSet::new();
+    // Missing braces: detect function headers not followed by '{' while body is indented
+    let mut previous_header: Option<(usize, usize, usize)> = None; // (line_no, column, indent)
+    for (idx, raw_line) in source.lines().enumerate() {
+        let line_no = idx + 1;
+        let line = raw_line;
+        if cfg.linter.check_trailing_whitespace && (line.ends_with(' ') || line.ends_with('\t')) {
+            issues.push(issue(
+                cfg,
+                "style.noTrailingWhitespace",
+                "Trailing whitespace".into(),
+                line_no,
+                line.trim_end_matches([' ', '\t']).chars().count() + 1,
+            ));
+        }
+
+        if cfg.linter.check_duplicate_includes {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("#include") {
+                // naive extract between quotes or after space
+                let token = trimmed.split_whitespace().nth(1).unwrap_or("");
+                if !token.is_empty() && !seen_includes.insert(token.to_string()) {
+                    issues.push(issue(
+                        cfg,
+                        "suspicious.duplicateInclude",
+                        format!("Duplicate include: {}", token),
+                        line_no,
+                        column_of(line, token),
+                    ));
+                }
+            }
+        }
+
+        if cfg.linter.check_missing_braces {
+            let trimmed = line.trim_end();
+            let tstart = trimmed.trim_start();
+            let is_header = (tstart.ends_with("()") || tstart.ends_with(")"))
+                && !tstart.contains('{')
+                && !tstart.starts_with('#');
+            if is_header {
+                let indent = indent_width(line, cfg.formatter.tab_width);
+                previous_header = Some((line_no, column_of(line, tstart), indent));
+                continue;
+            }
+            if let Some((hdr_line, hdr_column, hdr_indent)) = previous_header
+                && !tstart.is_empty()
+            {
+                let indent = indent_width(line, cfg.formatter.tab_width);
+                if indent > hdr_indent {
+                    issues.push(issue(
+                        cfg,
+                        "style.addMissingBraces",
+                        "Function-like header without braces around body".into(),
+                        hdr_line,
+                        hdr_column,
+                    ));
+                }
+                previous_header = None;
+            }
+        }
+    }
+    if cfg.linter.check_indentation {
+        check_indentation(source, cfg, &mut issues);
+    }
+    // final newline check
+    if cfg.linter.enabled
+        && cfg.linter.check_newline_eof
+        && !source.is_empty()
+        && !source.ends_with('\n')
+    {
+        issues.push(issue(
+            cfg,
+            "style.newlineAtEndOfFile",
+            "File should end with a newline".into(),
+            source.lines().count(),
+            source.lines().last().map_or(0, |l| l.chars().count()) + 1,
+        ));
+    }
+
+    // Rules that need the syntax tree only run when the source parses
+    if let Ok(program) = Parser::new(source).and_then(|mut parser| parser.parse_program()) {
+        issues.extend(lint_ast(&program, cfg));
+    }
+    issues
+}
+
+/// Run the AST-based lint rules over a parsed program
+pub fn lint_ast(program: &AstNode, cfg: &Config) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    if cfg.linter.check_unreachable_code {
+        check_unreachable(program, cfg, &mut issues);
+    }
+    if cfg.linter.check_unused_variables || cfg.linter.check_unused_parameters {
+        check_unused(program, cfg, &mut issues);
+    }
+    if cfg.linter.check_assignment_in_condition {
+        check_assignment_in_condition(program, Span::default(), cfg, &mut issues);
+    }
+    if cfg.linter.check_comparison_chaining {
+        check_comparison_chaining(program, cfg, &mut issues);
+    }
+    if cfg.linter.check_magic_numbers {
+        check_magic_numbers(program, cfg, &mut issues);
+    }
+    issues
+}
+
+/// Report variables and parameters that are declared but never read
+fn check_unused(program: &AstNode, cfg: &Config, issues: &mut Vec<LintIssue>) {
+    let mut visitor = SymbolTableVisitor::new();
+    // Semantic errors are the compiler's business; usage data is still valid
+    let _ = visitor.analyze(program);
+
+    for symbol in visitor.get_unused() {
+        let (enabled, rule, kind) = if symbol.is_parameter {
+            (
+                cfg.linter.check_unused_parameters,
+                "suspicious.unusedParameter",
+                "Parameter",
+            )
+        } else {
+            (
+                cfg.linter.check_unused_variables,
+                "suspicious.unusedVariable",
+                "Variable",
+            )
+        };
+        if enabled {
+            issues.push(issue(
+                cfg,
+                rule,
+                format!("{} '{}' is never used", kind, symbol.name),
+                symbol.span.line,
+                symbol.span.column,
+            ));
+        }
+    }
+}
+
+/// Report the first statement following a `return`, `break` or `continue`
+/// in each block
+fn check_unreachable(node: &AstNode, cfg: &Config, issues: &mut Vec<LintIssue>) {
+    match node.inner() {
+        AstNode::Program(items) => {
+            for item in items {
+                check_unreachable(item, cfg, issues);
+            }
+        }
+        AstNode::Function { body, .. } | AstNode::Block(body) => {
+            let mut terminated = false;
+            for stmt in body {
+                if terminated {
+                    let span = stmt.span().unwrap_or_default();
+                    issues.push(issue(
+                        cfg,
+                        "suspicious.unreachableCode",
+                        "Unreachable code".into(),
+                        span.line,
+                        span.column,
+                    ));
+                    break;
+                }
+                check_unreachable(stmt, cfg, issues);
+                terminated = always_exits(stmt);
+            }
+        }
+        AstNode::If {
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            check_unreachable(then_branch, cfg, issues);
+            if let Some(else_branch) = else_branch {
+                check_unreachable(else_branch, cfg, issues);
+            }
+        }
+        AstNode::While { body, .. } | AstNode::DoWhile { body, .. } | AstNode::For { body, .. } => {
+            check_unreachable(body, cfg, issues);
+        }
+        _ => {}
+    }
+}
+
+/// Report `if`, `while`, `do`-`while` and `for` conditions that are a bare
+/// assignment, which is usually a mistyped `==`
+fn check_assignment_in_condition(
+    node: &AstNode,
+    span: Span,
+    cfg: &Config,
+    issues: &mut Vec<LintIssue>,
+) {
+    let check = |condition: &AstNode, issues: &mut Vec<LintIssue>| {
+        if matches!(condition.inner(), AstNode::Assignment { .. }) {
+            issues.push(issue(
+                cfg,
+                "suspicious.assignmentInCondition",
+                "Assignment used as a condition; did you mean '=='?".into(),
+                span.line,
+                span.column,
+            ));
+        }
+    };
+    match node {
+        AstNode::Located { span, node } => check_assignment_in_condition(node, *span, cfg, issues),
+        AstNode::Program(items) | AstNode::Function { body: items, .. } | AstNode::Block(items) => {
+            for item in items {
+                check_assignment_in_condition(item, span, cfg, issues);
+            }
+        }
+        AstNode::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            check(condition, issues);
+            check_assignment_in_condition(then_branch, span, cfg, issues);
+            if let Some(else_branch) = else_branch {
+                check_assignment_in_condition(else_branch, span, cfg, issues);
+            }
+        }
+        AstNode::While { condition, body } | AstNode::DoWhile { body, condition } => {
+            check(condition, issues);
+            check_assignment_in_condition(body, span, cfg, issues);
+        }
+        AstNode::For {
+            condition, body, ..
+        } => {
+            if let Some(condition) = condition {
+                check(condition, issues);
+            }
+            check_assignment_in_condition(body, span, cfg, issues);
+        }
+        _ => {}
+    }
+}
+
+/// Report relational comparisons with a comparison as an operand, such as
+/// `a < b < c`, which compares the 0 or 1 of `a < b` against `c`
+fn check_comparison_chaining(program: &AstNode, cfg: &Config, issues: &mut Vec<LintIssue>) {
+    let compares = |operand: &AstNode| matches!(operand.inner(), AstNode::BinaryOp { operator, .. } if is_relational(operator));
+    walk_statements(program, Span::default(), &mut |node, span| {
+        if let AstNode::BinaryOp {
+            left,
+            operator,
+            right,
+        } = node
+            && is_relational(operator)
+            && (compares(left) || compares(right))
+        {
+            issues.push(issue(
+                cfg,
+                "suspicious.comparisonChaining",
+                "Chained comparison compares a true/false result; \
+                 combine the comparisons with '&&'"
+                    .into(),
+                span.line,
+                span.column,
+            ));
+        }
+        true
+    });
+}
+
+/// Report numeric literals other than -1, 0, 1 and the configured
+/// allowlist, which are better given a name
+///
+/// Initializers of `const` declarations are what naming the number looks
+/// like, so they are not reported.
+fn check_magic_numbers(program: &AstNode, cfg: &Config, issues: &mut Vec<LintIssue>) {
+    let allowed = |value: f64| {
+        [-1.0, 0.0, 1.0].contains(&value)
+            || cfg
+                .linter
+                .magic_number_allowlist
+                .iter()
+                .any(|&allowed| f64::from(allowed) == value)
+    };
+    walk_statements(program, Span::default(), &mut |node, span| {
+        let (literal, value) = match node {
+            AstNode::VariableDeclaration { is_const: true, .. } => return false,
+            AstNode::UnaryOp {
+                operator: UnaryOperator::Minus,
+                operand,
+            } => match literal_value(operand) {
+                Some((literal, value)) => (format!("-{}", literal), -value),
+                None => return true,
+            },
+            _ => match literal_value(node) {
+                Some(literal) => literal,
+                None => return true,
+            },
+        };
+        if !allowed(value) {
+            issues.push(issue(
+                cfg,
+                "suspicious.magicNumber",
+                format!("Magic number {}; consider a named constant", literal),
+                span.line,
+                span.column,
+            ));
+        }
+        false
+    });
+}
+
+/// Source text and value of a numeric literal
+fn literal_value(node: &AstNode) -> Option<(String, f64)> {
+    match node.inner() {
+        AstNode::Integer(value) => Some((value.to_string(), f64::from(*value))),
+        AstNode::Float(value) => Some((value.to_string(), f64::from(*value))),
+        _ => None,
+    }
+}
+
+/// Call `visit` on every statement and expression under `node`, along with
+/// the span of the statement it is part of
+///
+/// The children of a node are visited only when `visit` returns true. Array
+/// sizes of declarations are not visited.
+fn walk_statements(node: &AstNode, span: Span, visit: &mut dyn FnMut(&AstNode, Span) -> bool) {
+    if let AstNode::Located { span, node } = node {
+        return walk_statements(node, *span, visit);
+    }
+    if !visit(node, span) {
+        return;
+    }
+    let mut walk = |child: &AstNode| walk_statements(child, span, visit);
+    match node {
+        AstNode::Program(items) | AstNode::Function { body: items, .. } | AstNode::Block(items) => {
+            items.iter().for_each(walk);
+        }
+        AstNode::FunctionCall { arguments, .. } => arguments.iter().for_each(walk),
+        AstNode::Expression(inner)
+        | AstNode::Assert(inner)
+        | AstNode::Return(Some(inner))
+        | AstNode::UnaryOp { operand: inner, .. } => walk(inner),
+        AstNode::VariableDeclaration { initializer, .. } => {
+            initializer.iter().for_each(|init| walk(init));
+        }
+        AstNode::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            walk(condition);
+            walk(then_branch);
+            else_branch.iter().for_each(|branch| walk(branch));
+        }
+        AstNode::While { condition, body } | AstNode::DoWhile { body, condition } => {
+            walk(condition);
+            walk(body);
+        }
+        AstNode::For {
+            init,
+            condition,
+            update,
+            body,
+        } => {
+            for clause in [init, condition, update].into_iter().flatten() {
+                walk(clause);
+            }
+            walk(body);
+        }
+        AstNode::Assignment { target, value }
+        | AstNode::ArrayAccess {
+            array: target,
+            index: value,
+        }
+        | AstNode::BinaryOp {
+            left: target,
+            right: value,
+            ..
+        } => {
+            walk(target);
+            walk(value);
+        }
+        AstNode::Ternary {
+            condition,
+            then_value,
+            else_value,
+        } => {
+            walk(condition);
+            walk(then_value);
+            walk(else_value);
+        }
+        _ => {}
+    }
+}
+
+fn is_relational(operator: &BinaryOperator) -> bool {
+    matches!(
+        operator,
+        BinaryOperator::Less
+            | BinaryOperator::LessEqual
+            | BinaryOperator::Greater
+            | BinaryOperator::GreaterEqual
+    )
+}
+
+/// Whether control never continues past this statement
+fn always_exits(stmt: &AstNode) -> bool {
+    match stmt.inner() {
+        AstNode::Return(_) | AstNode::Break | AstNode::Continue => true,
+        AstNode::Block(statements) => statements.iter().any(always_exits),
+        AstNode::If {
+            then_branch,
+            else_branch: Some(else_branch),
+            ..
+        } => always_exits(then_branch) && always_exits(else_branch),
+        _ => false,
+    }
+}
+
+/// Report lines whose indentation mixes tabs and spaces, or uses the
+/// character the rest of the file does not
+fn check_indentation(source: &str, cfg: &Config, issues: &mut Vec<LintIssue>) {
+    let indents: Vec<&str> = source
+        .lines()
+        .map(|line| &line[..line.len() - line.trim_start_matches([' ', '\t']).len()])
+        .collect();
+    let tab_lines = indents.iter().filter(|i| i.starts_with('\t')).count();
+    let space_lines = indents.iter().filter(|i| i.starts_with(' ')).count();
+    // Without a majority neither style is wrong
+    let dominant = match tab_lines.cmp(&space_lines) {
+        std::cmp::Ordering::Greater => Some(('\t', "tabs", "spaces")),
+        std::cmp::Ordering::Less => Some((' ', "spaces", "tabs")),
+        std::cmp::Ordering::Equal => None,
+    };
+
+    for (idx, indent) in indents.iter().enumerate() {
+        let message = if indent.contains(' ') && indent.contains('\t') {
+            "Indentation mixes tabs and spaces".to_string()
+        } else if let Some((style, expected, found)) = dominant
+            && !indent.is_empty()
+            && !indent.starts_with(style)
+        {
+            format!("Indented with {} but the file uses {}", found, expected)
+        } else {
+            continue;
+        };
+        issues.push(issue(
+            cfg,
+            "style.consistentIndentation",
+            message,
+            idx + 1,
+            1,
+        ));
+    }
+}
+
+/// 1-based column at which `part`, a subslice of `line`, starts
+fn column_of(line: &str, part: &str) -> usize {
+    let offset = part.as_ptr() as usize - line.as_ptr() as usize;
+    line[..offset].chars().count() + 1
+}
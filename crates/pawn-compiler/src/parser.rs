@@ -1,402 +1,1254 @@
-//! Parser for Pawn source code
-
-use crate::ast::*;
-use crate::error::*;
-use crate::lexer::*;
-
-/// Parser for Pawn source code
-pub struct Parser {
-    lexer: Lexer,
-    current_token: Token,
-    peek_token: Option<Token>,
-}
-
-impl Parser {
-    /// Create a new parser
-    pub fn new(input: &str) -> CompilerResult<Self> {
-        let mut lexer = Lexer::new(input);
-        let current_token = lexer.next_token()?;
-        let peek_token = Some(lexer.next_token()?);
-
-        Ok(Parser {
-            lexer,
-            current_token,
-            peek_token,
-        })
-    }
-
-    /// Advance to the next token
-    fn advance(&mut self) -> CompilerResult<()> {
-        self.current_token = self.peek_token.take().unwrap_or(Token::EndOfFile);
-        if self.current_token != Token::EndOfFile {
-            self.peek_token = Some(self.lexer.next_token()?);
-        }
-        Ok(())
-    }
-
-    /// Check if current token matches expected
-    fn expect(&mut self, expected: Token) -> CompilerResult<()> {
-        if self.current_token == expected {
-            self.advance()?;
-            Ok(())
-        } else {
-            Err(CompilerError::ParserError(format!(
-                "Expected {:?}, found {:?}",
-                expected, self.current_token
-            )))
-        }
-    }
-
-    /// Parse a complete program
-    pub fn parse_program(&mut self) -> CompilerResult<AstNode> {
-        let mut statements = Vec::new();
-
-        while self.current_token != Token::EndOfFile {
-            match self.parse_statement()? {
-                Some(stmt) => statements.push(stmt),
-                None => break,
-            }
-        }
-
-        Ok(AstNode::Program(statements))
-    }
-
-    /// Parse a statement
-    fn parse_statement(&mut self) -> CompilerResult<Option<AstNode>> {
-        match &self.current_token {
-            Token::Main => {
-                self.advance()?;
-                self.expect(Token::LeftParen)?;
-                self.expect(Token::RightParen)?;
-
-                // Check if there's a left brace, if not, parse single statement
-                let mut body = Vec::new();
-                if self.current_token == Token::LeftBrace {
-                    self.advance()?;
-                    while self.current_token != Token::RightBrace
-                        && self.current_token != Token::EndOfFile
-                    {
-                        if let Some(stmt) = self.parse_statement()? {
-                            body.push(stmt);
-                        }
-                    }
-                    self.expect(Token::RightBrace)?;
-                } else {
-                    // Parse single statement without braces: skip trivia first
-                    loop {
-                        match self.current_token {
-                            Token::Newline | Token::Semicolon | Token::Comment(_) => {
-                                self.advance()?;
-                            }
-                            _ => break,
-                        }
-                    }
-                    if let Some(stmt) = self.parse_statement()? {
-                        body.push(stmt);
-                    }
-                }
-
-                Ok(Some(AstNode::Function {
-                    name: "main".to_string(),
-                    parameters: Vec::new(),
-                    return_type: None,
-                    body,
-                    is_public: false,
-                    is_native: false,
-                    is_forward: false,
-                }))
-            }
-
-            Token::Identifier(name) => {
-                if name == "printf" {
-                    self.advance()?;
-
-                    // Check if there's a left parenthesis
-                    if self.current_token == Token::LeftParen {
-                        self.advance()?;
-
-                        let format_string = if let Token::String(s) = &self.current_token {
-                            let s = s.clone();
-                            self.advance()?;
-                            s
-                        } else {
-                            return Err(CompilerError::ParserError(
-                                "Expected format string".to_string(),
-                            ));
-                        };
-
-                        self.expect(Token::RightParen)?;
-                        self.expect(Token::Semicolon)?;
-
-                        Ok(Some(AstNode::FunctionCall {
-                            name: "printf".to_string(),
-                            arguments: vec![AstNode::String(format_string)],
-                        }))
-                    } else {
-                        // printf without parentheses - just take the next string
-                        let format_string = if let Token::String(s) = &self.current_token {
-                            let s = s.clone();
-                            self.advance()?;
-                            s
-                        } else {
-                            return Err(CompilerError::ParserError(
-                                "Expected format string".to_string(),
-                            ));
-                        };
-
-                        Ok(Some(AstNode::FunctionCall {
-                            name: "printf".to_string(),
-                            arguments: vec![AstNode::String(format_string)],
-                        }))
-                    }
-                } else {
-                    // For MVP, skip unknown identifier-started statements until EOL or semicolon
-                    while self.current_token != Token::Semicolon
-                        && self.current_token != Token::Newline
-                        && self.current_token != Token::EndOfFile
-                    {
-                        self.advance()?;
-                    }
-                    if self.current_token == Token::Semicolon {
-                        self.advance()?;
-                    }
-                    Ok(None)
-                }
-            }
-
-            Token::Semicolon => {
-                self.advance()?;
-                Ok(None)
-            }
-
-            Token::Comment(_) => {
-                self.advance()?;
-                Ok(None)
-            }
-
-            Token::Newline => {
-                self.advance()?;
-                Ok(None)
-            }
-
-            // Gracefully skip constructs we don't implement in MVP
-            Token::Enum | Token::Forward | Token::New | Token::Const | Token::Static => {
-                // Skip until end of line or closing brace or semicolon
-                while self.current_token != Token::Semicolon
-                    && self.current_token != Token::Newline
-                    && self.current_token != Token::RightBrace
-                    && self.current_token != Token::EndOfFile
-                {
-                    self.advance()?;
-                }
-                if self.current_token == Token::Semicolon {
-                    self.advance()?;
-                }
-                Ok(None)
-            }
-
-            _ => {
-                // Skip unrecognized token lines conservatively
-                while self.current_token != Token::Semicolon
-                    && self.current_token != Token::Newline
-                    && self.current_token != Token::EndOfFile
-                {
-                    self.advance()?;
-                }
-                if self.current_token == Token::Semicolon {
-                    self.advance()?;
-                }
-                Ok(None)
-            }
-        }
-    }
-
-    /// Parse an expression
-    #[allow(dead_code)]
-    fn parse_expression(&mut self) -> CompilerResult<AstNode> {
-        self.parse_equality()
-    }
-
-    /// Parse equality expressions
-    #[allow(dead_code)]
-    fn parse_equality(&mut self) -> CompilerResult<AstNode> {
-        let mut left = self.parse_comparison()?;
-
-        while matches!(self.current_token, Token::Equal | Token::NotEqual) {
-            let operator = match self.current_token {
-                Token::Equal => BinaryOperator::Equal,
-                Token::NotEqual => BinaryOperator::NotEqual,
-                _ => {
-                    return Err(CompilerError::ParserError(
-                        "Invalid equality operator".into(),
-                    ));
-                }
-            };
-            self.advance()?;
-            let right = self.parse_comparison()?;
-            left = AstNode::BinaryOp {
-                left: Box::new(left),
-                operator,
-                right: Box::new(right),
-            };
-        }
-
-        Ok(left)
-    }
-
-    /// Parse comparison expressions
-    #[allow(dead_code)]
-    fn parse_comparison(&mut self) -> CompilerResult<AstNode> {
-        let mut left = self.parse_term()?;
-
-        while matches!(
-            self.current_token,
-            Token::Less | Token::LessEqual | Token::Greater | Token::GreaterEqual
-        ) {
-            let operator = match self.current_token {
-                Token::Less => BinaryOperator::Less,
-                Token::LessEqual => BinaryOperator::LessEqual,
-                Token::Greater => BinaryOperator::Greater,
-                Token::GreaterEqual => BinaryOperator::GreaterEqual,
-                _ => {
-                    return Err(CompilerError::ParserError(
-                        "Invalid comparison operator".into(),
-                    ));
-                }
-            };
-            self.advance()?;
-            let right = self.parse_term()?;
-            left = AstNode::BinaryOp {
-                left: Box::new(left),
-                operator,
-                right: Box::new(right),
-            };
-        }
-
-        Ok(left)
-    }
-
-    /// Parse term expressions
-    #[allow(dead_code)]
-    fn parse_term(&mut self) -> CompilerResult<AstNode> {
-        let mut left = self.parse_factor()?;
-
-        while matches!(self.current_token, Token::Plus | Token::Minus) {
-            let operator = match self.current_token {
-                Token::Plus => BinaryOperator::Add,
-                Token::Minus => BinaryOperator::Subtract,
-                _ => return Err(CompilerError::ParserError("Invalid term operator".into())),
-            };
-            self.advance()?;
-            let right = self.parse_factor()?;
-            left = AstNode::BinaryOp {
-                left: Box::new(left),
-                operator,
-                right: Box::new(right),
-            };
-        }
-
-        Ok(left)
-    }
-
-    /// Parse factor expressions
-    #[allow(dead_code)]
-    fn parse_factor(&mut self) -> CompilerResult<AstNode> {
-        let mut left = self.parse_unary()?;
-
-        while matches!(
-            self.current_token,
-            Token::Multiply | Token::Divide | Token::Modulo
-        ) {
-            let operator = match self.current_token {
-                Token::Multiply => BinaryOperator::Multiply,
-                Token::Divide => BinaryOperator::Divide,
-                Token::Modulo => BinaryOperator::Modulo,
-                _ => return Err(CompilerError::ParserError("Invalid factor operator".into())),
-            };
-            self.advance()?;
-            let right = self.parse_unary()?;
-            left = AstNode::BinaryOp {
-                left: Box::new(left),
-                operator,
-                right: Box::new(right),
-            };
-        }
-
-        Ok(left)
-    }
-
-    /// Parse unary expressions
-    #[allow(dead_code)]
-    fn parse_unary(&mut self) -> CompilerResult<AstNode> {
-        match self.current_token {
-            Token::Plus => {
-                self.advance()?;
-                let operand = self.parse_unary()?;
-                Ok(AstNode::UnaryOp {
-                    operator: UnaryOperator::Plus,
-                    operand: Box::new(operand),
-                })
-            }
-            Token::Minus => {
-                self.advance()?;
-                let operand = self.parse_unary()?;
-                Ok(AstNode::UnaryOp {
-                    operator: UnaryOperator::Minus,
-                    operand: Box::new(operand),
-                })
-            }
-            Token::LogicalNot => {
-                self.advance()?;
-                let operand = self.parse_unary()?;
-                Ok(AstNode::UnaryOp {
-                    operator: UnaryOperator::LogicalNot,
-                    operand: Box::new(operand),
-                })
-            }
-            _ => self.parse_primary(),
-        }
-    }
-
-    /// Parse primary expressions
-    #[allow(dead_code)]
-    fn parse_primary(&mut self) -> CompilerResult<AstNode> {
-        match &self.current_token {
-            Token::Number(n) => {
-                let value = *n;
-                self.advance()?;
-                Ok(AstNode::Integer(value))
-            }
-            Token::Float(f) => {
-                let value = *f;
-                self.advance()?;
-                Ok(AstNode::Float(value))
-            }
-            Token::String(s) => {
-                let value = s.clone();
-                self.advance()?;
-                Ok(AstNode::String(value))
-            }
-            Token::Character(c) => {
-                let value = *c;
-                self.advance()?;
-                Ok(AstNode::Character(value))
-            }
-            Token::Identifier(name) => {
-                let name = name.clone();
-                self.advance()?;
-                Ok(AstNode::Identifier(name))
-            }
-            Token::LeftParen => {
-                self.advance()?;
-                let expr = self.parse_expression()?;
-                self.expect(Token::RightParen)?;
-                Ok(expr)
-            }
-            _ => Err(CompilerError::ParserError(format!(
-                "Unexpected token in expression: {:?}",
-                self.current_token
-            ))),
-        }
-    }
-}
+//! Parser for Pawn source code
+
+use crate::ast::*;
+use crate::error::*;
+use crate::lexer::*;
+
+/// Default limit on how deeply expressions and statements may nest
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// Parser for Pawn source code
+pub struct Parser {
+    lexer: Lexer,
+    current_token: Token,
+    peek_token: Option<Token>,
+    /// Whether a line break separates the current token from the previous one
+    newline_before: bool,
+    /// Whether a line break separates the peek token from the current one
+    peek_newline_before: bool,
+    /// Source position of the current token
+    span: Span,
+    peek_span: Span,
+    /// Length in characters of the current token
+    token_len: usize,
+    peek_token_len: usize,
+    /// Diagnostics collected while parsing
+    errors: Vec<CompilerError>,
+    /// Current nesting of recursive expression and statement rules
+    depth: usize,
+    max_depth: usize,
+}
+
+impl Parser {
+    /// Create a new parser
+    pub fn new(input: &str) -> CompilerResult<Self> {
+        let mut parser = Parser {
+            lexer: Lexer::new(input),
+            current_token: Token::EndOfFile,
+            peek_token: None,
+            newline_before: false,
+            peek_newline_before: false,
+            span: Span::default(),
+            peek_span: Span::default(),
+            token_len: 0,
+            peek_token_len: 0,
+            errors: Vec::new(),
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+        };
+        let (token, newline, span, len) = parser.next_significant_token();
+        parser.peek_token = Some(token);
+        parser.peek_newline_before = newline;
+        parser.peek_span = span;
+        parser.peek_token_len = len;
+        parser.advance()?;
+
+        Ok(parser)
+    }
+
+    /// Read the next token that is not a comment or a line break
+    ///
+    /// Lexical errors are recorded and lexing resumes after the offending
+    /// input, so a single bad character does not hide later diagnostics.
+    fn next_significant_token(&mut self) -> (Token, bool, Span, usize) {
+        let mut newline = false;
+        loop {
+            match self.lexer.next_token() {
+                Ok(Token::Newline) => newline = true,
+                Ok(Token::Comment(_)) => {}
+                Ok(token) => {
+                    let span = Span {
+                        line: self.lexer.token_line(),
+                        column: self.lexer.token_column(),
+                    };
+                    // Tokens spanning lines are underlined on their first
+                    let len = if self.lexer.line() == span.line {
+                        self.lexer.column() - span.column
+                    } else {
+                        1
+                    };
+                    return (token, newline, span, len);
+                }
+                Err(e) => {
+                    self.errors
+                        .push(e.at(self.lexer.token_line(), self.lexer.token_column(), 1))
+                }
+            }
+        }
+    }
+
+    /// Advance to the next token
+    fn advance(&mut self) -> CompilerResult<()> {
+        self.current_token = self.peek_token.take().unwrap_or(Token::EndOfFile);
+        self.newline_before = self.peek_newline_before;
+        self.span = self.peek_span;
+        self.token_len = self.peek_token_len;
+        if self.current_token != Token::EndOfFile {
+            let (token, newline, span, len) = self.next_significant_token();
+            self.peek_token = Some(token);
+            self.peek_newline_before = newline;
+            self.peek_span = span;
+            self.peek_token_len = len;
+        }
+        Ok(())
+    }
+
+    /// Check if current token matches expected
+    fn expect(&mut self, expected: Token) -> CompilerResult<()> {
+        if self.current_token == expected {
+            self.advance()?;
+            Ok(())
+        } else {
+            Err(CompilerError::ParserError(format!(
+                "Expected {:?}, found {:?}",
+                expected, self.current_token
+            )))
+        }
+    }
+
+    /// Consume an identifier and return its name
+    fn expect_identifier(&mut self) -> CompilerResult<String> {
+        if let Token::Identifier(name) = &self.current_token {
+            let name = name.clone();
+            self.advance()?;
+            Ok(name)
+        } else {
+            Err(CompilerError::ParserError(format!(
+                "Expected identifier, found {:?}",
+                self.current_token
+            )))
+        }
+    }
+
+    /// Check whether the current token terminates a statement
+    ///
+    /// Semicolons are optional in Pawn when the statement ends at a line break.
+    fn at_statement_end(&self) -> bool {
+        self.newline_before
+            || matches!(
+                self.current_token,
+                Token::Semicolon | Token::RightBrace | Token::EndOfFile
+            )
+    }
+
+    /// Consume the end of a statement
+    fn end_statement(&mut self) -> CompilerResult<()> {
+        if self.current_token == Token::Semicolon {
+            self.advance()
+        } else if self.at_statement_end() {
+            Ok(())
+        } else {
+            Err(CompilerError::ParserError(format!(
+                "Expected Semicolon, found {:?}",
+                self.current_token
+            )))
+        }
+    }
+
+    /// Skip tokens until the start of the next statement
+    ///
+    /// Used for error recovery: stops after a `;`, before a `}` or at the
+    /// first token on a new line.
+    fn synchronize(&mut self) -> CompilerResult<()> {
+        loop {
+            match self.current_token {
+                Token::EndOfFile | Token::RightBrace => return Ok(()),
+                Token::Semicolon => return self.advance(),
+                _ => {
+                    self.advance()?;
+                    if self.newline_before {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Attach the position of the current token to an error
+    fn locate(&self, error: CompilerError) -> CompilerError {
+        error.at(self.span.line, self.span.column, self.token_len)
+    }
+
+    /// Skip a declaration we don't implement yet, up to the end of its line
+    fn skip_declaration(&mut self) -> CompilerResult<()> {
+        self.advance()?;
+        while !self.at_statement_end() {
+            self.advance()?;
+        }
+        if self.current_token == Token::Semicolon {
+            self.advance()?;
+        }
+        Ok(())
+    }
+
+    /// Limit how deeply expressions and statements may nest
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    /// Parse a complete program
+    pub fn parse_program(&mut self) -> CompilerResult<AstNode> {
+        let (program, errors) = self.parse_program_recovering();
+        match errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(program),
+        }
+    }
+
+    /// Parse a complete program, recovering from errors at statement boundaries
+    ///
+    /// Returns the (possibly partial) program together with every lexical and
+    /// syntax error encountered.
+    pub fn parse_program_recovering(&mut self) -> (AstNode, Vec<CompilerError>) {
+        let mut statements = Vec::new();
+
+        while self.current_token != Token::EndOfFile {
+            let span = self.span;
+            let item = if matches!(self.current_token, Token::New | Token::Static) {
+                self.parse_global_declaration(&mut statements).map(|_| None)
+            } else {
+                self.parse_top_level()
+                    .map(|item| item.map(|node| Self::located(span, node)))
+            };
+            match item {
+                Ok(Some(item)) => statements.push(item),
+                Ok(None) => {}
+                Err(e) => {
+                    self.errors.push(self.locate(e));
+                    if let Err(e) = self.synchronize() {
+                        self.errors.push(self.locate(e));
+                        break;
+                    }
+                }
+            }
+        }
+
+        (
+            AstNode::Program(statements),
+            std::mem::take(&mut self.errors),
+        )
+    }
+
+    /// Parse input consisting of a single expression, such as `1 + 2 * 3`
+    pub fn parse_standalone_expression(&mut self) -> CompilerResult<AstNode> {
+        let expr = self.parse_expression().map_err(|e| self.locate(e))?;
+        if self.current_token != Token::EndOfFile {
+            return Err(self.locate(CompilerError::ParserError(format!(
+                "Unexpected token after expression: {:?}",
+                self.current_token
+            ))));
+        }
+        Ok(expr)
+    }
+
+    /// Get the diagnostics collected so far
+    pub fn get_errors(&self) -> &[CompilerError] {
+        &self.errors
+    }
+
+    /// Parse a top-level declaration
+    fn parse_top_level(&mut self) -> CompilerResult<Option<AstNode>> {
+        match &self.current_token {
+            Token::Main => {
+                self.advance()?;
+                self.parse_function("main".to_string(), None, false)
+                    .map(Some)
+            }
+
+            Token::Public => {
+                self.advance()?;
+                let (name, return_type) = self.parse_function_name()?;
+                self.parse_function(name, return_type, true).map(Some)
+            }
+
+            Token::Native => {
+                self.advance()?;
+                let (name, return_type) = self.parse_function_name()?;
+                let parameters = self.parse_parameters()?;
+                self.end_statement()?;
+                Ok(Some(AstNode::Function {
+                    name,
+                    parameters,
+                    return_type,
+                    body: Vec::new(),
+                    is_public: false,
+                    is_native: true,
+                    is_forward: false,
+                    states: None,
+                }))
+            }
+
+            Token::Identifier(name) => {
+                // `stock` only affects unused-function warnings
+                if name == "stock" {
+                    self.advance()?;
+                }
+                let (name, return_type) = self.parse_function_name()?;
+                self.parse_function(name, return_type, false).map(Some)
+            }
+
+            Token::Semicolon => {
+                self.advance()?;
+                Ok(None)
+            }
+
+            // Gracefully skip constructs we don't implement in MVP
+            Token::Enum | Token::Forward | Token::Const => {
+                self.skip_declaration()?;
+                Ok(None)
+            }
+
+            Token::RightBrace => {
+                self.advance()?;
+                Err(CompilerError::ParserError(
+                    "Unexpected RightBrace outside of a function".to_string(),
+                ))
+            }
+
+            _ => Err(CompilerError::ParserError(format!(
+                "Unexpected token at top level: {:?}",
+                self.current_token
+            ))),
+        }
+    }
+
+    /// Parse a global `new`/`static` declaration, or a `static` function
+    fn parse_global_declaration(&mut self, items: &mut Vec<AstNode>) -> CompilerResult<()> {
+        let is_static = self.current_token == Token::Static;
+        self.advance()?;
+
+        if is_static
+            && matches!(self.current_token, Token::Identifier(_))
+            && self.peek_token == Some(Token::LeftParen)
+        {
+            let name = self.expect_identifier()?;
+            items.push(self.parse_function(name, None, false)?);
+            return Ok(());
+        }
+
+        items.extend(self.parse_declarators(is_static)?);
+        Ok(())
+    }
+
+    /// Parse the variables of a `new`/`static` declaration after the keyword
+    ///
+    /// `new a, b[5] = ...;` yields one declaration node per variable.
+    fn parse_declarators(&mut self, is_static: bool) -> CompilerResult<Vec<AstNode>> {
+        let is_const = self.current_token == Token::Const;
+        if is_const {
+            self.advance()?;
+        }
+
+        let mut declarations = Vec::new();
+        loop {
+            let span = self.span;
+            let mut var_type = String::new();
+            let mut name = self.expect_identifier()?;
+            if self.current_token == Token::Colon {
+                self.advance()?;
+                var_type = name;
+                name = self.expect_identifier()?;
+            }
+
+            let array_size = if self.current_token == Token::LeftBracket {
+                self.advance()?;
+                let size = self.parse_expression()?;
+                self.expect(Token::RightBracket)?;
+                if self.current_token == Token::LeftBracket {
+                    return Err(CompilerError::ParserError(
+                        "Multi-dimensional arrays are not supported".to_string(),
+                    ));
+                }
+                Some(Box::new(size))
+            } else {
+                None
+            };
+
+            let initializer = if self.current_token == Token::Assign {
+                self.advance()?;
+                Some(Box::new(self.parse_expression()?))
+            } else {
+                None
+            };
+
+            declarations.push(Self::located(
+                span,
+                AstNode::VariableDeclaration {
+                    name,
+                    var_type,
+                    array_size,
+                    initializer,
+                    is_const,
+                    is_static,
+                },
+            ));
+
+            if self.current_token != Token::Comma {
+                break;
+            }
+            self.advance()?;
+        }
+        self.end_statement()?;
+
+        Ok(declarations)
+    }
+
+    /// Parse a function definition after its name
+    ///
+    /// A header followed by `;` is treated as a forward declaration.
+    fn parse_function(
+        &mut self,
+        name: String,
+        return_type: Option<String>,
+        is_public: bool,
+    ) -> CompilerResult<AstNode> {
+        let parameters = self.parse_parameters()?;
+        let states = if self.current_token == Token::Less {
+            Some(self.parse_function_states()?)
+        } else {
+            None
+        };
+
+        let mut is_forward = false;
+        let body = match self.current_token {
+            Token::Semicolon => {
+                self.advance()?;
+                is_forward = true;
+                Vec::new()
+            }
+            Token::LeftBrace => self.parse_block()?,
+            // Single statement without braces
+            _ => self.parse_statement()?.into_iter().collect(),
+        };
+
+        Ok(AstNode::Function {
+            name,
+            parameters,
+            return_type,
+            body,
+            is_public,
+            is_native: false,
+            is_forward,
+            states,
+        })
+    }
+
+    /// Parse the `<automaton:state, ...>` suffix of a stateful function
+    fn parse_function_states(&mut self) -> CompilerResult<FunctionStates> {
+        self.expect(Token::Less)?;
+        let mut automaton = None;
+        let mut states = Vec::new();
+        while self.current_token != Token::Greater {
+            let name = self.expect_identifier()?;
+            if automaton.is_none() && states.is_empty() && self.current_token == Token::Colon {
+                self.advance()?;
+                automaton = Some(name);
+                continue;
+            }
+            states.push(name);
+            if self.current_token != Token::Comma {
+                break;
+            }
+            self.advance()?;
+        }
+        self.expect(Token::Greater)?;
+        Ok(FunctionStates { automaton, states })
+    }
+
+    /// Parse a function name with an optional return tag, as in `Float:name`
+    fn parse_function_name(&mut self) -> CompilerResult<(String, Option<String>)> {
+        let name = self.expect_identifier()?;
+        if self.current_token != Token::Colon {
+            return Ok((name, None));
+        }
+        self.advance()?;
+        Ok((self.expect_identifier()?, Some(name)))
+    }
+
+    /// Parse a parenthesized parameter list
+    fn parse_parameters(&mut self) -> CompilerResult<Vec<Parameter>> {
+        self.expect(Token::LeftParen)?;
+
+        let mut parameters = Vec::new();
+        while self.current_token != Token::RightParen {
+            if !parameters.is_empty() {
+                self.expect(Token::Comma)?;
+            }
+            let is_const = self.current_token == Token::Const;
+            if is_const {
+                self.advance()?;
+            }
+            let is_reference = self.current_token == Token::BitwiseAnd;
+            if is_reference {
+                self.advance()?;
+            }
+
+            // Variable arguments: `...`
+            if self.current_token == Token::Dot {
+                for _ in 0..3 {
+                    self.expect(Token::Dot)?;
+                }
+                parameters.push(Parameter {
+                    name: "...".to_string(),
+                    param_type: String::new(),
+                    is_reference,
+                    is_const,
+                    dimensions: Vec::new(),
+                    default_value: None,
+                });
+                continue;
+            }
+
+            let mut param_type = String::new();
+            let mut name = self.expect_identifier()?;
+            if self.current_token == Token::Colon {
+                self.advance()?;
+                param_type = name;
+                name = self.expect_identifier()?;
+            }
+            let mut dimensions = Vec::new();
+            while self.current_token == Token::LeftBracket {
+                self.advance()?;
+                let size = if self.current_token == Token::RightBracket {
+                    None
+                } else {
+                    Some(Box::new(self.parse_expression()?))
+                };
+                self.expect(Token::RightBracket)?;
+                dimensions.push(size);
+            }
+
+            let default_value = if self.current_token == Token::Assign {
+                self.advance()?;
+                Some(Box::new(self.parse_expression()?))
+            } else {
+                None
+            };
+
+            parameters.push(Parameter {
+                name,
+                param_type,
+                is_reference,
+                is_const,
+                dimensions,
+                default_value,
+            });
+        }
+        self.expect(Token::RightParen)?;
+
+        Ok(parameters)
+    }
+
+    /// Parse a braced block, recovering from errors in its statements
+    fn parse_block(&mut self) -> CompilerResult<Vec<AstNode>> {
+        self.expect(Token::LeftBrace)?;
+
+        let mut statements = Vec::new();
+        while !matches!(self.current_token, Token::RightBrace | Token::EndOfFile) {
+            let item = if matches!(self.current_token, Token::New | Token::Static) {
+                let is_static = self.current_token == Token::Static;
+                self.advance()
+                    .and_then(|_| self.parse_declarators(is_static))
+                    .map(|declarations| statements.extend(declarations))
+            } else {
+                self.parse_statement().map(|stmt| statements.extend(stmt))
+            };
+            if let Err(e) = item {
+                self.errors.push(self.locate(e));
+                self.synchronize()?;
+            }
+        }
+        self.expect(Token::RightBrace)?;
+
+        Ok(statements)
+    }
+
+    /// Parse the body of a control statement, which is always a node
+    fn parse_body(&mut self) -> CompilerResult<AstNode> {
+        Ok(self
+            .parse_statement()?
+            .unwrap_or_else(|| AstNode::Block(Vec::new())))
+    }
+
+    /// Parse a parenthesized condition
+    fn parse_condition(&mut self) -> CompilerResult<AstNode> {
+        self.expect(Token::LeftParen)?;
+        let condition = self.parse_expression()?;
+        self.expect(Token::RightParen)?;
+        Ok(condition)
+    }
+
+    /// Attach a source position to a node
+    fn located(span: Span, node: AstNode) -> AstNode {
+        AstNode::Located {
+            span,
+            node: Box::new(node),
+        }
+    }
+
+    /// Parse a statement, recording where it starts
+    fn parse_statement(&mut self) -> CompilerResult<Option<AstNode>> {
+        let span = self.span;
+        Ok(self
+            .nested(Self::parse_unlocated_statement)?
+            .map(|node| Self::located(span, node)))
+    }
+
+    /// Parse a statement without its source position
+    fn parse_unlocated_statement(&mut self) -> CompilerResult<Option<AstNode>> {
+        match &self.current_token {
+            Token::LeftBrace => Ok(Some(AstNode::Block(self.parse_block()?))),
+
+            Token::If => {
+                self.advance()?;
+                let condition = self.parse_condition()?;
+                let then_branch = self.parse_body()?;
+                let else_branch = if self.current_token == Token::Else {
+                    self.advance()?;
+                    Some(Box::new(self.parse_body()?))
+                } else {
+                    None
+                };
+                Ok(Some(AstNode::If {
+                    condition: Box::new(condition),
+                    then_branch: Box::new(then_branch),
+                    else_branch,
+                }))
+            }
+
+            Token::While => {
+                self.advance()?;
+                let condition = self.parse_condition()?;
+                let body = self.parse_body()?;
+                Ok(Some(AstNode::While {
+                    condition: Box::new(condition),
+                    body: Box::new(body),
+                }))
+            }
+
+            Token::For => {
+                self.advance()?;
+                self.expect(Token::LeftParen)?;
+                let init = self.parse_for_init()?;
+                let condition = if self.current_token == Token::Semicolon {
+                    None
+                } else {
+                    Some(Box::new(self.parse_expression()?))
+                };
+                self.expect(Token::Semicolon)?;
+                let update = if self.current_token == Token::RightParen {
+                    None
+                } else {
+                    Some(Box::new(self.parse_expression()?))
+                };
+                self.expect(Token::RightParen)?;
+                let body = self.parse_body()?;
+                Ok(Some(AstNode::For {
+                    init,
+                    condition,
+                    update,
+                    body: Box::new(body),
+                }))
+            }
+
+            Token::Do => {
+                self.advance()?;
+                let body = self.parse_body()?;
+                self.expect(Token::While)?;
+                let condition = self.parse_condition()?;
+                self.end_statement()?;
+                Ok(Some(AstNode::DoWhile {
+                    body: Box::new(body),
+                    condition: Box::new(condition),
+                }))
+            }
+
+            Token::Return => {
+                self.advance()?;
+                let value = if self.at_statement_end() {
+                    None
+                } else {
+                    Some(Box::new(self.parse_expression()?))
+                };
+                self.end_statement()?;
+                Ok(Some(AstNode::Return(value)))
+            }
+
+            Token::Assert => {
+                self.advance()?;
+                let condition = self.parse_expression()?;
+                self.end_statement()?;
+                Ok(Some(AstNode::Assert(Box::new(condition))))
+            }
+
+            Token::Break => {
+                self.advance()?;
+                self.end_statement()?;
+                Ok(Some(AstNode::Break))
+            }
+
+            Token::Continue => {
+                self.advance()?;
+                self.end_statement()?;
+                Ok(Some(AstNode::Continue))
+            }
+
+            Token::State => {
+                self.advance()?;
+                let name = self.expect_identifier()?;
+                let (automaton, state) = if self.current_token == Token::Colon {
+                    self.advance()?;
+                    (Some(name), self.expect_identifier()?)
+                } else {
+                    (None, name)
+                };
+                self.end_statement()?;
+                Ok(Some(AstNode::StateChange { automaton, state }))
+            }
+
+            Token::Semicolon => {
+                self.advance()?;
+                Ok(None)
+            }
+
+            // printf without parentheses - just take the next string
+            Token::Identifier(name)
+                if name == "printf" && matches!(self.peek_token, Some(Token::String(_))) =>
+            {
+                self.advance()?;
+                let format_string = self.parse_primary()?;
+                self.end_statement()?;
+                Ok(Some(AstNode::FunctionCall {
+                    name: "printf".to_string(),
+                    arguments: vec![format_string],
+                }))
+            }
+
+            // Gracefully skip constructs we don't implement in MVP
+            Token::Enum | Token::Forward | Token::Const => {
+                self.skip_declaration()?;
+                Ok(None)
+            }
+
+            Token::New | Token::Static => Err(CompilerError::ParserError(
+                "Declarations are only allowed directly inside a block".to_string(),
+            )),
+
+            Token::Switch | Token::Goto => Err(CompilerError::ParserError(format!(
+                "Unsupported statement: {:?}",
+                self.current_token
+            ))),
+
+            _ => {
+                let expr = self.parse_expression()?;
+                self.end_statement()?;
+                Ok(Some(expr))
+            }
+        }
+    }
+
+    /// Parse the initializer clause of a `for` loop, up to and including
+    /// its `;`
+    ///
+    /// The clause is an expression or the declaration of one variable, which
+    /// is scoped to the loop.
+    fn parse_for_init(&mut self) -> CompilerResult<Option<Box<AstNode>>> {
+        match self.current_token {
+            Token::Semicolon => {
+                self.advance()?;
+                Ok(None)
+            }
+            Token::New => {
+                self.advance()?;
+                let mut declarations = self.parse_declarators(false)?;
+                if declarations.len() > 1 {
+                    return Err(CompilerError::ParserError(
+                        "Only one variable may be declared in a for loop initializer".to_string(),
+                    ));
+                }
+                Ok(declarations.pop().map(Box::new))
+            }
+            _ => {
+                let init = self.parse_expression()?;
+                self.expect(Token::Semicolon)?;
+                Ok(Some(Box::new(init)))
+            }
+        }
+    }
+
+    /// Parse an expression
+    fn parse_expression(&mut self) -> CompilerResult<AstNode> {
+        self.nested(Self::parse_assignment)
+    }
+
+    /// Run a recursive rule one level deeper, failing cleanly instead of
+    /// overflowing the stack on pathologically nested input
+    fn nested<T>(&mut self, rule: fn(&mut Self) -> CompilerResult<T>) -> CompilerResult<T> {
+        if self.depth >= self.max_depth {
+            return Err(CompilerError::ParserError(
+                "expression nesting too deep".to_string(),
+            ));
+        }
+        self.depth += 1;
+        let result = rule(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Parse plain and compound assignments, which are right-associative
+    fn parse_assignment(&mut self) -> CompilerResult<AstNode> {
+        let target = self.parse_conditional()?;
+
+        let compound = match self.current_token {
+            Token::Assign => None,
+            Token::PlusAssign => Some(BinaryOperator::AddAssign),
+            Token::MinusAssign => Some(BinaryOperator::SubtractAssign),
+            Token::MultiplyAssign => Some(BinaryOperator::MultiplyAssign),
+            Token::DivideAssign => Some(BinaryOperator::DivideAssign),
+            Token::ModuloAssign => Some(BinaryOperator::ModuloAssign),
+            Token::AndAssign => Some(BinaryOperator::AndAssign),
+            Token::OrAssign => Some(BinaryOperator::OrAssign),
+            Token::XorAssign => Some(BinaryOperator::XorAssign),
+            Token::LeftShiftAssign => Some(BinaryOperator::LeftShiftAssign),
+            Token::RightShiftAssign => Some(BinaryOperator::RightShiftAssign),
+            _ => return Ok(target),
+        };
+        if !matches!(target, AstNode::Identifier(_) | AstNode::ArrayAccess { .. }) {
+            return Err(CompilerError::ParserError(format!(
+                "Invalid assignment target: {:?}",
+                target
+            )));
+        }
+        self.advance()?;
+        let value = self.nested(Self::parse_assignment)?;
+
+        Ok(match compound {
+            Some(operator) => AstNode::BinaryOp {
+                left: Box::new(target),
+                operator,
+                right: Box::new(value),
+            },
+            None => AstNode::Assignment {
+                target: Box::new(target),
+                value: Box::new(value),
+            },
+        })
+    }
+
+    /// Parse the conditional operator `a ? b : c`, which is right-associative
+    fn parse_conditional(&mut self) -> CompilerResult<AstNode> {
+        let condition = self.parse_logical_or()?;
+
+        if self.current_token != Token::Question {
+            return Ok(condition);
+        }
+        self.advance()?;
+        let then_value = self.parse_expression()?;
+        self.expect(Token::Colon)?;
+        let else_value = self.nested(Self::parse_conditional)?;
+
+        Ok(AstNode::Ternary {
+            condition: Box::new(condition),
+            then_value: Box::new(then_value),
+            else_value: Box::new(else_value),
+        })
+    }
+
+    /// Parse logical or expressions
+    fn parse_logical_or(&mut self) -> CompilerResult<AstNode> {
+        let mut left = self.parse_logical_and()?;
+
+        while self.current_token == Token::LogicalOr {
+            self.advance()?;
+            let right = self.parse_logical_and()?;
+            left = AstNode::BinaryOp {
+                left: Box::new(left),
+                operator: BinaryOperator::LogicalOr,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(left)
+    }
+
+    /// Parse logical and expressions, which bind tighter than `||`
+    fn parse_logical_and(&mut self) -> CompilerResult<AstNode> {
+        let mut left = self.parse_bitwise_or()?;
+
+        while self.current_token == Token::LogicalAnd {
+            self.advance()?;
+            let right = self.parse_bitwise_or()?;
+            left = AstNode::BinaryOp {
+                left: Box::new(left),
+                operator: BinaryOperator::LogicalAnd,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(left)
+    }
+
+    /// Parse `|` expressions, which bind tighter than `&&`
+    fn parse_bitwise_or(&mut self) -> CompilerResult<AstNode> {
+        self.parse_left_associative(Self::parse_bitwise_xor, |token| match token {
+            Token::BitwiseOr => Some(BinaryOperator::BitwiseOr),
+            _ => None,
+        })
+    }
+
+    /// Parse `^` expressions
+    fn parse_bitwise_xor(&mut self) -> CompilerResult<AstNode> {
+        self.parse_left_associative(Self::parse_bitwise_and, |token| match token {
+            Token::BitwiseXor => Some(BinaryOperator::BitwiseXor),
+            _ => None,
+        })
+    }
+
+    /// Parse `&` expressions, which bind looser than equality as in C
+    fn parse_bitwise_and(&mut self) -> CompilerResult<AstNode> {
+        self.parse_left_associative(Self::parse_equality, |token| match token {
+            Token::BitwiseAnd => Some(BinaryOperator::BitwiseAnd),
+            _ => None,
+        })
+    }
+
+    /// Parse a chain of left-associative binary operators of one precedence
+    /// level, whose operands are parsed by `operand`
+    fn parse_left_associative(
+        &mut self,
+        operand: fn(&mut Self) -> CompilerResult<AstNode>,
+        operator_for: fn(&Token) -> Option<BinaryOperator>,
+    ) -> CompilerResult<AstNode> {
+        let mut left = operand(self)?;
+
+        while let Some(operator) = operator_for(&self.current_token) {
+            self.advance()?;
+            let right = operand(self)?;
+            left = AstNode::BinaryOp {
+                left: Box::new(left),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(left)
+    }
+
+    /// Parse equality expressions
+    fn parse_equality(&mut self) -> CompilerResult<AstNode> {
+        let mut left = self.parse_comparison()?;
+
+        while matches!(self.current_token, Token::Equal | Token::NotEqual) {
+            let operator = match self.current_token {
+                Token::Equal => BinaryOperator::Equal,
+                Token::NotEqual => BinaryOperator::NotEqual,
+                _ => {
+                    return Err(CompilerError::ParserError(
+                        "Invalid equality operator".into(),
+                    ));
+                }
+            };
+            self.advance()?;
+            let right = self.parse_comparison()?;
+            left = AstNode::BinaryOp {
+                left: Box::new(left),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(left)
+    }
+
+    /// Parse comparison expressions
+    fn parse_comparison(&mut self) -> CompilerResult<AstNode> {
+        let mut left = self.parse_shift()?;
+
+        while matches!(
+            self.current_token,
+            Token::Less | Token::LessEqual | Token::Greater | Token::GreaterEqual
+        ) {
+            let operator = match self.current_token {
+                Token::Less => BinaryOperator::Less,
+                Token::LessEqual => BinaryOperator::LessEqual,
+                Token::Greater => BinaryOperator::Greater,
+                Token::GreaterEqual => BinaryOperator::GreaterEqual,
+                _ => {
+                    return Err(CompilerError::ParserError(
+                        "Invalid comparison operator".into(),
+                    ));
+                }
+            };
+            self.advance()?;
+            let right = self.parse_shift()?;
+            left = AstNode::BinaryOp {
+                left: Box::new(left),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(left)
+    }
+
+    /// Parse shift expressions, which bind looser than `+` and `-`
+    fn parse_shift(&mut self) -> CompilerResult<AstNode> {
+        self.parse_left_associative(Self::parse_term, |token| match token {
+            Token::LeftShift => Some(BinaryOperator::LeftShift),
+            Token::RightShift => Some(BinaryOperator::RightShift),
+            _ => None,
+        })
+    }
+
+    /// Parse term expressions
+    fn parse_term(&mut self) -> CompilerResult<AstNode> {
+        let mut left = self.parse_factor()?;
+
+        while matches!(self.current_token, Token::Plus | Token::Minus) {
+            let operator = match self.current_token {
+                Token::Plus => BinaryOperator::Add,
+                Token::Minus => BinaryOperator::Subtract,
+                _ => return Err(CompilerError::ParserError("Invalid term operator".into())),
+            };
+            self.advance()?;
+            let right = self.parse_factor()?;
+            left = AstNode::BinaryOp {
+                left: Box::new(left),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(left)
+    }
+
+    /// Parse factor expressions
+    fn parse_factor(&mut self) -> CompilerResult<AstNode> {
+        let mut left = self.parse_unary()?;
+
+        while matches!(
+            self.current_token,
+            Token::Multiply | Token::Divide | Token::Modulo
+        ) {
+            let operator = match self.current_token {
+                Token::Multiply => BinaryOperator::Multiply,
+                Token::Divide => BinaryOperator::Divide,
+                Token::Modulo => BinaryOperator::Modulo,
+                _ => return Err(CompilerError::ParserError("Invalid factor operator".into())),
+            };
+            self.advance()?;
+            let right = self.parse_unary()?;
+            left = AstNode::BinaryOp {
+                left: Box::new(left),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(left)
+    }
+
+    /// Parse unary expressions
+    fn parse_unary(&mut self) -> CompilerResult<AstNode> {
+        match self.current_token {
+            Token::Plus => {
+                self.advance()?;
+                let operand = self.nested(Self::parse_unary)?;
+                // A signed literal is a literal, not an operation
+                Ok(match operand {
+                    AstNode::Integer(_) | AstNode::Float(_) => operand,
+                    _ => AstNode::UnaryOp {
+                        operator: UnaryOperator::Plus,
+                        operand: Box::new(operand),
+                    },
+                })
+            }
+            Token::Minus => {
+                self.advance()?;
+                let operand = self.nested(Self::parse_unary)?;
+                Ok(match operand {
+                    AstNode::Integer(value) => AstNode::Integer(value.wrapping_neg()),
+                    AstNode::Float(value) => AstNode::Float(-value),
+                    _ => AstNode::UnaryOp {
+                        operator: UnaryOperator::Minus,
+                        operand: Box::new(operand),
+                    },
+                })
+            }
+            Token::LogicalNot => {
+                self.advance()?;
+                let operand = self.nested(Self::parse_unary)?;
+                Ok(AstNode::UnaryOp {
+                    operator: UnaryOperator::LogicalNot,
+                    operand: Box::new(operand),
+                })
+            }
+            Token::BitwiseNot => {
+                self.advance()?;
+                let operand = self.nested(Self::parse_unary)?;
+                Ok(AstNode::UnaryOp {
+                    operator: UnaryOperator::BitwiseNot,
+                    operand: Box::new(operand),
+                })
+            }
+            Token::Sizeof | Token::Tagof => {
+                let operator = if self.current_token == Token::Sizeof {
+                    UnaryOperator::Sizeof
+                } else {
+                    UnaryOperator::Tagof
+                };
+                self.advance()?;
+                let parenthesized = self.current_token == Token::LeftParen;
+                if parenthesized {
+                    self.advance()?;
+                }
+                let Token::Identifier(name) = self.current_token.clone() else {
+                    return Err(CompilerError::ParserError(format!(
+                        "Expected a variable name after {:?}, found {:?}",
+                        operator, self.current_token
+                    )));
+                };
+                self.advance()?;
+                if parenthesized {
+                    self.expect(Token::RightParen)?;
+                }
+                Ok(AstNode::UnaryOp {
+                    operator,
+                    operand: Box::new(AstNode::Identifier(name)),
+                })
+            }
+            Token::Increment | Token::Decrement => {
+                let operator = if self.current_token == Token::Increment {
+                    UnaryOperator::Increment
+                } else {
+                    UnaryOperator::Decrement
+                };
+                self.advance()?;
+                let operand = self.nested(Self::parse_unary)?;
+                Self::expect_lvalue(&operand, &operator)?;
+                Ok(AstNode::UnaryOp {
+                    operator,
+                    operand: Box::new(operand),
+                })
+            }
+            _ => {
+                let operand = self.parse_primary()?;
+                let operator = match self.current_token {
+                    Token::Increment => UnaryOperator::PostIncrement,
+                    Token::Decrement => UnaryOperator::PostDecrement,
+                    _ => return Ok(operand),
+                };
+                self.advance()?;
+                Self::expect_lvalue(&operand, &operator)?;
+                Ok(AstNode::UnaryOp {
+                    operator,
+                    operand: Box::new(operand),
+                })
+            }
+        }
+    }
+
+    /// Check that the operand of `++` or `--` is a variable or array element
+    fn expect_lvalue(operand: &AstNode, operator: &UnaryOperator) -> CompilerResult<()> {
+        match operand {
+            AstNode::Identifier(_) | AstNode::ArrayAccess { .. } => Ok(()),
+            _ => Err(CompilerError::ParserError(format!(
+                "Operand of {:?} must be a variable or array element",
+                operator
+            ))),
+        }
+    }
+
+    /// Parse primary expressions
+    fn parse_primary(&mut self) -> CompilerResult<AstNode> {
+        match &self.current_token {
+            Token::Number(n) => {
+                let value = *n;
+                self.advance()?;
+                Ok(AstNode::Integer(value))
+            }
+            Token::Float(f) => {
+                let value = *f;
+                self.advance()?;
+                Ok(AstNode::Float(value))
+            }
+            Token::String(s) => {
+                let value = s.clone();
+                self.advance()?;
+                Ok(AstNode::String(value))
+            }
+            Token::PackedString(s) => {
+                let value = s.clone();
+                self.advance()?;
+                Ok(AstNode::PackedString(value))
+            }
+            Token::Character(c) => {
+                let value = *c;
+                self.advance()?;
+                Ok(AstNode::Character(value))
+            }
+            // Predefined `bool:` constants
+            Token::Identifier(name) if name == "true" || name == "false" => {
+                let value = name == "true";
+                self.advance()?;
+                Ok(AstNode::Boolean(value))
+            }
+            Token::Identifier(name) => {
+                let name = name.clone();
+                self.advance()?;
+                if self.current_token == Token::LeftParen {
+                    let arguments = self.parse_arguments()?;
+                    return Ok(AstNode::FunctionCall { name, arguments });
+                }
+
+                let mut expr = AstNode::Identifier(name);
+                while self.current_token == Token::LeftBracket {
+                    self.advance()?;
+                    let index = self.parse_expression()?;
+                    self.expect(Token::RightBracket)?;
+                    expr = AstNode::ArrayAccess {
+                        array: Box::new(expr),
+                        index: Box::new(index),
+                    };
+                }
+                Ok(expr)
+            }
+            Token::LeftParen => {
+                self.advance()?;
+                let expr = self.parse_expression()?;
+                self.expect(Token::RightParen)?;
+                Ok(expr)
+            }
+            _ => Err(CompilerError::ParserError(format!(
+                "Unexpected token in expression: {:?}",
+                self.current_token
+            ))),
+        }
+    }
+
+    /// Parse a parenthesized, comma-separated argument list
+    fn parse_arguments(&mut self) -> CompilerResult<Vec<AstNode>> {
+        self.expect(Token::LeftParen)?;
+
+        let mut arguments = Vec::new();
+        while self.current_token != Token::RightParen {
+            if !arguments.is_empty() {
+                self.expect(Token::Comma)?;
+            }
+            arguments.push(self.parse_expression()?);
+        }
+        self.expect(Token::RightParen)?;
+
+        Ok(arguments)
+    }
+}
@@ -0,0 +1,157 @@
+//! Compilation of programs spread over several source files
+//!
+//! `#include` and `#tryinclude` directives are expanded in place, so every
+//! file ends up in one translation unit with a single symbol table. As with
+//! pawncc, each file is included at most once.
+
+use crate::config::Config;
+use crate::error::*;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Extensions tried, in order, when an include names a file without one
+const INCLUDE_EXTENSIONS: [&str; 3] = ["inc", "p", "pwn"];
+
+/// Compile the program whose main file is `entry`
+///
+/// Quoted includes (`#include "file"`) are looked up next to the including
+/// file first; angle-bracket includes (`#include <file>`) and quoted ones not
+/// found there are looked up in `include_paths`, in order. Located
+/// diagnostics name the file they were found in and its own line numbers.
+pub fn compile_project(
+    entry: &Path,
+    include_paths: &[PathBuf],
+    cfg: &Config,
+) -> CompilerResult<Vec<u8>> {
+    let expander = IncludeExpander::run(entry, include_paths)?;
+    crate::compile_with_config(&expander.output, cfg).map_err(|e| expander.locate(e))
+}
+
+/// Read `entry` and expand its include directives recursively
+pub fn expand_includes(entry: &Path, include_paths: &[PathBuf]) -> CompilerResult<String> {
+    Ok(IncludeExpander::run(entry, include_paths)?.output)
+}
+
+struct IncludeExpander<'a> {
+    include_paths: &'a [PathBuf],
+    /// Canonical paths of the files expanded so far
+    included: HashSet<PathBuf>,
+    output: String,
+    /// File and 1-based line each line of `output` came from
+    origins: Vec<(PathBuf, usize)>,
+}
+
+impl<'a> IncludeExpander<'a> {
+    fn run(entry: &Path, include_paths: &'a [PathBuf]) -> CompilerResult<Self> {
+        let mut expander = IncludeExpander {
+            include_paths,
+            included: HashSet::new(),
+            output: String::new(),
+            origins: Vec::new(),
+        };
+        expander.expand(entry)?;
+        Ok(expander)
+    }
+
+    /// Point a located error at the file and line it came from, instead of
+    /// the line of the expanded source
+    fn locate(&self, error: CompilerError) -> CompilerError {
+        let Some((line, column, len)) = error.span() else {
+            return error;
+        };
+        let Some((path, line)) = self.origins.get(line.wrapping_sub(1)) else {
+            return error;
+        };
+        CompilerError::InFile {
+            error: Box::new(error.kind().clone().at(*line, column, len)),
+            path: path.clone(),
+        }
+    }
+
+    fn expand(&mut self, path: &Path) -> CompilerResult<()> {
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        if !self.included.insert(canonical) {
+            return Ok(());
+        }
+        let source = fs::read_to_string(path).map_err(|e| {
+            CompilerError::FileError(format!("Cannot read '{}': {}", path.display(), e))
+        })?;
+        let directory = path.parent().unwrap_or(Path::new("."));
+
+        for (index, line) in source.lines().enumerate() {
+            let include = parse_include(line).map_err(|error| CompilerError::InFile {
+                error: Box::new(error.at(index + 1, 1, line.len())),
+                path: path.to_path_buf(),
+            })?;
+            let Some((name, quoted, required)) = include else {
+                self.output.push_str(line);
+                self.output.push('\n');
+                self.origins.push((path.to_path_buf(), index + 1));
+                continue;
+            };
+            match self.resolve(name, quoted, directory) {
+                Some(file) => self.expand(&file)?,
+                None if required => {
+                    return Err(CompilerError::FileError(format!(
+                        "Cannot find include file '{}'",
+                        name
+                    )));
+                }
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Find the file an include directive refers to
+    fn resolve(&self, name: &str, quoted: bool, directory: &Path) -> Option<PathBuf> {
+        let local = quoted.then_some(directory);
+        local
+            .into_iter()
+            .chain(self.include_paths.iter().map(PathBuf::as_path))
+            .find_map(|dir| with_extensions(&dir.join(name)))
+    }
+}
+
+/// The file itself, or the first existing file with a default extension added
+fn with_extensions(path: &Path) -> Option<PathBuf> {
+    if path.is_file() {
+        return Some(path.to_path_buf());
+    }
+    INCLUDE_EXTENSIONS
+        .iter()
+        .map(|extension| path.with_extension(extension))
+        .find(|candidate| candidate.is_file())
+}
+
+/// The file named by an include directive, whether it was quoted, and whether
+/// it must exist (`#include`, not `#tryinclude`), or `None` for lines that
+/// are not include directives
+fn parse_include(line: &str) -> CompilerResult<Option<(&str, bool, bool)>> {
+    let Some(directive) = line.trim().strip_prefix('#').map(str::trim_start) else {
+        return Ok(None);
+    };
+    let (rest, required) = if let Some(rest) = directive.strip_prefix("include") {
+        (rest, true)
+    } else if let Some(rest) = directive.strip_prefix("tryinclude") {
+        (rest, false)
+    } else {
+        return Ok(None);
+    };
+    let rest = rest.trim();
+    let (name, quoted) = if let Some(name) = rest.strip_prefix('"') {
+        (name.split_once('"').map(|(name, _)| name), true)
+    } else if let Some(name) = rest.strip_prefix('<') {
+        (name.split_once('>').map(|(name, _)| name), false)
+    } else {
+        (None, false)
+    };
+    match name {
+        Some(name) => Ok(Some((name, quoted, required))),
+        None => Err(CompilerError::SyntaxError(format!(
+            "Expected a file name in quotes or angle brackets after '#{}'",
+            if required { "include" } else { "tryinclude" }
+        ))),
+    }
+}
@@ -1,502 +1,904 @@
-//! Symbol table for Pawn compiler
-
-use crate::ast::*;
-use crate::error::*;
-use std::collections::HashMap;
-
-/// Symbol table entry
-#[derive(Debug, Clone)]
-pub struct Symbol {
-    pub name: String,
-    pub symbol_type: SymbolType,
-    pub scope_level: usize,
-    pub is_defined: bool,
-}
-
-/// Types of symbols
-#[derive(Debug, Clone, PartialEq)]
-pub enum SymbolType {
-    Function {
-        parameters: Vec<Parameter>,
-        return_type: Option<String>,
-        is_public: bool,
-        is_native: bool,
-        is_forward: bool,
-    },
-    Variable {
-        var_type: String,
-        is_const: bool,
-        is_static: bool,
-        offset: Option<usize>,
-    },
-    Type {
-        definition: TypeDefinition,
-    },
-    Enum {
-        variants: Vec<EnumVariant>,
-    },
-}
-
-/// Symbol table for managing identifiers
-pub struct SymbolTable {
-    symbols: HashMap<String, Symbol>,
-    scope_stack: Vec<Vec<String>>,
-    current_scope: usize,
-}
-
-impl SymbolTable {
-    /// Create a new symbol table
-    pub fn new() -> Self {
-        Self {
-            symbols: HashMap::new(),
-            scope_stack: vec![Vec::new()],
-            current_scope: 0,
-        }
-    }
-
-    /// Enter a new scope
-    pub fn enter_scope(&mut self) {
-        self.current_scope += 1;
-        self.scope_stack.push(Vec::new());
-    }
-
-    /// Exit current scope
-    pub fn exit_scope(&mut self) {
-        if self.current_scope > 0 {
-            // Remove symbols from current scope
-            if let Some(scope_symbols) = self.scope_stack.pop() {
-                for symbol_name in scope_symbols {
-                    self.symbols.remove(&symbol_name);
-                }
-            }
-            self.current_scope -= 1;
-        }
-    }
-
-    /// Add a symbol to the table
-    pub fn add_symbol(&mut self, symbol: Symbol) -> CompilerResult<()> {
-        let name = symbol.name.clone();
-
-        // Check if symbol already exists in current scope
-        if self.symbols.contains_key(&name) {
-            return Err(CompilerError::SemanticError(format!(
-                "Symbol '{}' already declared in current scope",
-                name
-            )));
-        }
-
-        // Add to current scope
-        if let Some(current_scope) = self.scope_stack.last_mut() {
-            current_scope.push(name.clone());
-        }
-
-        self.symbols.insert(name, symbol);
-        Ok(())
-    }
-
-    /// Look up a symbol
-    pub fn lookup(&self, name: &str) -> Option<&Symbol> {
-        self.symbols.get(name)
-    }
-
-    /// Look up a symbol in current scope only
-    pub fn lookup_current_scope(&self, name: &str) -> Option<&Symbol> {
-        if let Some(scope_symbols) = self.scope_stack.last() {
-            if scope_symbols.contains(&name.to_string()) {
-                return self.symbols.get(name);
-            }
-        }
-        None
-    }
-
-    /// Check if symbol exists
-    pub fn exists(&self, name: &str) -> bool {
-        self.symbols.contains_key(name)
-    }
-
-    /// Get all symbols in current scope
-    pub fn get_current_scope_symbols(&self) -> Vec<&Symbol> {
-        let mut result = Vec::new();
-        if let Some(scope_symbols) = self.scope_stack.last() {
-            for symbol_name in scope_symbols {
-                if let Some(symbol) = self.symbols.get(symbol_name) {
-                    result.push(symbol);
-                }
-            }
-        }
-        result
-    }
-
-    /// Get current scope level
-    pub fn get_scope_level(&self) -> usize {
-        self.current_scope
-    }
-
-    /// Clear all symbols
-    pub fn clear(&mut self) {
-        self.symbols.clear();
-        self.scope_stack.clear();
-        self.scope_stack.push(Vec::new());
-        self.current_scope = 0;
-    }
-}
-
-impl Default for SymbolTable {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-/// Symbol table visitor for AST analysis
-pub struct SymbolTableVisitor {
-    symbol_table: SymbolTable,
-    errors: Vec<CompilerError>,
-}
-
-impl SymbolTableVisitor {
-    /// Create a new symbol table visitor
-    pub fn new() -> Self {
-        Self {
-            symbol_table: SymbolTable::new(),
-            errors: Vec::new(),
-        }
-    }
-
-    /// Analyze AST and build symbol table
-    pub fn analyze(&mut self, ast: &AstNode) -> CompilerResult<()> {
-        self.errors.clear();
-        self.symbol_table.clear();
-
-        // Add built-in functions
-        let printf_symbol = Symbol {
-            name: "printf".to_string(),
-            symbol_type: SymbolType::Function {
-                parameters: vec![Parameter {
-                    name: "format".to_string(),
-                    param_type: "string".to_string(),
-                    is_reference: false,
-                    default_value: None,
-                }],
-                return_type: Some("int".to_string()),
-                is_public: true,
-                is_native: true,
-                is_forward: false,
-            },
-            scope_level: 0,
-            is_defined: true,
-        };
-        self.symbol_table.add_symbol(printf_symbol).ok();
-
-        match ast.accept::<()>(self) {
-            Ok(_) => {
-                if self.errors.is_empty() {
-                    Ok(())
-                } else {
-                    Err(self.errors[0].clone())
-                }
-            }
-            Err(e) => Err(e),
-        }
-    }
-
-    /// Get the symbol table
-    pub fn get_symbol_table(&self) -> &SymbolTable {
-        &self.symbol_table
-    }
-
-    /// Get errors
-    pub fn get_errors(&self) -> &[CompilerError] {
-        &self.errors
-    }
-}
-
-impl AstVisitor<()> for SymbolTableVisitor {
-    fn visit_program(&mut self, nodes: &[AstNode]) -> CompilerResult<()> {
-        for node in nodes {
-            node.accept(self)?;
-        }
-        Ok(())
-    }
-
-    fn visit_function(
-        &mut self,
-        name: &str,
-        parameters: &[Parameter],
-        return_type: &Option<String>,
-        body: &[AstNode],
-        is_public: bool,
-        is_native: bool,
-        is_forward: bool,
-    ) -> CompilerResult<()> {
-        let symbol = Symbol {
-            name: name.to_string(),
-            symbol_type: SymbolType::Function {
-                parameters: parameters.to_vec(),
-                return_type: return_type.clone(),
-                is_public,
-                is_native,
-                is_forward,
-            },
-            scope_level: self.symbol_table.get_scope_level(),
-            is_defined: true,
-        };
-
-        if let Err(e) = self.symbol_table.add_symbol(symbol) {
-            self.errors.push(e);
-        }
-
-        // Enter function scope
-        self.symbol_table.enter_scope();
-
-        // Add parameters to symbol table
-        for param in parameters {
-            let param_symbol = Symbol {
-                name: param.name.clone(),
-                symbol_type: SymbolType::Variable {
-                    var_type: param.param_type.clone(),
-                    is_const: false,
-                    is_static: false,
-                    offset: None,
-                },
-                scope_level: self.symbol_table.get_scope_level(),
-                is_defined: true,
-            };
-
-            if let Err(e) = self.symbol_table.add_symbol(param_symbol) {
-                self.errors.push(e);
-            }
-        }
-
-        // Analyze function body
-        for stmt in body {
-            stmt.accept(self)?;
-        }
-
-        // Exit function scope
-        self.symbol_table.exit_scope();
-
-        Ok(())
-    }
-
-    fn visit_variable_declaration(
-        &mut self,
-        name: &str,
-        var_type: &str,
-        initializer: &Option<Box<AstNode>>,
-        is_const: bool,
-        is_static: bool,
-    ) -> CompilerResult<()> {
-        let symbol = Symbol {
-            name: name.to_string(),
-            symbol_type: SymbolType::Variable {
-                var_type: var_type.to_string(),
-                is_const,
-                is_static,
-                offset: None,
-            },
-            scope_level: self.symbol_table.get_scope_level(),
-            is_defined: true,
-        };
-
-        if let Err(e) = self.symbol_table.add_symbol(symbol) {
-            self.errors.push(e);
-        }
-
-        // Analyze initializer if present
-        if let Some(init) = initializer {
-            init.accept(self)?;
-        }
-
-        Ok(())
-    }
-
-    fn visit_block(&mut self, statements: &[AstNode]) -> CompilerResult<()> {
-        self.symbol_table.enter_scope();
-
-        for stmt in statements {
-            stmt.accept(self)?;
-        }
-
-        self.symbol_table.exit_scope();
-        Ok(())
-    }
-
-    fn visit_identifier(&mut self, name: &str) -> CompilerResult<()> {
-        if self.symbol_table.lookup(name).is_none() {
-            self.errors.push(CompilerError::SemanticError(format!(
-                "Undefined identifier: {}",
-                name
-            )));
-        }
-        Ok(())
-    }
-
-    // Default implementations for other visitor methods
-    fn visit_if(
-        &mut self,
-        condition: &AstNode,
-        then_branch: &AstNode,
-        else_branch: &Option<Box<AstNode>>,
-    ) -> CompilerResult<()> {
-        condition.accept(self)?;
-        then_branch.accept(self)?;
-        if let Some(else_stmt) = else_branch {
-            else_stmt.accept(self)?;
-        }
-        Ok(())
-    }
-
-    fn visit_while(&mut self, condition: &AstNode, body: &AstNode) -> CompilerResult<()> {
-        condition.accept(self)?;
-        body.accept(self)?;
-        Ok(())
-    }
-
-    fn visit_for(
-        &mut self,
-        init: &Option<Box<AstNode>>,
-        condition: &Option<Box<AstNode>>,
-        update: &Option<Box<AstNode>>,
-        body: &AstNode,
-    ) -> CompilerResult<()> {
-        if let Some(init_stmt) = init {
-            init_stmt.accept(self)?;
-        }
-        if let Some(cond) = condition {
-            cond.accept(self)?;
-        }
-        body.accept(self)?;
-        if let Some(update_stmt) = update {
-            update_stmt.accept(self)?;
-        }
-        Ok(())
-    }
-
-    fn visit_return(&mut self, value: &Option<Box<AstNode>>) -> CompilerResult<()> {
-        if let Some(val) = value {
-            val.accept(self)?;
-        }
-        Ok(())
-    }
-
-    fn visit_break(&mut self) -> CompilerResult<()> {
-        Ok(())
-    }
-
-    fn visit_continue(&mut self) -> CompilerResult<()> {
-        Ok(())
-    }
-
-    fn visit_binary_op(
-        &mut self,
-        left: &AstNode,
-        _operator: &BinaryOperator,
-        right: &AstNode,
-    ) -> CompilerResult<()> {
-        left.accept(self)?;
-        right.accept(self)?;
-        Ok(())
-    }
-
-    fn visit_unary_op(
-        &mut self,
-        _operator: &UnaryOperator,
-        operand: &AstNode,
-    ) -> CompilerResult<()> {
-        operand.accept(self)?;
-        Ok(())
-    }
-
-    fn visit_assignment(&mut self, target: &AstNode, value: &AstNode) -> CompilerResult<()> {
-        target.accept(self)?;
-        value.accept(self)?;
-        Ok(())
-    }
-
-    fn visit_function_call(&mut self, name: &str, arguments: &[AstNode]) -> CompilerResult<()> {
-        if self.symbol_table.lookup(name).is_none() {
-            self.errors.push(CompilerError::SemanticError(format!(
-                "Undefined function: {}",
-                name
-            )));
-        }
-
-        for arg in arguments {
-            arg.accept(self)?;
-        }
-        Ok(())
-    }
-
-    fn visit_array_access(&mut self, array: &AstNode, index: &AstNode) -> CompilerResult<()> {
-        array.accept(self)?;
-        index.accept(self)?;
-        Ok(())
-    }
-
-    fn visit_member_access(&mut self, object: &AstNode, _member: &str) -> CompilerResult<()> {
-        object.accept(self)?;
-        Ok(())
-    }
-
-    fn visit_integer(&mut self, _value: i32) -> CompilerResult<()> {
-        Ok(())
-    }
-
-    fn visit_float(&mut self, _value: f32) -> CompilerResult<()> {
-        Ok(())
-    }
-
-    fn visit_string(&mut self, _value: &str) -> CompilerResult<()> {
-        Ok(())
-    }
-
-    fn visit_character(&mut self, _value: char) -> CompilerResult<()> {
-        Ok(())
-    }
-
-    fn visit_boolean(&mut self, _value: bool) -> CompilerResult<()> {
-        Ok(())
-    }
-
-    fn visit_type_definition(
-        &mut self,
-        name: &str,
-        definition: &TypeDefinition,
-    ) -> CompilerResult<()> {
-        let symbol = Symbol {
-            name: name.to_string(),
-            symbol_type: SymbolType::Type {
-                definition: definition.clone(),
-            },
-            scope_level: self.symbol_table.get_scope_level(),
-            is_defined: true,
-        };
-
-        if let Err(e) = self.symbol_table.add_symbol(symbol) {
-            self.errors.push(e);
-        }
-
-        Ok(())
-    }
-
-    fn visit_enum_definition(
-        &mut self,
-        name: &str,
-        variants: &[EnumVariant],
-    ) -> CompilerResult<()> {
-        let symbol = Symbol {
-            name: name.to_string(),
-            symbol_type: SymbolType::Enum {
-                variants: variants.to_vec(),
-            },
-            scope_level: self.symbol_table.get_scope_level(),
-            is_defined: true,
-        };
-
-        if let Err(e) = self.symbol_table.add_symbol(symbol) {
-            self.errors.push(e);
-        }
-
-        Ok(())
-    }
-}
+//! Symbol table for Pawn compiler
+
+use crate::ast::*;
+use crate::error::*;
+use std::collections::HashMap;
+
+/// Symbol table entry
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub symbol_type: SymbolType,
+    pub scope_level: usize,
+    pub is_defined: bool,
+    /// Where the symbol was declared, if known
+    pub span: Option<Span>,
+    /// Number of times the symbol has been referenced
+    pub use_count: usize,
+}
+
+impl Symbol {
+    /// Tag of a variable, or the return tag of a function, if it has one
+    pub fn tag(&self) -> Option<&str> {
+        let tag = match &self.symbol_type {
+            SymbolType::Variable { var_type, .. } => var_type.as_str(),
+            SymbolType::Function { return_type, .. } => return_type.as_deref()?,
+            _ => return None,
+        };
+        (!tag.is_empty()).then_some(tag)
+    }
+}
+
+/// A declared variable or parameter that is never referenced
+#[derive(Debug, Clone)]
+pub struct UnusedSymbol {
+    pub name: String,
+    pub span: Span,
+    pub is_parameter: bool,
+}
+
+/// Types of symbols
+#[derive(Debug, Clone, PartialEq)]
+pub enum SymbolType {
+    Function {
+        parameters: Vec<Parameter>,
+        return_type: Option<String>,
+        is_public: bool,
+        is_native: bool,
+        is_forward: bool,
+    },
+    Variable {
+        var_type: String,
+        is_const: bool,
+        is_static: bool,
+        offset: Option<usize>,
+    },
+    Type {
+        definition: TypeDefinition,
+    },
+    Enum {
+        variants: Vec<EnumVariant>,
+    },
+}
+
+/// Symbol table for managing identifiers
+pub struct SymbolTable {
+    symbols: HashMap<String, Symbol>,
+    scope_stack: Vec<Vec<String>>,
+    current_scope: usize,
+}
+
+impl SymbolTable {
+    /// Create a new symbol table
+    pub fn new() -> Self {
+        Self {
+            symbols: HashMap::new(),
+            scope_stack: vec![Vec::new()],
+            current_scope: 0,
+        }
+    }
+
+    /// Enter a new scope
+    pub fn enter_scope(&mut self) {
+        self.current_scope += 1;
+        self.scope_stack.push(Vec::new());
+    }
+
+    /// Exit current scope, returning the symbols it declared
+    pub fn exit_scope(&mut self) -> Vec<Symbol> {
+        let mut removed = Vec::new();
+        if self.current_scope > 0 {
+            // Remove symbols from current scope
+            if let Some(scope_symbols) = self.scope_stack.pop() {
+                for symbol_name in scope_symbols {
+                    removed.extend(self.symbols.remove(&symbol_name));
+                }
+            }
+            self.current_scope -= 1;
+        }
+        removed
+    }
+
+    /// Add a symbol to the table
+    pub fn add_symbol(&mut self, symbol: Symbol) -> CompilerResult<()> {
+        let name = symbol.name.clone();
+
+        // Check if symbol already exists in current scope
+        if let Some(existing) = self.symbols.get(&name) {
+            return Err(CompilerError::SemanticError(match existing.span {
+                Some(span) => format!(
+                    "Symbol '{}' already declared (first at line {})",
+                    name, span.line
+                ),
+                None => format!("Symbol '{}' already declared in current scope", name),
+            }));
+        }
+
+        // Add to current scope
+        if let Some(current_scope) = self.scope_stack.last_mut() {
+            current_scope.push(name.clone());
+        }
+
+        self.symbols.insert(name, symbol);
+        Ok(())
+    }
+
+    /// Look up a symbol
+    pub fn lookup(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.get(name)
+    }
+
+    /// Record a reference to a symbol, returning whether it exists
+    pub fn mark_used(&mut self, name: &str) -> bool {
+        match self.symbols.get_mut(name) {
+            Some(symbol) => {
+                symbol.use_count += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Look up a symbol in current scope only
+    pub fn lookup_current_scope(&self, name: &str) -> Option<&Symbol> {
+        if let Some(scope_symbols) = self.scope_stack.last()
+            && scope_symbols.contains(&name.to_string())
+        {
+            return self.symbols.get(name);
+        }
+        None
+    }
+
+    /// Check if symbol exists
+    pub fn exists(&self, name: &str) -> bool {
+        self.symbols.contains_key(name)
+    }
+
+    /// Get all symbols in current scope
+    pub fn get_current_scope_symbols(&self) -> Vec<&Symbol> {
+        let mut result = Vec::new();
+        if let Some(scope_symbols) = self.scope_stack.last() {
+            for symbol_name in scope_symbols {
+                if let Some(symbol) = self.symbols.get(symbol_name) {
+                    result.push(symbol);
+                }
+            }
+        }
+        result
+    }
+
+    /// Get all symbols declared at the top level
+    pub fn get_global_symbols(&self) -> Vec<&Symbol> {
+        self.scope_stack[0]
+            .iter()
+            .filter_map(|name| self.symbols.get(name))
+            .collect()
+    }
+
+    /// Get current scope level
+    pub fn get_scope_level(&self) -> usize {
+        self.current_scope
+    }
+
+    /// Clear all symbols
+    pub fn clear(&mut self) {
+        self.symbols.clear();
+        self.scope_stack.clear();
+        self.scope_stack.push(Vec::new());
+        self.current_scope = 0;
+    }
+}
+
+impl Default for SymbolTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Symbol table visitor for AST analysis
+pub struct SymbolTableVisitor {
+    symbol_table: SymbolTable,
+    errors: Vec<CompilerError>,
+    /// Position of the statement being analyzed
+    current_span: Span,
+    unused: Vec<UnusedSymbol>,
+    /// Every declaration as (name, line)
+    definitions: Vec<(String, usize)>,
+    /// Every use of a name as (name, line)
+    references: Vec<(String, usize)>,
+    /// Host natives declared up front, besides `printf`
+    globals: Vec<String>,
+    /// Whether the program is a library, which needs no entry point
+    library: bool,
+}
+
+impl SymbolTableVisitor {
+    /// Create a new symbol table visitor
+    pub fn new() -> Self {
+        Self {
+            symbol_table: SymbolTable::new(),
+            errors: Vec::new(),
+            current_span: Span::default(),
+            unused: Vec::new(),
+            definitions: Vec::new(),
+            references: Vec::new(),
+            globals: Vec::new(),
+            library: false,
+        }
+    }
+
+    /// Treat each name as a native provided by the host, so calls to it are
+    /// not reported as undefined
+    pub fn set_globals(&mut self, globals: &[String]) {
+        self.globals = globals.to_vec();
+    }
+
+    /// Treat the program as a library, such as an include file, so a missing
+    /// `main` is not reported
+    pub fn set_library(&mut self, library: bool) {
+        self.library = library;
+    }
+
+    /// Analyze AST and build symbol table
+    pub fn analyze(&mut self, ast: &AstNode) -> CompilerResult<()> {
+        self.errors.clear();
+        self.unused.clear();
+        self.definitions.clear();
+        self.references.clear();
+        self.current_span = Span::default();
+        self.symbol_table.clear();
+
+        // Add built-in functions
+        let printf_symbol = Symbol {
+            name: "printf".to_string(),
+            symbol_type: SymbolType::Function {
+                parameters: vec![Parameter {
+                    name: "format".to_string(),
+                    param_type: "string".to_string(),
+                    is_reference: false,
+                    is_const: true,
+                    dimensions: vec![None],
+                    default_value: None,
+                }],
+                return_type: Some("int".to_string()),
+                is_public: true,
+                is_native: true,
+                is_forward: false,
+            },
+            scope_level: 0,
+            is_defined: true,
+            span: None,
+            use_count: 0,
+        };
+        self.symbol_table.add_symbol(printf_symbol)?;
+
+        for name in &self.globals {
+            let native = Symbol {
+                name: name.clone(),
+                symbol_type: SymbolType::Function {
+                    parameters: Vec::new(),
+                    return_type: None,
+                    is_public: false,
+                    is_native: true,
+                    is_forward: false,
+                },
+                scope_level: 0,
+                is_defined: true,
+                span: None,
+                use_count: 0,
+            };
+            // Names already declared, such as `printf`, keep their signature
+            if self.symbol_table.lookup(name).is_none() {
+                self.symbol_table.add_symbol(native)?;
+            }
+        }
+
+        let result = ast.accept::<()>(self);
+
+        // Globals stay in scope until the end of the program
+        let globals: Vec<Symbol> = self
+            .symbol_table
+            .get_current_scope_symbols()
+            .into_iter()
+            .cloned()
+            .collect();
+        self.record_unused(globals, &[]);
+        if result.is_ok() && !self.library {
+            self.check_entry_point();
+        }
+
+        match result {
+            Ok(_) => {
+                if self.errors.is_empty() {
+                    Ok(())
+                } else {
+                    Err(self.errors[0].clone())
+                }
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Get the symbol table
+    pub fn get_symbol_table(&self) -> &SymbolTable {
+        &self.symbol_table
+    }
+
+    /// Get errors
+    pub fn get_errors(&self) -> &[CompilerError] {
+        &self.errors
+    }
+
+    /// Get variables and parameters that were never referenced
+    pub fn get_unused(&self) -> &[UnusedSymbol] {
+        &self.unused
+    }
+
+    /// Top-level symbols, including built-in natives such as `printf`
+    pub fn globals(&self) -> Vec<&Symbol> {
+        self.symbol_table.get_global_symbols()
+    }
+
+    /// Top-level functions, natives and forward declarations
+    pub fn functions(&self) -> Vec<&Symbol> {
+        self.globals()
+            .into_iter()
+            .filter(|symbol| matches!(symbol.symbol_type, SymbolType::Function { .. }))
+            .collect()
+    }
+
+    /// Top-level variables and constants
+    pub fn variables(&self) -> Vec<&Symbol> {
+        self.globals()
+            .into_iter()
+            .filter(|symbol| matches!(symbol.symbol_type, SymbolType::Variable { .. }))
+            .collect()
+    }
+
+    /// Line on which `name` was first declared
+    pub fn definition_of(&self, name: &str) -> Option<usize> {
+        self.definitions
+            .iter()
+            .find(|(defined, _)| defined == name)
+            .map(|&(_, line)| line)
+    }
+
+    /// Lines on which `name` is used, in source order
+    pub fn references_of(&self, name: &str) -> Vec<usize> {
+        self.references
+            .iter()
+            .filter(|(referenced, _)| referenced == name)
+            .map(|&(_, line)| line)
+            .collect()
+    }
+
+    /// Add a symbol to the current scope and record where it was declared
+    fn add_symbol(&mut self, symbol: Symbol) {
+        let span = symbol.span;
+        let definition = (symbol.name.clone(), span.unwrap_or_default().line);
+        let len = symbol.name.chars().count();
+        match (self.symbol_table.add_symbol(symbol), span) {
+            (Ok(()), _) => self.definitions.push(definition),
+            // Point at the redeclaration; the message names the original
+            (Err(e), Some(span)) => self.errors.push(e.at(span.line, span.column, len)),
+            (Err(e), None) => self.errors.push(e),
+        }
+    }
+
+    /// Record a use of `name` on the current line
+    fn add_reference(&mut self, name: &str) {
+        self.references
+            .push((name.to_string(), self.current_span.line));
+    }
+
+    /// Report a write to a `const` variable or parameter, either directly or
+    /// through an element of a `const` array
+    fn check_writable(&mut self, target: &AstNode) {
+        let name = match target.inner() {
+            AstNode::Identifier(name) => name,
+            AstNode::ArrayAccess { array, .. } => match array.inner() {
+                AstNode::Identifier(name) => name,
+                _ => return,
+            },
+            _ => return,
+        };
+        if let Some(SymbolType::Variable { is_const: true, .. }) = self
+            .symbol_table
+            .lookup(name)
+            .map(|symbol| &symbol.symbol_type)
+        {
+            self.errors.push(CompilerError::TypeError(format!(
+                "Cannot modify const '{}'",
+                name
+            )));
+        }
+    }
+
+    /// Report a program the host cannot run: one with neither `main` nor a
+    /// public function, or whose `main` takes parameters
+    ///
+    /// Sources that define no functions, such as empty files or files of
+    /// includes and declarations, are exempt.
+    fn check_entry_point(&mut self) {
+        let mut has_function = false;
+        let mut has_public = false;
+        for symbol in self.symbol_table.get_global_symbols() {
+            let SymbolType::Function {
+                parameters,
+                is_public,
+                is_native: false,
+                ..
+            } = &symbol.symbol_type
+            else {
+                continue;
+            };
+            if symbol.name == "main" && !parameters.is_empty() {
+                self.errors.push(CompilerError::SemanticError(
+                    "main() must not take parameters".to_string(),
+                ));
+                return;
+            }
+            has_function = true;
+            has_public |= symbol.name == "main" || *is_public;
+        }
+        if has_function && !has_public {
+            self.errors.push(CompilerError::SemanticError(
+                "Program has no entry point: define main() or a public function".to_string(),
+            ));
+        }
+    }
+
+    /// Remember which of the given symbols were never referenced
+    ///
+    /// Names starting with an underscore are exempt by convention.
+    fn record_unused(&mut self, symbols: Vec<Symbol>, parameters: &[Parameter]) {
+        for symbol in symbols {
+            if symbol.use_count > 0
+                || symbol.name.starts_with('_')
+                || !matches!(symbol.symbol_type, SymbolType::Variable { .. })
+            {
+                continue;
+            }
+            self.unused.push(UnusedSymbol {
+                is_parameter: parameters.iter().any(|p| p.name == symbol.name),
+                span: symbol.span.unwrap_or_default(),
+                name: symbol.name,
+            });
+        }
+    }
+
+    /// Declare a function symbol
+    ///
+    /// Natives and forward declarations may be repeated or followed by a
+    /// definition; only two definitions of the same name are an error.
+    fn declare_function(&mut self, symbol: Symbol) {
+        let has_body = |symbol: &Symbol| {
+            matches!(
+                symbol.symbol_type,
+                SymbolType::Function {
+                    is_native: false,
+                    is_forward: false,
+                    ..
+                }
+            )
+        };
+
+        if let Some(existing) = self.symbol_table.lookup(&symbol.name)
+            && matches!(existing.symbol_type, SymbolType::Function { .. })
+            && !(has_body(existing) && has_body(&symbol))
+        {
+            return;
+        }
+
+        self.add_symbol(symbol);
+    }
+}
+
+impl Default for SymbolTableVisitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AstVisitor<()> for SymbolTableVisitor {
+    fn visit_program(&mut self, nodes: &[AstNode]) -> CompilerResult<()> {
+        // Declare every function up front so calls may precede definitions
+        for node in nodes {
+            if let AstNode::Function {
+                name,
+                parameters,
+                return_type,
+                is_public,
+                is_native,
+                is_forward,
+                ..
+            } = node.inner()
+            {
+                self.declare_function(Symbol {
+                    name: name.clone(),
+                    symbol_type: SymbolType::Function {
+                        parameters: parameters.clone(),
+                        return_type: return_type.clone(),
+                        is_public: *is_public,
+                        is_native: *is_native,
+                        is_forward: *is_forward,
+                    },
+                    scope_level: self.symbol_table.get_scope_level(),
+                    is_defined: true,
+                    span: node.span(),
+                    use_count: 0,
+                });
+            }
+        }
+
+        for node in nodes {
+            node.accept(self)?;
+        }
+        Ok(())
+    }
+
+    fn visit_function(
+        &mut self,
+        name: &str,
+        parameters: &[Parameter],
+        return_type: &Option<String>,
+        body: &[AstNode],
+        is_public: bool,
+        is_native: bool,
+        is_forward: bool,
+    ) -> CompilerResult<()> {
+        let symbol = Symbol {
+            name: name.to_string(),
+            symbol_type: SymbolType::Function {
+                parameters: parameters.to_vec(),
+                return_type: return_type.clone(),
+                is_public,
+                is_native,
+                is_forward,
+            },
+            scope_level: self.symbol_table.get_scope_level(),
+            is_defined: true,
+            span: Some(self.current_span),
+            use_count: 0,
+        };
+
+        if self.symbol_table.lookup(name).is_none() {
+            self.declare_function(symbol);
+        }
+
+        // Enter function scope
+        self.symbol_table.enter_scope();
+
+        // Add parameters to symbol table
+        for param in parameters {
+            let param_symbol = Symbol {
+                name: param.name.clone(),
+                symbol_type: SymbolType::Variable {
+                    var_type: param.param_type.clone(),
+                    is_const: param.is_const,
+                    is_static: false,
+                    offset: None,
+                },
+                scope_level: self.symbol_table.get_scope_level(),
+                is_defined: true,
+                span: Some(self.current_span),
+                use_count: 0,
+            };
+
+            self.add_symbol(param_symbol);
+        }
+
+        // Analyze function body
+        for stmt in body {
+            stmt.accept(self)?;
+        }
+
+        // Exit function scope; declarations without a body have no uses
+        let symbols = self.symbol_table.exit_scope();
+        if !is_native && !is_forward {
+            self.record_unused(symbols, parameters);
+        }
+
+        Ok(())
+    }
+
+    fn visit_variable_declaration(
+        &mut self,
+        name: &str,
+        var_type: &str,
+        array_size: &Option<Box<AstNode>>,
+        initializer: &Option<Box<AstNode>>,
+        is_const: bool,
+        is_static: bool,
+    ) -> CompilerResult<()> {
+        let symbol = Symbol {
+            name: name.to_string(),
+            symbol_type: SymbolType::Variable {
+                var_type: var_type.to_string(),
+                is_const,
+                is_static,
+                offset: None,
+            },
+            scope_level: self.symbol_table.get_scope_level(),
+            is_defined: true,
+            span: Some(self.current_span),
+            use_count: 0,
+        };
+
+        self.add_symbol(symbol);
+
+        if let Some(size) = array_size {
+            size.accept(self)?;
+        }
+
+        // Analyze initializer if present
+        if let Some(init) = initializer {
+            init.accept(self)?;
+        }
+
+        Ok(())
+    }
+
+    fn visit_block(&mut self, statements: &[AstNode]) -> CompilerResult<()> {
+        self.symbol_table.enter_scope();
+
+        for stmt in statements {
+            stmt.accept(self)?;
+        }
+
+        let symbols = self.symbol_table.exit_scope();
+        self.record_unused(symbols, &[]);
+        Ok(())
+    }
+
+    fn visit_located(&mut self, span: Span, node: &AstNode) -> CompilerResult<()> {
+        self.current_span = span;
+        node.accept(self)
+    }
+
+    fn visit_identifier(&mut self, name: &str) -> CompilerResult<()> {
+        self.add_reference(name);
+        if !self.symbol_table.mark_used(name) {
+            self.errors.push(CompilerError::SemanticError(format!(
+                "Undefined identifier: {}",
+                name
+            )));
+        }
+        Ok(())
+    }
+
+    // Default implementations for other visitor methods
+    fn visit_if(
+        &mut self,
+        condition: &AstNode,
+        then_branch: &AstNode,
+        else_branch: &Option<Box<AstNode>>,
+    ) -> CompilerResult<()> {
+        condition.accept(self)?;
+        then_branch.accept(self)?;
+        if let Some(else_stmt) = else_branch {
+            else_stmt.accept(self)?;
+        }
+        Ok(())
+    }
+
+    fn visit_while(&mut self, condition: &AstNode, body: &AstNode) -> CompilerResult<()> {
+        condition.accept(self)?;
+        body.accept(self)?;
+        Ok(())
+    }
+
+    fn visit_do_while(&mut self, body: &AstNode, condition: &AstNode) -> CompilerResult<()> {
+        body.accept(self)?;
+        condition.accept(self)?;
+        Ok(())
+    }
+
+    fn visit_for(
+        &mut self,
+        init: &Option<Box<AstNode>>,
+        condition: &Option<Box<AstNode>>,
+        update: &Option<Box<AstNode>>,
+        body: &AstNode,
+    ) -> CompilerResult<()> {
+        // A variable declared by the initializer is scoped to the loop
+        self.symbol_table.enter_scope();
+        if let Some(init_stmt) = init {
+            init_stmt.accept(self)?;
+        }
+        if let Some(cond) = condition {
+            cond.accept(self)?;
+        }
+        body.accept(self)?;
+        if let Some(update_stmt) = update {
+            update_stmt.accept(self)?;
+        }
+        let symbols = self.symbol_table.exit_scope();
+        self.record_unused(symbols, &[]);
+        Ok(())
+    }
+
+    fn visit_return(&mut self, value: &Option<Box<AstNode>>) -> CompilerResult<()> {
+        if let Some(val) = value {
+            val.accept(self)?;
+        }
+        Ok(())
+    }
+
+    fn visit_assert(&mut self, condition: &AstNode) -> CompilerResult<()> {
+        condition.accept(self)
+    }
+
+    fn visit_break(&mut self) -> CompilerResult<()> {
+        Ok(())
+    }
+
+    fn visit_continue(&mut self) -> CompilerResult<()> {
+        Ok(())
+    }
+
+    fn visit_state_change(
+        &mut self,
+        _automaton: &Option<String>,
+        _state: &str,
+    ) -> CompilerResult<()> {
+        Ok(())
+    }
+
+    fn visit_binary_op(
+        &mut self,
+        left: &AstNode,
+        operator: &BinaryOperator,
+        right: &AstNode,
+    ) -> CompilerResult<()> {
+        if matches!(
+            operator,
+            BinaryOperator::AddAssign
+                | BinaryOperator::SubtractAssign
+                | BinaryOperator::MultiplyAssign
+                | BinaryOperator::DivideAssign
+                | BinaryOperator::ModuloAssign
+                | BinaryOperator::AndAssign
+                | BinaryOperator::OrAssign
+                | BinaryOperator::XorAssign
+                | BinaryOperator::LeftShiftAssign
+                | BinaryOperator::RightShiftAssign
+        ) {
+            self.check_writable(left);
+        }
+        left.accept(self)?;
+        right.accept(self)?;
+        Ok(())
+    }
+
+    fn visit_unary_op(
+        &mut self,
+        operator: &UnaryOperator,
+        operand: &AstNode,
+    ) -> CompilerResult<()> {
+        if matches!(
+            operator,
+            UnaryOperator::Increment
+                | UnaryOperator::Decrement
+                | UnaryOperator::PostIncrement
+                | UnaryOperator::PostDecrement
+        ) {
+            self.check_writable(operand);
+        }
+        operand.accept(self)?;
+        Ok(())
+    }
+
+    fn visit_assignment(&mut self, target: &AstNode, value: &AstNode) -> CompilerResult<()> {
+        self.check_writable(target);
+        // Assigning to a variable does not count as reading it
+        match target.inner() {
+            AstNode::Identifier(name) if self.symbol_table.exists(name) => {
+                self.add_reference(name);
+            }
+            _ => target.accept(self)?,
+        }
+        value.accept(self)?;
+        Ok(())
+    }
+
+    fn visit_ternary(
+        &mut self,
+        condition: &AstNode,
+        then_value: &AstNode,
+        else_value: &AstNode,
+    ) -> CompilerResult<()> {
+        condition.accept(self)?;
+        then_value.accept(self)?;
+        else_value.accept(self)?;
+        Ok(())
+    }
+
+    fn visit_function_call(&mut self, name: &str, arguments: &[AstNode]) -> CompilerResult<()> {
+        self.add_reference(name);
+        if self.symbol_table.lookup(name).is_none() {
+            self.errors.push(CompilerError::SemanticError(format!(
+                "Undefined function: {}",
+                name
+            )));
+        }
+
+        for arg in arguments {
+            arg.accept(self)?;
+        }
+        Ok(())
+    }
+
+    fn visit_array_access(&mut self, array: &AstNode, index: &AstNode) -> CompilerResult<()> {
+        array.accept(self)?;
+        index.accept(self)?;
+        Ok(())
+    }
+
+    fn visit_member_access(&mut self, object: &AstNode, _member: &str) -> CompilerResult<()> {
+        object.accept(self)?;
+        Ok(())
+    }
+
+    fn visit_integer(&mut self, _value: i32) -> CompilerResult<()> {
+        Ok(())
+    }
+
+    fn visit_float(&mut self, _value: f32) -> CompilerResult<()> {
+        Ok(())
+    }
+
+    fn visit_string(&mut self, _value: &str) -> CompilerResult<()> {
+        Ok(())
+    }
+
+    fn visit_character(&mut self, _value: char) -> CompilerResult<()> {
+        Ok(())
+    }
+
+    fn visit_boolean(&mut self, _value: bool) -> CompilerResult<()> {
+        Ok(())
+    }
+
+    fn visit_type_definition(
+        &mut self,
+        name: &str,
+        definition: &TypeDefinition,
+    ) -> CompilerResult<()> {
+        let symbol = Symbol {
+            name: name.to_string(),
+            symbol_type: SymbolType::Type {
+                definition: definition.clone(),
+            },
+            scope_level: self.symbol_table.get_scope_level(),
+            is_defined: true,
+            span: Some(self.current_span),
+            use_count: 0,
+        };
+
+        self.add_symbol(symbol);
+
+        Ok(())
+    }
+
+    fn visit_enum_definition(
+        &mut self,
+        name: &str,
+        variants: &[EnumVariant],
+    ) -> CompilerResult<()> {
+        let symbol = Symbol {
+            name: name.to_string(),
+            symbol_type: SymbolType::Enum {
+                variants: variants.to_vec(),
+            },
+            scope_level: self.symbol_table.get_scope_level(),
+            is_defined: true,
+            span: Some(self.current_span),
+            use_count: 0,
+        };
+
+        self.add_symbol(symbol);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,461 @@
+//! Tag checking for Pawn expressions
+//!
+//! Pawn values carry a tag such as `Float:` or `bool:`; untagged values have
+//! the empty tag. This pass infers the tag of every expression and reports
+//! operations that mix incompatible tags.
+
+use crate::ast::*;
+use crate::error::*;
+use crate::symbol_table::*;
+
+/// Tag of values without an explicit tag
+const UNTAGGED: &str = "";
+
+/// Tags whose mismatches are reported
+const CHECKED_TAGS: [&str; 2] = ["Float", "bool"];
+
+/// Infers expression tags and reports mismatches as `CompilerError::TypeError`
+pub struct TagChecker {
+    symbol_table: SymbolTable,
+    errors: Vec<CompilerError>,
+    /// Position of the statement being checked
+    current_span: Span,
+}
+
+impl TagChecker {
+    /// Create a new tag checker
+    pub fn new() -> Self {
+        Self {
+            symbol_table: SymbolTable::new(),
+            errors: Vec::new(),
+            current_span: Span::default(),
+        }
+    }
+
+    /// Check the tags of every expression in the program
+    pub fn check(&mut self, ast: &AstNode) -> CompilerResult<()> {
+        self.errors.clear();
+        self.current_span = Span::default();
+        self.symbol_table.clear();
+
+        ast.accept::<String>(self)?;
+
+        match self.errors.first() {
+            Some(error) => Err(error.clone()),
+            None => Ok(()),
+        }
+    }
+
+    /// Get errors
+    pub fn get_errors(&self) -> &[CompilerError] {
+        &self.errors
+    }
+
+    /// Whether a value tagged `actual` may be used where `expected` is needed
+    ///
+    /// Weak tags, which start with a lowercase letter like `bool:`, are
+    /// silently dropped when the value is used as an untagged one.
+    fn compatible(expected: &str, actual: &str) -> bool {
+        expected == actual
+            || (expected == UNTAGGED && actual.starts_with(|c: char| c.is_ascii_lowercase()))
+            || !(CHECKED_TAGS.contains(&expected) || CHECKED_TAGS.contains(&actual))
+    }
+
+    /// Report a mismatch unless `actual` is compatible with `expected`
+    fn expect_tag(&mut self, expected: &str, actual: &str) {
+        if !Self::compatible(expected, actual) {
+            self.errors.push(CompilerError::TypeError(format!(
+                "Tag mismatch on line {}: expected {}, found {}",
+                self.current_span.line,
+                Self::display(expected),
+                Self::display(actual)
+            )));
+        }
+    }
+
+    /// Report a mismatch unless either operand's tag accepts the other
+    fn expect_operands(&mut self, left: &str, right: &str) {
+        if !Self::compatible(right, left) {
+            self.expect_tag(left, right);
+        }
+    }
+
+    /// Render a tag the way Pawn writes it, with `_:` for untagged values
+    fn display(tag: &str) -> String {
+        if tag == UNTAGGED {
+            "_:".to_string()
+        } else {
+            format!("{}:", tag)
+        }
+    }
+
+    /// Declare a variable with its tag
+    fn declare_variable(&mut self, name: &str, var_type: &str) {
+        // Duplicates are reported by the symbol table analysis
+        self.symbol_table
+            .add_symbol(Symbol {
+                name: name.to_string(),
+                symbol_type: SymbolType::Variable {
+                    var_type: var_type.to_string(),
+                    is_const: false,
+                    is_static: false,
+                    offset: None,
+                },
+                scope_level: self.symbol_table.get_scope_level(),
+                is_defined: true,
+                span: Some(self.current_span),
+                use_count: 0,
+            })
+            .ok();
+    }
+}
+
+impl Default for TagChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AstVisitor<String> for TagChecker {
+    fn visit_program(&mut self, nodes: &[AstNode]) -> CompilerResult<String> {
+        // Return tags must be known before any call is checked
+        for node in nodes {
+            if let AstNode::Function {
+                name,
+                parameters,
+                return_type,
+                is_public,
+                is_native,
+                is_forward,
+                ..
+            } = node.inner()
+            {
+                self.symbol_table
+                    .add_symbol(Symbol {
+                        name: name.clone(),
+                        symbol_type: SymbolType::Function {
+                            parameters: parameters.clone(),
+                            return_type: return_type.clone(),
+                            is_public: *is_public,
+                            is_native: *is_native,
+                            is_forward: *is_forward,
+                        },
+                        scope_level: 0,
+                        is_defined: true,
+                        span: node.span(),
+                        use_count: 0,
+                    })
+                    .ok();
+            }
+        }
+
+        for node in nodes {
+            node.accept(self)?;
+        }
+        Ok(UNTAGGED.to_string())
+    }
+
+    fn visit_located(&mut self, span: Span, node: &AstNode) -> CompilerResult<String> {
+        self.current_span = span;
+        node.accept(self)
+    }
+
+    fn visit_function(
+        &mut self,
+        _name: &str,
+        parameters: &[Parameter],
+        _return_type: &Option<String>,
+        body: &[AstNode],
+        _is_public: bool,
+        _is_native: bool,
+        _is_forward: bool,
+    ) -> CompilerResult<String> {
+        self.symbol_table.enter_scope();
+        for param in parameters {
+            self.declare_variable(&param.name, &param.param_type);
+        }
+        for stmt in body {
+            stmt.accept(self)?;
+        }
+        self.symbol_table.exit_scope();
+        Ok(UNTAGGED.to_string())
+    }
+
+    fn visit_variable_declaration(
+        &mut self,
+        name: &str,
+        var_type: &str,
+        array_size: &Option<Box<AstNode>>,
+        initializer: &Option<Box<AstNode>>,
+        _is_const: bool,
+        _is_static: bool,
+    ) -> CompilerResult<String> {
+        if let Some(size) = array_size {
+            size.accept(self)?;
+        }
+        if let Some(init) = initializer {
+            let tag = init.accept(self)?;
+            self.expect_tag(var_type, &tag);
+        }
+        self.declare_variable(name, var_type);
+        Ok(var_type.to_string())
+    }
+
+    fn visit_block(&mut self, statements: &[AstNode]) -> CompilerResult<String> {
+        self.symbol_table.enter_scope();
+        for stmt in statements {
+            stmt.accept(self)?;
+        }
+        self.symbol_table.exit_scope();
+        Ok(UNTAGGED.to_string())
+    }
+
+    fn visit_if(
+        &mut self,
+        condition: &AstNode,
+        then_branch: &AstNode,
+        else_branch: &Option<Box<AstNode>>,
+    ) -> CompilerResult<String> {
+        condition.accept(self)?;
+        then_branch.accept(self)?;
+        if let Some(else_stmt) = else_branch {
+            else_stmt.accept(self)?;
+        }
+        Ok(UNTAGGED.to_string())
+    }
+
+    fn visit_while(&mut self, condition: &AstNode, body: &AstNode) -> CompilerResult<String> {
+        condition.accept(self)?;
+        body.accept(self)?;
+        Ok(UNTAGGED.to_string())
+    }
+
+    fn visit_do_while(&mut self, body: &AstNode, condition: &AstNode) -> CompilerResult<String> {
+        body.accept(self)?;
+        condition.accept(self)?;
+        Ok(UNTAGGED.to_string())
+    }
+
+    fn visit_for(
+        &mut self,
+        init: &Option<Box<AstNode>>,
+        condition: &Option<Box<AstNode>>,
+        update: &Option<Box<AstNode>>,
+        body: &AstNode,
+    ) -> CompilerResult<String> {
+        for node in [init, condition, update].into_iter().flatten() {
+            node.accept(self)?;
+        }
+        body.accept(self)?;
+        Ok(UNTAGGED.to_string())
+    }
+
+    fn visit_return(&mut self, value: &Option<Box<AstNode>>) -> CompilerResult<String> {
+        match value {
+            Some(val) => val.accept(self),
+            None => Ok(UNTAGGED.to_string()),
+        }
+    }
+
+    fn visit_assert(&mut self, condition: &AstNode) -> CompilerResult<String> {
+        condition.accept(self)?;
+        Ok(UNTAGGED.to_string())
+    }
+
+    fn visit_break(&mut self) -> CompilerResult<String> {
+        Ok(UNTAGGED.to_string())
+    }
+
+    fn visit_continue(&mut self) -> CompilerResult<String> {
+        Ok(UNTAGGED.to_string())
+    }
+
+    fn visit_state_change(
+        &mut self,
+        _automaton: &Option<String>,
+        _state: &str,
+    ) -> CompilerResult<String> {
+        Ok(UNTAGGED.to_string())
+    }
+
+    fn visit_binary_op(
+        &mut self,
+        left: &AstNode,
+        operator: &BinaryOperator,
+        right: &AstNode,
+    ) -> CompilerResult<String> {
+        let left_tag = left.accept(self)?;
+        let right_tag = right.accept(self)?;
+
+        // Generated code would add the IEEE bits of floats as integers
+        let arithmetic = matches!(
+            operator,
+            BinaryOperator::Add
+                | BinaryOperator::Subtract
+                | BinaryOperator::Multiply
+                | BinaryOperator::Divide
+                | BinaryOperator::Modulo
+                | BinaryOperator::AddAssign
+                | BinaryOperator::SubtractAssign
+                | BinaryOperator::MultiplyAssign
+                | BinaryOperator::DivideAssign
+                | BinaryOperator::ModuloAssign
+        );
+        if arithmetic && (left_tag == "Float" || right_tag == "Float") {
+            self.errors.push(CompilerError::TypeError(format!(
+                "Float arithmetic is not supported yet on line {}",
+                self.current_span.line
+            )));
+        }
+
+        match operator {
+            BinaryOperator::LogicalAnd | BinaryOperator::LogicalOr => Ok("bool".to_string()),
+            BinaryOperator::Equal
+            | BinaryOperator::NotEqual
+            | BinaryOperator::Less
+            | BinaryOperator::LessEqual
+            | BinaryOperator::Greater
+            | BinaryOperator::GreaterEqual => {
+                self.expect_operands(&left_tag, &right_tag);
+                Ok("bool".to_string())
+            }
+            BinaryOperator::Assign
+            | BinaryOperator::AddAssign
+            | BinaryOperator::SubtractAssign
+            | BinaryOperator::MultiplyAssign
+            | BinaryOperator::DivideAssign
+            | BinaryOperator::ModuloAssign
+            | BinaryOperator::AndAssign
+            | BinaryOperator::OrAssign
+            | BinaryOperator::XorAssign
+            | BinaryOperator::LeftShiftAssign
+            | BinaryOperator::RightShiftAssign => {
+                self.expect_tag(&left_tag, &right_tag);
+                Ok(left_tag)
+            }
+            _ => {
+                self.expect_operands(&left_tag, &right_tag);
+                // An untagged operand adopts the tag of the other one
+                Ok(if left_tag == UNTAGGED {
+                    right_tag
+                } else {
+                    left_tag
+                })
+            }
+        }
+    }
+
+    fn visit_unary_op(
+        &mut self,
+        operator: &UnaryOperator,
+        operand: &AstNode,
+    ) -> CompilerResult<String> {
+        let tag = operand.accept(self)?;
+        Ok(match operator {
+            UnaryOperator::LogicalNot => "bool".to_string(),
+            UnaryOperator::Sizeof | UnaryOperator::Tagof => UNTAGGED.to_string(),
+            _ => tag,
+        })
+    }
+
+    fn visit_assignment(&mut self, target: &AstNode, value: &AstNode) -> CompilerResult<String> {
+        let target_tag = target.accept(self)?;
+        let value_tag = value.accept(self)?;
+        self.expect_tag(&target_tag, &value_tag);
+        Ok(target_tag)
+    }
+
+    fn visit_ternary(
+        &mut self,
+        condition: &AstNode,
+        then_value: &AstNode,
+        else_value: &AstNode,
+    ) -> CompilerResult<String> {
+        condition.accept(self)?;
+        let tag = then_value.accept(self)?;
+        else_value.accept(self)?;
+        Ok(tag)
+    }
+
+    fn visit_function_call(&mut self, name: &str, arguments: &[AstNode]) -> CompilerResult<String> {
+        let (parameters, return_type) = match self.symbol_table.lookup(name) {
+            Some(Symbol {
+                symbol_type:
+                    SymbolType::Function {
+                        parameters,
+                        return_type,
+                        ..
+                    },
+                ..
+            }) => (parameters.clone(), return_type.clone()),
+            _ => (Vec::new(), None),
+        };
+
+        for (index, arg) in arguments.iter().enumerate() {
+            let tag = arg.accept(self)?;
+            if let Some(param) = parameters.get(index)
+                && param.name != "..."
+            {
+                self.expect_tag(&param.param_type, &tag);
+            }
+        }
+        Ok(return_type.unwrap_or_default())
+    }
+
+    fn visit_array_access(&mut self, array: &AstNode, index: &AstNode) -> CompilerResult<String> {
+        let tag = array.accept(self)?;
+        index.accept(self)?;
+        Ok(tag)
+    }
+
+    fn visit_member_access(&mut self, object: &AstNode, _member: &str) -> CompilerResult<String> {
+        object.accept(self)?;
+        Ok(UNTAGGED.to_string())
+    }
+
+    fn visit_integer(&mut self, _value: i32) -> CompilerResult<String> {
+        Ok(UNTAGGED.to_string())
+    }
+
+    fn visit_float(&mut self, _value: f32) -> CompilerResult<String> {
+        Ok("Float".to_string())
+    }
+
+    fn visit_string(&mut self, _value: &str) -> CompilerResult<String> {
+        Ok(UNTAGGED.to_string())
+    }
+
+    fn visit_character(&mut self, _value: char) -> CompilerResult<String> {
+        Ok(UNTAGGED.to_string())
+    }
+
+    fn visit_boolean(&mut self, _value: bool) -> CompilerResult<String> {
+        Ok("bool".to_string())
+    }
+
+    fn visit_identifier(&mut self, name: &str) -> CompilerResult<String> {
+        Ok(match self.symbol_table.lookup(name) {
+            Some(Symbol {
+                symbol_type: SymbolType::Variable { var_type, .. },
+                ..
+            }) => var_type.clone(),
+            _ => UNTAGGED.to_string(),
+        })
+    }
+
+    fn visit_type_definition(
+        &mut self,
+        _name: &str,
+        _definition: &TypeDefinition,
+    ) -> CompilerResult<String> {
+        Ok(UNTAGGED.to_string())
+    }
+
+    fn visit_enum_definition(
+        &mut self,
+        _name: &str,
+        _variants: &[EnumVariant],
+    ) -> CompilerResult<String> {
+        Ok(UNTAGGED.to_string())
+    }
+}
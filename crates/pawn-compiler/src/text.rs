@@ -0,0 +1,20 @@
+//! Text measurements shared by the formatter and the linter
+
+/// Columns `s` takes up when displayed, with a tab stop every `tab_width`
+/// columns
+pub fn visual_width(s: &str, tab_width: usize) -> usize {
+    let tab_width = tab_width.max(1);
+    s.chars().fold(0, |column, c| {
+        if c == '\t' {
+            (column / tab_width + 1) * tab_width
+        } else {
+            column + 1
+        }
+    })
+}
+
+/// Visual width of the spaces and tabs `line` starts with
+pub(crate) fn indent_width(line: &str, tab_width: usize) -> usize {
+    let rest = line.trim_start_matches([' ', '\t']);
+    visual_width(&line[..line.len() - rest.len()], tab_width)
+}
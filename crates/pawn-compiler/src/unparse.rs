@@ -0,0 +1,507 @@
+//! Rendering syntax trees back into Pawn source
+//!
+//! [`unparse`] is the inverse of the parser: parsing its output yields the
+//! same tree, apart from source locations. The layout is canonical, with one
+//! statement per line and four-space indentation, and expressions get only
+//! the parentheses their operators' precedence requires.
+
+use crate::ast::*;
+
+/// Binding strength of an expression; higher binds tighter
+type Precedence = u8;
+
+const ASSIGNMENT: Precedence = 1;
+const CONDITIONAL: Precedence = 2;
+const UNARY: Precedence = 13;
+const POSTFIX: Precedence = 14;
+const PRIMARY: Precedence = 15;
+
+const INDENT: &str = "    ";
+
+/// Render a syntax tree as Pawn source
+///
+/// A program renders as its declarations separated by blank lines, a
+/// statement or declaration as its lines, and an expression as a single line
+/// without a trailing `;`. An `else` that would otherwise attach to an `if`
+/// nested in the then-branch forces braces around that branch, so the branch
+/// re-parses as a block.
+pub fn unparse(node: &AstNode) -> String {
+    let mut out = String::new();
+    match node.inner() {
+        AstNode::Program(items) => {
+            for (index, item) in items.iter().enumerate() {
+                let adjacent_variables = index > 0
+                    && matches!(item.inner(), AstNode::VariableDeclaration { .. })
+                    && matches!(
+                        items[index - 1].inner(),
+                        AstNode::VariableDeclaration { .. }
+                    );
+                if index > 0 && !adjacent_variables {
+                    out.push('\n');
+                }
+                write_statement(&mut out, item, 0);
+            }
+        }
+        node if is_expression(node) => out.push_str(&expression(node)),
+        node => write_statement(&mut out, node, 0),
+    }
+    out
+}
+
+/// Append `text` as the rest of the current line, indenting it first when it
+/// starts a new line
+fn write_line(out: &mut String, depth: usize, text: &str) {
+    if out.is_empty() || out.ends_with('\n') {
+        out.push_str(&INDENT.repeat(depth));
+    }
+    out.push_str(text);
+    out.push('\n');
+}
+
+/// Continue the closing `}` line of a block, so `else` and `while` follow it
+fn join_closing_brace(out: &mut String) {
+    if out.ends_with("}\n") {
+        out.pop();
+        out.push(' ');
+    }
+}
+
+/// Append a statement or declaration
+fn write_statement(out: &mut String, node: &AstNode, depth: usize) {
+    match node.inner() {
+        AstNode::Function {
+            name,
+            parameters,
+            return_type,
+            body,
+            is_public,
+            is_native,
+            is_forward,
+            states,
+        } => {
+            let mut header = String::new();
+            if *is_native {
+                header.push_str("native ");
+            }
+            if *is_public {
+                header.push_str("public ");
+            }
+            if let Some(tag) = return_type {
+                header.push_str(&format!("{}:", tag));
+            }
+            let parameters: Vec<String> = parameters.iter().map(parameter).collect();
+            header.push_str(&format!("{}({})", name, parameters.join(", ")));
+            if let Some(states) = states {
+                header.push_str(&format!(" <{}>", states));
+            }
+            if *is_native || *is_forward {
+                write_line(out, depth, &format!("{};", header));
+            } else {
+                write_block(out, depth, &header, body);
+            }
+        }
+        AstNode::VariableDeclaration { .. } => {
+            write_line(out, depth, &format!("{};", declaration(node.inner())));
+        }
+        AstNode::Block(statements) => write_block(out, depth, "", statements),
+        AstNode::Expression(expr) => write_line(out, depth, &format!("{};", expression(expr))),
+        AstNode::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            let header = format!("if ({})", expression(condition));
+            match else_branch {
+                Some(_) if ends_in_open_if(then_branch) => {
+                    write_block(out, depth, &header, std::slice::from_ref(then_branch))
+                }
+                _ => write_clause(out, depth, &header, then_branch),
+            }
+            if let Some(else_branch) = else_branch {
+                join_closing_brace(out);
+                if let AstNode::If { .. } = else_branch.inner() {
+                    if out.ends_with('\n') {
+                        out.push_str(&INDENT.repeat(depth));
+                    }
+                    out.push_str("else ");
+                    write_statement(out, else_branch, depth);
+                } else {
+                    write_clause(out, depth, "else", else_branch);
+                }
+            }
+        }
+        AstNode::While { condition, body } => {
+            write_clause(
+                out,
+                depth,
+                &format!("while ({})", expression(condition)),
+                body,
+            );
+        }
+        AstNode::DoWhile { body, condition } => {
+            write_clause(out, depth, "do", body);
+            join_closing_brace(out);
+            write_line(out, depth, &format!("while ({});", expression(condition)));
+        }
+        AstNode::For {
+            init,
+            condition,
+            update,
+            body,
+        } => {
+            let init = init
+                .as_deref()
+                .map_or(String::new(), |init| match init.inner() {
+                    AstNode::VariableDeclaration { .. } => declaration(init.inner()),
+                    init => expression(init),
+                });
+            let clause = |clause: &Option<Box<AstNode>>| {
+                clause
+                    .as_deref()
+                    .map_or(String::new(), |clause| format!(" {}", expression(clause)))
+            };
+            let header = format!("for ({};{};{})", init, clause(condition), clause(update));
+            write_clause(out, depth, &header, body);
+        }
+        AstNode::Return(value) => match value {
+            Some(value) => write_line(out, depth, &format!("return {};", expression(value))),
+            None => write_line(out, depth, "return;"),
+        },
+        AstNode::Assert(condition) => {
+            write_line(out, depth, &format!("assert {};", expression(condition)));
+        }
+        AstNode::Break => write_line(out, depth, "break;"),
+        AstNode::Continue => write_line(out, depth, "continue;"),
+        AstNode::StateChange { automaton, state } => match automaton {
+            Some(automaton) => write_line(out, depth, &format!("state {}:{};", automaton, state)),
+            None => write_line(out, depth, &format!("state {};", state)),
+        },
+        AstNode::EnumDefinition { name, variants } => {
+            let variants: Vec<String> = variants
+                .iter()
+                .map(|variant| match &variant.value {
+                    Some(value) => format!("{} = {}", variant.name, expression(value)),
+                    None => variant.name.clone(),
+                })
+                .collect();
+            write_line(
+                out,
+                depth,
+                &format!("enum {} {{ {} }}", name, variants.join(", ")),
+            );
+        }
+        AstNode::TypeDefinition { name, definition } => {
+            // Type definitions have no Pawn syntax of their own
+            write_line(out, depth, &format!("/* {} = {:?} */", name, definition));
+        }
+        AstNode::Program(_) => out.push_str(&unparse(node)),
+        expr => write_line(out, depth, &format!("{};", expression(expr))),
+    }
+}
+
+/// Append `header {`, the statements one level deeper, and the closing `}`
+fn write_block(out: &mut String, depth: usize, header: &str, statements: &[AstNode]) {
+    let opening = if header.is_empty() {
+        "{".to_string()
+    } else {
+        format!("{} {{", header)
+    };
+    write_line(out, depth, &opening);
+    for statement in statements {
+        write_statement(out, statement, depth + 1);
+    }
+    write_line(out, depth, "}");
+}
+
+/// Append a control statement header and its body, which shares the
+/// header's line when it is a block
+fn write_clause(out: &mut String, depth: usize, header: &str, body: &AstNode) {
+    match body.inner() {
+        AstNode::Block(statements) => write_block(out, depth, header, statements),
+        _ => {
+            write_line(out, depth, header);
+            write_statement(out, body, depth + 1);
+        }
+    }
+}
+
+/// Whether a following `else` would attach to an `if` inside `node`
+fn ends_in_open_if(node: &AstNode) -> bool {
+    match node.inner() {
+        AstNode::If {
+            else_branch: None, ..
+        } => true,
+        AstNode::If {
+            else_branch: Some(else_branch),
+            ..
+        } => ends_in_open_if(else_branch),
+        AstNode::While { body, .. } | AstNode::For { body, .. } => ends_in_open_if(body),
+        _ => false,
+    }
+}
+
+/// A variable declaration without its trailing `;`
+fn declaration(node: &AstNode) -> String {
+    let AstNode::VariableDeclaration {
+        name,
+        var_type,
+        array_size,
+        initializer,
+        is_const,
+        is_static,
+    } = node
+    else {
+        return expression(node);
+    };
+    let mut text = String::from(if *is_static { "static " } else { "new " });
+    if *is_const {
+        text.push_str("const ");
+    }
+    if !var_type.is_empty() {
+        text.push_str(&format!("{}:", var_type));
+    }
+    text.push_str(name);
+    if let Some(size) = array_size {
+        text.push_str(&format!("[{}]", expression(size)));
+    }
+    if let Some(initializer) = initializer {
+        text.push_str(&format!(" = {}", expression(initializer)));
+    }
+    text
+}
+
+/// A function parameter, as written in a parameter list
+fn parameter(param: &Parameter) -> String {
+    let mut text = String::new();
+    if param.is_const {
+        text.push_str("const ");
+    }
+    if param.is_reference {
+        text.push('&');
+    }
+    if !param.param_type.is_empty() {
+        text.push_str(&format!("{}:", param.param_type));
+    }
+    text.push_str(&param.name);
+    for size in &param.dimensions {
+        let size = size.as_deref().map(expression).unwrap_or_default();
+        text.push_str(&format!("[{}]", size));
+    }
+    if let Some(default) = &param.default_value {
+        text.push_str(&format!(" = {}", expression(default)));
+    }
+    text
+}
+
+fn is_expression(node: &AstNode) -> bool {
+    matches!(
+        node,
+        AstNode::BinaryOp { .. }
+            | AstNode::UnaryOp { .. }
+            | AstNode::Assignment { .. }
+            | AstNode::Ternary { .. }
+            | AstNode::FunctionCall { .. }
+            | AstNode::ArrayAccess { .. }
+            | AstNode::MemberAccess { .. }
+            | AstNode::Integer(_)
+            | AstNode::Float(_)
+            | AstNode::String(_)
+            | AstNode::PackedString(_)
+            | AstNode::Character(_)
+            | AstNode::Boolean(_)
+            | AstNode::Identifier(_)
+    )
+}
+
+/// How tightly an expression binds to its neighbours
+fn precedence(node: &AstNode) -> Precedence {
+    match node.inner() {
+        AstNode::Assignment { .. } => ASSIGNMENT,
+        AstNode::Ternary { .. } => CONDITIONAL,
+        AstNode::BinaryOp { operator, .. } => binary_precedence(operator),
+        AstNode::UnaryOp {
+            operator: UnaryOperator::PostIncrement | UnaryOperator::PostDecrement,
+            ..
+        } => POSTFIX,
+        AstNode::UnaryOp { .. } => UNARY,
+        // Negative literals are written with a leading minus
+        AstNode::Integer(value) if *value < 0 => UNARY,
+        AstNode::Float(value) if value.is_sign_negative() => UNARY,
+        _ => PRIMARY,
+    }
+}
+
+fn binary_precedence(operator: &BinaryOperator) -> Precedence {
+    use BinaryOperator::*;
+    match operator {
+        LogicalOr => 3,
+        LogicalAnd => 4,
+        BitwiseOr => 5,
+        BitwiseXor => 6,
+        BitwiseAnd => 7,
+        Equal | NotEqual => 8,
+        Less | LessEqual | Greater | GreaterEqual => 9,
+        LeftShift | RightShift => 10,
+        Add | Subtract => 11,
+        Multiply | Divide | Modulo => 12,
+        Assign | AddAssign | SubtractAssign | MultiplyAssign | DivideAssign | ModuloAssign
+        | AndAssign | OrAssign | XorAssign | LeftShiftAssign | RightShiftAssign => ASSIGNMENT,
+    }
+}
+
+fn binary_symbol(operator: &BinaryOperator) -> &'static str {
+    use BinaryOperator::*;
+    match operator {
+        Add => "+",
+        Subtract => "-",
+        Multiply => "*",
+        Divide => "/",
+        Modulo => "%",
+        Equal => "==",
+        NotEqual => "!=",
+        Less => "<",
+        LessEqual => "<=",
+        Greater => ">",
+        GreaterEqual => ">=",
+        LogicalAnd => "&&",
+        LogicalOr => "||",
+        BitwiseAnd => "&",
+        BitwiseOr => "|",
+        BitwiseXor => "^",
+        LeftShift => "<<",
+        RightShift => ">>",
+        Assign => "=",
+        AddAssign => "+=",
+        SubtractAssign => "-=",
+        MultiplyAssign => "*=",
+        DivideAssign => "/=",
+        ModuloAssign => "%=",
+        AndAssign => "&=",
+        OrAssign => "|=",
+        XorAssign => "^=",
+        LeftShiftAssign => "<<=",
+        RightShiftAssign => ">>=",
+    }
+}
+
+/// An operand, parenthesized when it binds more loosely than `min`
+fn operand(node: &AstNode, min: Precedence) -> String {
+    let text = expression(node);
+    if precedence(node) < min {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
+
+/// An expression on a single line
+fn expression(node: &AstNode) -> String {
+    match node.inner() {
+        AstNode::BinaryOp {
+            left,
+            operator,
+            right,
+        } => {
+            let level = binary_precedence(operator);
+            // Assignments are right-associative, everything else left
+            let (left_min, right_min) = if level == ASSIGNMENT {
+                (PRIMARY, ASSIGNMENT)
+            } else {
+                (level, level + 1)
+            };
+            format!(
+                "{} {} {}",
+                operand(left, left_min),
+                binary_symbol(operator),
+                operand(right, right_min)
+            )
+        }
+        AstNode::Assignment { target, value } => {
+            format!(
+                "{} = {}",
+                operand(target, PRIMARY),
+                operand(value, ASSIGNMENT)
+            )
+        }
+        AstNode::UnaryOp {
+            operator,
+            operand: inner,
+        } => {
+            let prefix = match operator {
+                UnaryOperator::PostIncrement => return format!("{}++", operand(inner, PRIMARY)),
+                UnaryOperator::PostDecrement => return format!("{}--", operand(inner, PRIMARY)),
+                UnaryOperator::Sizeof => return format!("sizeof {}", operand(inner, PRIMARY)),
+                UnaryOperator::Tagof => return format!("tagof {}", operand(inner, PRIMARY)),
+                UnaryOperator::Plus => "+",
+                UnaryOperator::Minus => "-",
+                UnaryOperator::LogicalNot => "!",
+                UnaryOperator::BitwiseNot => "~",
+                UnaryOperator::Increment => "++",
+                UnaryOperator::Decrement => "--",
+                UnaryOperator::AddressOf => "&",
+                UnaryOperator::Dereference => "*",
+            };
+            let text = operand(inner, UNARY);
+            // `-(-x)` must not run together into the `--` operator
+            if text.starts_with(prefix.chars().last().unwrap_or_default()) {
+                format!("{}({})", prefix, text)
+            } else {
+                format!("{}{}", prefix, text)
+            }
+        }
+        AstNode::Ternary {
+            condition,
+            then_value,
+            else_value,
+        } => format!(
+            "{} ? {} : {}",
+            operand(condition, CONDITIONAL + 1),
+            expression(then_value),
+            operand(else_value, CONDITIONAL)
+        ),
+        AstNode::FunctionCall { name, arguments } => {
+            let arguments: Vec<String> = arguments.iter().map(expression).collect();
+            format!("{}({})", name, arguments.join(", "))
+        }
+        AstNode::ArrayAccess { array, index } => {
+            format!("{}[{}]", operand(array, PRIMARY), expression(index))
+        }
+        AstNode::MemberAccess { object, member } => {
+            format!("{}.{}", operand(object, PRIMARY), member)
+        }
+        AstNode::Integer(value) => value.to_string(),
+        AstNode::Float(value) => {
+            let mut text = value.to_string();
+            if !text.contains('.') {
+                text.push_str(".0");
+            }
+            text
+        }
+        AstNode::String(value) => quote(value, '"'),
+        AstNode::PackedString(value) => format!("!{}", quote(value, '"')),
+        AstNode::Character(value) => quote(&value.to_string(), '\''),
+        AstNode::Boolean(value) => value.to_string(),
+        AstNode::Identifier(name) => name.clone(),
+        other => unparse(other).trim_end().to_string(),
+    }
+}
+
+/// A string or character literal, with the escapes the lexer understands
+fn quote(value: &str, delimiter: char) -> String {
+    let mut text = String::from(delimiter);
+    for ch in value.chars() {
+        match ch {
+            '\n' => text.push_str("\\n"),
+            '\t' => text.push_str("\\t"),
+            '\r' => text.push_str("\\r"),
+            '\\' => text.push_str("\\\\"),
+            ch if ch == delimiter => {
+                text.push('\\');
+                text.push(ch);
+            }
+            ch => text.push(ch),
+        }
+    }
+    text.push(delimiter);
+    text
+}
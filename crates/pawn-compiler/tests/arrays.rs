@@ -0,0 +1,118 @@
+use pawn_amx::instructions::{Instruction, Opcode};
+use pawn_amx::{
+    AMX_EXEC_MAIN, AmxFlags, AmxRuntime, AmxRuntimeError, Cell, read_header, write_header,
+};
+use pawn_compiler::{CodeGenerator, Parser, compile};
+
+fn run(bytecode: &[u8]) -> Result<Cell, AmxRuntimeError> {
+    let mut runtime = AmxRuntime::new();
+    runtime.init(bytecode).expect("runtime init should succeed");
+    runtime.exec(AMX_EXEC_MAIN)
+}
+
+#[test]
+fn writes_and_reads_local_array_element() {
+    let source = r#"
+        main() {
+            new arr[5];
+            arr[3] = 42;
+            return arr[3];
+        }
+    "#;
+
+    let bytecode = compile(source).expect("compile should succeed");
+    assert_eq!(run(&bytecode).unwrap(), 42);
+}
+
+#[test]
+fn writes_and_reads_global_array_element() {
+    let source = r#"
+        new arr[5];
+        new count = 7;
+
+        main() {
+            arr[3] = count;
+            arr[4] = 1;
+            return arr[3] + arr[4] + arr[0];
+        }
+    "#;
+
+    let bytecode = compile(source).expect("compile should succeed");
+    assert_eq!(run(&bytecode).unwrap(), 8);
+}
+
+#[test]
+fn out_of_bounds_index_raises_array_bounds() {
+    let source = r#"
+        main() {
+            new i = 5;
+            new arr[5];
+            return arr[i];
+        }
+    "#;
+
+    let bytecode = compile(source).expect("compile should succeed");
+    assert!(matches!(
+        run(&bytecode).unwrap_err().kind(),
+        AmxRuntimeError::ArrayBounds
+    ));
+
+    // With bounds checking disabled the read goes through to memory, which
+    // holds `i` just above the array
+    let mut header = read_header(&bytecode).unwrap();
+    let mut flags = AmxFlags::from_bits(header.flags);
+    flags.no_checks = true;
+    header.flags = flags.to_bits();
+    let mut unchecked = bytecode.clone();
+    let header_bytes = write_header(&header);
+    unchecked[..header_bytes.len()].copy_from_slice(&header_bytes);
+    assert_eq!(run(&unchecked).unwrap(), 5);
+}
+
+#[test]
+fn unchecked_build_omits_bounds_instructions() {
+    let source = "main() { new arr[5]; arr[4] = 9; return arr[4]; }";
+    let ast = Parser::new(source).unwrap().parse_program().unwrap();
+
+    let count_bounds = |no_checks: bool| {
+        let mut codegen = CodeGenerator::new();
+        codegen.set_no_checks(no_checks);
+        let bytecode = codegen.generate(&ast).unwrap();
+        let header = read_header(&bytecode).unwrap();
+        assert_eq!(AmxFlags::from_bits(header.flags).no_checks, no_checks);
+        assert_eq!(run(&bytecode).unwrap(), 9);
+        decode(&bytecode[header.cod as usize..header.dat as usize])
+            .iter()
+            .filter(|instruction| instruction.opcode == Opcode::Bounds)
+            .count()
+    };
+
+    assert_eq!(count_bounds(false), 2);
+    assert_eq!(count_bounds(true), 0);
+}
+
+#[test]
+fn sizeof_yields_declared_cell_count() {
+    let source = r#"
+        new g;
+        main() {
+            new a[10];
+            return sizeof a * 100 + sizeof(g);
+        }
+    "#;
+
+    let bytecode = compile(source).expect("compile should succeed");
+    assert_eq!(run(&bytecode).unwrap(), 1001);
+}
+
+/// Decode a plain code section into its instructions
+fn decode(code: &[u8]) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+    while offset < code.len() {
+        let instruction = Instruction::from_bytes(code, offset).unwrap();
+        offset += instruction.size();
+        instructions.push(instruction);
+    }
+    instructions
+}
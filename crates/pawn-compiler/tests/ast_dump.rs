@@ -0,0 +1,35 @@
+use pawn_compiler::{Parser, format_ast};
+
+fn dump(source: &str) -> String {
+    let mut parser = Parser::new(source).unwrap();
+    format_ast(&parser.parse_program().unwrap())
+}
+
+#[test]
+fn dump_nests_call_inside_function() {
+    let tree = dump("main() { printf(\"hi\"); }");
+    let lines: Vec<&str> = tree.lines().collect();
+
+    let function = lines
+        .iter()
+        .position(|line| line.trim_start().starts_with("Function main"))
+        .expect("missing Function main line");
+    let call = lines
+        .iter()
+        .position(|line| line.trim_start().starts_with("FunctionCall printf"))
+        .expect("missing FunctionCall printf line");
+
+    let indent = |line: &str| line.len() - line.trim_start().len();
+    assert!(call > function);
+    assert!(indent(lines[call]) > indent(lines[function]));
+    assert!(tree.contains("String \"hi\""));
+}
+
+#[test]
+fn dump_shows_operator_names_and_elides_empty_fields() {
+    let tree = dump("main() { new x = 1 + 2; return; }");
+    assert!(tree.contains("BinaryOp Add"));
+    assert!(tree.contains("init"));
+    assert!(!tree.contains("size"));
+    assert!(!tree.contains("None"));
+}
@@ -0,0 +1,75 @@
+use pawn_compiler::{Config, Severity, load_config};
+
+#[test]
+fn config_round_trips_through_json() {
+    let mut config = Config::default();
+    config.files.include_globs = vec!["src/**/*.pwn".into(), "include/*.inc".into()];
+    config.files.exclude_globs = vec!["**/target".into(), "vendor/**".into()];
+    config.formatter.enabled = true;
+    config.formatter.use_tabs = true;
+    config.formatter.indent_width = 2;
+    config.formatter.line_width = 120;
+    config.formatter.max_consecutive_blank_lines = 2;
+    config.linter.enabled = true;
+    config.linter.check_trailing_whitespace = true;
+    config.linter.check_unused_variables = true;
+    config.linter.check_magic_numbers = true;
+    config.linter.magic_number_allowlist = vec![2, 100];
+    config
+        .linter
+        .severities
+        .insert("suspicious.unusedVariable".into(), Severity::Error);
+    config.pawn.globals = vec!["printf".into(), "random".into()];
+    config.pawn.library = true;
+    config.codegen.optimize = true;
+    config.codegen.cell_alignment = 8;
+
+    let json = config.to_json();
+    assert!(json.contains("\"!vendor/**\""));
+    assert_eq!(Config::from_json(&json).unwrap(), config);
+}
+
+#[test]
+fn defaults_match_an_empty_file_and_compact_json_is_read() {
+    assert_eq!(Config::from_json("{}").unwrap(), Config::default());
+    assert_eq!(
+        Config::from_json(&Config::default().to_json()).unwrap(),
+        Config::default()
+    );
+
+    let path = std::env::temp_dir().join(format!("pwnrust-{}-compact.json", std::process::id()));
+    std::fs::write(
+        &path,
+        r#"{"linter":{"enabled":true},"formatter":{"lineWidth":80},"pawn":{"globals":["SetTimer"]}}"#,
+    )
+    .unwrap();
+    let config = load_config(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(config.linter.enabled);
+    assert_eq!(config.formatter.line_width, 80);
+    assert_eq!(config.pawn.globals, vec!["SetTimer"]);
+}
+
+#[test]
+fn repository_config_reads_structurally() {
+    let text = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/../../pwnrust.json"))
+        .unwrap();
+    let config = Config::from_json(&text).unwrap();
+
+    assert_eq!(config.files.include_globs, vec!["**/*.{p,pwn,inc}"]);
+    assert!(
+        config
+            .files
+            .exclude_globs
+            .contains(&"**/target".to_string())
+    );
+    assert!(config.formatter.enabled && config.linter.enabled);
+    assert!(!config.linter.check_missing_braces && !config.formatter.add_missing_braces);
+    assert!(!config.linter.check_magic_numbers);
+    assert_eq!(
+        config.linter.severity_for("suspicious.duplicateInclude"),
+        Severity::Error
+    );
+
+    assert!(Config::from_json("{ not json").is_err());
+}
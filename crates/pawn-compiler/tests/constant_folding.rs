@@ -0,0 +1,105 @@
+use pawn_compiler::{
+    AstNode, BinaryOperator, UnaryOperator, eval_constant, fold_constants, parse_expression,
+};
+
+fn int(value: i32) -> Box<AstNode> {
+    Box::new(AstNode::Integer(value))
+}
+
+fn binary(left: Box<AstNode>, operator: BinaryOperator, right: Box<AstNode>) -> Box<AstNode> {
+    Box::new(AstNode::BinaryOp {
+        left,
+        operator,
+        right,
+    })
+}
+
+#[test]
+fn folds_nested_integer_arithmetic() {
+    let expr = binary(
+        int(1),
+        BinaryOperator::Add,
+        binary(int(2), BinaryOperator::Multiply, int(3)),
+    );
+    assert_eq!(fold_constants(*expr), AstNode::Integer(7));
+}
+
+#[test]
+fn integer_overflow_wraps() {
+    let expr = binary(int(i32::MAX), BinaryOperator::Add, int(1));
+    assert_eq!(fold_constants(*expr), AstNode::Integer(i32::MIN));
+}
+
+#[test]
+fn division_floors_toward_negative_infinity() {
+    let divide = |a, operator, b| eval_constant(&binary(int(a), operator, int(b)));
+    assert_eq!(divide(-7, BinaryOperator::Divide, 2), Some(-4));
+    assert_eq!(divide(7, BinaryOperator::Divide, -2), Some(-4));
+    assert_eq!(divide(-8, BinaryOperator::Modulo, 3), Some(1));
+    assert_eq!(divide(8, BinaryOperator::Modulo, -3), Some(-1));
+    assert_eq!(divide(-6, BinaryOperator::Modulo, 3), Some(0));
+}
+
+#[test]
+fn division_by_zero_is_left_for_run_time() {
+    let expr = *binary(int(1), BinaryOperator::Divide, int(0));
+    assert_eq!(fold_constants(expr.clone()), expr);
+}
+
+#[test]
+fn float_operators_are_not_folded() {
+    let expr = AstNode::BinaryOp {
+        left: Box::new(AstNode::UnaryOp {
+            operator: UnaryOperator::Minus,
+            operand: Box::new(AstNode::Float(1.5)),
+        }),
+        operator: BinaryOperator::Multiply,
+        right: Box::new(AstNode::Float(2.0)),
+    };
+    // Only the negated literal folds
+    assert_eq!(
+        fold_constants(expr),
+        *binary(
+            Box::new(AstNode::Float(-1.5)),
+            BinaryOperator::Multiply,
+            Box::new(AstNode::Float(2.0))
+        )
+    );
+}
+
+#[test]
+fn parses_and_evaluates_standalone_expression() {
+    let expr = parse_expression("1 + 2 * 3").unwrap();
+    assert_eq!(
+        expr,
+        *binary(
+            int(1),
+            BinaryOperator::Add,
+            binary(int(2), BinaryOperator::Multiply, int(3))
+        )
+    );
+    assert_eq!(eval_constant(&expr), Some(7));
+}
+
+#[test]
+fn expression_with_variable_is_not_constant() {
+    let expr = parse_expression("a + 1").unwrap();
+    assert!(matches!(expr, AstNode::BinaryOp { .. }));
+    assert_eq!(eval_constant(&expr), None);
+
+    assert!(parse_expression("1 + 2 )").is_err());
+}
+
+#[test]
+fn negative_literals_parse_as_literals() {
+    assert_eq!(parse_expression("-5").unwrap(), AstNode::Integer(-5));
+    assert_eq!(parse_expression("+5").unwrap(), AstNode::Integer(5));
+    assert_eq!(parse_expression("-2.5").unwrap(), AstNode::Float(-2.5));
+    assert_eq!(
+        parse_expression("-x").unwrap(),
+        AstNode::UnaryOp {
+            operator: UnaryOperator::Minus,
+            operand: Box::new(AstNode::Identifier("x".to_string())),
+        }
+    );
+}
@@ -0,0 +1,84 @@
+use pawn_amx::instructions::{Instruction, Opcode};
+use pawn_amx::{AMX_EXEC_MAIN, AmxError, AmxFlags, AmxRuntime, AmxRuntimeError, Cell, read_header};
+use pawn_compiler::{CodeGenerator, Parser, compile};
+
+const SOURCE: &str = "main() { new x = 20;\n    return x + 22; }";
+
+fn compile_with_debug(source: &str) -> Vec<u8> {
+    let ast = Parser::new(source).unwrap().parse_program().unwrap();
+    let mut codegen = CodeGenerator::new();
+    codegen.set_debug(true);
+    codegen.generate(&ast).unwrap()
+}
+
+#[test]
+fn line_table_maps_statements_to_source_lines() {
+    let bytecode = compile_with_debug(SOURCE);
+    assert!(AmxFlags::from_bits(read_header(&bytecode).unwrap().flags).debug);
+
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+    let lines = runtime.debug_lines().to_vec();
+    assert_eq!(lines.first(), Some(&(0, 1)));
+    let (second_statement, _) = *lines.iter().find(|&&(_, line)| line == 2).unwrap();
+    assert!(second_statement > 0);
+
+    // The program halts inside the return statement on line 2
+    assert_eq!(runtime.exec(AMX_EXEC_MAIN).unwrap(), 42);
+    assert!(runtime.amx.cip >= second_statement);
+    assert_eq!(runtime.current_line(), Some(2));
+}
+
+#[test]
+fn debug_info_is_off_by_default() {
+    let bytecode = compile(SOURCE).unwrap();
+    assert!(!AmxFlags::from_bits(read_header(&bytecode).unwrap().flags).debug);
+
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+    assert!(runtime.debug_lines().is_empty());
+    assert_eq!(runtime.current_line(), None);
+}
+
+#[test]
+fn runtime_error_reports_instruction_and_line() {
+    let bytecode = compile_with_debug("main() { new x = 0;\n    return 10 / x; }");
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+
+    let error = runtime.exec(AMX_EXEC_MAIN).unwrap_err();
+    let AmxRuntimeError::Runtime { kind, cip, line } = &error else {
+        panic!("expected a located runtime error, got {:?}", error);
+    };
+    assert!(matches!(
+        **kind,
+        AmxRuntimeError::AmxError(AmxError::Divide)
+    ));
+    assert_eq!(*line, Some(2));
+
+    // The error points at the division itself
+    let cod = read_header(&bytecode).unwrap().cod;
+    let instruction = Instruction::from_bytes(&bytecode, (cod as Cell + cip) as usize).unwrap();
+    assert_eq!(instruction.opcode, Opcode::Sdiv);
+    assert!(error.to_string().starts_with("runtime error at line 2: "));
+}
+
+#[test]
+fn failed_assert_reports_its_line() {
+    let bytecode = compile_with_debug("main() {\n    assert 1;\n    assert 0;\n    return 1; }");
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+
+    let error = runtime.exec(AMX_EXEC_MAIN).unwrap_err();
+    assert!(matches!(error.kind(), AmxRuntimeError::AssertionFailed));
+    assert_eq!(runtime.current_line(), Some(3));
+    assert_eq!(AmxError::from(error), AmxError::Assert);
+}
+
+#[test]
+fn passing_assert_continues() {
+    let bytecode = compile("main() { new x = 2; assert x == 2; return x; }").unwrap();
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+    assert_eq!(runtime.exec(AMX_EXEC_MAIN).unwrap(), 2);
+}
@@ -0,0 +1,125 @@
+use pawn_compiler::{
+    CompilerError, Config, Parser, SymbolTableVisitor, compile_all, compile_all_with_config,
+    render_diagnostic,
+};
+
+#[test]
+fn reports_every_undefined_function() {
+    let source = r#"
+        main() {
+            first_missing();
+            second_missing(1, 2);
+        }
+    "#;
+
+    let errors = compile_all(source).expect_err("compile should fail");
+    let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+    assert_eq!(errors.len(), 2, "{:?}", messages);
+    assert!(messages[0].contains("first_missing"));
+    assert!(messages[1].contains("second_missing"));
+}
+
+#[test]
+fn recovers_after_syntax_error() {
+    let source = r#"
+        main() {
+            printf("ok";
+            missing();
+        }
+    "#;
+
+    let errors = compile_all(source).expect_err("compile should fail");
+    assert!(matches!(errors[0].kind(), CompilerError::ParserError(_)));
+    assert!(errors.iter().any(|e| e.to_string().contains("missing")));
+}
+
+#[test]
+fn functions_may_be_called_before_definition() {
+    let source = r#"
+        main() {
+            helper();
+        }
+
+        helper() {
+            printf("helper");
+        }
+    "#;
+
+    let ast = Parser::new(source).unwrap().parse_program().unwrap();
+    let mut visitor = SymbolTableVisitor::new();
+    assert!(visitor.analyze(&ast).is_ok());
+}
+
+#[test]
+fn deeply_nested_expression_fails_cleanly() {
+    let depth = 10_000;
+    let source = format!(
+        "main() {{ return {}1{}; }}",
+        "(".repeat(depth),
+        ")".repeat(depth)
+    );
+
+    let error = Parser::new(&source)
+        .unwrap()
+        .parse_program()
+        .expect_err("nesting should be rejected");
+    assert!(error.to_string().contains("expression nesting too deep"));
+
+    let mut parser = Parser::new("main() { return ((((1)))); }").unwrap();
+    parser.set_max_depth(4);
+    assert!(parser.parse_program().is_err());
+}
+
+#[test]
+fn renders_parser_error_with_caret() {
+    let source = "main() {\n    printf(\"ok\";\n}\n";
+
+    let errors = compile_all(source).expect_err("compile should fail");
+    assert_eq!(errors[0].span(), Some((2, 16, 1)));
+
+    let rendered = render_diagnostic(source, &errors[0]);
+    let expected = concat!(
+        "error: Parser error: Expected Comma, found Semicolon\n",
+        "  --> 2:16\n",
+        "  |\n",
+        "2 |     printf(\"ok\";\n",
+        "  |                ^\n",
+    );
+    assert_eq!(rendered, expected);
+}
+
+#[test]
+fn redeclaration_names_both_lines() {
+    let source = "main()\n{\n    new x;\n    x = 1;\n    new x;\n}\n";
+    let errors = compile_all(source).unwrap_err();
+    let message = errors
+        .iter()
+        .map(ToString::to_string)
+        .find(|message| message.contains("already declared"))
+        .expect("redeclaration should be reported");
+    assert!(message.starts_with("5:"), "{}", message);
+    assert!(message.contains("first at line 3"), "{}", message);
+}
+
+#[test]
+fn programs_need_an_entry_point_unless_compiled_as_libraries() {
+    assert!(compile_all("main() { return 1; }").is_ok());
+    assert!(compile_all("public OnInit() { return 1; }").is_ok());
+
+    let library = "native print(const s[]);\nhelper(x) { return x * 2; }";
+    let errors = compile_all(library).expect_err("compile should fail");
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(errors[0].kind(), CompilerError::SemanticError(_)));
+    assert!(errors[0].to_string().contains("no entry point"));
+
+    let errors = compile_all("main(argc) { return argc; }").expect_err("compile should fail");
+    assert!(
+        errors[0]
+            .to_string()
+            .contains("main() must not take parameters")
+    );
+
+    let mut config = Config::default();
+    config.pawn.library = true;
+    assert!(compile_all_with_config(library, &config).is_ok());
+}
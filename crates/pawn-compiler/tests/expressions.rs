@@ -0,0 +1,203 @@
+use pawn_amx::{AMX_EXEC_MAIN, AmxRuntime, Cell};
+use pawn_compiler::compile;
+
+/// Compile and run a program, returning the value main returns
+fn run(source: &str) -> Cell {
+    let bytecode = compile(source).expect("compile should succeed");
+    let mut runtime = AmxRuntime::new();
+    runtime
+        .init(&bytecode)
+        .expect("runtime init should succeed");
+    runtime.exec(AMX_EXEC_MAIN).expect("exec should succeed")
+}
+
+/// Compile `main() { return <expr>; }` and return the result
+fn eval(expression: &str) -> Cell {
+    run(&format!("main() {{ return {}; }}", expression))
+}
+
+#[test]
+fn ternary_selects_branch() {
+    assert_eq!(eval("(1 ? 10 : 20) + (0 ? 1 : 2)"), 12);
+}
+
+#[test]
+fn nested_ternary_associates_right() {
+    assert_eq!(eval("0 ? 1 : 0 ? 2 : 3"), 3);
+    assert_eq!(eval("0 ? 1 : 1 ? 2 : 3"), 2);
+    assert_eq!(eval("1 ? 1 : 0 ? 2 : 3"), 1);
+}
+
+#[test]
+fn if_else_statement_picks_branch() {
+    let source = r#"
+        main() {
+            new x = 0;
+            if (x == 0) x = 5; else x = 7;
+            if (x == 7) {
+                x = 100;
+            }
+            return x;
+        }
+    "#;
+    assert_eq!(run(source), 5);
+}
+
+#[test]
+fn binary_operands_keep_their_order() {
+    assert_eq!(eval("10 - 3"), 7);
+    assert_eq!(eval("12 / 4"), 3);
+    assert_eq!(eval("2 < 3"), 1);
+    assert_eq!(eval("3 >= 4"), 0);
+}
+
+#[test]
+fn compound_assignment_updates_variable() {
+    assert_eq!(run("main() { new x = 5; x += 3; return x; }"), 8);
+    assert_eq!(run("new g = 10; main() { g -= 3; g *= 2; return g; }"), 14);
+    assert_eq!(run("main() { new x = 9; return x /= 3; }"), 3);
+}
+
+#[test]
+fn compound_assignment_into_array_element() {
+    let source = r#"
+        main() {
+            new arr[3];
+            arr[1] = 4;
+            arr[1] *= 5;
+            arr[2] -= arr[1];
+            return arr[1] * 10 + arr[2];
+        }
+    "#;
+    assert_eq!(run(source), 180);
+}
+
+#[test]
+fn compound_assignment_evaluates_index_once() {
+    let source = r#"
+        main() {
+            new arr[3];
+            new i = 0;
+            arr[i = i + 1] += 5;
+            return i * 10 + arr[1];
+        }
+    "#;
+    assert_eq!(run(source), 15);
+}
+
+#[test]
+fn logical_and_bitwise_not_at_run_time() {
+    // Variables keep the operators from being folded at compile time
+    let not = |value: i32, operator: &str| {
+        run(&format!(
+            "main() {{ new x = {}; return {}x; }}",
+            value, operator
+        ))
+    };
+    assert_eq!(not(0, "!"), 1);
+    assert_eq!(not(5, "!"), 0);
+    assert_eq!(not(0, "~"), -1);
+    assert_eq!(not(5, "-"), -5);
+}
+
+#[test]
+fn logical_operators_short_circuit() {
+    // The division by zero would fail if the right operand were evaluated
+    assert_eq!(eval("0 && 1 / 0"), 0);
+    assert_eq!(eval("1 || 1 / 0"), 1);
+    assert_eq!(eval("(2 && 3) + (0 || 4)"), 2);
+
+    let source = r#"
+        main() {
+            new x = 0;
+            new skipped = 0 && (x = 5);
+            new taken = 1 && (x = 7);
+            return x * 10 + skipped + taken;
+        }
+    "#;
+    assert_eq!(run(source), 71);
+}
+
+#[test]
+fn postfix_increment_yields_original_value() {
+    let source = "main() { new x = 5; new y = x++; return y * 100 + x; }";
+    assert_eq!(run(source), 506);
+}
+
+#[test]
+fn prefix_increment_yields_new_value() {
+    let source = "main() { new x = 5; new y = x++; new z = ++x; return z * 100 + y; }";
+    assert_eq!(run(source), 705);
+}
+
+#[test]
+fn decrement_works_on_array_elements() {
+    let source = "main() { new a[2]; a[1] = 3; new old = a[1]--; --a[1]; return old * 10 + a[1]; }";
+    assert_eq!(run(source), 31);
+}
+
+#[test]
+fn comparison_results_combine_arithmetically() {
+    assert_eq!(eval("(1 < 2) + (3 < 4)"), 2);
+    let source = r#"
+        main() {
+            new a = 1;
+            new b = 2;
+            new c = 4;
+            new d = 3;
+            return (a < b) * 10 + (c < d) + ((a != b) - (d >= c));
+        }
+    "#;
+    assert_eq!(run(source), 11);
+}
+
+#[test]
+fn division_of_negative_operands_floors() {
+    let source = r#"
+        main() {
+            new x = -8, y = -7;
+            return (x % 3) * 100 + y / 2;
+        }
+    "#;
+    assert_eq!(run(source), 100 - 4);
+    assert_eq!(eval("-7 / 2"), -4);
+    assert_eq!(eval("-8 % 3"), 1);
+}
+
+#[test]
+fn bitwise_shift_and_remainder_operators_run_on_variables() {
+    let source = r#"
+        main() {
+            new a = 29, b = 6, n = 3;
+            new r = (a % b) * 1000000;
+            r += (a & b) * 100000 + (a | b) * 1000 + (a ^ b) * 10;
+            r += (a << n) - (a >> 1) - (-a >> 1);
+            return r;
+        }
+    "#;
+    // 5, 4, 31, 27, then 232 - 14 + 15
+    assert_eq!(run(source), 5_000_000 + 400_000 + 31_000 + 270 + 233);
+
+    let compound = r#"
+        main() {
+            new x = 29;
+            x %= 10;
+            x <<= 4;
+            x |= 3;
+            x &= 126;
+            x ^= 1;
+            x >>= 2;
+            return x;
+        }
+    "#;
+    // 9, 144, 147, 18, 19, 4
+    assert_eq!(run(compound), 4);
+}
+
+#[test]
+fn bitwise_operators_bind_between_logical_and_equality() {
+    assert_eq!(eval("1 | 2 ^ 3 & 1"), 1 | (2 ^ (3 & 1)));
+    assert_eq!(eval("1 << 2 + 1"), 8);
+    assert_eq!(eval("6 & 3 == 3"), 0);
+    assert_eq!(eval("0 && 1 | 1"), 0);
+}
@@ -0,0 +1,140 @@
+use pawn_compiler::{Config, format_source, visual_width};
+
+/// Format `source`, checking that formatting the result again changes nothing
+fn format(source: &str, cfg: &Config) -> String {
+    let out = format_source(source, cfg);
+    assert_eq!(
+        format_source(&out, cfg),
+        out,
+        "formatting is not idempotent"
+    );
+    out
+}
+
+fn formatter_config() -> Config {
+    let mut cfg = Config::default();
+    cfg.formatter.enabled = true;
+    cfg.formatter.indent_width = 4;
+    cfg
+}
+
+#[test]
+fn reindents_nested_blocks() {
+    let source = "main()\n{\n\tif (x)\n  {\n\t\t  printf(\"deep\");\n   }\n}\n";
+    let expected = "main()\n{\n    if (x)\n    {\n        printf(\"deep\");\n    }\n}\n";
+    assert_eq!(format(source, &formatter_config()), expected);
+}
+
+#[test]
+fn braces_in_strings_and_comments_do_not_change_depth() {
+    let source = "main()\n{\nprintf(\"{\"); // {\n/* {\n   } } */\nreturn 0;\n}\n";
+    let expected = "main()\n{\n    printf(\"{\"); // {\n    /* {\n   } } */\n    return 0;\n}\n";
+    assert_eq!(format(source, &formatter_config()), expected);
+}
+
+#[test]
+fn indents_with_tabs() {
+    let mut cfg = formatter_config();
+    cfg.formatter.use_tabs = true;
+    assert_eq!(
+        format("main()\n{\n    return 0;\n}\n", &cfg),
+        "main()\n{\n\treturn 0;\n}\n"
+    );
+}
+
+fn spacing_config() -> Config {
+    let mut cfg = Config::default();
+    cfg.formatter.enabled = true;
+    cfg.formatter.space_around_operators = true;
+    cfg.formatter.indent_width = 0;
+    cfg
+}
+
+#[test]
+fn spaces_binary_operators() {
+    assert_eq!(format("x=1+2*3;\n", &spacing_config()), "x = 1 + 2 * 3;\n");
+    assert_eq!(
+        format("    if (a==b&&c<=d) y+=arr[i]-1;\n", &spacing_config()),
+        "    if (a == b && c <= d) y += arr[i] - 1;\n"
+    );
+}
+
+#[test]
+fn leaves_state_suffixes_alone() {
+    for line in [
+        "public OnTick() <auto:idle> {\n",
+        "OnTick() <idle, walking>\n",
+        "forward Float:Speed() <>;\n",
+    ] {
+        assert_eq!(format(line, &spacing_config()), line);
+    }
+    assert_eq!(
+        format("x=f(a)<b;\nif (f(a)<b) {}\n", &spacing_config()),
+        "x = f(a) < b;\nif (f(a) < b) {}\n"
+    );
+}
+
+#[test]
+fn leaves_unary_operators_alone() {
+    assert_eq!(
+        format("x=-1;\ni++;\ny = !z;\nf(&a, -b);\n", &spacing_config()),
+        "x = -1;\ni++;\ny = !z;\nf(&a, -b);\n"
+    );
+}
+
+#[test]
+fn preserves_strings_and_comments_verbatim() {
+    assert_eq!(
+        format("printf(\"a=b\");  // x=y\n", &spacing_config()),
+        "printf(\"a=b\");  // x=y\n"
+    );
+    assert_eq!(
+        format("/* a=b\nc=d */\n", &spacing_config()),
+        "/* a=b\nc=d */\n"
+    );
+}
+
+#[test]
+fn collapses_runs_of_blank_lines() {
+    let mut cfg = formatter_config();
+    cfg.formatter.max_consecutive_blank_lines = 1;
+    let source = "main()\n{\n}\n\n\n\nfoo()\n{\n}\n";
+    assert_eq!(format(source, &cfg), "main()\n{\n}\n\nfoo()\n{\n}\n");
+}
+
+#[test]
+fn trailing_blank_lines_become_one_newline() {
+    let mut cfg = formatter_config();
+    cfg.formatter.max_consecutive_blank_lines = 1;
+    cfg.formatter.insert_final_newline = true;
+    assert_eq!(format("main()\n{\n}\n\n\n", &cfg), "main()\n{\n}\n");
+}
+
+#[test]
+fn blank_lines_in_comments_are_kept() {
+    let mut cfg = formatter_config();
+    cfg.formatter.max_consecutive_blank_lines = 1;
+    let source = "/* notes\n\n\n\nend */\nmain()\n{\n}\n";
+    assert_eq!(format(source, &cfg), source);
+}
+
+#[test]
+fn formatting_braced_source_is_idempotent() {
+    let mut cfg = formatter_config();
+    cfg.formatter.add_missing_braces = true;
+    let source = "main()\n{\n    if (x)\n    {\n        printf(\"x\");\n    }\n}\n\nstock Add(a, b)\n{\n    return a + b;\n}\n";
+    let once = format(source, &cfg);
+    let twice = format(&once, &cfg);
+    assert_eq!(once, source);
+    assert_eq!(twice, source);
+}
+
+#[test]
+fn visual_width_advances_tabs_to_the_next_stop() {
+    assert_eq!(visual_width("\t", 2), 2);
+    assert_eq!(visual_width(" \t", 2), 2);
+    assert_eq!(visual_width("  \t ", 2), 5);
+    assert_eq!(visual_width("\t\t", 8), 16);
+    assert_eq!(visual_width("   \t", 8), 8);
+    assert_eq!(visual_width("\t  x", 8), 11);
+}
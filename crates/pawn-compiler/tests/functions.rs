@@ -0,0 +1,176 @@
+use pawn_amx::{AMX_EXEC_MAIN, AmxRuntime, AmxRuntimeError};
+use pawn_compiler::{AstNode, CompilerError, FunctionStates, Parser, compile, compile_all};
+
+fn runtime_for(source: &str) -> AmxRuntime {
+    let bytecode = compile(source).expect("compile should succeed");
+    let mut runtime = AmxRuntime::new();
+    runtime
+        .init(&bytecode)
+        .expect("runtime init should succeed");
+    runtime
+}
+
+#[test]
+fn host_calls_public_with_arguments() {
+    let mut runtime = runtime_for("public twice(x) return x*2;");
+    let stk = runtime.amx.stk;
+
+    assert_eq!(runtime.call_public("twice", &[21]).unwrap(), 42);
+    assert_eq!(runtime.amx.stk, stk);
+    assert_eq!(runtime.call_public("twice", &[-4]).unwrap(), -8);
+}
+
+#[test]
+fn public_sees_arguments_in_order_alongside_locals() {
+    let source = r#"
+        main() {
+            return 1;
+        }
+
+        public combine(a, b) {
+            new scaled = a * 10;
+            return scaled + b;
+        }
+    "#;
+    let mut runtime = runtime_for(source);
+
+    assert_eq!(runtime.exec(AMX_EXEC_MAIN).unwrap(), 1);
+    assert_eq!(runtime.call_public("combine", &[4, 2]).unwrap(), 42);
+    assert!(matches!(
+        runtime.call_public("missing", &[]),
+        Err(AmxRuntimeError::PublicNotFound(_))
+    ));
+}
+
+#[test]
+fn const_parameter_cannot_be_modified() {
+    for body in ["value = 2;", "value += 2;", "value++;", "list[0] = 1;"] {
+        let source = format!(
+            "change(const value, const list[]) {{ {} return value + list[0]; }}\nmain() {{ new a[1]; return change(1, a); }}",
+            body
+        );
+        let errors = compile_all(&source).unwrap_err();
+        assert!(
+            matches!(&errors[..], [CompilerError::TypeError(msg)] if msg.contains("const")),
+            "{}: {:?}",
+            body,
+            errors
+        );
+    }
+}
+
+#[test]
+fn array_parameters_are_passed_by_address() {
+    let source = r#"
+        change(const value, const list[]) { return value + list[0]; }
+        sum(const list[3]) { return list[0] + list[1] + list[2]; }
+        fill(list[], n) {
+            for (new i = 0; i < n; i++) list[i] = i + 1;
+            return sum(list);
+        }
+        main() {
+            new a[1], b[3];
+            a[0] = 41;
+            return change(1, a) * 100 + fill(b, 3) * 10 + b[2];
+        }
+    "#;
+    let mut runtime = runtime_for(source);
+    assert_eq!(runtime.exec(AMX_EXEC_MAIN).unwrap(), 4263);
+}
+
+#[test]
+fn const_parameter_can_be_read() {
+    let mut runtime = runtime_for("public twice(const value) return value * 2;");
+    assert_eq!(runtime.call_public("twice", &[21]).unwrap(), 42);
+}
+
+#[test]
+fn native_receives_constant_and_local_address() {
+    let source = r#"
+        native Probe(value, &cell);
+
+        main() {
+            new x = 5;
+            Probe(42, x);
+            return x;
+        }
+    "#;
+    let mut runtime = runtime_for(source);
+    runtime.register_native_with_context("Probe".to_string(), |ctx, params| {
+        assert_eq!(params[1], 42);
+        assert_eq!(ctx.read_cell(params[2]).unwrap(), 5);
+        ctx.write_cell(params[2], 7).unwrap();
+        0
+    });
+
+    assert_eq!(runtime.exec(AMX_EXEC_MAIN).unwrap(), 7);
+}
+
+#[test]
+fn reference_argument_must_be_a_variable() {
+    let source = "native Probe(&cell);\nmain() { Probe(1 + 2); }";
+    assert!(compile(source).is_err());
+}
+
+#[test]
+fn parses_state_suffixes_and_transitions() {
+    let source = r#"
+        OnTick() <game:running, paused> {
+            state game:stopped;
+        }
+
+        OnTick() <> {
+            state idle;
+        }
+    "#;
+    let ast = Parser::new(source).unwrap().parse_program().unwrap();
+    let AstNode::Program(items) = ast.inner() else {
+        panic!("expected a program");
+    };
+    let functions: Vec<_> = items
+        .iter()
+        .filter_map(|item| match item.inner() {
+            AstNode::Function { states, body, .. } => Some((states.clone(), body[0].inner())),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(
+        functions[0].0,
+        Some(FunctionStates {
+            automaton: Some("game".to_string()),
+            states: vec!["running".to_string(), "paused".to_string()],
+        })
+    );
+    assert_eq!(
+        functions[0].1,
+        &AstNode::StateChange {
+            automaton: Some("game".to_string()),
+            state: "stopped".to_string(),
+        }
+    );
+    assert_eq!(
+        functions[1].0,
+        Some(FunctionStates {
+            automaton: None,
+            states: Vec::new(),
+        })
+    );
+    assert_eq!(
+        functions[1].1,
+        &AstNode::StateChange {
+            automaton: None,
+            state: "idle".to_string(),
+        }
+    );
+}
+
+#[test]
+fn states_are_rejected_by_code_generation() {
+    let error = compile("main() <run> { state stop; }").unwrap_err();
+    assert!(
+        error.to_string().contains("States are not yet supported"),
+        "{}",
+        error
+    );
+}
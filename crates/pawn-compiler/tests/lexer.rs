@@ -0,0 +1,237 @@
+use pawn_compiler::{CompilerError, Lexer, Token};
+
+#[test]
+fn tokens_iterator_ends_with_eof() {
+    let tokens = Lexer::new("main() { return 1; }")
+        .tokens()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    // main ( ) { return 1 ; } EOF
+    assert_eq!(tokens.len(), 9);
+    assert_eq!(tokens[tokens.len() - 2], Token::RightBrace);
+    assert_eq!(tokens.last(), Some(&Token::EndOfFile));
+}
+
+#[test]
+fn tokens_iterator_composes_with_filters() {
+    let significant = Lexer::new("// note\nx = 1;\n")
+        .tokens()
+        .filter(|token| !matches!(token, Ok(Token::Comment(_) | Token::Newline)))
+        .count();
+    // x = 1 ; EOF
+    assert_eq!(significant, 5);
+}
+
+#[test]
+fn byte_order_mark_is_skipped() {
+    let tokens = Lexer::new("\u{feff}foo();")
+        .tokens()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(tokens[0], Token::Identifier("foo".to_string()));
+}
+
+#[test]
+fn non_ascii_identifier_character_is_explained() {
+    let errors: Vec<String> = Lexer::new("new café = 1;\nx = $;")
+        .tokens()
+        .filter_map(|token| token.err().map(|e| e.to_string()))
+        .collect();
+
+    assert_eq!(errors.len(), 2);
+    assert!(errors[0].contains("'é' (U+00E9) at byte 7"));
+    assert!(errors[0].contains("identifier"));
+    assert!(errors[1].contains("Unexpected character '$' (U+0024) at byte 19"));
+}
+
+#[test]
+fn backslash_continues_directive_onto_next_line() {
+    let mut lexer = Lexer::new("#define SUM(%0,%1) \\\n    (%0 + %1)\nx;");
+
+    assert_eq!(
+        lexer.next_token().unwrap(),
+        Token::Comment("define SUM(%0,%1)     (%0 + %1)".to_string())
+    );
+    assert_eq!(lexer.next_token().unwrap(), Token::Newline);
+    assert_eq!(
+        lexer.next_token().unwrap(),
+        Token::Identifier("x".to_string())
+    );
+    assert_eq!((lexer.token_line(), lexer.token_column()), (3, 1));
+}
+
+#[test]
+fn backslash_continues_string_literal() {
+    let tokens = Lexer::new("\"hello \\\nworld\"")
+        .tokens()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(tokens[0], Token::String("hello world".to_string()));
+}
+
+#[test]
+fn escaped_backslash_at_end_of_line_does_not_continue_string() {
+    // `\\` is an escaped backslash, which leaves the line break in the string
+    let (tokens, errors) = Lexer::new("\"one \\\\\ntwo\"").lex_all();
+    assert!(errors[0].to_string().contains("starting on line 1"));
+    assert_ne!(
+        tokens.first(),
+        Some(&Token::String("one \\two".to_string()))
+    );
+
+    // A third backslash continues the line after the escaped one
+    let (tokens, errors) = Lexer::new("\"one \\\\\\\ntwo\"").lex_all();
+    assert!(errors.is_empty());
+    assert_eq!(tokens, vec![Token::String("one \\two".to_string())]);
+}
+
+#[test]
+fn block_comments_nest_only_when_enabled() {
+    let source = "/* outer /* inner */ still outer */ x";
+
+    let mut nested = Lexer::new(source);
+    nested.set_nested_block_comments(true);
+    assert_eq!(
+        nested.next_token().unwrap(),
+        Token::Comment(" outer /* inner */ still outer ".to_string())
+    );
+    assert_eq!(
+        nested.next_token().unwrap(),
+        Token::Identifier("x".to_string())
+    );
+
+    // Pawn ends the comment at the first `*/`
+    let mut flat = Lexer::new(source);
+    assert_eq!(
+        flat.next_token().unwrap(),
+        Token::Comment(" outer /* inner ".to_string())
+    );
+    assert_eq!(
+        flat.next_token().unwrap(),
+        Token::Identifier("still".to_string())
+    );
+}
+
+#[test]
+fn unterminated_block_comment_reports_start_line() {
+    let mut lexer = Lexer::new("x;\n/* never closed\n\n");
+    let error = std::iter::from_fn(|| Some(lexer.next_token()))
+        .find_map(Result::err)
+        .unwrap();
+    assert!(error.to_string().contains("starting on line 2"));
+    assert_eq!(lexer.next_token().unwrap(), Token::EndOfFile);
+}
+
+#[test]
+fn unsigned_literal_wraps_to_cell() {
+    let mut lexer = Lexer::new("4294967295 2147483648");
+    assert_eq!(lexer.next_token().unwrap(), Token::Number(-1));
+    assert_eq!(lexer.next_token().unwrap(), Token::Number(i32::MIN));
+}
+
+#[test]
+fn literal_beyond_32_bits_is_a_range_error() {
+    let error = Lexer::new("99999999999").next_token().unwrap_err();
+    let message = error.to_string();
+    assert!(message.contains("out of range"));
+    assert!(message.contains("-2147483648 to 4294967295"));
+}
+
+#[test]
+fn trivia_tokens_rebuild_the_source() {
+    let source = "// entry point\nmain() {\n    return 1; /* done */\n}\n";
+    let tokens = Lexer::new(source).lex_all_with_trivia().unwrap();
+
+    let (token, span, text) = &tokens[0];
+    assert_eq!(*token, Token::Comment(" entry point".to_string()));
+    assert_eq!((span.line, span.column), (1, 1));
+    assert_eq!(text, "// entry point");
+
+    let (_, span, text) = tokens
+        .iter()
+        .find(|(token, _, _)| matches!(token, Token::Comment(c) if c.contains("done")))
+        .unwrap();
+    assert_eq!((span.line, span.column), (3, 15));
+    assert_eq!(text, "/* done */");
+
+    // Whitespace between tokens is implied by their positions
+    let mut rebuilt = String::new();
+    let mut column = 1;
+    for (token, span, text) in &tokens {
+        rebuilt.push_str(&" ".repeat(span.column - column));
+        rebuilt.push_str(text);
+        column = if *token == Token::Newline {
+            1
+        } else {
+            span.column + text.chars().count()
+        };
+    }
+    assert_eq!(rebuilt, source);
+}
+
+#[test]
+fn lex_all_recovers_from_stray_character() {
+    let (tokens, errors) = Lexer::new("main() { $ printf(); }").lex_all();
+
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Main,
+            Token::LeftParen,
+            Token::RightParen,
+            Token::LeftBrace,
+            Token::Identifier("printf".to_string()),
+            Token::LeftParen,
+            Token::RightParen,
+            Token::Semicolon,
+            Token::RightBrace,
+        ]
+    );
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].span(), Some((1, 10, 1)));
+}
+
+#[test]
+fn extra_keywords_are_recognized_only_when_enabled() {
+    let source = "emit x";
+    let (tokens, _) = Lexer::new(source).lex_all();
+    assert_eq!(tokens[0], Token::Identifier("emit".to_string()));
+
+    let (tokens, _) = Lexer::with_keywords(source, &[("emit", Token::Emit)]).lex_all();
+    assert_eq!(tokens[0], Token::Emit);
+    assert_eq!(tokens[1], Token::Identifier("x".to_string()));
+
+    let mut lexer = Lexer::new("goto");
+    lexer.remove_keyword("goto");
+    assert_eq!(
+        lexer.lex_all().0,
+        vec![Token::Identifier("goto".to_string())]
+    );
+}
+
+#[test]
+fn unterminated_string_at_end_of_file_is_an_error() {
+    let (tokens, errors) = Lexer::new("printf(\"oops").lex_all();
+    assert_eq!(tokens.len(), 2);
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(
+        errors[0].kind(),
+        CompilerError::LexicalError(message)
+            if message == "Unterminated string literal starting on line 1"
+    ));
+}
+
+#[test]
+fn string_broken_by_newline_is_an_error() {
+    let (tokens, errors) = Lexer::new("x = \"one\ntwo\";\ny").lex_all();
+    assert_eq!(errors.len(), 2);
+    assert!(errors[0].to_string().contains("starting on line 1"));
+    // Lexing resumes on the next line, where the stray quote opens another string
+    assert!(errors[1].to_string().contains("starting on line 2"));
+    assert_eq!(tokens.last(), Some(&Token::Identifier("y".to_string())));
+
+    let (tokens, errors) = Lexer::new("\"one \\\ntwo\"").lex_all();
+    assert!(errors.is_empty());
+    assert_eq!(tokens, vec![Token::String("one two".to_string())]);
+}
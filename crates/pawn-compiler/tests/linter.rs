@@ -0,0 +1,262 @@
+use pawn_compiler::{
+    Config, FileLintIssue, LintSummary, Severity, issues_to_json, lint_source, load_config,
+};
+
+fn linter_config() -> Config {
+    let mut cfg = Config::default();
+    cfg.linter.enabled = true;
+    cfg.linter.check_trailing_whitespace = true;
+    cfg
+}
+
+#[test]
+fn json_output_includes_rule_line_and_severity() {
+    let mut cfg = linter_config();
+    cfg.linter
+        .severities
+        .insert("style.noTrailingWhitespace".to_string(), Severity::Error);
+
+    let issues = lint_source("main()\n{ \n}\n", &cfg);
+    let entries: Vec<FileLintIssue> = issues
+        .iter()
+        .map(|issue| FileLintIssue {
+            file: "test.pwn",
+            issue,
+        })
+        .collect();
+    let json: serde_json::Value = serde_json::from_str(&issues_to_json(&entries)).unwrap();
+
+    let first = &json[0];
+    assert_eq!(first["file"], "test.pwn");
+    assert_eq!(first["rule"], "style.noTrailingWhitespace");
+    assert_eq!(first["line"], 2);
+    assert_eq!(first["severity"], "error");
+}
+
+#[test]
+fn severity_defaults_to_warning() {
+    let issues = lint_source("main() \n", &linter_config());
+    assert_eq!(issues[0].severity, Severity::Warning);
+}
+
+#[test]
+fn trailing_whitespace_column_points_at_first_trailing_space() {
+    let issues = lint_source("main()\n{\n    printf(\"hi\");  \t\n}\n", &linter_config());
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].line, 3);
+    assert_eq!(issues[0].column, "    printf(\"hi\");".len() + 1);
+}
+
+#[test]
+fn duplicate_include_column_points_at_include_path() {
+    let mut cfg = linter_config();
+    cfg.linter.check_duplicate_includes = true;
+    let issues = lint_source("#include <a_samp>\n  #include <a_samp>\n", &cfg);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].column, 12);
+}
+
+fn unreachable_config() -> Config {
+    let mut cfg = Config::default();
+    cfg.linter.enabled = true;
+    cfg.linter.check_unreachable_code = true;
+    cfg
+}
+
+#[test]
+fn flags_statement_after_return() {
+    let source = "main()\n{\n    return 1;\n    printf(\"dead\");\n    printf(\"also dead\");\n}\n";
+    let issues = lint_source(source, &unreachable_config());
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].rule, "suspicious.unreachableCode");
+    assert_eq!(issues[0].line, 4);
+    assert_eq!(issues[0].column, 5);
+}
+
+#[test]
+fn statement_after_conditional_return_is_reachable() {
+    let source = "main()\n{\n    new x = 1;\n    if (x) {\n        return 1;\n    }\n    printf(\"alive\");\n}\n";
+    assert!(lint_source(source, &unreachable_config()).is_empty());
+}
+
+#[test]
+fn statement_after_if_else_that_both_return_is_unreachable() {
+    let source =
+        "main()\n{\n    new x = 1;\n    if (x) return 1;\n    else return 2;\n    x = 3;\n}\n";
+    let issues = lint_source(source, &unreachable_config());
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].line, 6);
+}
+
+fn unused_config() -> Config {
+    let mut cfg = Config::default();
+    cfg.linter.enabled = true;
+    cfg.linter.check_unused_variables = true;
+    cfg.linter.check_unused_parameters = true;
+    cfg
+}
+
+#[test]
+fn flags_unused_local_and_parameter() {
+    let source = "helper(value)\n{\n    return 1;\n}\n\nmain()\n{\n    new used = 1;\n    new unused;\n    unused = 2;\n    return used + helper(used);\n}\n";
+    let issues = lint_source(source, &unused_config());
+    let found: Vec<(&str, usize)> = issues.iter().map(|i| (i.rule, i.line)).collect();
+    assert_eq!(
+        found,
+        vec![
+            ("suspicious.unusedParameter", 1),
+            ("suspicious.unusedVariable", 9),
+        ]
+    );
+    assert!(issues[1].message.contains("unused"));
+}
+
+#[test]
+fn underscore_prefixed_names_are_exempt() {
+    let source = "helper(_value)\n{\n    return 1;\n}\n\nmain()\n{\n    new _ignored;\n    return helper(0);\n}\n";
+    assert!(lint_source(source, &unused_config()).is_empty());
+}
+
+fn indentation_config() -> Config {
+    let mut cfg = Config::default();
+    cfg.linter.enabled = true;
+    cfg.linter.check_indentation = true;
+    cfg
+}
+
+#[test]
+fn flags_tab_indented_line_in_space_indented_file() {
+    let source = "main()\n{\n    new x = 1;\n\tx = 2;\n    return x;\n}\n";
+    let issues = lint_source(source, &indentation_config());
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].rule, "style.consistentIndentation");
+    assert_eq!(issues[0].line, 4);
+    assert!(issues[0].message.contains("file uses spaces"));
+}
+
+#[test]
+fn flags_indentation_mixing_tabs_and_spaces() {
+    let source = "main()\n{\n\tnew x = 1;\n\t return x;\n}\n";
+    let issues = lint_source(source, &indentation_config());
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].line, 4);
+    assert!(issues[0].message.contains("mixes tabs and spaces"));
+}
+
+#[test]
+fn summary_counts_by_severity_and_picks_exit_code() {
+    let mut cfg = linter_config();
+    cfg.linter.check_newline_eof = true;
+
+    let mut summary = LintSummary::default();
+    summary.add_file(&lint_source("main() \n{\n}", &cfg));
+    summary.add_file(&lint_source("main()\n{\n}\n", &cfg));
+    assert_eq!(summary.issues, 2);
+    assert_eq!(summary.files, 1);
+    assert_eq!(summary.warnings, 2);
+    assert_eq!(summary.exit_code(), 1);
+    assert_eq!(
+        summary.to_string(),
+        "2 issues in 1 files (0 errors, 2 warnings)"
+    );
+
+    cfg.linter
+        .severities
+        .insert("style.noTrailingWhitespace".to_string(), Severity::Error);
+    summary.add_file(&lint_source("main() \n", &cfg));
+    assert_eq!(summary.errors, 1);
+    assert_eq!(summary.files, 2);
+    assert_eq!(summary.exit_code(), 2);
+
+    assert_eq!(LintSummary::default().exit_code(), 0);
+}
+
+fn assignment_config() -> Config {
+    let mut cfg = Config::default();
+    cfg.linter.enabled = true;
+    cfg.linter.check_assignment_in_condition = true;
+    cfg
+}
+
+#[test]
+fn flags_assignment_used_as_condition() {
+    let source = "main()\n{\n    new x;\n    if (x = 1)\n    {\n        x++;\n    }\n}\n";
+    let issues = lint_source(source, &assignment_config());
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].rule, "suspicious.assignmentInCondition");
+    assert_eq!(issues[0].line, 4);
+}
+
+#[test]
+fn comparison_conditions_are_not_flagged() {
+    let cfg = assignment_config();
+    let equality = "main()\n{\n    new x;\n    if (x == 1)\n    {\n        x++;\n    }\n}\n";
+    assert!(lint_source(equality, &cfg).is_empty());
+
+    let compared = "f()\n{\n    return 1;\n}\n\nmain()\n{\n    new x;\n    if ((x = f()) != 0)\n    {\n        x++;\n    }\n}\n";
+    assert!(lint_source(compared, &cfg).is_empty());
+}
+
+fn chaining_config() -> Config {
+    let mut cfg = Config::default();
+    cfg.linter.enabled = true;
+    cfg.linter.check_comparison_chaining = true;
+    cfg
+}
+
+#[test]
+fn flags_chained_relational_comparison() {
+    let source = "main()\n{\n    new a;\n    new b = 1;\n    new c = 2;\n    if (a < b < c)\n    {\n        a++;\n    }\n}\n";
+    let issues = lint_source(source, &chaining_config());
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].rule, "suspicious.comparisonChaining");
+    assert_eq!(issues[0].line, 6);
+}
+
+#[test]
+fn comparisons_joined_by_logical_and_are_not_flagged() {
+    let source = "main()\n{\n    new a;\n    new b = 1;\n    new c = 2;\n    if ((a < b) && (b < c))\n    {\n        a++;\n    }\n}\n";
+    assert!(lint_source(source, &chaining_config()).is_empty());
+}
+
+fn magic_number_config(allowlist: &[i32]) -> Config {
+    let mut cfg = Config::default();
+    cfg.linter.enabled = true;
+    cfg.linter.check_magic_numbers = true;
+    cfg.linter.magic_number_allowlist = allowlist.to_vec();
+    cfg
+}
+
+#[test]
+fn flags_magic_numbers_outside_the_allowlist() {
+    let source = "main()\n{\n    new x;\n    new list[16];\n    x = 42;\n    x = 1;\n    x = -1;\n    list[0] = x;\n}\n";
+    let issues = lint_source(source, &magic_number_config(&[]));
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].rule, "suspicious.magicNumber");
+    assert_eq!(issues[0].line, 5);
+    assert!(issues[0].message.contains("42"));
+
+    assert!(lint_source(source, &magic_number_config(&[42])).is_empty());
+}
+
+#[test]
+fn magic_number_rule_is_opt_in() {
+    let path = std::env::temp_dir().join(format!("pwnrust-{}-magic.json", std::process::id()));
+    std::fs::write(&path, r#"{ "linter": { "enabled": true } }"#).unwrap();
+    assert!(!load_config(&path).linter.check_magic_numbers);
+
+    std::fs::write(
+        &path,
+        r#"{
+            "linter": {
+                "rules": { "suspicious": { "magicNumber": "warn" } },
+                "magicNumberAllowlist": [7, 42]
+            }
+        }"#,
+    )
+    .unwrap();
+    let cfg = load_config(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(cfg.linter.check_magic_numbers);
+    assert_eq!(cfg.linter.magic_number_allowlist, vec![7, 42]);
+}
@@ -0,0 +1,102 @@
+use pawn_amx::instructions::{Instruction, Opcode};
+use pawn_amx::{AMX_EXEC_MAIN, AmxRuntime, AmxRuntimeError, CELL_BYTES, Cell, read_header};
+use pawn_compiler::compile;
+
+const CELL: Cell = CELL_BYTES as Cell;
+
+#[test]
+fn prologue_reserves_and_zeroes_locals() {
+    let source = r#"
+        main() {
+            {
+                new junk = 7;
+            }
+            new a;
+            new b;
+            return a + b;
+        }
+    "#;
+    let bytecode = compile(source).unwrap();
+
+    let cod = read_header(&bytecode).unwrap().cod as usize;
+    let prologue = Instruction::from_bytes(&bytecode, cod).unwrap();
+    assert_eq!(prologue.opcode, Opcode::Stack);
+    assert_eq!(prologue.operand, -2 * CELL);
+
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+    assert!(matches!(
+        runtime.exec_with_limit(AMX_EXEC_MAIN, 1),
+        Err(AmxRuntimeError::InstructionLimitExceeded)
+    ));
+    assert_eq!(runtime.amx.frm - runtime.amx.stk, 2 * CELL);
+
+    // `a` reuses the slot `junk` left behind, and still starts at zero
+    runtime.init(&bytecode).unwrap();
+    assert_eq!(runtime.exec(AMX_EXEC_MAIN).unwrap(), 0);
+}
+
+#[test]
+fn locals_sit_below_the_frame_and_arguments_above_it() {
+    let source = r#"
+        scale(value, factor) {
+            new scaled = value * factor;
+            new offset = 2;
+            return scaled + offset;
+        }
+
+        main() {
+            return scale(5, 8);
+        }
+    "#;
+    let bytecode = compile(source).unwrap();
+
+    let header = read_header(&bytecode).unwrap();
+    let frame_operands = |opcode: Opcode| -> Vec<Cell> {
+        decode(&bytecode[header.cod as usize..header.dat as usize])
+            .into_iter()
+            .filter(|instruction| instruction.opcode == opcode)
+            .map(|instruction| instruction.operand)
+            .collect()
+    };
+    assert_eq!(frame_operands(Opcode::StorSPri), vec![-CELL, -2 * CELL]);
+    assert!(frame_operands(Opcode::LoadSPri).contains(&(3 * CELL)));
+    assert!(frame_operands(Opcode::LoadSPri).contains(&(4 * CELL)));
+
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+    assert_eq!(runtime.exec(AMX_EXEC_MAIN).unwrap(), 42);
+}
+
+#[test]
+fn globals_load_absolute_and_locals_frame_relative() {
+    let source = "new g = 5; main() { new l = 9; g = g + 1; return g * 10 + l; }";
+    let bytecode = compile(source).unwrap();
+
+    let header = read_header(&bytecode).unwrap();
+    let code = decode(&bytecode[header.cod as usize..header.dat as usize]);
+    let uses = |opcode: Opcode, operand: Cell| {
+        code.iter()
+            .any(|instruction| instruction.opcode == opcode && instruction.operand == operand)
+    };
+    assert!(uses(Opcode::LoadPri, 0));
+    assert!(uses(Opcode::StorPri, 0));
+    assert!(uses(Opcode::LoadSPri, -CELL));
+
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+    assert_eq!(runtime.exec(AMX_EXEC_MAIN).unwrap(), 69);
+    assert_eq!(runtime.read_cell_at(0).unwrap(), 6);
+}
+
+/// Decode a plain code section into its instructions
+fn decode(code: &[u8]) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+    while offset < code.len() {
+        let instruction = Instruction::from_bytes(code, offset).unwrap();
+        offset += instruction.size();
+        instructions.push(instruction);
+    }
+    instructions
+}
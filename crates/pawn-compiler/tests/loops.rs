@@ -0,0 +1,201 @@
+use pawn_amx::instructions::Opcode;
+use pawn_amx::{AMX_EXEC_CONT, AMX_EXEC_MAIN, AmxRuntime, AmxRuntimeError};
+use pawn_compiler::{CodeGenerator, CompilerError, Parser, compile};
+
+fn runtime_for(source: &str) -> AmxRuntime {
+    let bytecode = compile(source).expect("compile should succeed");
+    let mut runtime = AmxRuntime::new();
+    runtime
+        .init(&bytecode)
+        .expect("runtime init should succeed");
+    runtime
+}
+
+#[test]
+fn while_loop_runs_until_condition_fails() {
+    let source = r#"
+        main() {
+            new i = 0, sum = 0;
+            while (i < 5) {
+                i += 1;
+                sum += i;
+            }
+            return sum;
+        }
+    "#;
+    assert_eq!(runtime_for(source).exec(AMX_EXEC_MAIN).unwrap(), 15);
+}
+
+#[test]
+fn comparison_conditions_branch_without_a_flag() {
+    let source = r#"
+        main() {
+            new i = 0, sum = 0;
+            while (i < 5) i++;
+            for (new j = 0; j <= 3; j++) sum += j;
+            do sum++; while (sum != 10);
+            if (sum == 10) sum += i;
+            return sum;
+        }
+    "#;
+    assert_eq!(runtime_for(source).exec(AMX_EXEC_MAIN).unwrap(), 15);
+
+    let ast = Parser::new(source).unwrap().parse_program().unwrap();
+    let mut codegen = CodeGenerator::new();
+    codegen.generate(&ast).unwrap();
+    let opcodes: Vec<Opcode> = codegen.instructions().iter().map(|i| i.opcode).collect();
+    for jump in [Opcode::Jsgeq, Opcode::Jsgrtr, Opcode::Jneq] {
+        assert!(opcodes.contains(&jump), "missing {:?}", jump);
+    }
+    for flag in [
+        Opcode::Less,
+        Opcode::Leq,
+        Opcode::Sless,
+        Opcode::Sleq,
+        Opcode::Neq,
+        Opcode::Eq,
+        Opcode::Jzer,
+    ] {
+        assert!(!opcodes.contains(&flag), "unexpected {:?}", flag);
+    }
+}
+
+#[test]
+fn do_while_runs_body_once_when_condition_is_false() {
+    let source = r#"
+        main() {
+            new runs = 0;
+            do {
+                runs += 1;
+            } while (0);
+            return runs;
+        }
+    "#;
+    assert_eq!(runtime_for(source).exec(AMX_EXEC_MAIN).unwrap(), 1);
+}
+
+#[test]
+fn do_while_repeats_until_condition_fails() {
+    let source = r#"
+        main() {
+            new i = 0;
+            do {
+                i += 1;
+            } while (i < 7);
+            return i;
+        }
+    "#;
+    assert_eq!(runtime_for(source).exec(AMX_EXEC_MAIN).unwrap(), 7);
+}
+
+#[test]
+fn break_leaves_loop_early() {
+    let source = r#"
+        main() {
+            new i = 0;
+            while (1) {
+                new step = 1;
+                if (i == 4) break;
+                i += step;
+            }
+            return i;
+        }
+    "#;
+    assert_eq!(runtime_for(source).exec(AMX_EXEC_MAIN).unwrap(), 4);
+}
+
+#[test]
+fn continue_skips_rest_of_iteration() {
+    let source = r#"
+        main() {
+            new i = 0, sum = 0;
+            do {
+                i += 1;
+                if (i == 3) continue;
+                sum += i;
+            } while (i < 5);
+            return sum;
+        }
+    "#;
+    assert_eq!(runtime_for(source).exec(AMX_EXEC_MAIN).unwrap(), 12);
+}
+
+#[test]
+fn break_outside_loop_is_an_error() {
+    let result = compile("main() { break; }");
+    assert!(matches!(result, Err(CompilerError::SemanticError(_))));
+}
+
+#[test]
+fn instruction_limit_stops_infinite_loop() {
+    let source = r#"
+        main() {
+            new x = 0;
+            while (1) {
+                x += 1;
+            }
+        }
+    "#;
+    let mut runtime = runtime_for(source);
+
+    assert!(matches!(
+        runtime.exec_with_limit(AMX_EXEC_MAIN, 10_000),
+        Err(AmxRuntimeError::InstructionLimitExceeded)
+    ));
+    let cip = runtime.amx.cip;
+
+    // The state is left intact, so the script can be continued
+    assert!(matches!(
+        runtime.exec_with_limit(AMX_EXEC_CONT, 1),
+        Err(AmxRuntimeError::InstructionLimitExceeded)
+    ));
+    assert_ne!(runtime.amx.cip, cip);
+}
+
+#[test]
+fn for_loop_runs_all_three_clauses() {
+    let source = r#"
+        main() {
+            new sum = 0;
+            for (new i = 0; i < 5; i++) sum += i;
+            for (new i = 10; i < 12; i++) {
+                sum += i;
+            }
+            return sum;
+        }
+    "#;
+    assert_eq!(runtime_for(source).exec(AMX_EXEC_MAIN).unwrap(), 31);
+}
+
+#[test]
+fn continue_in_for_loop_runs_the_update() {
+    // Skipping the update would loop forever on i == 2
+    let source = r#"
+        main() {
+            new sum = 0;
+            new i;
+            for (i = 0; i < 5; i++) {
+                if (i == 2) continue;
+                sum += i;
+            }
+            return sum * 10 + i;
+        }
+    "#;
+    let mut runtime = runtime_for(source);
+    assert_eq!(runtime.exec_with_limit(AMX_EXEC_MAIN, 10_000).unwrap(), 85);
+}
+
+#[test]
+fn for_loop_clauses_are_optional() {
+    let source = r#"
+        main() {
+            new n = 0;
+            for (;;) {
+                if (n == 3) break;
+                n++;
+            }
+            return n;
+        }
+    "#;
+    assert_eq!(runtime_for(source).exec(AMX_EXEC_MAIN).unwrap(), 3);
+}
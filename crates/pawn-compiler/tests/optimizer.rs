@@ -0,0 +1,67 @@
+use pawn_amx::{AMX_EXEC_MAIN, AmxRuntime, Cell};
+use pawn_compiler::{CodeGenerator, Parser, compile_with_config, load_config};
+
+const SOURCE: &str = r#"
+    Add(a, b) {
+        return a + b;
+    }
+
+    main() {
+        new total = 0;
+        new i = 0;
+        while (i < 5) {
+            total = Add(total, i * 2);
+            i++;
+        }
+        return total;
+    }
+"#;
+
+fn run(bytecode: &[u8]) -> Cell {
+    let mut runtime = AmxRuntime::new();
+    runtime.init(bytecode).unwrap();
+    runtime.exec(AMX_EXEC_MAIN).unwrap()
+}
+
+#[test]
+fn codegen_section_enables_the_optimizer() {
+    let path = std::env::temp_dir().join(format!("pwnrust-{}-codegen.json", std::process::id()));
+    std::fs::write(&path, r#"{ "codegen": { "optimize": true } }"#).unwrap();
+    let cfg = load_config(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(cfg.codegen.optimize);
+    assert!(!cfg.codegen.emit_debug);
+
+    let ast = Parser::new(SOURCE).unwrap().parse_program().unwrap();
+    let mut plain = CodeGenerator::new();
+    plain.generate(&ast).unwrap();
+    let mut optimized = CodeGenerator::with_config(&cfg.codegen);
+    optimized.generate(&ast).unwrap();
+    assert!(optimized.instructions().len() < plain.instructions().len());
+}
+
+#[test]
+fn codegen_keys_outside_the_codegen_section_are_ignored() {
+    let path = std::env::temp_dir().join(format!("pwnrust-{}-elsewhere.json", std::process::id()));
+    std::fs::write(
+        &path,
+        r#"{ "formatter": { "optimize": true }, "codegen": { "emitDebug": true } }"#,
+    )
+    .unwrap();
+    let cfg = load_config(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(!cfg.codegen.optimize);
+    assert!(cfg.codegen.emit_debug);
+}
+
+#[test]
+fn optimized_program_computes_the_same_result() {
+    let mut cfg = pawn_compiler::Config::default();
+    let plain = compile_with_config(SOURCE, &cfg).unwrap();
+    cfg.codegen.optimize = true;
+    let optimized = compile_with_config(SOURCE, &cfg).unwrap();
+
+    assert!(optimized.len() < plain.len());
+    assert_eq!(run(&plain), 20);
+    assert_eq!(run(&optimized), 20);
+}
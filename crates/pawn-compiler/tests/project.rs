@@ -0,0 +1,80 @@
+use pawn_amx::AmxRuntime;
+use pawn_compiler::{CompilerError, Config, compile_project};
+use std::path::PathBuf;
+
+/// Create a directory unique to this test holding the given files
+fn project_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pawn-project-{}-{}", std::process::id(), name));
+    std::fs::create_dir_all(&dir).unwrap();
+    for (file, contents) in files {
+        std::fs::write(dir.join(file), contents).unwrap();
+    }
+    dir
+}
+
+#[test]
+fn function_from_included_file_is_callable() {
+    let dir = project_dir(
+        "include",
+        &[
+            (
+                "main.pwn",
+                "#include \"helper\"\n\nforward Twice(x);\n\npublic Run()\n{\n    return Twice(21);\n}\n",
+            ),
+            ("helper.inc", "Twice(x)\n{\n    return x * 2;\n}\n"),
+        ],
+    );
+
+    let bytecode = compile_project(&dir.join("main.pwn"), &[], &Config::default());
+    std::fs::remove_dir_all(&dir).ok();
+    let bytecode = bytecode.expect("project should compile");
+
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+    assert_eq!(runtime.call_public("Run", &[]).unwrap(), 42);
+}
+
+#[test]
+fn missing_include_is_an_error() {
+    let dir = project_dir(
+        "missing",
+        &[("main.pwn", "#include <nowhere>\n\nmain()\n{\n}\n")],
+    );
+
+    let result = compile_project(
+        &dir.join("main.pwn"),
+        std::slice::from_ref(&dir),
+        &Config::default(),
+    );
+    std::fs::remove_dir_all(&dir).ok();
+    assert!(matches!(result, Err(CompilerError::FileError(_))));
+}
+
+#[test]
+fn errors_in_included_files_name_the_file_and_its_line() {
+    let dir = project_dir(
+        "located",
+        &[
+            ("main.pwn", "#include \"broken\"\n\nmain()\n{\n}\n"),
+            ("broken.inc", "Broken()\n{\n    return 1 +;\n}\n"),
+        ],
+    );
+
+    let result = compile_project(&dir.join("main.pwn"), &[], &Config::default());
+    std::fs::remove_dir_all(&dir).ok();
+    let error = result.unwrap_err();
+    assert_eq!(error.path(), Some(dir.join("broken.inc").as_path()));
+    assert_eq!(error.span().map(|(line, _, _)| line), Some(3));
+}
+
+#[test]
+fn include_without_quotes_or_brackets_is_an_error() {
+    let dir = project_dir("unquoted", &[("main.pwn", "\n#include a_samp\n")]);
+
+    let result = compile_project(&dir.join("main.pwn"), &[], &Config::default());
+    std::fs::remove_dir_all(&dir).ok();
+    let error = result.unwrap_err();
+    assert!(matches!(error.kind(), CompilerError::SyntaxError(_)));
+    assert_eq!(error.path(), Some(dir.join("main.pwn").as_path()));
+    assert_eq!(error.span().map(|(line, _, _)| line), Some(2));
+}
@@ -0,0 +1,89 @@
+use pawn_amx::{AMX_EXEC_MAIN, AmxRuntime};
+use pawn_compiler::{Config, Parser, SymbolTableVisitor, SymbolType, compile_with_config};
+
+fn analyze(source: &str) -> SymbolTableVisitor {
+    let ast = Parser::new(source).unwrap().parse_program().unwrap();
+    let mut visitor = SymbolTableVisitor::new();
+    visitor.analyze(&ast).expect("analysis should succeed");
+    visitor
+}
+
+#[test]
+fn records_function_definition_and_calls() {
+    let source = r#"main() {
+    new total = twice(1);
+    total += twice(total);
+    return total;
+}
+
+twice(value) {
+    return value * 2;
+}
+"#;
+    let visitor = analyze(source);
+
+    assert_eq!(visitor.definition_of("twice"), Some(7));
+    assert_eq!(visitor.references_of("twice"), vec![2, 3]);
+    assert_eq!(visitor.definition_of("total"), Some(2));
+    assert_eq!(visitor.references_of("total"), vec![3, 3, 4]);
+    assert_eq!(visitor.definition_of("missing"), None);
+}
+
+#[test]
+fn enumerates_top_level_symbols() {
+    let source = r#"new counter = 0;
+
+main() {
+    counter = helper();
+}
+
+helper() {
+    return counter + 1;
+}
+"#;
+    let visitor = analyze(source);
+
+    let names: Vec<&str> = visitor.globals().iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, ["printf", "main", "helper", "counter"]);
+
+    let functions: Vec<&str> = visitor
+        .functions()
+        .iter()
+        .map(|s| s.name.as_str())
+        .collect();
+    assert_eq!(functions, ["printf", "main", "helper"]);
+
+    let variables = visitor.variables();
+    assert_eq!(variables.len(), 1);
+    assert_eq!(variables[0].name, "counter");
+    assert!(matches!(
+        variables[0].symbol_type,
+        SymbolType::Variable { .. }
+    ));
+    assert!(
+        visitor
+            .functions()
+            .iter()
+            .all(|s| matches!(s.symbol_type, SymbolType::Function { .. }))
+    );
+}
+
+#[test]
+fn config_globals_compile_to_host_native_calls() {
+    let source = r#"main() {
+    return CreateVehicle(411, 7, 0);
+}
+"#;
+    assert!(compile_with_config(source, &Config::default()).is_err());
+
+    let mut config = Config::default();
+    config.pawn.globals = vec!["CreateVehicle".into()];
+    let bytecode = compile_with_config(source, &config).expect("CreateVehicle is a host native");
+
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+    runtime.register_native("CreateVehicle".to_string(), |_, params| {
+        params[1] + params[2]
+    });
+    assert_eq!(runtime.exec(AMX_EXEC_MAIN).unwrap(), 418);
+}
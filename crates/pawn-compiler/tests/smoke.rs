@@ -1,29 +1,86 @@
-use pawn_amx::AmxRuntime;
-use pawn_compiler::compile;
-
-#[test]
-fn compiles_and_runs_minimal_printf_program() {
-    let source = r#"
-        main() {
-            printf("hello from pawn");
-        }
-    "#;
-
-    // Compile to bytecode
-    let bytecode = compile(source).expect("compile should succeed");
-
-    // Initialize AMX runtime and execute main
-    let mut runtime = AmxRuntime::new();
-    runtime
-        .init(&bytecode)
-        .expect("runtime init should succeed");
-
-    // Register a dummy printf; current Sysreq handler ignores it in MVP
-    runtime.register_native("printf".to_string(), |_amx, _params| 0);
-
-    // Execute; should complete without error
-    let result = runtime
-        .exec(pawn_amx::AMX_EXEC_MAIN)
-        .expect("exec should succeed");
-    assert_eq!(result, 0);
-}
+use pawn_amx::AmxRuntime;
+use pawn_compiler::compile;
+
+#[test]
+fn compiles_and_runs_minimal_printf_program() {
+    let source = r#"
+        main() {
+            printf("hello from pawn");
+        }
+    "#;
+
+    // Compile to bytecode
+    let bytecode = compile(source).expect("compile should succeed");
+
+    // Initialize AMX runtime and execute main
+    let mut runtime = AmxRuntime::new();
+    runtime
+        .init(&bytecode)
+        .expect("runtime init should succeed");
+
+    // Register a dummy printf for the SYSREQ emitted by codegen
+    runtime.register_native("printf".to_string(), |_amx, _params| 0);
+
+    // Execute; should complete without error
+    let result = runtime
+        .exec(pawn_amx::AMX_EXEC_MAIN)
+        .expect("exec should succeed");
+    assert_eq!(result, 0);
+}
+
+#[test]
+fn emits_generated_instructions_as_assembly() {
+    let source = r#"
+        main() {
+            printf("hello from pawn");
+        }
+    "#;
+
+    let asm = pawn_compiler::emit_asm_with_config(source, &pawn_compiler::Config::default())
+        .expect("compile should succeed");
+    assert!(asm.trim_end().ends_with("HALT"), "{}", asm);
+    assert!(asm.contains("SYSREQ 0 # printf"), "{}", asm);
+
+    // The listing is valid input for the assembler
+    pawn_amx::assemble(&asm).expect("listing should assemble");
+}
+
+#[test]
+fn full_disassembly_renders_string_literal_in_data_section() {
+    let source = r#"
+        main() {
+            printf("hello from pawn");
+        }
+    "#;
+    let bytecode = compile(source).expect("compile should succeed");
+
+    let listing = pawn_amx::disassemble_full(&bytecode).expect("listing should succeed");
+    let (code, data) = listing
+        .split_once("\nDATA\n")
+        .expect("listing has a DATA section");
+    assert!(code.contains("SYSREQ 0  ; printf"), "{}", listing);
+    assert!(code.trim_end().ends_with("HALT 0"), "{}", listing);
+    assert!(data.contains(".asciz \"hello from pawn\""), "{}", listing);
+}
+
+#[test]
+fn sources_without_code_compile_to_runnable_programs() {
+    for source in [
+        "",
+        "   \n\n\t\n",
+        "// nothing here\n/* or here */\n",
+        "#include <a_samp>\n#include \"helper\"\n",
+    ] {
+        let bytecode = compile(source).unwrap_or_else(|e| panic!("{:?}: {}", source, e));
+        let mut runtime = AmxRuntime::new();
+        runtime
+            .init(&bytecode)
+            .unwrap_or_else(|e| panic!("{:?}: {}", source, e));
+        assert_eq!(
+            runtime.exec(pawn_amx::AMX_EXEC_MAIN).unwrap(),
+            0,
+            "{:?}",
+            source
+        );
+    }
+}
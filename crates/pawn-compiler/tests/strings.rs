@@ -0,0 +1,88 @@
+use pawn_amx::instructions::{Instruction, Opcode};
+use pawn_amx::{AMX_EXEC_MAIN, Amx, AmxRuntime, CELL_BYTES, Cell, read_header};
+use pawn_compiler::{CodeGenerator, Parser, compile};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Native-style accessor returning character `params[1]` of the packed
+/// string at `params[0]`
+fn char_at(amx: &mut Amx, params: &[Cell]) -> Cell {
+    amx.packed_char(params[0], params[1])
+        .map(Cell::from)
+        .unwrap_or(-1)
+}
+
+#[test]
+fn packed_string_round_trips_through_native() {
+    let bytecode = compile(r#"main() { return !"Pawn rocks"; }"#).expect("compile should succeed");
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+    let address = runtime.exec(AMX_EXEC_MAIN).unwrap();
+
+    // As many characters as fit in a cell, first character in the high byte
+    let first_cell = b"Pawn rocks"[..CELL_BYTES].try_into().unwrap();
+    assert_eq!(
+        runtime.amx.read_cell(address).unwrap(),
+        Cell::from_be_bytes(first_cell)
+    );
+
+    let mut text = String::new();
+    for index in 0.. {
+        match char_at(&mut runtime.amx, &[address, index]) {
+            0 => break,
+            -1 => panic!("read past the end of the data region"),
+            ch => text.push(ch as u8 as char),
+        }
+    }
+    assert_eq!(text, "Pawn rocks");
+}
+
+#[test]
+fn identical_literals_share_one_data_copy() {
+    let source = r#"
+        new scores[3];
+
+        main() {
+            printf("hi");
+            printf("hi");
+        }
+    "#;
+    let ast = Parser::new(source).unwrap().parse_program().unwrap();
+    let mut codegen = CodeGenerator::new();
+    let bytecode = codegen.generate(&ast).unwrap();
+
+    // The array takes three cells; "hi" and its terminator take three more
+    let address = 3 * CELL_BYTES as Cell;
+    assert_eq!(codegen.data_len(), 6 * CELL_BYTES);
+
+    let header = read_header(&bytecode).unwrap();
+    let string_loads = decode(&bytecode[header.cod as usize..header.dat as usize])
+        .into_iter()
+        .filter(|i| i.opcode == Opcode::PushC && i.operand == address)
+        .count();
+    assert_eq!(string_loads, 2);
+
+    // A native reads the string back from that address
+    let printed = Rc::new(RefCell::new(Vec::new()));
+    let sink = Rc::clone(&printed);
+    let mut runtime = AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+    runtime.register_native_closure("printf".to_string(), move |amx, params| {
+        sink.borrow_mut().push(amx.get_string(params[1]).unwrap());
+        0
+    });
+    runtime.exec(AMX_EXEC_MAIN).unwrap();
+    assert_eq!(*printed.borrow(), ["hi", "hi"]);
+}
+
+/// Decode a plain code section into its instructions
+fn decode(code: &[u8]) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+    while offset < code.len() {
+        let instruction = Instruction::from_bytes(code, offset).unwrap();
+        offset += instruction.size();
+        instructions.push(instruction);
+    }
+    instructions
+}
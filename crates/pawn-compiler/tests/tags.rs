@@ -0,0 +1,146 @@
+use pawn_compiler::{CompilerError, Parser, SymbolTableVisitor, TagChecker, compile_all};
+
+fn tag_errors(source: &str) -> Vec<CompilerError> {
+    let ast = Parser::new(source).unwrap().parse_program().unwrap();
+    let mut checker = TagChecker::new();
+    let _ = checker.check(&ast);
+    checker.get_errors().to_vec()
+}
+
+#[test]
+fn float_mixed_with_untagged_is_a_mismatch() {
+    let errors = tag_errors("main() { new Float:f; new i; i = f + 1; }");
+    assert!(
+        errors
+            .iter()
+            .any(|error| matches!(error, CompilerError::TypeError(msg) if msg.contains("Float:")))
+    );
+}
+
+#[test]
+fn float_arithmetic_is_reported_as_unsupported() {
+    for source in [
+        "main() { new Float:f = 2.0; new Float:g = f + 1.0; }",
+        "main() { new Float:f = 1.5 * 2.0; }",
+        "main() { new Float:f = 2.0; f -= 1.0; }",
+    ] {
+        let errors = tag_errors(source);
+        assert!(
+            matches!(errors.as_slice(), [CompilerError::TypeError(msg)] if msg.contains("Float arithmetic")),
+            "{}: {:?}",
+            source,
+            errors
+        );
+    }
+}
+
+#[test]
+fn true_and_false_are_bool_constants() {
+    let source = r#"
+        main() {
+            new bool:yes = true;
+            new bool:no = false;
+            new i = yes, j = no, k = true && !false;
+            return i * 100 + j * 10 + k;
+        }
+    "#;
+    assert!(tag_errors(source).is_empty());
+
+    let bytecode = pawn_compiler::compile(source).expect("compile should succeed");
+    let mut runtime = pawn_amx::AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+    assert_eq!(runtime.exec(pawn_amx::AMX_EXEC_MAIN).unwrap(), 101);
+
+    assert_eq!(tag_errors("main() { new Float:f = true; }").len(), 1);
+}
+
+#[test]
+fn assigning_float_to_untagged_is_reported_by_compile() {
+    let errors = compile_all("main() { new Float:f = 1.5; new i = f; return i; }").unwrap_err();
+    assert!(matches!(
+        errors.as_slice(),
+        [CompilerError::TypeError(msg)] if msg.contains("line 1")
+    ));
+}
+
+#[test]
+fn bool_tag_is_weak() {
+    let source = r#"
+        native Float:float(value);
+
+        main() {
+            new bool:b = 1 < 2;
+            new i = b;
+            new Float:f = float(i);
+            b = f > 0.5;
+        }
+    "#;
+    assert!(tag_errors(source).is_empty());
+    assert_eq!(tag_errors("main() { new bool:b = 1; }").len(), 1);
+}
+
+#[test]
+fn declarations_record_their_tag() {
+    let ast = Parser::new("new Float:x;\nnew MyTag:y;\nnew z;\nFloat:half() { return 0.5; }\n")
+        .unwrap()
+        .parse_program()
+        .unwrap();
+    let mut visitor = SymbolTableVisitor::new();
+    visitor.set_library(true);
+    visitor.analyze(&ast).unwrap();
+
+    let tag_of = |name: &str| {
+        visitor
+            .globals()
+            .into_iter()
+            .find(|symbol| symbol.name == name)
+            .and_then(|symbol| symbol.tag().map(str::to_string))
+    };
+    assert_eq!(tag_of("x").as_deref(), Some("Float"));
+    assert_eq!(tag_of("y").as_deref(), Some("MyTag"));
+    assert_eq!(tag_of("z"), None);
+    assert_eq!(tag_of("half").as_deref(), Some("Float"));
+}
+
+#[test]
+fn tagof_yields_tag_identifier() {
+    let source = r#"
+        main() {
+            new Float:f;
+            new n;
+            return tagof f + tagof n;
+        }
+    "#;
+
+    let bytecode = pawn_compiler::compile(source).expect("compile should succeed");
+    let mut runtime = pawn_amx::AmxRuntime::new();
+    runtime.init(&bytecode).unwrap();
+    // The first tag gets identifier 1, flagged as fixed for its uppercase name
+    assert_eq!(runtime.exec(pawn_amx::AMX_EXEC_MAIN).unwrap(), 0x4000_0001);
+    assert_eq!(
+        runtime.find_tag("Float").map(|tag| tag.tag_id),
+        Some(0x4000_0001)
+    );
+}
+
+#[test]
+fn sizeof_of_unknown_symbol_is_an_error() {
+    let errors = compile_all("main() { return sizeof nothing; }").unwrap_err();
+    assert!(
+        errors
+            .iter()
+            .any(|e| matches!(e.kind(), CompilerError::SemanticError(_))),
+        "{:?}",
+        errors
+    );
+}
+
+#[test]
+fn sizeof_of_non_variable_is_a_parser_error() {
+    let errors = compile_all("main() { return sizeof 5; }").unwrap_err();
+    assert!(
+        matches!(errors[0].kind(), CompilerError::ParserError(_)),
+        "{:?}",
+        errors
+    );
+}
@@ -0,0 +1,109 @@
+use pawn_compiler::{AstNode, Parser, parse_expression, unparse};
+
+fn parse(source: &str) -> AstNode {
+    Parser::new(source).unwrap().parse_program().unwrap()
+}
+
+/// Unparse `source` and check the output parses back to the same tree
+fn round_trip(source: &str) -> String {
+    let tree = parse(source);
+    let text = unparse(&tree);
+    let reparsed = Parser::new(&text)
+        .and_then(|mut parser| parser.parse_program())
+        .unwrap_or_else(|e| panic!("unparsed source does not parse: {}\n{}", e, text));
+    assert_eq!(
+        reparsed.without_locations(),
+        tree.without_locations(),
+        "round trip changed the tree:\n{}",
+        text
+    );
+    text
+}
+
+#[test]
+fn expressions_keep_only_needed_parentheses() {
+    let cases = [
+        ("1 + 2 * 3", "1 + 2 * 3"),
+        ("(1 + 2) * 3", "(1 + 2) * 3"),
+        ("a - (b - c)", "a - (b - c)"),
+        ("(a - b) - c", "a - b - c"),
+        ("-(a + b)", "-(a + b)"),
+        ("- -a", "-(-a)"),
+        ("a - -5", "a - -5"),
+        ("!(a && b) || c", "!(a && b) || c"),
+        ("a ? b : c ? d : e", "a ? b : c ? d : e"),
+        ("(a ? b : c) ? d : e", "(a ? b : c) ? d : e"),
+        ("x = y = 3", "x = y = 3"),
+        ("arr[i + 1] += f(a, b * 2)", "arr[i + 1] += f(a, b * 2)"),
+        ("x++ + ++y", "x++ + ++y"),
+        ("sizeof(arr) - 1", "sizeof arr - 1"),
+        (
+            "f(\"say \\\"hi\\\"\\n\", 2.5)",
+            "f(\"say \\\"hi\\\"\\n\", 2.5)",
+        ),
+    ];
+    for (source, expected) in cases {
+        let tree = parse_expression(source).unwrap();
+        let text = unparse(&tree);
+        assert_eq!(text, expected, "unparsing {}", source);
+        assert_eq!(
+            parse_expression(&text).unwrap(),
+            tree,
+            "re-parsing {}",
+            text
+        );
+    }
+}
+
+#[test]
+fn control_flow_round_trips() {
+    let text = round_trip(
+        "new counter = 0, Float:ratio = 1.0;
+         native print(const string[]);
+         forward helper(&value);
+         helper(&value) { value *= 2; }
+         public OnUpdate() <game:running> { state game:paused; }
+         Float:scale(Float:x, factor = 2) return x;
+         main() {
+             new total = 0;
+             for (new i = 0; i < 10; i++) {
+                 if (i % 2 == 0) continue;
+                 else if (i > 7) break;
+                 else total += i;
+             }
+             for (;;) break;
+             while (total > 0) total--;
+             do { total++; } while (total < 3);
+             if (total) { if (counter) total = 1; } else total = 2;
+             assert total != 0;
+             print(\"done\");
+             return;
+         }",
+    );
+
+    assert!(text.contains("for (new i = 0; i < 10; i++) {\n"));
+    assert!(text.contains("} while (total < 3);\n"));
+    assert!(text.contains("public OnUpdate() <game:running> {\n"));
+}
+
+#[test]
+fn else_after_nested_if_keeps_its_braces() {
+    let source = "main() { if (a) { if (b) x = 1; } else x = 2; }";
+    let text = unparse(&parse(source));
+
+    assert!(text.contains("if (a) {\n"));
+    assert_eq!(
+        parse(&text).without_locations(),
+        parse(source).without_locations()
+    );
+}
+
+#[test]
+fn parameters_keep_their_dimensions() {
+    let text = round_trip("f(&a, const b[], c = 5, grid[3][4]) return a;");
+    assert!(
+        text.contains("f(&a, const b[], c = 5, grid[3][4])"),
+        "{}",
+        text
+    );
+}